@@ -9,7 +9,8 @@
 //! across major versions, in case it fails to compile after an update.
 //!
 //! It must be regenerated when adding new locales to Rust, or if Rust's usage of ICU4X
-//! grows to need more kinds of data.
+//! grows to need more kinds of data. For example, lists separated by "or" are currently
+//! formatted in English for all locales, because `list/or@1` isn't part of the data.
 //!
 //! To regenerate the data, run this command:
 //!
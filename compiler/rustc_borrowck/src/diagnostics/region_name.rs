@@ -193,6 +193,12 @@ fn into_diagnostic_arg(self) -> rustc_errors::DiagnosticArgValue<'static> {
     }
 }
 
+impl rustc_errors::AsDiagnosticArg for RegionName {
+    fn as_diagnostic_arg(&self) -> rustc_errors::DiagnosticArgValue<'static> {
+        self.to_string().into_diagnostic_arg()
+    }
+}
+
 impl<'tcx> MirBorrowckCtxt<'_, 'tcx> {
     pub(crate) fn mir_def_id(&self) -> hir::def_id::LocalDefId {
         self.body.source.def_id().expect_local()
@@ -881,7 +881,8 @@ fn report_invalid_references(
             desc: num_args_desc,
             highlight: SingleLabelManySpans {
                 spans: args.explicit_args().iter().map(|arg| arg.expr.span).collect(),
-                label: "",
+                label: "".into(),
+                args: vec![],
             },
         });
         // Point out `{:.*}` placeholders: those take an extra argument.
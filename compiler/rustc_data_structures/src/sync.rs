@@ -81,6 +81,12 @@ pub fn is_dyn_thread_safe() -> bool {
         }
     }
 
+    // Whether thread safety is enabled, without panicking if the mode isn't set yet.
+    #[inline]
+    pub fn is_known_dyn_thread_safe() -> bool {
+        DYN_THREAD_SAFE_MODE.load(Ordering::Relaxed) == DYN_THREAD_SAFE
+    }
+
     // Whether thread safety might be enabled.
     #[inline]
     pub fn might_be_dyn_thread_safe() -> bool {
@@ -102,7 +108,7 @@ pub fn set_dyn_thread_safe_mode(mode: bool) {
     }
 }
 
-pub use mode::{is_dyn_thread_safe, set_dyn_thread_safe_mode};
+pub use mode::{is_dyn_thread_safe, is_known_dyn_thread_safe, set_dyn_thread_safe_mode};
 
 cfg_match! {
     cfg(not(parallel_compiler)) => {
@@ -4,6 +4,7 @@ driver_impl_ice_bug_report_internal_feature = using internal features is not sup
 driver_impl_ice_exclude_cargo_defaults = some of the compiler flags provided by cargo are hidden
 
 driver_impl_ice_flags = compiler flags: {$flags}
+driver_impl_ice_json_path = a machine-readable report of the ICE was written to `{$path}`
 driver_impl_ice_path = please attach the file at `{$path}` to your bug report
 driver_impl_ice_path_error = the ICE couldn't be written to `{$path}`: {$error}
 driver_impl_ice_path_error_env = the environment variable `RUSTC_ICE` is set to `{$env_var}`
@@ -23,6 +23,7 @@
 use rustc_data_structures::profiling::{
     get_resident_set_size, print_time_passes_entry, TimePassesFormat,
 };
+use rustc_errors::json::JsonArray;
 use rustc_errors::registry::{InvalidErrorCode, Registry};
 use rustc_errors::{markdown, ColorConfig};
 use rustc_errors::{DiagCtxt, ErrorGuaranteed, PResult};
@@ -47,12 +48,12 @@
 use std::cmp::max;
 use std::collections::BTreeMap;
 use std::env;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::fmt::Write as _;
 use std::fs::{self, File};
 use std::io::{self, IsTerminal, Read, Write};
 use std::panic::{self, catch_unwind, PanicInfo};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{self, Command, Stdio};
 use std::str;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -147,15 +148,19 @@ pub(super) fn install() {}
 
 pub fn abort_on_err<T>(result: Result<T, ErrorGuaranteed>, sess: &Session) -> T {
     match result {
-        Err(..) => {
+        Err(guar) => {
             sess.dcx().abort_if_errors();
-            panic!("error reported but abort_if_errors didn't abort???");
+            panic!("error reported but abort_if_errors didn't abort??? ({guar:?})");
         }
         Ok(x) => x,
     }
 }
 
 pub trait Callbacks {
+    /// Called before anything else, to register the error codes of the driver along with those
+    /// of rustc, see `Registry::register_namespace`. The registry is used for `--explain` and for
+    /// the explanations of error codes in diagnostics.
+    fn register_error_codes(&mut self, _registry: &mut Registry) {}
     /// Called before creating the compiler instance
     fn config(&mut self, _config: &mut interface::Config) {}
     /// Called after parsing the crate root. Submodules are not yet parsed when
@@ -307,8 +312,11 @@ fn run_compiler(
 
     let sopts = config::build_session_options(&mut default_early_dcx, &matches);
 
+    let mut registry = diagnostics_registry();
+    callbacks.register_error_codes(&mut registry);
+
     if let Some(ref code) = matches.opt_str("explain") {
-        handle_explain(&default_early_dcx, diagnostics_registry(), code, sopts.color);
+        handle_explain(&default_early_dcx, &registry, code, sopts.color);
         return Ok(());
     }
 
@@ -329,7 +337,7 @@ fn run_compiler(
         register_lints: None,
         override_queries: None,
         make_codegen_backend,
-        registry: diagnostics_registry(),
+        registry,
         using_internal_features,
         expanded_args: args,
     };
@@ -367,7 +375,10 @@ fn run_compiler(
             return sess.compile_status();
         }
 
-        let early_dcx = EarlyDiagCtxt::new(sess.opts.error_format);
+        let early_dcx = EarlyDiagCtxt::with_json_array(
+            sess.opts.error_format,
+            sess.opts.unstable_opts.json_array,
+        );
 
         if print_crate_info(&early_dcx, codegen_backend, sess, has_input) == Compilation::Stop {
             return sess.compile_status();
@@ -534,11 +545,12 @@ pub enum Compilation {
     Continue,
 }
 
-fn handle_explain(early_dcx: &EarlyDiagCtxt, registry: Registry, code: &str, color: ColorConfig) {
-    let upper_cased_code = code.to_ascii_uppercase();
-    let normalised =
-        if upper_cased_code.starts_with('E') { upper_cased_code } else { format!("E{code:0>4}") };
-    match registry.try_find_description(&normalised) {
+fn handle_explain(early_dcx: &EarlyDiagCtxt, registry: &Registry, code: &str, color: ColorConfig) {
+    let description = registry
+        .normalize_code(code)
+        .ok_or(InvalidErrorCode)
+        .and_then(|code| registry.try_find_description(&code));
+    match description {
         Ok(description) => {
             let mut is_in_code_block = false;
             let mut text = String::new();
@@ -1247,9 +1259,13 @@ pub fn catch_fatal_errors<F: FnOnce() -> R, R>(f: F) -> Result<R, ErrorGuarantee
 /// Variant of `catch_fatal_errors` for the `interface::Result` return type
 /// that also computes the exit code.
 pub fn catch_with_exit_code(f: impl FnOnce() -> interface::Result<()>) -> i32 {
-    match catch_fatal_errors(f).flatten() {
-        Ok(()) => EXIT_SUCCESS,
-        Err(_) => EXIT_FAILURE,
+    let result = catch_fatal_errors(f).flatten();
+    // The session writes the JSON array of `-Zjson-array` when it finishes, which it doesn't if
+    // an early error aborted the compilation before.
+    let finished = JsonArray::stderr().finish();
+    match (result, finished) {
+        (Ok(()), Ok(())) => EXIT_SUCCESS,
+        _ => EXIT_FAILURE,
     }
 }
 
@@ -1265,30 +1281,178 @@ fn ice_path() -> &'static Option<PathBuf> {
         {
             return None;
         }
-        let mut path = match std::env::var_os("RUSTC_ICE") {
+        let value = match std::env::var_os("RUSTC_ICE") {
             Some(s) => {
                 if s == "0" {
                     // Explicitly opting out of writing ICEs to disk.
                     return None;
                 }
-                PathBuf::from(s)
+                s
             }
-            None => std::env::current_dir().unwrap_or_default(),
+            None => std::env::current_dir().unwrap_or_default().into_os_string(),
         };
+        Some(ice_file_path(&value, "txt"))
+    })
+}
+
+static ICE_JSON_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Where the machine-readable ICE report is written, if `RUSTC_ICE_JSON` is set to a directory
+/// or a template like `RUSTC_ICE`. Unlike the ICE dump, it is only written when asked for.
+fn ice_json_path() -> &'static Option<PathBuf> {
+    ICE_JSON_PATH.get_or_init(|| Some(ice_file_path(&std::env::var_os("RUSTC_ICE_JSON")?, "json")))
+}
+
+/// The path of a file that an ICE is reported to, given the value of the environment variable
+/// that configures it. This is either the directory the file is created in, or a template of the
+/// path of the file, with these placeholders:
+///
+/// - `{crate}`, the name of the crate being compiled,
+/// - `{timestamp}`, when rustc was started,
+/// - `{pid}`, the process id of rustc.
+///
+/// The directories in the path are created when the ICE is reported.
+fn ice_file_path(value: &OsStr, extension: &str) -> PathBuf {
+    let (timestamp, pid) = ice_timestamp_and_pid();
+    match value.to_str() {
+        Some(template) if template.contains('{') => PathBuf::from(
+            template
+                .replace("{crate}", &crate_name_from_args())
+                .replace("{timestamp}", timestamp)
+                .replace("{pid}", &pid.to_string()),
+        ),
+        _ => Path::new(value).join(format!("rustc-ice-{timestamp}-{pid}.{extension}")),
+    }
+}
+
+static ICE_TIMESTAMP: OnceLock<String> = OnceLock::new();
+
+/// When rustc was started, as far as the files that ICEs are reported to are concerned, and its
+/// process id.
+fn ice_timestamp_and_pid() -> (&'static str, u32) {
+    let timestamp = ICE_TIMESTAMP.get_or_init(|| {
         let now: OffsetDateTime = SystemTime::now().into();
-        let file_now = now
-            .format(
-                // Don't use a standard datetime format because Windows doesn't support `:` in paths
-                &time::format_description::parse("[year]-[month]-[day]T[hour]_[minute]_[second]")
-                    .unwrap(),
-            )
-            .unwrap_or_default();
-        let pid = std::process::id();
-        path.push(format!("rustc-ice-{file_now}-{pid}.txt"));
-        Some(path)
+        now.format(
+            // Don't use a standard datetime format because Windows doesn't support `:` in paths
+            &time::format_description::parse("[year]-[month]-[day]T[hour]_[minute]_[second]")
+                .unwrap(),
+        )
+        .unwrap_or_default()
+    });
+    (timestamp, std::process::id())
+}
+
+/// The name of the crate being compiled, as far as the command-line arguments tell, for the
+/// `{crate}` placeholder of `ice_file_path`. The session doesn't exist yet when the path is
+/// decided, as delayed bugs are written to it too.
+fn crate_name_from_args() -> String {
+    let mut args = env::args_os().skip(1).map(|arg| arg.to_string_lossy().into_owned());
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        if arg == "--crate-name" {
+            return args.next().unwrap_or_default();
+        } else if let Some(name) = arg.strip_prefix("--crate-name=") {
+            return name.to_owned();
+        } else if arg.ends_with(".rs") {
+            input = Some(arg);
+        }
+    }
+    input
+        .as_deref()
+        .and_then(|input| Path::new(input).file_stem()?.to_str())
+        .map_or_else(|| "unknown".to_owned(), |stem| stem.replace('-', "_"))
+}
+
+/// Opens a file that an ICE is reported to for appending, creating it and the directories it is in
+/// if needed.
+fn open_ice_file(path: &Path) -> io::Result<File> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    File::options().create(true).append(true).open(path)
+}
+
+/// What ICE reports contain besides the panic, its backtrace and the query stack.
+struct IceConfig {
+    /// Whether all command-line arguments are included, rather than only the compiler flags.
+    /// (`RUSTC_ICE_ARGS=full`)
+    full_args: bool,
+    /// Which environment variables are included, and whether secrets are redacted from them.
+    /// (`RUSTC_ICE_ENV=redacted` or `RUSTC_ICE_ENV=all`)
+    env: IceEnv,
+    /// How many frames of the query stack are included, if not all of them.
+    /// (`RUSTC_ICE_QUERY_DEPTH=<n>`)
+    query_stack_depth: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum IceEnv {
+    /// No environment variables are included, which is the default.
+    None,
+    /// All environment variables are included, but the values of those whose name suggests they
+    /// hold a secret, like `GITHUB_TOKEN`, are replaced with `[REDACTED]`.
+    Redacted,
+    /// All environment variables are included as they are.
+    All,
+}
+
+static ICE_CONFIG: OnceLock<IceConfig> = OnceLock::new();
+
+fn ice_config() -> &'static IceConfig {
+    ICE_CONFIG.get_or_init(|| IceConfig {
+        full_args: env::var_os("RUSTC_ICE_ARGS").is_some_and(|v| v == "full"),
+        env: match env::var("RUSTC_ICE_ENV").as_deref() {
+            Ok("redacted") => IceEnv::Redacted,
+            Ok("all") => IceEnv::All,
+            _ => IceEnv::None,
+        },
+        query_stack_depth: env::var("RUSTC_ICE_QUERY_DEPTH").ok().and_then(|v| v.parse().ok()),
     })
 }
 
+impl IceConfig {
+    /// The command-line arguments to include in ICE reports, if all of them are.
+    fn args(&self) -> Option<Vec<String>> {
+        self.full_args
+            .then(|| env::args_os().map(|arg| arg.to_string_lossy().into_owned()).collect())
+    }
+
+    /// The environment variables to include in ICE reports.
+    fn env_vars(&self) -> Vec<(String, String)> {
+        const SECRETS: &[&str] =
+            &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL", "AUTH", "KEY"];
+
+        if self.env == IceEnv::None {
+            return vec![];
+        }
+        env::vars_os()
+            .map(|(name, value)| {
+                let name = name.to_string_lossy().into_owned();
+                let upper = name.to_uppercase();
+                let value = if self.env == IceEnv::Redacted
+                    && SECRETS.iter().any(|secret| upper.contains(secret))
+                {
+                    "[REDACTED]".to_owned()
+                } else {
+                    value.to_string_lossy().into_owned()
+                };
+                (name, value)
+            })
+            .collect()
+    }
+}
+
+/// The message that a panic was started with, if it's a string.
+fn panic_message<'a>(info: &'a PanicInfo<'_>) -> &'a str {
+    match info.payload().downcast_ref::<&'static str>() {
+        Some(s) => *s,
+        None => match info.payload().downcast_ref::<String>() {
+            Some(s) => &s[..],
+            None => "Box<dyn Any>",
+        },
+    }
+}
+
 /// Installs a panic hook that will print the ICE message on unexpected panics.
 ///
 /// The hook is intended to be useable even by external tools. You can pass a custom
@@ -1344,17 +1508,11 @@ pub fn install_ice_hook(
                 eprintln!();
 
                 if let Some(ice_path) = ice_path()
-                    && let Ok(mut out) = File::options().create(true).append(true).open(&ice_path)
+                    && let Ok(mut out) = open_ice_file(ice_path)
                 {
                     // The current implementation always returns `Some`.
                     let location = info.location().unwrap();
-                    let msg = match info.payload().downcast_ref::<&'static str>() {
-                        Some(s) => *s,
-                        None => match info.payload().downcast_ref::<String>() {
-                            Some(s) => &s[..],
-                            None => "Box<dyn Any>",
-                        },
-                    };
+                    let msg = panic_message(info);
                     let thread = std::thread::current();
                     let name = thread.name().unwrap_or("<unnamed>");
                     let _ = write!(
@@ -1417,11 +1575,21 @@ fn report_ice(
 
     let file = if let Some(path) = ice_path() {
         // Create the ICE dump target file.
-        match crate::fs::File::options().create(true).append(true).open(&path) {
+        match open_ice_file(path) {
             Ok(mut file) => {
                 dcx.emit_note(session_diagnostics::IcePath { path: path.clone() });
                 if FIRST_PANIC.swap(false, Ordering::SeqCst) {
                     let _ = write!(file, "\n\nrustc version: {version}\nplatform: {triple}");
+                    if let Some(args) = ice_config().args() {
+                        let _ = write!(file, "\narguments: {}", args.join(" "));
+                    }
+                    let env_vars = ice_config().env_vars();
+                    if !env_vars.is_empty() {
+                        let _ = write!(file, "\nenvironment:");
+                        for (name, value) in env_vars {
+                            let _ = write!(file, "\n    {name}={value}");
+                        }
+                    }
                 }
                 Some(file)
             }
@@ -1450,12 +1618,29 @@ fn report_ice(
         }
     }
 
+    if let Some(path) = ice_json_path() {
+        let report = ice_json_report(info, version, triple);
+        // Each ICE is reported on its own line, as the compiler may panic more than once.
+        match open_ice_file(path).and_then(|mut file| writeln!(file, "{report}")) {
+            Ok(()) => {
+                dcx.emit_note(session_diagnostics::IceJsonPath { path: path.clone() });
+            }
+            Err(err) => {
+                dcx.emit_warn(session_diagnostics::IcePathError {
+                    path: path.clone(),
+                    error: err.to_string(),
+                    env_var: None,
+                });
+            }
+        }
+    }
+
     // If backtraces are enabled, also print the query stack
     let backtrace = env::var_os("RUST_BACKTRACE").is_some_and(|x| &x != "0");
 
     let num_frames = if backtrace { None } else { Some(2) };
 
-    interface::try_print_query_stack(&dcx, num_frames, file);
+    interface::try_print_query_stack(&dcx, num_frames, file, ice_config().query_stack_depth);
 
     // We don't trust this callback not to panic itself, so run it at the end after we're sure we've
     // printed all the relevant info.
@@ -1468,6 +1653,63 @@ fn report_ice(
     }
 }
 
+/// Describes the ICE in `info` as a JSON object, for crash reporting tools that would otherwise
+/// have to scrape the human-readable report, see `ice_json_path`.
+fn ice_json_report(info: &PanicInfo<'_>, version: &str, triple: &str) -> String {
+    // A frame has several symbols if functions were inlined into it, each of them is an entry of
+    // the backtrace like in the human-readable one.
+    let backtrace = backtrace::Backtrace::new();
+    let frames = backtrace
+        .frames()
+        .iter()
+        .flat_map(|frame| frame.symbols())
+        .map(|symbol| {
+            serde_json::json!({
+                "function": symbol.name().map(|name| name.to_string()),
+                "file": symbol.filename().map(|file| file.display().to_string()),
+                "line": symbol.lineno(),
+                "col": symbol.colno(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let delayed_bugs = info
+        .payload()
+        .downcast_ref::<rustc_errors::DelayedBugPanic>()
+        .map_or(&[][..], |panic| &panic.bugs[..])
+        .iter()
+        .map(|(message, emitted_at)| {
+            serde_json::json!({ "message": message, "emitted_at": emitted_at })
+        })
+        .collect::<Vec<_>>();
+    let flags = rustc_session::utils::extra_compiler_flags().map(|(flags, _)| flags);
+    let mut query_stack = interface::try_describe_query_stack();
+    if let Some(depth) = ice_config().query_stack_depth {
+        query_stack.truncate(depth);
+    }
+    let env_vars = ice_config().env_vars().into_iter().collect::<BTreeMap<_, _>>();
+
+    serde_json::json!({
+        "message": panic_message(info),
+        // Like the `emitted_at` of diagnostics in JSON output.
+        "location": info.location().map(|location| serde_json::json!({
+            "file": location.file(),
+            "line": location.line(),
+            "col": location.column(),
+        })),
+        "thread": std::thread::current().name(),
+        "backtrace": frames,
+        "query_stack": query_stack,
+        "delayed_bugs": delayed_bugs,
+        "rustc_version": version,
+        "host": triple,
+        "flags": flags.unwrap_or_default(),
+        "arguments": ice_config().args(),
+        "environment": env_vars,
+    })
+    .to_string()
+}
+
 /// This allows tools to enable rust logging without having to magically match rustc's
 /// tracing crate version.
 pub fn init_rustc_env_logger(early_dcx: &EarlyDiagCtxt) {
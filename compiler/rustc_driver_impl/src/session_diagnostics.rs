@@ -59,6 +59,12 @@ pub(crate) struct IcePath {
     pub path: std::path::PathBuf,
 }
 
+#[derive(Diagnostic)]
+#[diag(driver_impl_ice_json_path)]
+pub(crate) struct IceJsonPath {
+    pub path: std::path::PathBuf,
+}
+
 #[derive(Diagnostic)]
 #[diag(driver_impl_ice_path_error)]
 pub(crate) struct IcePathError {
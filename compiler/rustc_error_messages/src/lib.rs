@@ -11,16 +11,20 @@
 extern crate tracing;
 
 use fluent_bundle::FluentResource;
+use fluent_syntax::ast;
 use fluent_syntax::parser::ParserError;
 use icu_provider_adapters::fallback::{LocaleFallbackProvider, LocaleFallbacker};
+use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::sync::{IntoDynSyncSend, Lrc};
 use rustc_macros::{Decodable, Encodable};
-use rustc_span::Span;
+use rustc_span::{Span, Symbol};
 use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io;
+use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 
 #[cfg(not(parallel_compiler))]
@@ -36,17 +40,108 @@
 pub use fluent_bundle::{self, types::FluentType, FluentArgs, FluentError, FluentValue};
 pub use unic_langid::{langid, LanguageIdentifier};
 
-pub type FluentBundle =
-    IntoDynSyncSend<fluent_bundle::bundle::FluentBundle<FluentResource, IntlLangMemoizer>>;
+type InnerFluentBundle = fluent_bundle::bundle::FluentBundle<FluentResource, IntlLangMemoizer>;
 
-#[cfg(not(parallel_compiler))]
-fn new_bundle(locales: Vec<LanguageIdentifier>) -> FluentBundle {
-    IntoDynSyncSend(fluent_bundle::bundle::FluentBundle::new(locales))
+/// A Fluent bundle with the messages of a chain of locales, such as `pt-BR`, `pt`. Each message
+/// comes from the first locale of the chain that has it, and the bundle remembers which one that
+/// is, see `FluentBundle::message_locale`.
+pub struct FluentBundle {
+    bundle: IntoDynSyncSend<InnerFluentBundle>,
+    /// The locale that each message and term of the bundle comes from, if it was added with
+    /// `add_locale_resource`.
+    message_locales: FxHashMap<String, LanguageIdentifier>,
 }
 
-#[cfg(parallel_compiler)]
-fn new_bundle(locales: Vec<LanguageIdentifier>) -> FluentBundle {
-    IntoDynSyncSend(fluent_bundle::bundle::FluentBundle::new_concurrent(locales))
+impl FluentBundle {
+    #[cfg(not(parallel_compiler))]
+    pub fn new(locales: Vec<LanguageIdentifier>) -> FluentBundle {
+        FluentBundle {
+            bundle: IntoDynSyncSend(fluent_bundle::bundle::FluentBundle::new(locales)),
+            message_locales: Default::default(),
+        }
+    }
+
+    #[cfg(parallel_compiler)]
+    pub fn new(locales: Vec<LanguageIdentifier>) -> FluentBundle {
+        FluentBundle {
+            bundle: IntoDynSyncSend(fluent_bundle::bundle::FluentBundle::new_concurrent(locales)),
+            message_locales: Default::default(),
+        }
+    }
+
+    /// Returns the locale of the chain that the message `id` comes from, or `None` if the bundle
+    /// doesn't have it. Messages that weren't added for a particular locale, like those of
+    /// `-Z translate-additional-ftl`, are attributed to the first locale of the chain.
+    pub fn message_locale(&self, id: &str) -> Option<&LanguageIdentifier> {
+        if !self.has_message(id) {
+            return None;
+        }
+        self.message_locales.get(id).or_else(|| self.locales.first())
+    }
+
+    /// Adds the messages of `resource` for `locale`. Locales have to be added in the order of the
+    /// chain: messages that an earlier locale already provides are kept, but a resource
+    /// conflicting with another one of the same locale is still an error.
+    pub fn add_locale_resource(
+        &mut self,
+        resource: FluentResource,
+        locale: &LanguageIdentifier,
+    ) -> Result<(), TranslationBundleError> {
+        let ids = resource_ids(&resource);
+        if let Err(errs) = self.bundle.add_resource(resource) {
+            let errs = errs
+                .into_iter()
+                .filter(|err| match err {
+                    FluentError::Overriding { id, .. } => {
+                        self.message_locales.get(id).map_or(true, |provided| provided == locale)
+                    }
+                    _ => true,
+                })
+                .collect::<Vec<_>>();
+            if !errs.is_empty() {
+                return Err(TranslationBundleError::from(errs));
+            }
+        }
+        for id in ids {
+            self.message_locales.entry(id).or_insert_with(|| locale.clone());
+        }
+        Ok(())
+    }
+
+    /// Adds the messages of `resource`, replacing those that the bundle already has for any
+    /// locale.
+    pub fn add_overriding_resource(&mut self, resource: FluentResource) {
+        for id in resource_ids(&resource) {
+            self.message_locales.remove(&id);
+        }
+        self.bundle.add_resource_overriding(resource);
+    }
+}
+
+impl Deref for FluentBundle {
+    type Target = InnerFluentBundle;
+
+    fn deref(&self) -> &InnerFluentBundle {
+        &self.bundle
+    }
+}
+
+impl DerefMut for FluentBundle {
+    fn deref_mut(&mut self) -> &mut InnerFluentBundle {
+        &mut self.bundle
+    }
+}
+
+/// The identifiers of the messages and terms of `resource`, which share a namespace in bundles.
+fn resource_ids(resource: &FluentResource) -> Vec<String> {
+    resource
+        .entries()
+        .filter_map(|entry| match entry {
+            ast::Entry::Message(ast::Message { id, .. })
+            | ast::Entry::Term(ast::Term { id, .. }) => Some(id.name.to_owned()),
+            _ => None,
+        })
+        .collect()
 }
 
 #[derive(Debug)]
@@ -118,33 +213,42 @@ fn from(mut errs: Vec<FluentError>) -> Self {
 }
 
 /// Returns Fluent bundle with the user's locale resources from
-/// `$sysroot/share/locale/$requested_locale/*.ftl`.
+/// `$sysroot/share/locale/$requested_locale/*.ftl`, for each of the requested locales in order.
+/// Messages are taken from the first locale that has them, the fallback bundle is used for
+/// those that none of the locales have. Locales are skipped if they aren't in the sysroot, as
+/// long as one of them is.
 ///
 /// If `-Z additional-ftl-path` was provided, load that resource and add it  to the bundle
 /// (overriding any conflicting messages).
 #[instrument(level = "trace")]
 pub fn fluent_bundle(
-    mut user_provided_sysroot: Option<PathBuf>,
-    mut sysroot_candidates: Vec<PathBuf>,
-    requested_locale: Option<LanguageIdentifier>,
+    user_provided_sysroot: Option<PathBuf>,
+    sysroot_candidates: Vec<PathBuf>,
+    mut requested_locales: Vec<LanguageIdentifier>,
     additional_ftl_path: Option<&Path>,
     with_directionality_markers: bool,
+    pseudo_localize: bool,
 ) -> Result<Option<Lrc<FluentBundle>>, TranslationBundleError> {
-    if requested_locale.is_none() && additional_ftl_path.is_none() {
-        return Ok(None);
-    }
-
+    // The fallback bundle has every message, so locales after the fallback locale are never
+    // used.
     let fallback_locale = langid!("en-US");
-    let requested_fallback_locale = requested_locale.as_ref() == Some(&fallback_locale);
-    trace!(?requested_fallback_locale);
-    if requested_fallback_locale && additional_ftl_path.is_none() {
+    if let Some(fallback) = requested_locales.iter().position(|l| *l == fallback_locale) {
+        requested_locales.truncate(fallback);
+    }
+    trace!(?requested_locales);
+    if requested_locales.is_empty() && additional_ftl_path.is_none() {
         return Ok(None);
     }
+
     // If there is only `-Z additional-ftl-path`, assume locale is "en-US", otherwise use user
-    // provided locale.
-    let locale = requested_locale.clone().unwrap_or(fallback_locale);
-    trace!(?locale);
-    let mut bundle = new_bundle(vec![locale]);
+    // provided locales.
+    let locales = if requested_locales.is_empty() {
+        vec![fallback_locale]
+    } else {
+        requested_locales.clone()
+    };
+    trace!(?locales);
+    let mut bundle = FluentBundle::new(locales);
 
     // Add convenience functions available to ftl authors.
     register_functions(&mut bundle);
@@ -155,14 +259,17 @@ pub fn fluent_bundle(
     // may be worth investigating in future (for example: if type names are left-to-right and the
     // surrounding diagnostic messages are right-to-left, then these might be helpful).
     bundle.set_use_isolating(with_directionality_markers);
+    // Pseudo-localization replaces the number formatting, as it brackets all arguments.
+    localize_numbers(&mut bundle);
+    if pseudo_localize {
+        pseudo_localize_bundle(&mut bundle);
+    }
 
-    // If the user requests the default locale then don't try to load anything.
-    if let Some(requested_locale) = requested_locale {
-        let mut found_resources = false;
-        for sysroot in user_provided_sysroot.iter_mut().chain(sysroot_candidates.iter_mut()) {
-            sysroot.push("share");
-            sysroot.push("locale");
-            sysroot.push(requested_locale.to_string());
+    // If the user only requests the default locale then don't try to load anything.
+    let mut found_resources = false;
+    for requested_locale in &requested_locales {
+        for sysroot in user_provided_sysroot.iter().chain(sysroot_candidates.iter()) {
+            let sysroot = sysroot.join("share").join("locale").join(requested_locale.to_string());
             trace!(?sysroot);
 
             if !sysroot.exists() {
@@ -188,14 +295,14 @@ pub fn fluent_bundle(
                 let resource =
                     FluentResource::try_new(resource_str).map_err(TranslationBundleError::from)?;
                 trace!(?resource);
-                bundle.add_resource(resource).map_err(TranslationBundleError::from)?;
+                bundle.add_locale_resource(resource, requested_locale)?;
                 found_resources = true;
             }
         }
+    }
 
-        if !found_resources {
-            return Err(TranslationBundleError::MissingLocale);
-        }
+    if !requested_locales.is_empty() && !found_resources {
+        return Err(TranslationBundleError::MissingLocale);
     }
 
     if let Some(additional_ftl_path) = additional_ftl_path {
@@ -204,7 +311,7 @@ pub fn fluent_bundle(
         let resource =
             FluentResource::try_new(resource_str).map_err(TranslationBundleError::from)?;
         trace!(?resource);
-        bundle.add_resource_overriding(resource);
+        bundle.add_overriding_resource(resource);
     }
 
     let bundle = Lrc::new(bundle);
@@ -218,6 +325,36 @@ fn register_functions(bundle: &mut FluentBundle) {
             _ => FluentValue::Error,
         })
         .expect("Failed to add a function to the bundle.");
+
+    // `NUMBER($n, type: "ordinal")` selects on the ordinal plural categories of `$n` instead of
+    // the cardinal ones, e.g. `one` for the "1st" and "21st" of English, `few` for the "3rd".
+    bundle
+        .add_function("NUMBER", |positional, named| match positional {
+            [FluentValue::Number(number)] => {
+                let mut number = number.clone();
+                number.options.merge(named);
+                FluentValue::Number(number)
+            }
+            _ => FluentValue::Error,
+        })
+        .expect("Failed to add a function to the bundle.");
+
+    // `RANGE($n, low, high)` selects `below`, `within` or `above` depending on where `$n` is
+    // relative to the inclusive range, for messages that vary by more than plural categories.
+    bundle
+        .add_function("RANGE", |positional, _named| match positional {
+            [FluentValue::Number(n), FluentValue::Number(low), FluentValue::Number(high)] => {
+                if n.value < low.value {
+                    "below".into()
+                } else if n.value > high.value {
+                    "above".into()
+                } else {
+                    "within".into()
+                }
+            }
+            _ => FluentValue::Error,
+        })
+        .expect("Failed to add a function to the bundle.");
 }
 
 /// Type alias for the result of `fallback_fluent_bundle` - a reference-counted pointer to a lazily
@@ -225,18 +362,31 @@ fn register_functions(bundle: &mut FluentBundle) {
 pub type LazyFallbackBundle = Lrc<Lazy<FluentBundle, impl FnOnce() -> FluentBundle>>;
 
 /// Return the default `FluentBundle` with standard "en-US" diagnostic messages.
-#[instrument(level = "trace", skip(resources))]
 pub fn fallback_fluent_bundle(
     resources: Vec<&'static str>,
     with_directionality_markers: bool,
+) -> LazyFallbackBundle {
+    fallback_fluent_bundle_with_options(resources, with_directionality_markers, false)
+}
+
+/// Like `fallback_fluent_bundle`, but the bundle can also be pseudo-localized, see
+/// `pseudo_localize_bundle`.
+#[instrument(level = "trace", skip(resources))]
+pub fn fallback_fluent_bundle_with_options(
+    resources: Vec<&'static str>,
+    with_directionality_markers: bool,
+    pseudo_localize: bool,
 ) -> LazyFallbackBundle {
     Lrc::new(Lazy::new(move || {
-        let mut fallback_bundle = new_bundle(vec![langid!("en-US")]);
+        let mut fallback_bundle = FluentBundle::new(vec![langid!("en-US")]);
 
         register_functions(&mut fallback_bundle);
 
         // See comment in `fluent_bundle`.
         fallback_bundle.set_use_isolating(with_directionality_markers);
+        if pseudo_localize {
+            pseudo_localize_bundle(&mut fallback_bundle);
+        }
 
         for resource in resources {
             let resource = FluentResource::try_new(resource.to_string())
@@ -248,6 +398,96 @@ pub fn fallback_fluent_bundle(
     }))
 }
 
+/// Makes `bundle` pseudo-localize its messages for `-Ztranslate-pseudo`: the letters of their
+/// text are accented and their vowels doubled, and their arguments put between brackets. This
+/// shows text that doesn't come from the bundle, arguments glued together into sentences and
+/// output that only works with messages as short as the English ones.
+pub fn pseudo_localize_bundle(bundle: &mut FluentBundle) {
+    bundle.set_transform(Some(pseudo_localize));
+    bundle.set_formatter(Some(bracket_arg));
+}
+
+fn pseudo_localize(text: &str) -> Cow<'_, str> {
+    const UPPERCASE: &str = "ȦƁƇḒḖƑƓĦĪĴĶĿḾȠǾƤɊŘŞŦŬṼẆẊẎẐ";
+    const LOWERCASE: &str = "ȧƀƈḓḗƒɠħīĵķŀḿƞǿƥɋřşŧŭṽẇẋẏẑ";
+
+    let mut localized = String::with_capacity(text.len() * 2);
+    for c in text.chars() {
+        let accented = match c {
+            'A'..='Z' => UPPERCASE.chars().nth(usize::from(c as u8 - b'A')).unwrap_or(c),
+            'a'..='z' => LOWERCASE.chars().nth(usize::from(c as u8 - b'a')).unwrap_or(c),
+            _ => c,
+        };
+        localized.push(accented);
+        if matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u') {
+            localized.push(accented);
+        }
+    }
+    Cow::Owned(localized)
+}
+
+fn bracket_arg<M>(value: &FluentValue<'_>, _memoizer: &M) -> Option<String> {
+    match value {
+        FluentValue::String(s) => Some(format!("[{s}]")),
+        FluentValue::Number(n) => Some(format!("[{}]", n.as_string())),
+        _ => None,
+    }
+}
+
+/// Makes `bundle` group the digits of integer arguments the way its locale does, e.g.
+/// `1,048,576` in English and `1.048.576` in German. Numbers that are code rather than prose
+/// can be left as they are with `NUMBER($n, useGrouping: "false")`.
+pub fn localize_numbers(bundle: &mut FluentBundle) {
+    bundle.set_formatter(Some(group_digits));
+}
+
+fn group_digits(value: &FluentValue<'_>, memoizer: &IntlLangMemoizer) -> Option<String> {
+    let FluentValue::Number(number) = value else { return None };
+    let options = &number.options;
+    if !options.use_grouping
+        || options.minimum_integer_digits.is_some()
+        || options.minimum_fraction_digits.is_some()
+        || number.value.fract() != 0.0
+        || number.value.abs() < 1000.0
+    {
+        return None;
+    }
+
+    let separator = memoizer.with_try_get::<DigitGroupSeparator, _, _>((), |s| s.0).ok()?;
+    let digits = number.value.abs().to_string();
+    let mut grouped = String::with_capacity(digits.len() * 2);
+    if number.value < 0.0 {
+        grouped.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    Some(grouped)
+}
+
+/// The separator between groups of three digits of a locale, see `localize_numbers`.
+struct DigitGroupSeparator(&'static str);
+
+impl intl_memoizer::Memoizable for DigitGroupSeparator {
+    type Args = ();
+    type Error = ();
+
+    fn construct(lang: LanguageIdentifier, _args: ()) -> Result<Self, ()> {
+        let separator = match lang.language.as_str() {
+            "da" | "de" | "el" | "es" | "id" | "it" | "nl" | "pt" | "ro" | "sl" | "sr" | "tr"
+            | "vi" => ".",
+            // A narrow no-break space.
+            "bg" | "cs" | "et" | "fi" | "fr" | "hu" | "lt" | "lv" | "nb" | "pl" | "ru" | "sk"
+            | "sv" | "uk" => "\u{202f}",
+            _ => ",",
+        };
+        Ok(DigitGroupSeparator(separator))
+    }
+}
+
 /// Identifier for the Fluent message/attribute corresponding to a diagnostic message.
 type FluentId = Cow<'static, str>;
 
@@ -258,6 +498,7 @@ pub fn fallback_fluent_bundle(
 /// message so messages of this type must be combined with a `DiagnosticMessage` (using
 /// `DiagnosticMessage::with_subdiagnostic_message`) before rendering. However, subdiagnostics from
 /// the `Subdiagnostic` derive refer to Fluent identifiers directly.
+#[derive(Clone)]
 #[rustc_diagnostic_item = "SubdiagnosticMessage"]
 pub enum SubdiagnosticMessage {
     /// Non-translatable diagnostic message.
@@ -411,6 +652,144 @@ fn into(self) -> SubdiagnosticMessage {
     }
 }
 
+/// Name of a diagnostic argument.
+pub type DiagnosticArgName<'source> = Cow<'source, str>;
+
+/// Simplified version of `FluentValue` that can implement `Encodable` and `Decodable`. Converted
+/// to a `FluentValue` by the emitter to be used in diagnostic translation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Encodable, Decodable)]
+pub enum DiagnosticArgValue<'source> {
+    Str(Cow<'source, str>),
+    /// An interned string, which avoids allocating a `String` for the argument unless the
+    /// diagnostic is rendered. Rendered like an identifier, i.e. with an `r#` prefix if needed.
+    Symbol(Symbol),
+    /// An integer, which Fluent can select on. Translated messages group its digits the way
+    /// their locale does, numbers that are code should be added as strings instead.
+    Number(i128),
+    Float(DiagnosticFloat),
+    StrListSepByAnd(Vec<Cow<'source, str>>),
+    /// Like `StrListSepByAnd`, but only the given number of elements are rendered, followed by
+    /// "and N more". The JSON output still contains the full list.
+    StrListSepByAndCapped(Vec<Cow<'source, str>>, usize),
+    StrListSepByOr(Vec<Cow<'source, str>>),
+    /// A list joined with the given separator, which is used as is, regardless of the locale.
+    StrListSepByCustom(Vec<Cow<'source, str>>, Cow<'source, str>),
+    /// Machine-readable facts for tools, which the JSON output contains as is in its `args`
+    /// field. In messages, it is rendered flattened, see `StructuredArg`'s `Display` impl.
+    Structured(StructuredArg),
+    /// The source code that a span points to, trimmed and quoted with backticks. It is looked up
+    /// when the diagnostic is emitted, and rendered as `UNAVAILABLE_SNIPPET` if the code isn't
+    /// available.
+    Snippet(Span),
+}
+
+/// How a `DiagnosticArgValue::Snippet` is rendered if its code can't be looked up.
+pub const UNAVAILABLE_SNIPPET: &str = "`...`";
+
+impl<'source> DiagnosticArgValue<'source> {
+    /// Removes the cap of a capped list, for outputs that have room for the full list.
+    pub fn uncapped(self) -> Self {
+        match self {
+            DiagnosticArgValue::StrListSepByAndCapped(l, _) => {
+                DiagnosticArgValue::StrListSepByAnd(l)
+            }
+            value => value,
+        }
+    }
+}
+
+/// A JSON-like value, see `DiagnosticArgValue::Structured`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Encodable, Decodable)]
+pub enum StructuredArg {
+    Str(String),
+    Number(i128),
+    Bool(bool),
+    List(Vec<StructuredArg>),
+    /// Entries are kept in the order they were added.
+    Map(Vec<(String, StructuredArg)>),
+}
+
+impl fmt::Display for StructuredArg {
+    /// Renders lists as `a, b` and maps as `key: value, key: value`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructuredArg::Str(s) => f.write_str(s),
+            StructuredArg::Number(n) => write!(f, "{n}"),
+            StructuredArg::Bool(b) => write!(f, "{b}"),
+            StructuredArg::List(l) => {
+                for (i, value) in l.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                Ok(())
+            }
+            StructuredArg::Map(m) => {
+                for (i, (key, value)) in m.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key}: {value}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The largest magnitude of the integers that `FluentNumber` represents exactly, as it is backed
+/// by a `f64`. Larger numbers are passed to Fluent as strings.
+pub const MAX_FLUENT_INTEGER: u128 = 1 << f64::MANTISSA_DIGITS;
+
+/// A floating point `DiagnosticArgValue`. It is compared and hashed by its bits so that
+/// `DiagnosticArgValue` can implement `Eq` and `Hash`.
+#[derive(Clone, Copy, Debug, Encodable, Decodable)]
+pub struct DiagnosticFloat(pub f64);
+
+impl PartialEq for DiagnosticFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for DiagnosticFloat {}
+
+impl Hash for DiagnosticFloat {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
+
+impl<'source> Into<FluentValue<'source>> for DiagnosticArgValue<'source> {
+    fn into(self) -> FluentValue<'source> {
+        match self {
+            DiagnosticArgValue::Str(s) => From::from(s),
+            DiagnosticArgValue::Symbol(s) => From::from(s.to_ident_string()),
+            DiagnosticArgValue::Number(n) if n.unsigned_abs() <= MAX_FLUENT_INTEGER => {
+                From::from(n)
+            }
+            DiagnosticArgValue::Number(n) => From::from(n.to_string()),
+            DiagnosticArgValue::Float(f) => From::from(f.0),
+            DiagnosticArgValue::StrListSepByAnd(l) => fluent_value_from_str_list_sep_by_and(l),
+            DiagnosticArgValue::StrListSepByAndCapped(l, cap) if l.len() > cap => {
+                let more = l.len() - cap;
+                let list = l.into_iter().take(cap).map(Cow::into_owned).collect();
+                FluentValue::Custom(Box::new(CappedStrList { list, more }))
+            }
+            DiagnosticArgValue::StrListSepByAndCapped(l, _) => {
+                fluent_value_from_str_list_sep_by_and(l)
+            }
+            DiagnosticArgValue::StrListSepByOr(l) => fluent_value_from_str_list_sep_by_or(l),
+            DiagnosticArgValue::StrListSepByCustom(l, sep) => From::from(l.join(&*sep)),
+            DiagnosticArgValue::Structured(s) => From::from(s.to_string()),
+            // Snippets are replaced by their code when the diagnostic is emitted, so this is only
+            // reached if there is no source map.
+            DiagnosticArgValue::Snippet(_) => From::from(UNAVAILABLE_SNIPPET),
+        }
+    }
+}
+
 /// A span together with some additional data.
 #[derive(Clone, Debug)]
 pub struct SpanLabel {
@@ -423,8 +802,14 @@ pub struct SpanLabel {
 
     /// What label should we attach to this span (if any)?
     pub label: Option<DiagnosticMessage>,
+
+    /// Arguments that only the label uses, in addition to those of its diagnostic.
+    pub args: SpanLabelArgs,
 }
 
+/// The arguments of a span label, see `MultiSpan::push_span_label_with_args`.
+pub type SpanLabelArgs = Vec<(DiagnosticArgName<'static>, DiagnosticArgValue<'static>)>;
+
 /// A collection of `Span`s.
 ///
 /// Spans have two orthogonal attributes:
@@ -436,7 +821,7 @@ pub struct SpanLabel {
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Encodable, Decodable)]
 pub struct MultiSpan {
     primary_spans: Vec<Span>,
-    span_labels: Vec<(Span, DiagnosticMessage)>,
+    span_labels: Vec<(Span, DiagnosticMessage, SpanLabelArgs)>,
 }
 
 impl MultiSpan {
@@ -455,7 +840,19 @@ pub fn from_spans(mut vec: Vec<Span>) -> MultiSpan {
     }
 
     pub fn push_span_label(&mut self, span: Span, label: impl Into<DiagnosticMessage>) {
-        self.span_labels.push((span, label.into()));
+        self.span_labels.push((span, label.into(), Vec::new()));
+    }
+
+    /// Like `push_span_label`, but with arguments that only the label uses. They take precedence
+    /// over arguments of the diagnostic with the same name, so that several labels can use the
+    /// same message with different values.
+    pub fn push_span_label_with_args(
+        &mut self,
+        span: Span,
+        label: impl Into<DiagnosticMessage>,
+        args: SpanLabelArgs,
+    ) {
+        self.span_labels.push((span, label.into(), args));
     }
 
     /// Selects the first primary span (if any).
@@ -498,7 +895,12 @@ pub fn replace(&mut self, before: Span, after: Span) -> bool {
     }
 
     pub fn pop_span_label(&mut self) -> Option<(Span, DiagnosticMessage)> {
-        self.span_labels.pop()
+        self.span_labels.pop().map(|(span, label, _)| (span, label))
+    }
+
+    /// Returns the messages of the span labels, e.g. to translate them eagerly.
+    pub fn span_label_messages_mut(&mut self) -> impl Iterator<Item = &mut DiagnosticMessage> {
+        self.span_labels.iter_mut().map(|(_, msg, _)| msg)
     }
 
     /// Returns the strings to highlight. We always ensure that there
@@ -512,16 +914,22 @@ pub fn span_labels(&self) -> Vec<SpanLabel> {
         let mut span_labels = self
             .span_labels
             .iter()
-            .map(|&(span, ref label)| SpanLabel {
+            .map(|&(span, ref label, ref args)| SpanLabel {
                 span,
                 is_primary: is_primary(span),
                 label: Some(label.clone()),
+                args: args.clone(),
             })
             .collect::<Vec<_>>();
 
         for &span in &self.primary_spans {
             if !span_labels.iter().any(|sl| sl.span == span) {
-                span_labels.push(SpanLabel { span, is_primary: true, label: None });
+                span_labels.push(SpanLabel {
+                    span,
+                    is_primary: true,
+                    label: None,
+                    args: Vec::new(),
+                });
             }
         }
 
@@ -530,7 +938,7 @@ pub fn span_labels(&self) -> Vec<SpanLabel> {
 
     /// Returns `true` if any of the span labels is displayable.
     pub fn has_span_labels(&self) -> bool {
-        self.span_labels.iter().any(|(sp, _)| !sp.is_dummy())
+        self.span_labels.iter().any(|(sp, _, _)| !sp.is_dummy())
     }
 
     /// Clone this `MultiSpan` without keeping any of the span labels - sometimes a `MultiSpan` is
@@ -559,19 +967,38 @@ fn icu_locale_from_unic_langid(lang: LanguageIdentifier) -> Option<icu_locid::Lo
 }
 
 pub fn fluent_value_from_str_list_sep_by_and(l: Vec<Cow<'_, str>>) -> FluentValue<'_> {
+    fluent_value_from_str_list(l, ListKind::And)
+}
+
+/// Like [`fluent_value_from_str_list_sep_by_and`], but for lists of alternatives, e.g. "a, b, or
+/// c".
+///
+/// `rustc_baked_icu_data` doesn't contain the patterns for "or" lists yet, so these are always
+/// formatted in English, even in bundles for other locales.
+pub fn fluent_value_from_str_list_sep_by_or(l: Vec<Cow<'_, str>>) -> FluentValue<'_> {
+    fluent_value_from_str_list(l, ListKind::Or)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum ListKind {
+    And,
+    Or,
+}
+
+fn fluent_value_from_str_list(l: Vec<Cow<'_, str>>, kind: ListKind) -> FluentValue<'_> {
     // Fluent requires 'static value here for its AnyEq usages.
     #[derive(Clone, PartialEq, Debug)]
-    struct FluentStrListSepByAnd(Vec<String>);
+    struct FluentStrList(Vec<String>, ListKind);
 
-    impl FluentType for FluentStrListSepByAnd {
+    impl FluentType for FluentStrList {
         fn duplicate(&self) -> Box<dyn FluentType + Send> {
             Box::new(self.clone())
         }
 
         fn as_string(&self, intls: &intl_memoizer::IntlLangMemoizer) -> Cow<'static, str> {
             let result = intls
-                .with_try_get::<MemoizableListFormatter, _, _>((), |list_formatter| {
-                    list_formatter.format_to_string(self.0.iter())
+                .with_try_get::<MemoizableListFormatter, _, _>(self.1, |list_formatter| {
+                    list_formatter.format(&self.0)
                 })
                 .unwrap();
             Cow::Owned(result)
@@ -591,28 +1018,36 @@ fn as_string_threadsafe(
             intls: &intl_memoizer::concurrent::IntlLangMemoizer,
         ) -> Cow<'static, str> {
             let result = intls
-                .with_try_get::<MemoizableListFormatter, _, _>((), |list_formatter| {
-                    list_formatter.format_to_string(self.0.iter())
+                .with_try_get::<MemoizableListFormatter, _, _>(self.1, |list_formatter| {
+                    list_formatter.format(&self.0)
                 })
                 .unwrap();
             Cow::Owned(result)
         }
     }
 
-    struct MemoizableListFormatter(icu_list::ListFormatter);
-
-    impl std::ops::Deref for MemoizableListFormatter {
-        type Target = icu_list::ListFormatter;
-        fn deref(&self) -> &Self::Target {
-            &self.0
+    /// The baked ICU data only contains "and" lists, so "or" lists fall back to English if the
+    /// data is missing, rather than failing to render the diagnostic. Once `list/or@1` is added
+    /// to `rustc_baked_icu_data`, the fallback is only used for locales without data.
+    struct MemoizableListFormatter(Option<icu_list::ListFormatter>);
+
+    impl MemoizableListFormatter {
+        fn format(&self, l: &[String]) -> String {
+            match (&self.0, l) {
+                (Some(list_formatter), _) => list_formatter.format_to_string(l.iter()),
+                (None, []) => String::new(),
+                (None, [a]) => a.clone(),
+                (None, [a, b]) => format!("{a} or {b}"),
+                (None, [init @ .., last]) => format!("{}, or {last}", init.join(", ")),
+            }
         }
     }
 
     impl intl_memoizer::Memoizable for MemoizableListFormatter {
-        type Args = ();
+        type Args = ListKind;
         type Error = ();
 
-        fn construct(lang: LanguageIdentifier, _args: Self::Args) -> Result<Self, Self::Error>
+        fn construct(lang: LanguageIdentifier, kind: Self::Args) -> Result<Self, Self::Error>
         where
             Self: Sized,
         {
@@ -624,13 +1059,22 @@ fn construct(lang: LanguageIdentifier, _args: Self::Args) -> Result<Self, Self::
                 LocaleFallbackProvider::new_with_fallbacker(baked_data_provider, locale_fallbacker);
             let locale = icu_locale_from_unic_langid(lang)
                 .unwrap_or_else(|| rustc_baked_icu_data::supported_locales::EN);
-            let list_formatter =
-                icu_list::ListFormatter::try_new_and_with_length_with_any_provider(
+            let list_formatter = match kind {
+                ListKind::And => Some(
+                    icu_list::ListFormatter::try_new_and_with_length_with_any_provider(
+                        &data_provider,
+                        &locale.into(),
+                        icu_list::ListLength::Wide,
+                    )
+                    .expect("Failed to create list formatter"),
+                ),
+                ListKind::Or => icu_list::ListFormatter::try_new_or_with_length_with_any_provider(
                     &data_provider,
                     &locale.into(),
                     icu_list::ListLength::Wide,
                 )
-                .expect("Failed to create list formatter");
+                .ok(),
+            };
 
             Ok(MemoizableListFormatter(list_formatter))
         }
@@ -638,5 +1082,53 @@ fn construct(lang: LanguageIdentifier, _args: Self::Args) -> Result<Self, Self::
 
     let l = l.into_iter().map(|x| x.into_owned()).collect();
 
-    FluentValue::Custom(Box::new(FluentStrListSepByAnd(l)))
+    FluentValue::Custom(Box::new(FluentStrList(l, kind)))
+}
+
+/// A list that is rendered like "a, b and 3 more", see `DiagnosticArgValue::StrListSepByAndCapped`.
+/// "3 more" is a message of its own, `errors_list_more`, which the list can't translate by itself,
+/// so it has to be resolved with `CappedStrList::resolve` before it is formatted.
+#[derive(Clone, PartialEq, Debug)]
+pub struct CappedStrList {
+    pub list: Vec<String>,
+    /// The number of elements that were left out.
+    pub more: usize,
+}
+
+impl CappedStrList {
+    /// Formats the list, with `more`, the translation of `errors_list_more`, as its last element.
+    pub fn resolve(&self, more: Cow<'_, str>) -> FluentValue<'static> {
+        let mut l: Vec<Cow<'static, str>> =
+            self.list.iter().map(|s| Cow::Owned(s.clone())).collect();
+        l.push(Cow::Owned(more.into_owned()));
+        fluent_value_from_str_list_sep_by_and(l)
+    }
+
+    /// The list if it wasn't resolved, with an untranslated ellipsis in place of "3 more".
+    fn unresolved(&self) -> FluentValue<'static> {
+        self.resolve(Cow::Borrowed("…"))
+    }
+}
+
+impl FluentType for CappedStrList {
+    fn duplicate(&self) -> Box<dyn FluentType + Send> {
+        Box::new(self.clone())
+    }
+
+    fn as_string(&self, intls: &intl_memoizer::IntlLangMemoizer) -> Cow<'static, str> {
+        match self.unresolved() {
+            FluentValue::Custom(list) => list.as_string(intls),
+            _ => unreachable!(),
+        }
+    }
+
+    fn as_string_threadsafe(
+        &self,
+        intls: &intl_memoizer::concurrent::IntlLangMemoizer,
+    ) -> Cow<'static, str> {
+        match self.unresolved() {
+            FluentValue::Custom(list) => list.as_string_threadsafe(intls),
+            _ => unreachable!(),
+        }
+    }
 }
@@ -1,9 +1,12 @@
+errors_confusable_char =
+    Unicode character {$found} (U+{$codepoint}) looks like {$expected}, but it is not
+
 errors_delayed_at_with_newline =
     delayed at {$emitted_at}
     {$note}
 
 errors_delayed_at_without_newline =
-    delayed at {$emitted_at} - {$note}
+    delayed at {$emitted_at}
 
 errors_expected_lifetime_parameter =
     expected lifetime {$count ->
@@ -20,6 +23,12 @@ errors_indicate_anonymous_lifetime =
 errors_invalid_flushed_delayed_diagnostic_level =
     `flushed_delayed` got diagnostic with level {$level}, instead of the expected `DelayedBug`
 
+errors_list_more =
+    {$count} more
+
+errors_similar_name =
+    {$kind} with a similar name exists
+
 errors_target_inconsistent_architecture =
     inconsistent target specification: "data-layout" claims architecture is {$dl}-endian, while "target-endian" is `{$target}`
 
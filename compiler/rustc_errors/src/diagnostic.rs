@@ -1,20 +1,34 @@
+use crate::interner::DiagnosticInterner;
 use crate::snippet::Style;
+use crate::source_provider::SourceProvider;
+use crate::translation::{to_fluent_args, Translate};
 use crate::{
-    CodeSuggestion, DelayedBugKind, DiagnosticBuilder, DiagnosticMessage, EmissionGuarantee, Level,
-    MultiSpan, SubdiagnosticMessage, Substitution, SubstitutionPart, SuggestionStyle,
+    CodeSuggestion, DelayedBugKind, DiagnosticBuilder, DiagnosticMessage, EmissionGuarantee,
+    FileSuggestion, Level, MultiSpan, SubdiagnosticMessage, Substitution, SubstitutionPart,
+    SuggestionStyle,
 };
-use rustc_data_structures::fx::{FxHashMap, FxIndexMap};
-use rustc_error_messages::fluent_value_from_str_list_sep_by_and;
-use rustc_error_messages::FluentValue;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet, FxIndexMap};
+use rustc_data_structures::stable_hasher::{Hash128, StableHasher};
+use rustc_data_structures::sync::{DynSend, DynSync, Lrc, OnceLock};
+use rustc_error_messages::UNAVAILABLE_SNIPPET;
 use rustc_lint_defs::{Applicability, LintExpectationId};
+use rustc_serialize::{Decodable, Encodable};
 use rustc_span::symbol::Symbol;
-use rustc_span::{Span, DUMMY_SP};
+use rustc_span::{Span, SpanDecoder, SpanEncoder, DUMMY_SP};
+use serde::Serialize;
 use std::borrow::Cow;
+use std::error::Report;
 use std::fmt::{self, Debug};
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::panic::Location;
+use std::path::PathBuf;
 
-/// Error type for `Diagnostic`'s `suggestions` field, indicating that
+pub use rustc_error_messages::{
+    DiagnosticArgName, DiagnosticArgValue, DiagnosticFloat, StructuredArg,
+};
+
+/// Error type for `DiagnosticExtras`'s `suggestions` field, indicating that
 /// `.disable_suggestions()` was called on the `Diagnostic`.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Encodable, Decodable)]
 pub struct SuggestionsDisabled;
@@ -25,16 +39,49 @@
 pub type DiagnosticArg<'iter, 'source> =
     (&'iter DiagnosticArgName<'source>, &'iter DiagnosticArgValue<'source>);
 
-/// Name of a diagnostic argument.
-pub type DiagnosticArgName<'source> = Cow<'source, str>;
+/// A diagnostic argument that is only computed when it is first used, see `Diagnostic::arg_lazy`.
+/// Clones share the computed value.
+#[derive(Clone)]
+pub struct LazyDiagnosticArg {
+    compute: Lrc<dyn Fn() -> DiagnosticArgValue<'static> + DynSend + DynSync>,
+    value: Lrc<OnceLock<DiagnosticArgValue<'static>>>,
+}
 
-/// Simplified version of `FluentValue` that can implement `Encodable` and `Decodable`. Converted
-/// to a `FluentValue` by the emitter to be used in diagnostic translation.
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Encodable, Decodable)]
-pub enum DiagnosticArgValue<'source> {
-    Str(Cow<'source, str>),
-    Number(i128),
-    StrListSepByAnd(Vec<Cow<'source, str>>),
+impl LazyDiagnosticArg {
+    pub fn new<T: IntoDiagnosticArg>(f: impl Fn() -> T + DynSend + DynSync + 'static) -> Self {
+        LazyDiagnosticArg {
+            compute: Lrc::new(move || f().into_diagnostic_arg()),
+            value: Lrc::new(OnceLock::new()),
+        }
+    }
+
+    /// Returns the value of the argument, computing it if this is the first use.
+    pub fn value(&self) -> &DiagnosticArgValue<'static> {
+        self.value.get_or_init(|| (self.compute)())
+    }
+}
+
+impl Debug for LazyDiagnosticArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't compute the value just for debug output.
+        match self.value.get() {
+            Some(value) => f.debug_tuple("LazyDiagnosticArg").field(value).finish(),
+            None => f.write_str("LazyDiagnosticArg(<not computed>)"),
+        }
+    }
+}
+
+impl<S: SpanEncoder> Encodable<S> for LazyDiagnosticArg {
+    fn encode(&self, s: &mut S) {
+        self.value().encode(s)
+    }
+}
+
+impl<D: SpanDecoder> Decodable<D> for LazyDiagnosticArg {
+    fn decode(d: &mut D) -> Self {
+        let value = DiagnosticArgValue::decode(d);
+        LazyDiagnosticArg::new(move || value.clone())
+    }
 }
 
 /// Converts a value of a type into a `DiagnosticArg` (typically a field of an `IntoDiagnostic`
@@ -45,24 +92,47 @@ pub trait IntoDiagnosticArg {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static>;
 }
 
+/// Converts a borrowed value into a `DiagnosticArg`, which makes references to it arguments, too.
+/// Unlike a conversion that clones the value first, this only reads what is shown, so large
+/// values, like those shown with their `Display` impl, aren't copied just to be formatted.
+pub trait AsDiagnosticArg {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static>;
+}
+
+impl<'source> AsDiagnosticArg for DiagnosticArgValue<'source> {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        // The borrowed strings of the value are copied either way.
+        self.clone().into_diagnostic_arg()
+    }
+}
+
 impl<'source> IntoDiagnosticArg for DiagnosticArgValue<'source> {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
         match self {
             DiagnosticArgValue::Str(s) => DiagnosticArgValue::Str(Cow::Owned(s.into_owned())),
+            DiagnosticArgValue::Symbol(s) => DiagnosticArgValue::Symbol(s),
             DiagnosticArgValue::Number(n) => DiagnosticArgValue::Number(n),
+            DiagnosticArgValue::Float(f) => DiagnosticArgValue::Float(f),
             DiagnosticArgValue::StrListSepByAnd(l) => DiagnosticArgValue::StrListSepByAnd(
                 l.into_iter().map(|s| Cow::Owned(s.into_owned())).collect(),
             ),
-        }
-    }
-}
-
-impl<'source> Into<FluentValue<'source>> for DiagnosticArgValue<'source> {
-    fn into(self) -> FluentValue<'source> {
-        match self {
-            DiagnosticArgValue::Str(s) => From::from(s),
-            DiagnosticArgValue::Number(n) => From::from(n),
-            DiagnosticArgValue::StrListSepByAnd(l) => fluent_value_from_str_list_sep_by_and(l),
+            DiagnosticArgValue::StrListSepByAndCapped(l, cap) => {
+                DiagnosticArgValue::StrListSepByAndCapped(
+                    l.into_iter().map(|s| Cow::Owned(s.into_owned())).collect(),
+                    cap,
+                )
+            }
+            DiagnosticArgValue::StrListSepByOr(l) => DiagnosticArgValue::StrListSepByOr(
+                l.into_iter().map(|s| Cow::Owned(s.into_owned())).collect(),
+            ),
+            DiagnosticArgValue::StrListSepByCustom(l, sep) => {
+                DiagnosticArgValue::StrListSepByCustom(
+                    l.into_iter().map(|s| Cow::Owned(s.into_owned())).collect(),
+                    Cow::Owned(sep.into_owned()),
+                )
+            }
+            DiagnosticArgValue::Structured(s) => DiagnosticArgValue::Structured(s),
+            DiagnosticArgValue::Snippet(span) => DiagnosticArgValue::Snippet(span),
         }
     }
 }
@@ -86,6 +156,20 @@ fn add_to_diagnostic_with<F>(self, diag: &mut Diagnostic, f: F)
         F: Fn(&mut Diagnostic, SubdiagnosticMessage) -> SubdiagnosticMessage;
 }
 
+/// Trait implemented by subdiagnostic enums whose variants share the primary span and arguments
+/// of the subdiagnostic containing them. This should not be implemented manually. Instead, use
+/// `#[derive(Subdiagnostic)]` with `#[shared]` on the enum and on the field containing it.
+pub trait AddToDiagnosticShared
+where
+    Self: Sized,
+{
+    /// Add a subdiagnostic to an existing diagnostic, using `span` for messages of variants
+    /// without a `#[primary_span]` of their own.
+    fn add_to_diagnostic_shared<F>(self, diag: &mut Diagnostic, span: Span, f: F)
+    where
+        F: Fn(&mut Diagnostic, SubdiagnosticMessage) -> SubdiagnosticMessage;
+}
+
 /// Trait implemented by lint types. This should not be implemented manually. Instead, use
 /// `#[derive(LintDiagnostic)]` -- see [rustc_macros::LintDiagnostic].
 #[rustc_diagnostic_item = "DecorateLint"]
@@ -107,7 +191,6 @@ pub struct Diagnostic {
     pub code: Option<String>,
     pub span: MultiSpan,
     pub children: Vec<SubDiagnostic>,
-    pub suggestions: Result<Vec<CodeSuggestion>, SuggestionsDisabled>,
     args: FxHashMap<DiagnosticArgName<'static>, DiagnosticArgValue<'static>>,
 
     /// This is not used for highlighting or rendering any error message. Rather, it can be used
@@ -115,16 +198,99 @@ pub struct Diagnostic {
     /// `span` if there is one. Otherwise, it is `DUMMY_SP`.
     pub sort_span: Span,
 
+    /// With `-Ztrack_diagnostics` enabled,
+    /// we print where in rustc this error was emitted.
+    pub emitted_at: DiagnosticLocation,
+
+    /// Fields that most diagnostics leave empty, boxed to keep `Diagnostic` small and only
+    /// allocated once one of them is set. Diagnostics are moved around a lot, so their size
+    /// matters more than the extra allocation. See `Diagnostic::extras`.
+    extras: Option<Box<DiagnosticExtras>>,
+}
+
+/// The rarely used parts of a `Diagnostic`, see `Diagnostic::extras`.
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct DiagnosticExtras {
+    pub suggestions: Result<Vec<CodeSuggestion>, SuggestionsDisabled>,
+    /// Suggestions for files that are not in the `SourceMap`, see `Diagnostic::file_suggestion`.
+    pub file_suggestions: Vec<FileSuggestion>,
+    lazy_args: Vec<(DiagnosticArgName<'static>, LazyDiagnosticArg)>,
+    /// Messages of subdiagnostics with the arguments that the subdiagnostic set, which take
+    /// precedence when translating the message. See `Diagnostic::subdiagnostic`.
+    scoped_args: Vec<(
+        DiagnosticMessage,
+        FxHashMap<DiagnosticArgName<'static>, DiagnosticArgValue<'static>>,
+    )>,
+    /// Hashes of the keys passed to `Diagnostic::subdiagnostic_once`.
+    subdiagnostic_once_keys: FxHashSet<Hash128>,
+
     /// If diagnostic is from Lint, custom hash function ignores children.
     /// Otherwise hash is based on the all the fields.
     pub is_lint: Option<IsLint>,
 
-    /// With `-Ztrack_diagnostics` enabled,
-    /// we print where in rustc this error was emitted.
-    pub emitted_at: DiagnosticLocation,
+    /// With `-Ztrack_diagnostics` enabled, the queries that were active when this diagnostic was
+    /// emitted, innermost first. See `DiagCtxt::set_query_stack_hook`.
+    pub query_stack: Vec<String>,
+
+    /// Width that this diagnostic is rendered at, overriding `--diagnostic-width`. This is useful
+    /// for diagnostics that embed long type names, which become useless when truncated.
+    pub rendering_width: Option<usize>,
+
+    /// The number of duplicates of the diagnostic that weren't shown, if there were any, see
+    /// `DiagCtxtFlags::deduplicate_diagnostics`.
+    pub duplicates: Option<usize>,
+
+    /// On the lint about an `#[expect]` attribute whose lint was never emitted, the expectation.
+    pub unfulfilled_expectation: Option<LintExpectationId>,
+
+    /// On future-incompatibility lints, what they warn about.
+    pub future_incompat: Option<FutureIncompat>,
+
+    /// The tool that the diagnostic is from, if the driver registered a display prefix for it.
+    pub tool: Option<DiagnosticTool>,
+}
+
+impl Default for DiagnosticExtras {
+    fn default() -> Self {
+        DiagnosticExtras {
+            suggestions: Ok(vec![]),
+            file_suggestions: vec![],
+            lazy_args: vec![],
+            scoped_args: vec![],
+            subdiagnostic_once_keys: Default::default(),
+            is_lint: None,
+            query_stack: vec![],
+            rendering_width: None,
+            duplicates: None,
+            unfulfilled_expectation: None,
+            future_incompat: None,
+            tool: None,
+        }
+    }
+}
+
+/// The tool that a diagnostic is from, see `DiagCtxt::set_tool_display_prefix`.
+#[derive(Clone, Debug, Encodable, Decodable)]
+pub struct DiagnosticTool {
+    /// The name of the tool, like `clippy`.
+    pub name: String,
+    /// What the level of the diagnostic is prefixed with when it is rendered, like `clippy` for
+    /// `clippy-warning`.
+    pub display_prefix: String,
 }
 
+/// What a future-incompatibility lint warns about, see `Diagnostic::future_incompat`.
 #[derive(Clone, Debug, Encodable, Decodable)]
+pub struct FutureIncompat {
+    /// The tracking issue or another reference explaining the change, usually a URL.
+    pub reference: String,
+    /// When the code is going to break: `future-release`, or the edition like `edition-2024`.
+    pub breaks_in: String,
+}
+
+/// Where in rustc a diagnostic was created, see `Diagnostic::emitted_at`. Machine-readable
+/// output has it as an object with `file`, `line` and `col`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Encodable, Decodable, Serialize)]
 pub struct DiagnosticLocation {
     file: Cow<'static, str>,
     line: u32,
@@ -151,6 +317,10 @@ pub struct IsLint {
     pub(crate) name: String,
     /// Indicates whether this lint should show up in cargo's future breakage report.
     has_future_breakage: bool,
+    /// The level of the lint unless attributes or flags change it, like `warn`.
+    pub(crate) default_level: String,
+    /// The groups that the lint is in, like `unused`, see `DiagCtxt::set_lint_groups`.
+    pub(crate) groups: Vec<String>,
 }
 
 /// A "sub"-diagnostic attached to a parent diagnostic.
@@ -160,6 +330,20 @@ pub struct SubDiagnostic {
     pub level: Level,
     pub messages: Vec<(DiagnosticMessage, Style)>,
     pub span: MultiSpan,
+    pub priority: SubdiagnosticPriority,
+    /// Documentation that the message points to, see `Diagnostic::help_with_url`.
+    pub url: Option<String>,
+}
+
+/// Where a subdiagnostic or suggestion is rendered among those of its diagnostic. Within the same
+/// priority, subdiagnostics come before suggestions, and both are rendered in the order in which
+/// they were added.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Encodable, Decodable)]
+pub enum SubdiagnosticPriority {
+    First,
+    #[default]
+    Normal,
+    Last,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -226,11 +410,10 @@ pub fn new_with_messages(level: Level, messages: Vec<(DiagnosticMessage, Style)>
             code: None,
             span: MultiSpan::new(),
             children: vec![],
-            suggestions: Ok(vec![]),
             args: Default::default(),
             sort_span: DUMMY_SP,
-            is_lint: None,
             emitted_at: DiagnosticLocation::caller(),
+            extras: None,
         }
     }
 
@@ -249,6 +432,7 @@ pub fn is_error(&self) -> bool {
 
             Level::ForceWarning(_)
             | Level::Warning
+            | Level::PerfNote
             | Level::DelayedBug(DelayedBugKind::GoodPath)
             | Level::Note
             | Level::OnceNote
@@ -286,13 +470,13 @@ pub(crate) fn update_unstable_expectation_id(
 
     /// Indicates whether this diagnostic should show up in cargo's future breakage report.
     pub(crate) fn has_future_breakage(&self) -> bool {
-        matches!(self.is_lint, Some(IsLint { has_future_breakage: true, .. }))
+        matches!(self.lint(), Some(IsLint { has_future_breakage: true, .. }))
     }
 
     pub(crate) fn is_force_warn(&self) -> bool {
         match self.level {
             Level::ForceWarning(_) => {
-                assert!(self.is_lint.is_some());
+                assert!(self.lint().is_some());
                 true
             }
             _ => false,
@@ -337,6 +521,21 @@ pub fn span_label(&mut self, span: Span, label: impl Into<SubdiagnosticMessage>)
         self
     }
 
+    /// Like [`Self::span_label()`], but with arguments that only the label uses. They are
+    /// resolved when the label is rendered, like those of the diagnostic, which makes it possible
+    /// to use the same message for several labels with different values.
+    #[rustc_lint_diagnostics]
+    pub fn span_label_with_args(
+        &mut self,
+        span: Span,
+        label: impl Into<SubdiagnosticMessage>,
+        args: impl IntoIterator<Item = (DiagnosticArgName<'static>, DiagnosticArgValue<'static>)>,
+    ) -> &mut Self {
+        let label = self.subdiagnostic_message_to_diagnostic_message(label);
+        self.span.push_span_label_with_args(span, label, args.into_iter().collect());
+        self
+    }
+
     /// Labels all the given spans with the provided label.
     /// See [`Self::span_label()`] for more information.
     pub fn span_labels(&mut self, spans: impl IntoIterator<Item = Span>, label: &str) -> &mut Self {
@@ -503,6 +702,19 @@ pub fn help_once(&mut self, msg: impl Into<SubdiagnosticMessage>) -> &mut Self {
         self
     }
 
+    /// Add a help message that points to further documentation at `url`, like a chapter of the
+    /// reference or the book. The URL is kept apart from the message, so that the human output
+    /// can show it as a hyperlink and the JSON output has it in the `url` field of the child.
+    pub fn help_with_url(
+        &mut self,
+        msg: impl Into<SubdiagnosticMessage>,
+        url: impl Into<String>,
+    ) -> &mut Self {
+        self.sub(Level::Help, msg, MultiSpan::new());
+        self.children.last_mut().unwrap().url = Some(url.into());
+        self
+    }
+
     /// Add a help message attached to this diagnostic with a customizable highlighted message.
     pub fn highlighted_help(&mut self, msg: Vec<(String, Style)>) -> &mut Self {
         self.sub_with_highlights(Level::Help, msg, MultiSpan::new());
@@ -525,13 +737,13 @@ pub fn span_help<S: Into<MultiSpan>>(
     /// Any suggestions attached e.g. with the `span_suggestion_*` methods
     /// (before and after the call to `disable_suggestions`) will be ignored.
     pub fn disable_suggestions(&mut self) -> &mut Self {
-        self.suggestions = Err(SuggestionsDisabled);
+        self.extras_mut().suggestions = Err(SuggestionsDisabled);
         self
     }
 
-    /// Helper for pushing to `self.suggestions`, if available (not disable).
+    /// Helper for pushing to `self.extras.suggestions`, if available (not disable).
     fn push_suggestion(&mut self, suggestion: CodeSuggestion) {
-        if let Ok(suggestions) = &mut self.suggestions {
+        if let Ok(suggestions) = &mut self.extras_mut().suggestions {
             suggestions.push(suggestion);
         }
     }
@@ -580,26 +792,61 @@ pub fn multipart_suggestion_with_style(
 
         let parts = suggestion
             .into_iter()
-            .map(|(span, snippet)| SubstitutionPart { snippet, span })
+            .map(|(span, snippet)| SubstitutionPart { snippet, span, applicability: None })
+            .collect::<Vec<_>>();
+
+        self.push_multipart_suggestion(msg, parts, applicability, style)
+    }
+
+    /// Show a suggestion that has multiple parts to it, where each part has its own
+    /// applicability. The applicability of the whole suggestion is the one of its least
+    /// applicable part, but tools can still apply the parts that are machine applicable.
+    pub fn multipart_suggestion_with_part_applicability(
+        &mut self,
+        msg: impl Into<SubdiagnosticMessage>,
+        mut suggestion: Vec<(Span, String, Applicability)>,
+    ) -> &mut Self {
+        suggestion.sort_unstable();
+        suggestion.dedup();
+
+        let applicability = suggestion
+            .iter()
+            .map(|&(_, _, applicability)| applicability)
+            .max()
+            .unwrap_or(Applicability::MachineApplicable);
+        let parts = suggestion
+            .into_iter()
+            .map(|(span, snippet, applicability)| SubstitutionPart {
+                snippet,
+                span,
+                applicability: Some(applicability),
+            })
             .collect::<Vec<_>>();
 
+        self.push_multipart_suggestion(msg, parts, applicability, SuggestionStyle::ShowCode)
+    }
+
+    fn push_multipart_suggestion(
+        &mut self,
+        msg: impl Into<SubdiagnosticMessage>,
+        parts: Vec<SubstitutionPart>,
+        applicability: Applicability,
+        style: SuggestionStyle,
+    ) -> &mut Self {
         assert!(!parts.is_empty());
         debug_assert_eq!(
             parts.iter().find(|part| part.span.is_empty() && part.snippet.is_empty()),
             None,
             "Span must not be empty and have no suggestion",
         );
-        debug_assert_eq!(
-            parts.array_windows().find(|[a, b]| a.span.overlaps(b.span)),
-            None,
-            "suggestion must not have overlapping parts",
-        );
 
         self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution { parts }],
             msg: self.subdiagnostic_message_to_diagnostic_message(msg),
             style,
             applicability,
+            rank: None,
+            priority: SubdiagnosticPriority::Normal,
         });
         self
     }
@@ -673,11 +920,17 @@ pub fn span_suggestion_with_style(
         );
         self.push_suggestion(CodeSuggestion {
             substitutions: vec![Substitution {
-                parts: vec![SubstitutionPart { snippet: suggestion.to_string(), span: sp }],
+                parts: vec![SubstitutionPart {
+                    snippet: suggestion.to_string(),
+                    span: sp,
+                    applicability: None,
+                }],
             }],
             msg: self.subdiagnostic_message_to_diagnostic_message(msg),
             style,
             applicability,
+            rank: None,
+            priority: SubdiagnosticPriority::Normal,
         });
         self
     }
@@ -734,7 +987,9 @@ pub fn span_suggestions_with_style(
                     !(sp.is_empty() && snippet.is_empty()),
                     "Span must not be empty and have no suggestion"
                 );
-                Substitution { parts: vec![SubstitutionPart { snippet, span: sp }] }
+                Substitution {
+                    parts: vec![SubstitutionPart { snippet, span: sp, applicability: None }],
+                }
             })
             .collect();
         self.push_suggestion(CodeSuggestion {
@@ -742,6 +997,8 @@ pub fn span_suggestions_with_style(
             msg: self.subdiagnostic_message_to_diagnostic_message(msg),
             style,
             applicability,
+            rank: None,
+            priority: SubdiagnosticPriority::Normal,
         });
         self
     }
@@ -760,7 +1017,7 @@ pub fn multipart_suggestions(
             .map(|sugg| {
                 let mut parts = sugg
                     .into_iter()
-                    .map(|(span, snippet)| SubstitutionPart { snippet, span })
+                    .map(|(span, snippet)| SubstitutionPart { snippet, span, applicability: None })
                     .collect::<Vec<_>>();
 
                 parts.sort_unstable_by_key(|part| part.span);
@@ -771,11 +1028,6 @@ pub fn multipart_suggestions(
                     None,
                     "Span must not be empty and have no suggestion",
                 );
-                debug_assert_eq!(
-                    parts.array_windows().find(|[a, b]| a.span.overlaps(b.span)),
-                    None,
-                    "suggestion must not have overlapping parts",
-                );
 
                 Substitution { parts }
             })
@@ -786,6 +1038,8 @@ pub fn multipart_suggestions(
             msg: self.subdiagnostic_message_to_diagnostic_message(msg),
             style: SuggestionStyle::ShowCode,
             applicability,
+            rank: None,
+            priority: SubdiagnosticPriority::Normal,
         });
         self
     }
@@ -856,22 +1110,267 @@ pub fn tool_only_span_suggestion(
         self
     }
 
+    /// Suggests replacing `range` of the file at `path` with `suggestion`, for files that are
+    /// not in the `SourceMap` (e.g. `Cargo.toml`). `contents` is the current contents of the
+    /// file, which is used to locate the edit. The suggestion is ignored if `range` isn't a valid
+    /// range of `contents`, e.g. because the file changed since the range was computed.
+    ///
+    /// The suggestion is rendered as a diff of the affected lines.
+    #[rustc_lint_diagnostics]
+    pub fn file_suggestion(
+        &mut self,
+        path: impl Into<PathBuf>,
+        contents: &str,
+        range: Range<usize>,
+        msg: impl Into<SubdiagnosticMessage>,
+        suggestion: impl ToString,
+        applicability: Applicability,
+    ) -> &mut Self {
+        if self.suggestions().is_err() {
+            return self;
+        }
+        let Some(original) = contents.get(range.clone()) else {
+            debug!("file_suggestion: {range:?} is not a valid range of the file");
+            return self;
+        };
+        let first_line = contents[..range.start].rfind('\n').map_or(0, |i| i + 1);
+        let last_line = contents[..range.end].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = contents[range.end..].find('\n').map_or(contents.len(), |i| range.end + i);
+        let line_start = contents[..range.start].matches('\n').count() + 1;
+        let suggestion = FileSuggestion {
+            msg: self.subdiagnostic_message_to_diagnostic_message(msg),
+            path: path.into(),
+            byte_start: range.start,
+            byte_end: range.end,
+            line_start,
+            line_end: line_start + original.matches('\n').count(),
+            column_start: contents[first_line..range.start].chars().count() + 1,
+            column_end: contents[last_line..range.end].chars().count() + 1,
+            original: original.to_string(),
+            snippet: suggestion.to_string(),
+            prefix: contents[first_line..range.start].to_string(),
+            suffix: contents[range.end..line_end].to_string(),
+            applicability,
+        };
+        self.extras_mut().file_suggestions.push(suggestion);
+        self
+    }
+
+    /// Sets the rank of the suggestion that was added last, for tools that have to choose between
+    /// several alternative suggestions. Suggestions with a lower rank are preferred, and unranked
+    /// suggestions come after all ranked ones.
+    ///
+    /// Does nothing if no suggestion was added, e.g. because the code that adds it only does so
+    /// for some inputs.
+    pub fn rank_last_suggestion(&mut self, rank: u32) -> &mut Self {
+        if let Some(suggestion) = self.suggestions_mut().and_then(|sugg| sugg.last_mut()) {
+            suggestion.rank = Some(rank);
+        }
+        self
+    }
+
+    /// Sets the priority of the note, help or warning that was added last, to render it before or
+    /// after the others regardless of the order in which they were added.
+    ///
+    /// Does nothing if no note, help or warning was added.
+    pub fn prioritize_last_child(&mut self, priority: SubdiagnosticPriority) -> &mut Self {
+        if let Some(child) = self.children.last_mut() {
+            child.priority = priority;
+        }
+        self
+    }
+
+    /// Sets the priority of the suggestion that was added last, see
+    /// `Diagnostic::prioritize_last_child`.
+    ///
+    /// Does nothing if no suggestion was added.
+    pub fn prioritize_last_suggestion(&mut self, priority: SubdiagnosticPriority) -> &mut Self {
+        if let Some(suggestion) = self.suggestions_mut().and_then(|sugg| sugg.last_mut()) {
+            suggestion.priority = priority;
+        }
+        self
+    }
+
+    /// Moves the note, help or warning that was added last to position `index` among the others,
+    /// or to the end if there are fewer than `index` others.
+    ///
+    /// Does nothing if no note, help or warning was added.
+    pub fn move_last_child(&mut self, index: usize) -> &mut Self {
+        if let Some(child) = self.children.pop() {
+            let index = index.min(self.children.len());
+            self.children.insert(index, child);
+        }
+        self
+    }
+
     /// Add a subdiagnostic from a type that implements `Subdiagnostic` (see
     /// [rustc_macros::Subdiagnostic]).
+    ///
+    /// The messages of the subdiagnostic are translated with the arguments that it set taking
+    /// precedence, so other subdiagnostics that set arguments with the same names don't change
+    /// them. The arguments are still added to the diagnostic for use by its other messages.
     pub fn subdiagnostic(&mut self, subdiagnostic: impl AddToDiagnostic) -> &mut Self {
-        subdiagnostic.add_to_diagnostic(self);
+        let parent_args = std::mem::take(&mut self.args);
+        subdiagnostic.add_to_diagnostic_with(self, |diag, msg| {
+            let translatable = matches!(
+                msg,
+                SubdiagnosticMessage::FluentIdentifier(_) | SubdiagnosticMessage::FluentAttr(_)
+            );
+            if translatable && !diag.args.is_empty() {
+                let scoped_msg = diag.subdiagnostic_message_to_diagnostic_message(msg.clone());
+                let args = diag.args.clone();
+                diag.extras_mut().scoped_args.push((scoped_msg, args));
+            }
+            msg
+        });
+        let args = std::mem::replace(&mut self.args, parent_args);
+        self.args.extend(args);
+        self
+    }
+
+    /// Add a subdiagnostic unless one with the same `key` was already added to this diagnostic with
+    /// this method. This is useful for subdiagnostics that are added in a loop, where repeating
+    /// the same note or help many times would only be noise.
+    pub fn subdiagnostic_once(
+        &mut self,
+        key: impl Hash,
+        subdiagnostic: impl AddToDiagnostic,
+    ) -> &mut Self {
+        let mut hasher = StableHasher::new();
+        key.hash(&mut hasher);
+        if self.extras_mut().subdiagnostic_once_keys.insert(hasher.finish()) {
+            self.subdiagnostic(subdiagnostic);
+        }
         self
     }
 
+    /// Replaces the owned strings in the messages and arguments of this diagnostic with shared
+    /// copies from `interner`. This is done for diagnostics that the `DiagCtxt` holds on to.
+    pub(crate) fn intern_strings(&mut self, interner: &mut DiagnosticInterner) {
+        self.for_each_string_mut(|s| interner.intern(s));
+    }
+
+    /// Undoes `Diagnostic::intern_strings`, for diagnostics that leave the `DiagCtxt`.
+    pub(crate) fn unintern_strings(&mut self, interner: &DiagnosticInterner) {
+        self.for_each_string_mut(|s| interner.unintern(s));
+    }
+
+    /// Calls `f` with each string in the messages and arguments of this diagnostic.
+    fn for_each_string_mut(&mut self, mut f: impl FnMut(&mut Cow<'static, str>)) {
+        let messages = self
+            .messages
+            .iter_mut()
+            .map(|(msg, _)| msg)
+            .chain(self.span.span_label_messages_mut())
+            .chain(self.children.iter_mut().flat_map(|child| {
+                child
+                    .messages
+                    .iter_mut()
+                    .map(|(msg, _)| msg)
+                    .chain(child.span.span_label_messages_mut())
+            }));
+        for msg in messages {
+            match msg {
+                DiagnosticMessage::Str(s) | DiagnosticMessage::Eager(s) => f(s),
+                DiagnosticMessage::FluentIdentifier(..) => {}
+            }
+        }
+        for value in self.args.values_mut() {
+            match value {
+                DiagnosticArgValue::Str(s) => f(s),
+                DiagnosticArgValue::StrListSepByAnd(list)
+                | DiagnosticArgValue::StrListSepByAndCapped(list, _)
+                | DiagnosticArgValue::StrListSepByOr(list) => list.iter_mut().for_each(&mut f),
+                DiagnosticArgValue::StrListSepByCustom(list, sep) => {
+                    list.iter_mut().for_each(&mut f);
+                    f(sep);
+                }
+                DiagnosticArgValue::Symbol(_)
+                | DiagnosticArgValue::Number(_)
+                | DiagnosticArgValue::Float(_)
+                | DiagnosticArgValue::Structured(_)
+                | DiagnosticArgValue::Snippet(_) => {}
+            }
+        }
+    }
+
+    /// Replaces the snippet arguments with the code they point to in `source`, see
+    /// `DiagnosticArgValue::Snippet`. This happens right before emission, like the translation of
+    /// scoped messages, so that only rendered diagnostics look their code up.
+    pub(crate) fn render_snippets(&mut self, source: Option<&dyn SourceProvider>) {
+        let Some(source) = source else { return };
+        let scoped_args = self.extras.iter_mut().flat_map(|extras| &mut extras.scoped_args);
+        let scoped_args = scoped_args.flat_map(|(_, args)| args.values_mut());
+        for value in self.args.values_mut().chain(scoped_args) {
+            let DiagnosticArgValue::Snippet(span) = *value else { continue };
+            let snippet = match source.snippet(span) {
+                Some(snippet) if !snippet.trim().is_empty() => format!("`{}`", snippet.trim()),
+                _ => UNAVAILABLE_SNIPPET.to_string(),
+            };
+            *value = DiagnosticArgValue::Str(Cow::Owned(snippet));
+        }
+    }
+
+    /// Translates the messages of subdiagnostics that have their own arguments, see
+    /// `Diagnostic::subdiagnostic`. This happens right before emission, once it's clear that the
+    /// diagnostic is going to be rendered.
+    pub(crate) fn translate_scoped_messages(&mut self, translator: &(impl Translate + ?Sized)) {
+        let Some(extras) = self.extras.as_deref_mut() else { return };
+        if extras.scoped_args.is_empty() {
+            return;
+        }
+        let mut scoped_args = std::mem::take(&mut extras.scoped_args);
+        let DiagnosticExtras { lazy_args, suggestions, .. } = extras;
+        let diag_args = || {
+            let lazy_args = lazy_args.iter().map(|(name, arg)| (name, arg.value()));
+            to_fluent_args(self.args.iter().chain(lazy_args))
+        };
+
+        // Identical messages are matched with their arguments in the order they were added in.
+        let messages = self
+            .span
+            .span_label_messages_mut()
+            .chain(self.children.iter_mut().flat_map(|child| {
+                child
+                    .messages
+                    .iter_mut()
+                    .map(|(msg, _)| msg)
+                    .chain(child.span.span_label_messages_mut())
+            }))
+            .chain(suggestions.iter_mut().flatten().map(|sugg| &mut sugg.msg));
+        for msg in messages {
+            let Some(i) = scoped_args.iter().position(|(scoped_msg, _)| scoped_msg == msg) else {
+                continue;
+            };
+            let (_, scope) = scoped_args.remove(i);
+            let mut args = diag_args();
+            for (name, value) in scope {
+                args.set(name, value);
+            }
+            let translated =
+                translator.translate_message(msg, &args).map_err(Report::new).unwrap().into_owned();
+            *msg = DiagnosticMessage::Eager(Cow::Owned(translated));
+            if scoped_args.is_empty() {
+                break;
+            }
+        }
+    }
+
     /// Add a subdiagnostic from a type that implements `Subdiagnostic` (see
     /// [rustc_macros::Subdiagnostic]). Performs eager translation of any translatable messages
     /// used in the subdiagnostic, so suitable for use with repeated messages (i.e. re-use of
     /// interpolated variables).
+    ///
+    /// Diagnostics that won't be rendered, like allowed or expected lints, skip the translation
+    /// and add the subdiagnostic like `Diagnostic::subdiagnostic` does instead.
     pub fn eager_subdiagnostic(
         &mut self,
         dcx: &crate::DiagCtxt,
         subdiagnostic: impl AddToDiagnostic,
     ) -> &mut Self {
+        if !dcx.may_render(self) {
+            return self.subdiagnostic(subdiagnostic);
+        }
         subdiagnostic.add_to_diagnostic_with(self, |diag, msg| {
             let args = diag.args();
             let msg = diag.subdiagnostic_message_to_diagnostic_message(msg);
@@ -888,8 +1387,15 @@ pub fn span<S: Into<MultiSpan>>(&mut self, sp: S) -> &mut Self {
         self
     }
 
-    pub fn is_lint(&mut self, name: String, has_future_breakage: bool) -> &mut Self {
-        self.is_lint = Some(IsLint { name, has_future_breakage });
+    pub fn is_lint(
+        &mut self,
+        name: String,
+        default_level: rustc_lint_defs::Level,
+        has_future_breakage: bool,
+    ) -> &mut Self {
+        let default_level = default_level.as_str().to_string();
+        self.extras_mut().is_lint =
+            Some(IsLint { name, has_future_breakage, default_level, groups: Vec::new() });
         self
     }
 
@@ -907,6 +1413,25 @@ pub fn get_code(&self) -> Option<&str> {
         self.code.as_deref()
     }
 
+    /// Records what this future-incompatibility lint warns about, which is shown to tools.
+    pub fn future_incompat(&mut self, reference: String, breaks_in: String) -> &mut Self {
+        self.extras_mut().future_incompat = Some(FutureIncompat { reference, breaks_in });
+        self
+    }
+
+    /// Marks this diagnostic as being about the unfulfilled expectation `id`, which is shown to
+    /// tools.
+    pub fn unfulfilled_expectation(&mut self, id: LintExpectationId) -> &mut Self {
+        self.extras_mut().unfulfilled_expectation = Some(id);
+        self
+    }
+
+    /// Renders this diagnostic at the given width, regardless of `--diagnostic-width`.
+    pub fn rendering_width(&mut self, width: usize) -> &mut Self {
+        self.extras_mut().rendering_width = Some(width);
+        self
+    }
+
     pub fn primary_message(&mut self, msg: impl Into<DiagnosticMessage>) -> &mut Self {
         self.messages[0] = (msg.into(), Style::NoStyle);
         self
@@ -916,7 +1441,8 @@ pub fn primary_message(&mut self, msg: impl Into<DiagnosticMessage>) -> &mut Sel
     // they're only used in interpolation.
     #[allow(rustc::potential_query_instability)]
     pub fn args(&self) -> impl Iterator<Item = DiagnosticArg<'_, 'static>> {
-        self.args.iter()
+        let lazy_args = self.extras().into_iter().flat_map(|extras| &extras.lazy_args);
+        self.args.iter().chain(lazy_args.map(|(name, arg)| (name, arg.value())))
     }
 
     pub fn arg(
@@ -924,7 +1450,42 @@ pub fn arg(
         name: impl Into<Cow<'static, str>>,
         arg: impl IntoDiagnosticArg,
     ) -> &mut Self {
-        self.args.insert(name.into(), arg.into_diagnostic_arg());
+        let name = name.into();
+        if let Some(extras) = &mut self.extras {
+            extras.lazy_args.retain(|(n, _)| *n != name);
+        }
+        self.args.insert(name, arg.into_diagnostic_arg());
+        self
+    }
+
+    /// Adds all `args`, e.g. from an iterator or an array. Use `DiagnosticArgValue`s for
+    /// arguments of different types.
+    pub fn set_args(
+        &mut self,
+        args: impl IntoIterator<Item = (impl Into<Cow<'static, str>>, impl IntoDiagnosticArg)>,
+    ) -> &mut Self {
+        for (name, arg) in args {
+            self.arg(name, arg);
+        }
+        self
+    }
+
+    /// Adds an argument whose value is computed by `f` only when the diagnostic is rendered, so
+    /// that no work is wasted on diagnostics that are cancelled, stashed and stolen, or not
+    /// emitted because of lint levels. Use this when computing the value is expensive, e.g. for
+    /// pretty-printed types.
+    ///
+    /// Lazy arguments are not taken into account when deduplicating diagnostics.
+    pub fn arg_lazy<T: IntoDiagnosticArg>(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        f: impl Fn() -> T + DynSend + DynSync + 'static,
+    ) -> &mut Self {
+        let name = name.into();
+        self.args.remove(&name);
+        let lazy_args = &mut self.extras_mut().lazy_args;
+        lazy_args.retain(|(n, _)| *n != name);
+        lazy_args.push((name, LazyDiagnosticArg::new(f)));
         self
     }
 
@@ -933,6 +1494,46 @@ pub fn replace_args(
         args: FxHashMap<DiagnosticArgName<'static>, DiagnosticArgValue<'static>>,
     ) {
         self.args = args;
+        if let Some(extras) = &mut self.extras {
+            extras.lazy_args.clear();
+        }
+    }
+
+    /// The rarely used parts of this diagnostic, or `None` if none of them were set.
+    pub fn extras(&self) -> Option<&DiagnosticExtras> {
+        self.extras.as_deref()
+    }
+
+    /// The rarely used parts of this diagnostic, which are allocated the first time this is
+    /// called.
+    pub fn extras_mut(&mut self) -> &mut DiagnosticExtras {
+        self.extras.get_or_insert_with(Default::default)
+    }
+
+    /// The suggestions of this diagnostic, or `Err` if they were disabled.
+    pub fn suggestions(&self) -> Result<&[CodeSuggestion], SuggestionsDisabled> {
+        match self.extras() {
+            Some(extras) => extras.suggestions.as_deref().map_err(Clone::clone),
+            None => Ok(&[]),
+        }
+    }
+
+    /// The suggestions of this diagnostic, or `None` if none were added or they were disabled.
+    pub fn suggestions_mut(&mut self) -> Option<&mut Vec<CodeSuggestion>> {
+        self.extras.as_mut().and_then(|extras| extras.suggestions.as_mut().ok())
+    }
+
+    /// The suggestions of this diagnostic for files that are not in the `SourceMap`.
+    pub fn file_suggestions(&self) -> &[FileSuggestion] {
+        match self.extras() {
+            Some(extras) => &extras.file_suggestions,
+            None => &[],
+        }
+    }
+
+    /// The lint that this diagnostic is from, if any, see `Diagnostic::is_lint`.
+    pub fn lint(&self) -> Option<&IsLint> {
+        self.extras().and_then(|extras| extras.is_lint.as_ref())
     }
 
     pub fn messages(&self) -> &[(DiagnosticMessage, Style)] {
@@ -963,6 +1564,8 @@ pub fn sub(&mut self, level: Level, message: impl Into<SubdiagnosticMessage>, sp
                 Style::NoStyle,
             )],
             span,
+            priority: SubdiagnosticPriority::Normal,
+            url: None,
         };
         self.children.push(sub);
     }
@@ -979,7 +1582,13 @@ fn sub_with_highlights<M: Into<SubdiagnosticMessage>>(
             .into_iter()
             .map(|m| (self.subdiagnostic_message_to_diagnostic_message(m.0), m.1))
             .collect();
-        let sub = SubDiagnostic { level, messages, span };
+        let sub = SubDiagnostic {
+            level,
+            messages,
+            span,
+            priority: SubdiagnosticPriority::Normal,
+            url: None,
+        };
         self.children.push(sub);
     }
 
@@ -991,20 +1600,29 @@ fn keys(
         &[(DiagnosticMessage, Style)],
         Vec<(&Cow<'static, str>, &DiagnosticArgValue<'static>)>,
         &Option<String>,
-        &Option<IsLint>,
+        Option<&IsLint>,
         &MultiSpan,
-        &Result<Vec<CodeSuggestion>, SuggestionsDisabled>,
+        Result<&[CodeSuggestion], SuggestionsDisabled>,
+        &[FileSuggestion],
         Option<&[SubDiagnostic]>,
+        Vec<(&DiagnosticMessage, Vec<DiagnosticArg<'_, 'static>>)>,
     ) {
         (
             &self.level,
             &self.messages,
-            self.args().collect(),
+            // Lazy arguments are left out so that they aren't computed for duplicates.
+            self.args.iter().collect(),
             &self.code,
-            &self.is_lint,
+            self.lint(),
             &self.span,
-            &self.suggestions,
-            (if self.is_lint.is_some() { None } else { Some(&self.children) }),
+            self.suggestions(),
+            self.file_suggestions(),
+            (if self.lint().is_some() { None } else { Some(&self.children) }),
+            self.extras()
+                .into_iter()
+                .flat_map(|extras| &extras.scoped_args)
+                .map(|(msg, args)| (msg, args.iter().collect()))
+                .collect(),
         )
     }
 }
@@ -1,18 +1,21 @@
 use crate::diagnostic::IntoDiagnosticArg;
 use crate::{DiagCtxt, Level, MultiSpan, StashKey};
 use crate::{
-    Diagnostic, DiagnosticMessage, DiagnosticStyledString, ErrorGuaranteed, ExplicitBug,
-    SubdiagnosticMessage,
+    Diagnostic, DiagnosticArgName, DiagnosticArgValue, DiagnosticMessage, DiagnosticStyledString,
+    ErrorGuaranteed, ExplicitBug, SubdiagnosticMessage,
 };
+use rustc_data_structures::sync::{DynSend, DynSync};
 use rustc_lint_defs::Applicability;
 use rustc_span::source_map::Spanned;
 
-use rustc_span::Span;
+use rustc_span::{Pos, Span};
 use std::borrow::Cow;
 use std::fmt::{self, Debug};
+use std::hash::Hash;
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 use std::panic;
+use std::path::PathBuf;
 use std::thread::panicking;
 
 /// Trait implemented by error types. This is rarely implemented manually. Instead, use
@@ -34,6 +37,46 @@ fn into_diagnostic(self, dcx: &'a DiagCtxt, level: Level) -> DiagnosticBuilder<'
     }
 }
 
+/// A collection of at least one diagnostic, which can be emitted at once, unlike a `Vec`, whose
+/// `IntoDiagnostic` impl would have no builder to return if it was empty. Use
+/// `DiagCtxt::emit_all` for collections that may be empty.
+pub struct DiagnosticBatch<T> {
+    rest: Vec<T>,
+    last: T,
+}
+
+impl<T> DiagnosticBatch<T> {
+    pub fn new(diag: T) -> Self {
+        DiagnosticBatch { rest: Vec::new(), last: diag }
+    }
+
+    /// Returns `None` if `diags` is empty.
+    pub fn from_vec(mut diags: Vec<T>) -> Option<Self> {
+        let last = diags.pop()?;
+        Some(DiagnosticBatch { rest: diags, last })
+    }
+
+    pub fn push(&mut self, diag: T) {
+        self.rest.push(std::mem::replace(&mut self.last, diag));
+    }
+}
+
+/// Emits all but the last diagnostic of the batch, whose builder is returned, so that the last
+/// one determines the emission guarantee.
+impl<'a, T, G> IntoDiagnostic<'a, G> for DiagnosticBatch<T>
+where
+    T: IntoDiagnostic<'a, G>,
+    G: EmissionGuarantee,
+{
+    #[track_caller]
+    fn into_diagnostic(self, dcx: &'a DiagCtxt, level: Level) -> DiagnosticBuilder<'a, G> {
+        for diag in self.rest {
+            diag.into_diagnostic(dcx, level).emit();
+        }
+        self.last.into_diagnostic(dcx, level)
+    }
+}
+
 /// Used for emitting structured error messages and other diagnostic information.
 /// Each constructed `DiagnosticBuilder` must be consumed by a function such as
 /// `emit`, `cancel`, `delay_as_bug`, or `into_diagnostic`. A panic occurrs if a
@@ -100,6 +143,7 @@ fn emit_producing_nothing(mut self) {
 
     /// `ErrorGuaranteed::emit_producing_guarantee` uses this.
     // FIXME(eddyb) make `ErrorGuaranteed` impossible to create outside `.emit()`.
+    #[track_caller]
     fn emit_producing_error_guaranteed(mut self) -> ErrorGuaranteed {
         let diag = self.take_diag();
 
@@ -117,6 +161,19 @@ fn emit_producing_error_guaranteed(mut self) -> ErrorGuaranteed {
     }
 }
 
+impl<'a> DiagnosticBuilder<'a, ErrorGuaranteed> {
+    /// Turns this error into a warning, for errors that turn out to be less severe than expected,
+    /// such as when they are allowed by a lint level. Only an emitted error produces an
+    /// `ErrorGuaranteed`, so nothing can have relied on this one yet, and the warning can't
+    /// produce one anymore.
+    #[track_caller]
+    pub fn downgrade_to_warning(mut self) -> DiagnosticBuilder<'a, ()> {
+        let mut diag = self.take_diag();
+        diag.level = Level::Warning;
+        DiagnosticBuilder::new_diagnostic(self.dcx, diag)
+    }
+}
+
 impl EmissionGuarantee for ErrorGuaranteed {
     fn emit_producing_guarantee(db: DiagnosticBuilder<'_, Self>) -> Self::EmitResult {
         db.emit_producing_error_guaranteed()
@@ -183,15 +240,15 @@ fn emit_producing_guarantee(db: DiagnosticBuilder<'_, Self>) -> Self::EmitResult
 /// method gives shorter code, e.g. `err.code(code)`.
 macro_rules! forward {
     (
-        ($f:ident, $with_f:ident)($($name:ident: $ty:ty),* $(,)?)
+        ($f:ident, $with_f:ident)$(<$($gen:ident: $bound:path),*>)?($($name:ident: $ty:ty),* $(,)?)
     ) => {
         #[doc = concat!("See [`Diagnostic::", stringify!($f), "()`].")]
-        pub fn $f(&mut self, $($name: $ty),*) -> &mut Self {
+        pub fn $f$(<$($gen: $bound),*>)?(&mut self, $($name: $ty),*) -> &mut Self {
             self.diag.as_mut().unwrap().$f($($name),*);
             self
         }
         #[doc = concat!("See [`Diagnostic::", stringify!($f), "()`].")]
-        pub fn $with_f(mut self, $($name: $ty),*) -> Self {
+        pub fn $with_f$(<$($gen: $bound),*>)?(mut self, $($name: $ty),*) -> Self {
             self.diag.as_mut().unwrap().$f($($name),*);
             self
         }
@@ -284,6 +341,11 @@ pub fn delay_as_bug(mut self) -> G::EmitResult {
         span: Span,
         label: impl Into<SubdiagnosticMessage>,
     ));
+    forward!((span_label_with_args, with_span_label_with_args)(
+        span: Span,
+        label: impl Into<SubdiagnosticMessage>,
+        args: impl IntoIterator<Item = (DiagnosticArgName<'static>, DiagnosticArgValue<'static>)>,
+    ));
     forward!((span_labels, with_span_labels)(
         spans: impl IntoIterator<Item = Span>,
         label: &str,
@@ -329,6 +391,10 @@ pub fn delay_as_bug(mut self) -> G::EmitResult {
     forward!((help_once, with_help_once)(
         msg: impl Into<SubdiagnosticMessage>,
     ));
+    forward!((help_with_url, with_help_with_url)(
+        msg: impl Into<SubdiagnosticMessage>,
+        url: impl Into<String>,
+    ));
     forward!((span_help, with_span_help_once)(
         sp: impl Into<MultiSpan>,
         msg: impl Into<SubdiagnosticMessage>,
@@ -338,6 +404,13 @@ pub fn delay_as_bug(mut self) -> G::EmitResult {
         suggestion: Vec<(Span, String)>,
         applicability: Applicability,
     ));
+    forward!((
+        multipart_suggestion_with_part_applicability,
+        with_multipart_suggestion_with_part_applicability
+    )(
+        msg: impl Into<SubdiagnosticMessage>,
+        suggestion: Vec<(Span, String, Applicability)>,
+    ));
     forward!((multipart_suggestion_verbose, with_multipart_suggestion_verbose)(
         msg: impl Into<SubdiagnosticMessage>,
         suggestion: Vec<(Span, String)>,
@@ -389,6 +462,17 @@ pub fn delay_as_bug(mut self) -> G::EmitResult {
         suggestion: impl ToString,
         applicability: Applicability,
     ));
+    forward!((file_suggestion, with_file_suggestion)(
+        path: impl Into<PathBuf>,
+        contents: &str,
+        range: Range<usize>,
+        msg: impl Into<SubdiagnosticMessage>,
+        suggestion: impl ToString,
+        applicability: Applicability,
+    ));
+    forward!((rank_last_suggestion, with_rank_last_suggestion)(
+        rank: u32,
+    ));
     forward!((primary_message, with_primary_message)(
         msg: impl Into<DiagnosticMessage>,
     ));
@@ -396,21 +480,96 @@ pub fn delay_as_bug(mut self) -> G::EmitResult {
         sp: impl Into<MultiSpan>,
     ));
     forward!((is_lint, with_is_lint)(
-        name: String, has_future_breakage: bool,
+        name: String, default_level: rustc_lint_defs::Level, has_future_breakage: bool,
     ));
     forward!((code, with_code)(
         s: String,
     ));
+    forward!((rendering_width, with_rendering_width)(
+        width: usize,
+    ));
     forward!((arg, with_arg)(
         name: impl Into<Cow<'static, str>>, arg: impl IntoDiagnosticArg,
     ));
+    forward!((set_args, with_args)(
+        args: impl IntoIterator<Item = (impl Into<Cow<'static, str>>, impl IntoDiagnosticArg)>,
+    ));
+
+    forward!((arg_lazy, with_arg_lazy)<T: IntoDiagnosticArg>(
+        name: impl Into<Cow<'static, str>>,
+        f: impl Fn() -> T + DynSend + DynSync + 'static,
+    ));
+
     forward!((subdiagnostic, with_subdiagnostic)(
         subdiagnostic: impl crate::AddToDiagnostic,
     ));
+    forward!((subdiagnostic_once, with_subdiagnostic_once)(
+        key: impl Hash,
+        subdiagnostic: impl crate::AddToDiagnostic,
+    ));
     forward!((eager_subdiagnostic, with_eager_subdiagnostic)(
         dcx: &DiagCtxt,
         subdiagnostic: impl crate::AddToDiagnostic,
     ));
+
+    /// Suggests inserting the block of code `lines` at `sp`, re-indenting it to match the
+    /// indentation of the line that `sp` starts on. The lines should only be indented relative to
+    /// each other, as if the block was at the top level of a file.
+    ///
+    /// If there's only whitespace before `sp` on its line, the block is inserted as whole lines
+    /// before that line. Otherwise, it's inserted on new lines right after `sp`.
+    pub fn span_suggestion_block(
+        &mut self,
+        sp: Span,
+        msg: impl Into<SubdiagnosticMessage>,
+        lines: impl IntoIterator<Item = impl AsRef<str>>,
+        applicability: Applicability,
+    ) -> &mut Self {
+        let line = self.dcx.source_map().and_then(|sm| {
+            let line = sm.lookup_line(sp.lo()).ok()?;
+            let text = line.sf.get_line(line.line)?;
+            let line_start = line.sf.line_bounds(line.line).start;
+            let before = text.get(..(sp.lo() - line_start).to_usize())?;
+            let indent = text.len() - text.trim_start().len();
+            Some((text[..indent].to_string(), before.trim().is_empty(), line_start))
+        });
+        let (sp, snippet) = match line {
+            Some((indent, true, line_start)) => {
+                let snippet = lines
+                    .into_iter()
+                    .map(|line| match line.as_ref() {
+                        "" => "\n".to_string(),
+                        line => format!("{indent}{line}\n"),
+                    })
+                    .collect::<String>();
+                (sp.with_lo(line_start).shrink_to_lo(), snippet)
+            }
+            _ => {
+                let indent = line.map_or(String::new(), |(indent, ..)| indent);
+                let snippet = lines
+                    .into_iter()
+                    .map(|line| match line.as_ref() {
+                        "" => "\n".to_string(),
+                        line => format!("\n{indent}{line}"),
+                    })
+                    .collect::<String>();
+                (sp.shrink_to_hi(), snippet)
+            }
+        };
+        self.span_suggestion_verbose(sp, msg, snippet, applicability)
+    }
+
+    /// See [`DiagnosticBuilder::span_suggestion_block()`].
+    pub fn with_span_suggestion_block(
+        mut self,
+        sp: Span,
+        msg: impl Into<SubdiagnosticMessage>,
+        lines: impl IntoIterator<Item = impl AsRef<str>>,
+        applicability: Applicability,
+    ) -> Self {
+        self.span_suggestion_block(sp, msg, lines, applicability);
+        self
+    }
 }
 
 impl<G: EmissionGuarantee> Debug for DiagnosticBuilder<'_, G> {
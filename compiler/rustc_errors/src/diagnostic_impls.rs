@@ -1,12 +1,15 @@
 use crate::diagnostic::DiagnosticLocation;
 use crate::{fluent_generated as fluent, AddToDiagnostic};
 use crate::{
-    DiagCtxt, DiagnosticArgValue, DiagnosticBuilder, EmissionGuarantee, IntoDiagnostic,
-    IntoDiagnosticArg, Level,
+    AsDiagnosticArg, DiagCtxt, DiagnosticArgValue, DiagnosticBuilder, DiagnosticFloat,
+    DiagnosticMessage, EmissionGuarantee, IntoDiagnostic, IntoDiagnosticArg, Level, StructuredArg,
+    SubdiagnosticMessage,
 };
 use rustc_ast as ast;
 use rustc_ast_pretty::pprust;
+use rustc_error_messages::MAX_FLUENT_INTEGER;
 use rustc_hir as hir;
+use rustc_span::edit_distance::find_best_match_for_name;
 use rustc_span::edition::Edition;
 use rustc_span::symbol::{Ident, MacroRulesNormalizedIdent, Symbol};
 use rustc_span::Span;
@@ -19,6 +22,56 @@
 use std::num::ParseIntError;
 use std::path::{Path, PathBuf};
 use std::process::ExitStatus;
+use std::time::Duration;
+
+/// An argument that renders as the code that the span points to, trimmed and quoted with
+/// backticks, see `DiagnosticArgValue::Snippet`.
+#[derive(Clone, Copy, Debug)]
+pub struct DiagnosticArgSnippet(pub Span);
+
+impl IntoDiagnosticArg for DiagnosticArgSnippet {
+    fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        DiagnosticArgValue::Snippet(self.0)
+    }
+}
+
+/// Converts references to types that are `Copy` by copying the value.
+macro_rules! as_diagnostic_arg_by_copy {
+    ($( $ty:ty ),+ $(,)?) => {
+        $(
+            impl AsDiagnosticArg for $ty {
+                fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+                    (*self).into_diagnostic_arg()
+                }
+            }
+        )+
+    }
+}
+
+as_diagnostic_arg_by_copy!(
+    DiagnosticArgSnippet,
+    i8,
+    u8,
+    i16,
+    u16,
+    i32,
+    u32,
+    i64,
+    u64,
+    i128,
+    u128,
+    isize,
+    usize,
+    f64,
+    f32,
+    bool,
+    char,
+    Duration,
+    Symbol,
+    hir::ConstContext,
+    type_ir::ClosureKind,
+    Level,
+);
 
 pub struct DiagnosticArgFromDisplay<'a>(pub &'a dyn fmt::Display);
 
@@ -40,9 +93,9 @@ fn from(t: &'a T) -> Self {
     }
 }
 
-impl<'a, T: Clone + IntoDiagnosticArg> IntoDiagnosticArg for &'a T {
+impl<'a, T: AsDiagnosticArg> IntoDiagnosticArg for &'a T {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        self.clone().into_diagnostic_arg()
+        self.as_diagnostic_arg()
     }
 }
 
@@ -51,6 +104,12 @@ macro_rules! into_diagnostic_arg_using_display {
         $(
             impl IntoDiagnosticArg for $ty {
                 fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+                    self.as_diagnostic_arg()
+                }
+            }
+
+            impl AsDiagnosticArg for $ty {
+                fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
                     self.to_string().into_diagnostic_arg()
                 }
             }
@@ -67,8 +126,10 @@ fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
                     // numbers using a f64 which can't represent all the i128 numbers
                     // So in order to be able to use fluent selectors and still
                     // have all the numbers representable we only convert numbers
-                    // below a certain threshold.
-                    if let Ok(n) = TryInto::<i128>::try_into(self) && n >= -100 && n <= 100 {
+                    // that a f64 represents exactly.
+                    if let Ok(n) = TryInto::<i128>::try_into(self)
+                        && n.unsigned_abs() <= MAX_FLUENT_INTEGER
+                    {
                         DiagnosticArgValue::Number(n)
                     } else {
                         self.to_string().into_diagnostic_arg()
@@ -84,19 +145,71 @@ fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
     std::io::Error,
     Box<dyn std::error::Error>,
     std::num::NonZeroU32,
-    hir::Target,
     Edition,
     Ident,
     MacroRulesNormalizedIdent,
     ParseIntError,
-    StackProtector,
     &TargetTriple,
-    SplitDebuginfo,
     ExitStatus,
 );
 
+macro_rules! into_diagnostic_arg_using_static_str {
+    ($( $ty:ty => $as_str:ident ),+ $(,)?) => {
+        $(
+            impl IntoDiagnosticArg for $ty {
+                fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+                    self.as_diagnostic_arg()
+                }
+            }
+
+            impl AsDiagnosticArg for $ty {
+                fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+                    DiagnosticArgValue::Str(Cow::Borrowed(self.$as_str()))
+                }
+            }
+        )+
+    }
+}
+
+into_diagnostic_arg_using_static_str!(
+    hir::Target => name,
+    PanicStrategy => desc,
+    StackProtector => as_str,
+    SplitDebuginfo => as_str,
+    type_ir::FloatTy => name_str,
+    rustc_lint_defs::Level => to_cmd_flag,
+);
+
 into_diagnostic_arg_for_number!(i8, u8, i16, u16, i32, u32, i64, u64, i128, u128, isize, usize);
 
+impl IntoDiagnosticArg for f64 {
+    fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        DiagnosticArgValue::Float(DiagnosticFloat(self))
+    }
+}
+
+impl IntoDiagnosticArg for f32 {
+    fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        // Converting with `as` would render e.g. `0.1f32` as `0.10000000149011612`, going through
+        // the shortest representation of the `f32` keeps the value the user would expect.
+        let f = self.to_string().parse().unwrap_or(self as f64);
+        DiagnosticArgValue::Float(DiagnosticFloat(f))
+    }
+}
+
+impl IntoDiagnosticArg for Duration {
+    fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        // The `Debug` output picks a suitable unit, e.g. `1.25s` or `300ms`.
+        DiagnosticArgValue::Str(Cow::Owned(format!("{self:?}")))
+    }
+}
+
+impl IntoDiagnosticArg for StructuredArg {
+    fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        DiagnosticArgValue::Structured(self)
+    }
+}
+
 impl IntoDiagnosticArg for bool {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
         if self {
@@ -115,15 +228,21 @@ fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
 
 impl IntoDiagnosticArg for Vec<char> {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for Vec<char> {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::StrListSepByAnd(
-            self.into_iter().map(|c| Cow::Owned(format!("{c:?}"))).collect(),
+            self.iter().map(|c| Cow::Owned(format!("{c:?}"))).collect(),
         )
     }
 }
 
 impl IntoDiagnosticArg for Symbol {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        self.to_ident_string().into_diagnostic_arg()
+        DiagnosticArgValue::Symbol(self)
     }
 }
 
@@ -139,12 +258,24 @@ fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
     }
 }
 
+impl AsDiagnosticArg for String {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        self.as_str().into_diagnostic_arg()
+    }
+}
+
 impl<'a> IntoDiagnosticArg for Cow<'a, str> {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::Str(Cow::Owned(self.into_owned()))
     }
 }
 
+impl<'a> AsDiagnosticArg for Cow<'a, str> {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        (**self).into_diagnostic_arg()
+    }
+}
+
 impl<'a> IntoDiagnosticArg for &'a Path {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::Str(Cow::Owned(self.display().to_string()))
@@ -153,13 +284,13 @@ fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
 
 impl IntoDiagnosticArg for PathBuf {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(Cow::Owned(self.display().to_string()))
+        self.as_path().into_diagnostic_arg()
     }
 }
 
-impl IntoDiagnosticArg for PanicStrategy {
-    fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(Cow::Owned(self.desc().to_string()))
+impl AsDiagnosticArg for PathBuf {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        self.as_path().into_diagnostic_arg()
     }
 }
 
@@ -175,79 +306,168 @@ fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
 
 impl IntoDiagnosticArg for ast::Expr {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(Cow::Owned(pprust::expr_to_string(&self)))
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for ast::Expr {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        DiagnosticArgValue::Str(Cow::Owned(pprust::expr_to_string(self)))
     }
 }
 
 impl IntoDiagnosticArg for ast::Path {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(Cow::Owned(pprust::path_to_string(&self)))
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for ast::Path {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        DiagnosticArgValue::Str(Cow::Owned(pprust::path_to_string(self)))
     }
 }
 
 impl IntoDiagnosticArg for ast::token::Token {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(pprust::token_to_string(&self))
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for ast::token::Token {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        DiagnosticArgValue::Str(pprust::token_to_string(self))
     }
 }
 
 impl IntoDiagnosticArg for ast::token::TokenKind {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(pprust::token_kind_to_string(&self))
+        self.as_diagnostic_arg()
     }
 }
 
-impl IntoDiagnosticArg for type_ir::FloatTy {
-    fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(Cow::Borrowed(self.name_str()))
+impl AsDiagnosticArg for ast::token::TokenKind {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        DiagnosticArgValue::Str(pprust::token_kind_to_string(self))
     }
 }
 
 impl IntoDiagnosticArg for std::ffi::CString {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for std::ffi::CString {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::Str(Cow::Owned(self.to_string_lossy().into_owned()))
     }
 }
 
 impl IntoDiagnosticArg for rustc_data_structures::small_c_str::SmallCStr {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for rustc_data_structures::small_c_str::SmallCStr {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::Str(Cow::Owned(self.to_string_lossy().into_owned()))
     }
 }
 
 impl IntoDiagnosticArg for ast::Visibility {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        let s = pprust::vis_to_string(&self);
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for ast::Visibility {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        let s = pprust::vis_to_string(self);
         let s = s.trim_end().to_string();
         DiagnosticArgValue::Str(Cow::Owned(s))
     }
 }
 
-impl IntoDiagnosticArg for rustc_lint_defs::Level {
+#[derive(Clone)]
+pub struct DiagnosticSymbolList {
+    symbols: Vec<Symbol>,
+    cap: Option<usize>,
+}
+
+impl DiagnosticSymbolList {
+    /// Only renders the first `cap` symbols, followed by "and N more", so that long lists don't
+    /// flood the terminal. The JSON output still contains all of them.
+    pub fn with_cap(mut self, cap: usize) -> Self {
+        self.cap = Some(cap);
+        self
+    }
+}
+
+impl From<Vec<Symbol>> for DiagnosticSymbolList {
+    fn from(symbols: Vec<Symbol>) -> Self {
+        DiagnosticSymbolList { symbols, cap: None }
+    }
+}
+
+impl IntoDiagnosticArg for DiagnosticSymbolList {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
-        DiagnosticArgValue::Str(Cow::Borrowed(self.to_cmd_flag()))
+        let symbols = self.symbols.into_iter().map(|sym| Cow::Owned(format!("`{sym}`"))).collect();
+        match self.cap {
+            Some(cap) => DiagnosticArgValue::StrListSepByAndCapped(symbols, cap),
+            None => DiagnosticArgValue::StrListSepByAnd(symbols),
+        }
     }
 }
 
+/// A list of diagnostic arguments of any type, rendered like "a, b and c". Unlike
+/// `DiagnosticSymbolList`, the elements are rendered as they would be on their own, without
+/// adding backticks. Snippets can't be elements, as their code is only looked up when the
+/// diagnostic is emitted.
 #[derive(Clone)]
-pub struct DiagnosticSymbolList(Vec<Symbol>);
+pub struct DiagnosticArgList<T>(pub Vec<T>);
 
-impl From<Vec<Symbol>> for DiagnosticSymbolList {
-    fn from(v: Vec<Symbol>) -> Self {
-        DiagnosticSymbolList(v)
+impl<T> From<Vec<T>> for DiagnosticArgList<T> {
+    fn from(v: Vec<T>) -> Self {
+        DiagnosticArgList(v)
     }
 }
 
-impl IntoDiagnosticArg for DiagnosticSymbolList {
+impl<T: IntoDiagnosticArg> IntoDiagnosticArg for DiagnosticArgList<T> {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::StrListSepByAnd(
-            self.0.into_iter().map(|sym| Cow::Owned(format!("`{sym}`"))).collect(),
+            self.0.into_iter().map(|arg| list_element(arg.into_diagnostic_arg())).collect(),
         )
     }
 }
 
+/// Renders an argument as an element of a list argument.
+fn list_element(arg: DiagnosticArgValue<'static>) -> Cow<'static, str> {
+    match arg {
+        DiagnosticArgValue::Str(s) => s,
+        DiagnosticArgValue::Symbol(s) => Cow::Owned(s.to_ident_string()),
+        DiagnosticArgValue::Number(n) => Cow::Owned(n.to_string()),
+        DiagnosticArgValue::Float(f) => Cow::Owned(f.0.to_string()),
+        DiagnosticArgValue::StrListSepByAnd(l)
+        | DiagnosticArgValue::StrListSepByAndCapped(l, _)
+        | DiagnosticArgValue::StrListSepByOr(l) => Cow::Owned(l.join(", ")),
+        DiagnosticArgValue::StrListSepByCustom(l, sep) => Cow::Owned(l.join(&*sep)),
+        DiagnosticArgValue::Structured(s) => Cow::Owned(s.to_string()),
+        DiagnosticArgValue::Snippet(_) => {
+            panic!("snippets can't be elements of a `DiagnosticArgList`, use a separate argument")
+        }
+    }
+}
+
 impl<Id> IntoDiagnosticArg for hir::def::Res<Id> {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        self.as_diagnostic_arg()
+    }
+}
+
+impl<Id> AsDiagnosticArg for hir::def::Res<Id> {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::Str(Cow::Borrowed(self.descr()))
     }
 }
@@ -280,8 +500,7 @@ fn into_diagnostic(self, dcx: &DiagCtxt, level: Level) -> DiagnosticBuilder<'_,
             }
             TargetDataLayoutErrors::InconsistentTargetArchitecture { dl, target } => {
                 DiagnosticBuilder::new(dcx, level, fluent::errors_target_inconsistent_architecture)
-                    .with_arg("dl", dl)
-                    .with_arg("target", target)
+                    .with_args([("dl", dl), ("target", target)])
             }
             TargetDataLayoutErrors::InconsistentTargetPointerWidth { pointer_size, target } => {
                 DiagnosticBuilder::new(dcx, level, fluent::errors_target_inconsistent_pointer_width)
@@ -299,11 +518,41 @@ fn into_diagnostic(self, dcx: &DiagCtxt, level: Level) -> DiagnosticBuilder<'_,
 /// Utility struct used to apply a single label while highlighting multiple spans
 pub struct SingleLabelManySpans {
     pub spans: Vec<Span>,
-    pub label: &'static str,
+    pub label: SubdiagnosticMessage,
+    /// Arguments of `label`.
+    pub args: Vec<(Cow<'static, str>, DiagnosticArgValue<'static>)>,
 }
 impl AddToDiagnostic for SingleLabelManySpans {
-    fn add_to_diagnostic_with<F>(self, diag: &mut crate::Diagnostic, _: F) {
-        diag.span_labels(self.spans, self.label);
+    fn add_to_diagnostic_with<F>(self, diag: &mut crate::Diagnostic, f: F)
+    where
+        F: Fn(&mut crate::Diagnostic, SubdiagnosticMessage) -> SubdiagnosticMessage,
+    {
+        diag.set_args(self.args);
+        let label = f(diag, self.label);
+        for span in self.spans {
+            diag.span_label(span, label.clone());
+        }
+    }
+}
+
+/// Utility struct used to apply a different label to each of multiple spans
+pub struct ManyLabelsManySpans {
+    pub labels: Vec<(Span, DiagnosticMessage)>,
+    /// Arguments of each label, in the same order as `labels`. Labels without an entry don't have
+    /// arguments of their own.
+    pub args: Vec<Vec<(Cow<'static, str>, DiagnosticArgValue<'static>)>>,
+}
+impl AddToDiagnostic for ManyLabelsManySpans {
+    fn add_to_diagnostic_with<F>(self, diag: &mut crate::Diagnostic, f: F)
+    where
+        F: Fn(&mut crate::Diagnostic, SubdiagnosticMessage) -> SubdiagnosticMessage,
+    {
+        let mut args = self.args.into_iter();
+        for (span, label) in self.labels {
+            diag.set_args(args.next().unwrap_or_default());
+            let label = f(diag, label.into());
+            diag.span_label(span, label);
+        }
     }
 }
 
@@ -329,17 +578,28 @@ pub struct DelayedAtWithoutNewline {
     #[primary_span]
     pub span: Span,
     pub emitted_at: DiagnosticLocation,
-    pub note: Backtrace,
 }
 
 impl IntoDiagnosticArg for DiagnosticLocation {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for DiagnosticLocation {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::Str(Cow::from(self.to_string()))
     }
 }
 
 impl IntoDiagnosticArg for Backtrace {
     fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
+        self.as_diagnostic_arg()
+    }
+}
+
+impl AsDiagnosticArg for Backtrace {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
         DiagnosticArgValue::Str(Cow::from(self.to_string()))
     }
 }
@@ -357,6 +617,73 @@ fn into_diagnostic_arg(self) -> DiagnosticArgValue<'static> {
     }
 }
 
+/// Finds the candidate that is most similar to `name`, for "did you mean" suggestions. Candidates
+/// that are too different from `name` or equal to it are never returned.
+///
+/// This uses the same edit distance heuristics as the suggestions for misspelled names elsewhere
+/// in the compiler, see [`find_best_match_for_name`].
+pub fn suggest_similar(
+    name: Symbol,
+    candidates: impl IntoIterator<Item = Symbol>,
+) -> Option<Symbol> {
+    let candidates =
+        candidates.into_iter().filter(|&candidate| candidate != name).collect::<Vec<_>>();
+    find_best_match_for_name(&candidates, name, None)
+}
+
+/// Suggests replacing a misspelled name with a similar one, rendered as
+/// "a function with a similar name exists".
+#[derive(Subdiagnostic)]
+#[suggestion(
+    errors_similar_name,
+    code = "{candidate}",
+    applicability = "maybe-incorrect",
+    style = "verbose"
+)]
+pub struct SimilarName {
+    #[primary_span]
+    pub span: Span,
+    /// Description of the named item with its article, e.g. "a function".
+    pub kind: &'static str,
+    /// The candidate, which is suggested as a raw identifier if it is a keyword in the edition of
+    /// `span`, so that the suggested code is valid.
+    pub candidate: Ident,
+}
+
+impl SimilarName {
+    /// Suggests the candidate most similar to `name` for the misspelled name at `span`, if there
+    /// is one, see [`suggest_similar`].
+    pub fn new(
+        span: Span,
+        kind: &'static str,
+        name: Symbol,
+        candidates: impl IntoIterator<Item = Symbol>,
+    ) -> Option<SimilarName> {
+        let candidate = suggest_similar(name, candidates)?;
+        Some(SimilarName { span, kind, candidate: Ident::new(candidate, span) })
+    }
+}
+
+/// Points out a character that is easily confused with another one and suggests replacing it,
+/// rendered as "Unicode character '−' (U+2212) looks like '-', but it is not".
+#[derive(Subdiagnostic)]
+#[suggestion(errors_confusable_char, code = "{expected}", applicability = "maybe-incorrect")]
+pub struct ConfusableCharNote {
+    /// Span of the confusable character.
+    #[primary_span]
+    pub span: Span,
+    pub found: char,
+    /// Code point of `found` in hexadecimal.
+    pub codepoint: String,
+    pub expected: char,
+}
+
+impl ConfusableCharNote {
+    pub fn new(span: Span, found: char, expected: char) -> ConfusableCharNote {
+        ConfusableCharNote { span, found, codepoint: format!("{:04X}", found as u32), expected }
+    }
+}
+
 #[derive(Subdiagnostic)]
 #[suggestion(errors_indicate_anonymous_lifetime, code = "{suggestion}", style = "verbose")]
 pub struct IndicateAnonymousLifetime {
@@ -8,23 +8,27 @@
 //! The output types are defined in `rustc_session::config::ErrorOutputType`.
 
 use rustc_span::source_map::SourceMap;
-use rustc_span::{FileLines, FileName, SourceFile, Span};
+use rustc_span::{FileLines, FileName, Pos, SourceFile, Span, StableSourceFileId};
 
+use crate::registry::Registry;
 use crate::snippet::{
     Annotation, AnnotationColumn, AnnotationType, Line, MultilineAnnotation, Style, StyledString,
 };
+use crate::source_provider::SourceProvider;
 use crate::styled_buffer::StyledBuffer;
-use crate::translation::{to_fluent_args, Translate};
+use crate::translation::{span_label_args, to_fluent_args, Translate};
 use crate::{
     diagnostic::DiagnosticLocation, CodeSuggestion, DiagCtxt, Diagnostic, DiagnosticMessage,
-    FluentBundle, LazyFallbackBundle, Level, MultiSpan, SubDiagnostic, SubstitutionHighlight,
-    SuggestionStyle, TerminalUrl,
+    FileSuggestion, FluentBundle, LazyFallbackBundle, Level, MacroBacktrace, MultiSpan,
+    SubDiagnostic, SubdiagnosticPriority, Substitution, SubstitutionHighlight, SuggestionStyle,
+    TerminalUrl, UnfulfilledExpectation,
 };
 use rustc_lint_defs::pluralize;
 
 use derive_setters::Setters;
-use rustc_data_structures::fx::{FxHashMap, FxIndexMap, FxIndexSet};
-use rustc_data_structures::sync::{DynSend, IntoDynSyncSend, Lrc};
+use rustc_data_structures::fx::{FxHashMap, FxHashSet, FxIndexMap, FxIndexSet};
+use rustc_data_structures::profiling::SelfProfilerRef;
+use rustc_data_structures::sync::{DynSend, IntoDynSyncSend, Lock, Lrc};
 use rustc_error_messages::{FluentArgs, SpanLabel};
 use rustc_span::hygiene::{ExpnKind, MacroKind};
 use std::borrow::Cow;
@@ -33,13 +37,23 @@
 use std::io::prelude::*;
 use std::io::{self, IsTerminal};
 use std::iter;
-use std::path::Path;
-use termcolor::{Ansi, Buffer, BufferWriter, ColorChoice, ColorSpec, StandardStream};
+use std::path::{Path, PathBuf};
+use termcolor::{Ansi, Buffer, BufferWriter, ColorChoice, ColorSpec, NoColor, StandardStream};
 use termcolor::{Color, WriteColor};
 
+#[cfg(test)]
+mod tests;
+
 /// Default column width, used in tests and when terminal dimensions cannot be determined.
 const DEFAULT_COLUMN_WIDTH: usize = 140;
 
+/// Lines longer than this many bytes, which usually come from generated code, take too long to
+/// highlight, so only an excerpt of them is shown, see `HumanEmitter::render_long_source_line`.
+const MAX_HIGHLIGHTED_LINE_LEN: usize = 1 << 16;
+
+/// Number of source lines the `HumanEmitter` keeps cached, see `HumanEmitter::source_line`.
+const LINE_CACHE_CAPACITY: usize = 1024;
+
 /// Describes the way the content of the `rendered` field of the json output is generated
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum HumanReadableErrorType {
@@ -203,6 +217,10 @@ fn emit_artifact_notification(&mut self, _path: &Path, _artifact_type: &str) {}
     /// Currently only supported for the JSON format.
     fn emit_future_breakage_report(&mut self, _diags: Vec<Diagnostic>) {}
 
+    /// Emit a diagnostic that will break in the future as soon as it is emitted, for tools that
+    /// want to attribute it to its span. Currently only supported for the JSON format.
+    fn emit_future_breakage_diagnostic(&mut self, _diag: &Diagnostic) {}
+
     /// Emit list of unused externs.
     /// Currently only supported for the JSON format.
     fn emit_unused_externs(
@@ -212,6 +230,19 @@ fn emit_unused_externs(
     ) {
     }
 
+    /// Emit the list of `#[expect]` attributes whose lints were never emitted.
+    /// Currently only supported for the JSON format.
+    fn emit_unfulfilled_expectations(&mut self, _expectations: &[UnfulfilledExpectation]) {}
+
+    /// Emit a header for the diagnostics that follow, such as the file that they point into
+    /// with `-Zgroup-diagnostics-by-file`.
+    /// Currently only supported for the human-readable format.
+    fn emit_group_header(&mut self, _header: &str) {}
+
+    /// Writes what the emitter held back until the end of the compilation, like the JSON array
+    /// of `-Zjson-array`.
+    fn finish(&mut self) {}
+
     /// Checks if should show explanations about "rustc --explain"
     fn should_show_explain(&self) -> bool {
         true
@@ -224,6 +255,16 @@ fn supports_color(&self) -> bool {
 
     fn source_map(&self) -> Option<&Lrc<SourceMap>>;
 
+    /// Where the source code and file names that the emitter shows come from, the source map
+    /// unless the emitter was given another `SourceProvider`.
+    fn source(&self) -> Option<&dyn SourceProvider> {
+        self.source_map().map(|sm| &**sm as &dyn SourceProvider)
+    }
+
+    /// Gives the emitter the profiler of the session, to record how long writing diagnostics takes
+    /// with `-Zself-profile`.
+    fn set_self_profiler(&mut self, _prof: SelfProfilerRef) {}
+
     /// Formats the substitutions of the primary_span
     ///
     /// There are a lot of conditions to this method, but in short:
@@ -240,7 +281,7 @@ fn primary_span_formatted<'a>(
         fluent_args: &FluentArgs<'_>,
     ) -> (MultiSpan, &'a [CodeSuggestion]) {
         let mut primary_span = diag.span.clone();
-        let suggestions = diag.suggestions.as_deref().unwrap_or(&[]);
+        let suggestions = diag.suggestions().unwrap_or(&[]);
         if let Some((sugg, rest)) = suggestions.split_first() {
             let msg = self.translate_message(&sugg.msg, fluent_args).map_err(Report::new).unwrap();
             if rest.is_empty() &&
@@ -260,6 +301,8 @@ fn primary_span_formatted<'a>(
                     SuggestionStyle::CompletelyHidden,
                     // subtle suggestion, never shown inline
                     SuggestionStyle::ShowAlways,
+                    // shown as a diff of the affected lines, never shown inline
+                    SuggestionStyle::Diff,
                ].contains(&sugg.style)
             {
                 let substitution = &sugg.substitutions[0].parts[0].snippet.trim();
@@ -272,8 +315,8 @@ fn primary_span_formatted<'a>(
                     format!(
                         "help: {}{}: `{}`",
                         msg,
-                        if self.source_map().is_some_and(|sm| is_case_difference(
-                            sm,
+                        if self.source().is_some_and(|source| is_case_difference(
+                            source,
                             substitution,
                             sugg.substitutions[0].parts[0].span,
                         )) {
@@ -305,7 +348,7 @@ fn fix_multispans_in_extern_macros_and_render_macro_backtrace(
         span: &mut MultiSpan,
         children: &mut Vec<SubDiagnostic>,
         level: &Level,
-        backtrace: bool,
+        backtrace: MacroBacktrace,
     ) {
         // Check for spans in macros, before `fix_multispans_in_extern_macros`
         // has a chance to replace them.
@@ -326,13 +369,30 @@ fn fix_multispans_in_extern_macros_and_render_macro_backtrace(
             })
             .collect();
 
-        if !backtrace {
+        if backtrace == MacroBacktrace::No {
             self.fix_multispans_in_extern_macros(span, children);
         }
 
-        self.render_multispans_macro_backtrace(span, children, backtrace);
+        let hidden_frames = self.render_multispans_macro_backtrace(span, children, backtrace);
+
+        if hidden_frames > 0 {
+            let s = pluralize!(hidden_frames);
+            children.push(SubDiagnostic {
+                level: Level::Note,
+                messages: vec![(
+                    DiagnosticMessage::from(format!(
+                        "{hidden_frames} macro expansion frame{s} hidden, \
+                        re-run with -Z macro-backtrace for all"
+                    )),
+                    Style::NoStyle,
+                )],
+                span: MultiSpan::new(),
+                priority: SubdiagnosticPriority::Normal,
+                url: None,
+            });
+        }
 
-        if !backtrace {
+        if backtrace == MacroBacktrace::No {
             if let Some((macro_kind, name)) = has_macro_spans.first() {
                 // Mark the actual macro this originates from
                 let and_then = if let Some((macro_kind, last_name)) = has_macro_spans.last()
@@ -354,6 +414,8 @@ fn fix_multispans_in_extern_macros_and_render_macro_backtrace(
                     level: Level::Note,
                     messages: vec![(DiagnosticMessage::from(msg), Style::NoStyle)],
                     span: MultiSpan::new(),
+                    priority: SubdiagnosticPriority::Normal,
+                    url: None,
                 });
             }
         }
@@ -363,14 +425,24 @@ fn render_multispans_macro_backtrace(
         &self,
         span: &mut MultiSpan,
         children: &mut Vec<SubDiagnostic>,
-        backtrace: bool,
-    ) {
+        backtrace: MacroBacktrace,
+    ) -> usize {
+        let mut hidden_frames = FxIndexSet::default();
         for span in iter::once(span).chain(children.iter_mut().map(|child| &mut child.span)) {
-            self.render_multispan_macro_backtrace(span, backtrace);
+            self.render_multispan_macro_backtrace(span, backtrace, &mut hidden_frames);
         }
+        hidden_frames.len()
     }
 
-    fn render_multispan_macro_backtrace(&self, span: &mut MultiSpan, always_backtrace: bool) {
+    /// Adds labels for the macro backtrace of `span`. The call sites of expansions that are left
+    /// out of a collapsed or depth-limited backtrace are added to `hidden_frames`.
+    fn render_multispan_macro_backtrace(
+        &self,
+        span: &mut MultiSpan,
+        backtrace: MacroBacktrace,
+        hidden_frames: &mut FxIndexSet<Span>,
+    ) {
+        let always_backtrace = backtrace != MacroBacktrace::No;
         let mut new_labels = FxIndexSet::default();
 
         for &sp in span.primary_spans() {
@@ -383,10 +455,36 @@ fn render_multispan_macro_backtrace(&self, span: &mut MultiSpan, always_backtrac
             // printed are contiguous (or omitted if there's only one entry).
             let macro_backtrace: Vec<_> = sp.macro_backtrace().collect();
             for (i, trace) in macro_backtrace.iter().rev().enumerate() {
+                if let MacroBacktrace::Depth(depth) = backtrace
+                    && i == depth
+                {
+                    hidden_frames.extend(
+                        macro_backtrace
+                            .iter()
+                            .rev()
+                            .skip(i)
+                            .filter(|trace| !trace.def_site.is_dummy())
+                            .map(|trace| trace.call_site),
+                    );
+                    break;
+                }
+
                 if trace.def_site.is_dummy() {
                     continue;
                 }
 
+                // Collapsed backtraces keep the outermost and innermost expansions, which show
+                // where the macro was used and where the problem is, and the expansions of macros
+                // from this crate, which are the ones the user can change.
+                if backtrace == MacroBacktrace::Collapsed
+                    && i != 0
+                    && i != macro_backtrace.len() - 1
+                    && !trace.macro_def_id.is_some_and(|def_id| def_id.is_local())
+                {
+                    hidden_frames.insert(trace.call_site);
+                    continue;
+                }
+
                 if always_backtrace {
                     new_labels.insert((
                         trace.def_site,
@@ -511,6 +609,17 @@ fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
     fn fallback_fluent_bundle(&self) -> &FluentBundle {
         &self.fallback_bundle
     }
+
+    fn finish_message<'a>(&self, message: Cow<'a, str>) -> Cow<'a, str> {
+        if !self.normalize_output {
+            return message;
+        }
+        let normalized = match normalize_symbol_hashes(&message) {
+            Cow::Owned(normalized) => Some(normalized),
+            Cow::Borrowed(_) => None,
+        };
+        normalized.map_or(message, Cow::Owned)
+    }
 }
 
 impl Emitter for HumanEmitter {
@@ -518,6 +627,14 @@ fn source_map(&self) -> Option<&Lrc<SourceMap>> {
         self.sm.as_ref()
     }
 
+    fn source(&self) -> Option<&dyn SourceProvider> {
+        self.sm.as_deref().map(|sm| self.sources(sm))
+    }
+
+    fn set_self_profiler(&mut self, prof: SelfProfilerRef) {
+        self.prof = prof;
+    }
+
     fn emit_diagnostic(&mut self, diag: &Diagnostic) {
         let fluent_args = to_fluent_args(diag.args());
 
@@ -532,16 +649,53 @@ fn emit_diagnostic(&mut self, diag: &Diagnostic) {
             self.macro_backtrace,
         );
 
+        if let Some(explanation) = self.inline_explanation(diag.code.as_ref()) {
+            children.push(SubDiagnostic {
+                level: Level::Note,
+                messages: vec![(DiagnosticMessage::from(explanation), Style::NoStyle)],
+                span: MultiSpan::new(),
+                priority: SubdiagnosticPriority::Last,
+                url: None,
+            });
+        }
+
+        // Diagnostics can opt out of the global width, e.g. so that long types aren't truncated.
+        let diagnostic_width = self.diagnostic_width;
+        if let Some(width) = diag.extras().and_then(|extras| extras.rendering_width) {
+            self.diagnostic_width = Some(width);
+        }
+
         self.emit_messages_default(
             &diag.level,
+            diag.extras()
+                .and_then(|extras| extras.tool.as_ref())
+                .map(|tool| tool.display_prefix.as_str()),
             &diag.messages,
             &fluent_args,
             &diag.code,
             &primary_span,
             &children,
             suggestions,
+            diag.file_suggestions(),
             self.track_diagnostics.then_some(&diag.emitted_at),
+            diag.extras().map(|extras| extras.query_stack.as_slice()).unwrap_or_default(),
         );
+
+        self.diagnostic_width = diagnostic_width;
+    }
+
+    fn emit_group_header(&mut self, header: &str) {
+        let mut buffer = StyledBuffer::new();
+        buffer.append(0, header, Style::MainHeaderMsg);
+        if let Err(e) = emit_to_destination(
+            &buffer.render(),
+            &Level::Note,
+            &mut self.dst,
+            self.short_message,
+            &self.prof,
+        ) {
+            panic!("failed to emit header: {e}")
+        }
     }
 
     fn should_show_explain(&self) -> bool {
@@ -553,40 +707,146 @@ fn supports_color(&self) -> bool {
     }
 }
 
-/// An emitter that does nothing when emitting a non-fatal diagnostic.
-/// Fatal diagnostics are forwarded to `fatal_dcx` to avoid silent
-/// failures of rustc, as witnessed e.g. in issue #89358.
-pub struct SilentEmitter {
-    pub fatal_dcx: DiagCtxt,
-    pub fatal_note: Option<String>,
+/// An emitter that doesn't show diagnostics, for drivers that only want to know whether the code
+/// is accepted. The `DiagCtxt` still counts the diagnostics, so `DiagCtxt::has_errors` and
+/// `DiagCtxt::counts` keep working.
+///
+/// Fatal diagnostics can be forwarded to another `DiagCtxt` with `NullEmitter::forward_fatal` to
+/// avoid silent failures of rustc, as witnessed e.g. in issue #89358.
+#[derive(Default)]
+pub struct NullEmitter {
+    fatal_dcx: Option<DiagCtxt>,
+    fatal_note: Option<String>,
+    error_codes: Option<Lrc<Lock<FxIndexSet<String>>>>,
+}
+
+impl NullEmitter {
+    pub fn new() -> NullEmitter {
+        NullEmitter::default()
+    }
+
+    /// Emits fatal diagnostics to `dcx` instead of dropping them, with `note` added to them, such
+    /// as where the code that couldn't be parsed comes from.
+    pub fn forward_fatal(mut self, dcx: DiagCtxt, note: Option<String>) -> NullEmitter {
+        self.fatal_dcx = Some(dcx);
+        self.fatal_note = note;
+        self
+    }
+
+    /// Records the codes of the errors that are emitted in `codes`, in the order they are first
+    /// emitted.
+    pub fn record_error_codes(mut self, codes: Lrc<Lock<FxIndexSet<String>>>) -> NullEmitter {
+        self.error_codes = Some(codes);
+        self
+    }
 }
 
-impl Translate for SilentEmitter {
+impl Translate for NullEmitter {
     fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
         None
     }
 
     fn fallback_fluent_bundle(&self) -> &FluentBundle {
-        panic!("silent emitter attempted to translate message")
+        panic!("null emitter attempted to translate message")
     }
 }
 
-impl Emitter for SilentEmitter {
+impl Emitter for NullEmitter {
     fn source_map(&self) -> Option<&Lrc<SourceMap>> {
         None
     }
 
     fn emit_diagnostic(&mut self, d: &Diagnostic) {
-        if d.level == Level::Fatal {
+        if let Some(codes) = &self.error_codes
+            && d.is_error()
+            && let Some(code) = &d.code
+        {
+            codes.lock().insert(code.clone());
+        }
+        if d.level == Level::Fatal
+            && let Some(fatal_dcx) = &self.fatal_dcx
+        {
             let mut d = d.clone();
             if let Some(ref note) = self.fatal_note {
                 d.note(note.clone());
             }
-            self.fatal_dcx.emit_diagnostic(d);
+            fatal_dcx.emit_diagnostic(d);
         }
     }
 }
 
+/// An emitter that keeps the diagnostics as they are instead of rendering them, for drivers and
+/// tools that want to look at them, render them their own way or emit them again later. The
+/// diagnostics are taken out with the `CapturedDiagnostics` returned by `CaptureEmitter::new`.
+pub struct CaptureEmitter {
+    sm: Option<Lrc<SourceMap>>,
+    fluent_bundle: Option<Lrc<FluentBundle>>,
+    fallback_bundle: LazyFallbackBundle,
+    captured: CapturedDiagnostics,
+}
+
+/// The diagnostics captured by a `CaptureEmitter`. Clones share the same diagnostics, so that
+/// they can be taken from another thread than the one emitting them.
+#[derive(Clone, Default)]
+pub struct CapturedDiagnostics {
+    diagnostics: Lrc<Lock<Vec<Diagnostic>>>,
+    future_breakage: Lrc<Lock<Vec<Diagnostic>>>,
+}
+
+impl CaptureEmitter {
+    pub fn new(
+        sm: Option<Lrc<SourceMap>>,
+        fluent_bundle: Option<Lrc<FluentBundle>>,
+        fallback_bundle: LazyFallbackBundle,
+    ) -> (CaptureEmitter, CapturedDiagnostics) {
+        let captured = CapturedDiagnostics::default();
+        (
+            CaptureEmitter { sm, fluent_bundle, fallback_bundle, captured: captured.clone() },
+            captured,
+        )
+    }
+}
+
+impl CapturedDiagnostics {
+    /// Takes the diagnostics emitted since the last call, in the order they were emitted.
+    pub fn drain(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.diagnostics.lock())
+    }
+
+    /// Takes the diagnostics of the future breakage report, once it has been emitted.
+    pub fn drain_future_breakage(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut *self.future_breakage.lock())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.lock().is_empty()
+    }
+}
+
+impl Translate for CaptureEmitter {
+    fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
+        self.fluent_bundle.as_ref()
+    }
+
+    fn fallback_fluent_bundle(&self) -> &FluentBundle {
+        &self.fallback_bundle
+    }
+}
+
+impl Emitter for CaptureEmitter {
+    fn source_map(&self) -> Option<&Lrc<SourceMap>> {
+        self.sm.as_ref()
+    }
+
+    fn emit_diagnostic(&mut self, diag: &Diagnostic) {
+        self.captured.diagnostics.lock().push(diag.clone());
+    }
+
+    fn emit_future_breakage_report(&mut self, diags: Vec<Diagnostic>) {
+        self.captured.future_breakage.lock().extend(diags);
+    }
+}
+
 /// Maximum number of suggestions to be shown
 ///
 /// Arbitrary, but taken from trait import suggestion limit
@@ -628,6 +888,8 @@ pub struct HumanEmitter {
     #[setters(skip)]
     dst: IntoDynSyncSend<Destination>,
     sm: Option<Lrc<SourceMap>>,
+    /// Where the source code shown comes from instead of `sm`, see `SourceProvider`.
+    source_provider: Option<Lrc<dyn SourceProvider>>,
     fluent_bundle: Option<Lrc<FluentBundle>>,
     #[setters(skip)]
     fallback_bundle: LazyFallbackBundle,
@@ -636,10 +898,31 @@ pub struct HumanEmitter {
     ui_testing: bool,
     ignored_directories_in_source_blocks: Vec<String>,
     diagnostic_width: Option<usize>,
-
-    macro_backtrace: bool,
+    diff_suggestions: bool,
+    /// Directory that file paths are shown relative to, see `HumanEmitter::display_file_name`.
+    path_base: Option<PathBuf>,
+    /// Whether files remapped with `--remap-path-prefix` are shown with their local paths.
+    local_paths: bool,
+    /// Whether the parts of the output that differ between machines and builds are replaced by
+    /// placeholders, see `HumanEmitter::display_location` and `normalize_symbol_hashes`.
+    normalize_output: bool,
+
+    macro_backtrace: MacroBacktrace,
     track_diagnostics: bool,
     terminal_url: TerminalUrl,
+
+    /// How many lines of the long explanation of an error code from `registry` are shown after
+    /// the first error with the code, see `HumanEmitter::inline_explanation`.
+    explain_inline: Option<usize>,
+    registry: Option<Registry>,
+    #[setters(skip)]
+    explained_codes: FxHashSet<String>,
+
+    /// Lines of source files looked up while rendering, see `HumanEmitter::source_line`.
+    #[setters(skip)]
+    line_cache: Lock<FxHashMap<(StableSourceFileId, usize), Option<Lrc<str>>>>,
+    #[setters(skip)]
+    prof: SelfProfilerRef,
 }
 
 #[derive(Debug)]
@@ -659,6 +942,7 @@ fn create(dst: Destination, fallback_bundle: LazyFallbackBundle) -> HumanEmitter
         HumanEmitter {
             dst: IntoDynSyncSend(dst),
             sm: None,
+            source_provider: None,
             fluent_bundle: None,
             fallback_bundle,
             short_message: false,
@@ -666,9 +950,18 @@ fn create(dst: Destination, fallback_bundle: LazyFallbackBundle) -> HumanEmitter
             ui_testing: false,
             ignored_directories_in_source_blocks: Vec::new(),
             diagnostic_width: None,
-            macro_backtrace: false,
+            diff_suggestions: false,
+            path_base: None,
+            local_paths: false,
+            normalize_output: false,
+            macro_backtrace: MacroBacktrace::No,
             track_diagnostics: false,
             terminal_url: TerminalUrl::No,
+            explain_inline: None,
+            registry: None,
+            explained_codes: FxHashSet::default(),
+            line_cache: Default::default(),
+            prof: SelfProfilerRef::new(None, None),
         }
     }
 
@@ -679,6 +972,138 @@ pub fn new(
         Self::create(dst, fallback_bundle)
     }
 
+    /// Writes to `dst` without colors, like to a file.
+    pub fn without_colors(
+        dst: Box<dyn Write + Send>,
+        fallback_bundle: LazyFallbackBundle,
+    ) -> HumanEmitter {
+        Self::create(Box::new(NoColor::new(dst)), fallback_bundle)
+    }
+
+    /// The messages of `child` followed by the URL it points to, if any, which is a hyperlink with
+    /// `-Zterminal-urls`.
+    fn messages_with_url<'a>(
+        &self,
+        child: &'a SubDiagnostic,
+    ) -> Cow<'a, [(DiagnosticMessage, Style)]> {
+        let Some(url) = &child.url else { return Cow::Borrowed(&child.messages) };
+        let link = match self.terminal_url {
+            TerminalUrl::Yes => format!("\x1b]8;;{url}\x07{url}\x1b]8;;\x07"),
+            _ => url.clone(),
+        };
+        let mut messages = child.messages.clone();
+        messages.push((DiagnosticMessage::from(format!(": {link}")), Style::NoStyle));
+        Cow::Owned(messages)
+    }
+
+    /// The long explanation of `code` that `-Zexplain-inline` shows after the first diagnostic
+    /// with the code, trimmed to the configured number of lines. Like `rustc --explain`, the
+    /// attributes of code blocks and their hidden lines are left out, and a code block that is
+    /// cut off is closed again.
+    fn inline_explanation(&mut self, code: Option<&String>) -> Option<String> {
+        let (max_lines, registry) = (self.explain_inline?, self.registry.as_ref()?);
+        let code = code?;
+        let description = registry.try_find_description(code).ok()?;
+        if self.short_message || !self.explained_codes.insert(code.clone()) {
+            return None;
+        }
+        let mut is_in_code_block = false;
+        let mut lines = Vec::new();
+        for line in description.trim().lines() {
+            let indent_level = line.find(|c: char| !c.is_whitespace()).unwrap_or(line.len());
+            let dedented_line = &line[indent_level..];
+            if dedented_line.starts_with("```") {
+                is_in_code_block = !is_in_code_block;
+                lines.push((&line[..indent_level + 3], is_in_code_block));
+            } else if !(is_in_code_block && dedented_line.starts_with("# ")) {
+                lines.push((line, is_in_code_block));
+            }
+        }
+        let shown = &lines[..lines.len().min(max_lines)];
+        let mut explanation = format!("explanation of {code}:\n");
+        explanation.push_str(&shown.iter().map(|&(line, _)| line).collect::<Vec<_>>().join("\n"));
+        if let Some(&(_, true)) = shown.last() {
+            explanation.push_str("\n```");
+        }
+        if lines.len() > max_lines {
+            let command = registry.explain_command(code);
+            explanation
+                .push_str(&format!("\n...\nthe full explanation is shown by `{command} {code}`"));
+        }
+        Some(explanation)
+    }
+
+    /// Returns the line of `file` at `line_index` (0-indexed). Diagnostic-heavy crates render the
+    /// same lines many times, so lines are cached rather than looked up again, which also requires
+    /// taking locks for the files of other crates.
+    fn source_line(&self, file: &SourceFile, line_index: usize) -> Option<Lrc<str>> {
+        let key = (file.stable_id, line_index);
+        let mut cache = self.line_cache.lock();
+        if let Some(line) = cache.get(&key) {
+            return line.clone();
+        }
+        // Diagnostics tend to point at the same few places, so there is no need to be clever
+        // about which lines to evict.
+        if cache.len() >= LINE_CACHE_CAPACITY {
+            cache.clear();
+        }
+        let line = match self.source() {
+            Some(source) => source.line(file, line_index),
+            None => file.get_line(line_index),
+        };
+        let line = line.map(|line| Lrc::from(&*line));
+        cache.insert(key, line.clone());
+        line
+    }
+
+    /// The provider of the source code that is shown for the spans of `sm`, which is `sm`
+    /// itself unless another one was given.
+    fn sources<'a>(&'a self, sm: &'a SourceMap) -> &'a dyn SourceProvider {
+        self.source_provider.as_deref().unwrap_or(sm)
+    }
+
+    /// The path of `file` as shown in diagnostics, relative to `path_base` if the file is within
+    /// that directory. With `local_paths`, remapped files are shown with their local paths if
+    /// those are known.
+    fn display_file_name(&self, sm: &SourceMap, file: &SourceFile) -> String {
+        let name = match self.local_paths.then(|| sm.local_path_of_remapped(file)).flatten() {
+            Some(local_path) => local_path.display().to_string(),
+            None => self.sources(sm).display_name(file),
+        };
+        if let Some(base) = &self.path_base
+            && let Ok(relative) = Path::new(&name).strip_prefix(base)
+            && !relative.as_os_str().is_empty()
+        {
+            return relative.display().to_string();
+        }
+        if self.normalize_output {
+            return normalize_file_name(sm, file, name);
+        }
+        name
+    }
+
+    /// The location of `line` and `col` (both 1-based) in `file` as shown in diagnostics. With
+    /// `normalize_output`, the positions in files of other crates are replaced by placeholders,
+    /// as they change whenever those crates do.
+    fn display_location(
+        &self,
+        sm: &SourceMap,
+        file: &SourceFile,
+        line: usize,
+        col: Option<usize>,
+    ) -> String {
+        let name = self.display_file_name(sm, file);
+        let (line, col) = if self.normalize_output && file.is_imported() {
+            (ANONYMIZED_LINE_NUM.to_string(), col.map(|_| "COL".to_string()))
+        } else {
+            (sm.doctest_offset_line(&file.name, line).to_string(), col.map(|col| col.to_string()))
+        };
+        match col {
+            Some(col) => format!("{name}:{line}:{col}"),
+            None => format!("{name}:{line}"),
+        }
+    }
+
     fn maybe_anonymized(&self, line_num: usize) -> Cow<'static, str> {
         if self.ui_testing {
             Cow::Borrowed(ANONYMIZED_LINE_NUM)
@@ -687,9 +1112,36 @@ fn maybe_anonymized(&self, line_num: usize) -> Cow<'static, str> {
         }
     }
 
+    /// Like `maybe_anonymized`, but the line numbers of files of other crates are also anonymized
+    /// with `normalize_output`, like in `display_location`.
+    fn maybe_anonymized_in(&self, file: &SourceFile, line_num: usize) -> Cow<'static, str> {
+        if self.normalize_output && file.is_imported() {
+            Cow::Borrowed(ANONYMIZED_LINE_NUM)
+        } else {
+            self.maybe_anonymized(line_num)
+        }
+    }
+
+    /// Returns the width that span labels and notes are soft-wrapped to, if any.
+    ///
+    /// When UI testing, text is only wrapped if a width was explicitly requested, so that the
+    /// output doesn't depend on the default width.
+    fn wrap_width(&self) -> Option<usize> {
+        if self.short_message {
+            None
+        } else if let Some(width) = self.diagnostic_width {
+            Some(width)
+        } else if self.ui_testing {
+            None
+        } else {
+            termize::dimensions().map(|(w, _)| w)
+        }
+    }
+
     fn draw_line(
         &self,
         buffer: &mut StyledBuffer,
+        file: &SourceFile,
         source_string: &str,
         line_index: usize,
         line_offset: usize,
@@ -729,7 +1181,7 @@ fn draw_line(
             // We have stripped some code after the right-most span end, make it clear we did so.
             buffer.puts(line_offset, code_offset + taken - 3, "...", Style::LineNumber);
         }
-        buffer.puts(line_offset, 0, &self.maybe_anonymized(line_index), Style::LineNumber);
+        buffer.puts(line_offset, 0, &self.maybe_anonymized_in(file, line_index), Style::LineNumber);
 
         draw_col_separator_no_space(buffer, line_offset, width_offset - 2);
     }
@@ -762,7 +1214,18 @@ fn render_source_line(
             return Vec::new();
         }
 
-        let source_string = match file.get_line(line.line_index - 1) {
+        let source_string = match self.source_line(&file, line.line_index - 1) {
+            Some(s) if s.len() > MAX_HIGHLIGHTED_LINE_LEN => {
+                return self.render_long_source_line(
+                    buffer,
+                    &file,
+                    &s,
+                    line,
+                    width_offset,
+                    code_offset,
+                    margin.column_width,
+                );
+            }
             Some(s) => normalize_whitespace(&s),
             None => return Vec::new(),
         };
@@ -782,6 +1245,7 @@ fn render_source_line(
 
         self.draw_line(
             buffer,
+            &file,
             &source_string,
             line.line_index,
             line_offset,
@@ -1005,6 +1469,48 @@ fn render_source_line(
             return vec![];
         }
 
+        // Soft-wrap labels that don't fit in the rendering width, with a hanging indent aligned
+        // under the start of the label:
+        //
+        //      vec.push(vec.pop().unwrap());
+        //      ---      ^^^ a long label that
+        //      |            has been wrapped
+        //      |
+        //      another long label that has
+        //      been wrapped
+        //
+        // Every line taken by a wrapped label pushes the labels below it down.
+        let wrap_width = self.wrap_width();
+        let label_lines: Vec<Vec<&str>> = annotations_position
+            .iter()
+            .map(|&(pos, annotation)| {
+                let Some(ref label) = annotation.label else {
+                    return vec![];
+                };
+                let col = if pos == 0 {
+                    (annotation.end_col.display + 1).saturating_sub(left)
+                } else {
+                    annotation.start_col.display.saturating_sub(left)
+                };
+                match wrap_width {
+                    Some(width) => {
+                        let width = width.saturating_sub(code_offset + col);
+                        wrap_text(label, width, width)
+                    }
+                    None => vec![label.as_str()],
+                }
+            })
+            .collect();
+        let max_pos = annotations_position.iter().map(|&(pos, _)| pos).max().unwrap_or(0);
+        let mut extra_lines = vec![0; max_pos + 1];
+        for (&(pos, _), lines) in iter::zip(&annotations_position, &label_lines) {
+            extra_lines[pos] = max(extra_lines[pos], lines.len().saturating_sub(1));
+        }
+        for (pos, _) in &mut annotations_position {
+            *pos += extra_lines[..*pos].iter().sum::<usize>();
+        }
+        line_len += extra_lines.iter().sum::<usize>();
+
         // Write the column separator.
         //
         // After this we will have:
@@ -1118,7 +1624,7 @@ fn render_source_line(
         // 3 |
         // 4 |   }
         //   |  _  test
-        for &(pos, annotation) in &annotations_position {
+        for (&(pos, annotation), lines) in iter::zip(&annotations_position, &label_lines) {
             let style =
                 if annotation.is_primary { Style::LabelPrimary } else { Style::LabelSecondary };
             let (pos, col) = if pos == 0 {
@@ -1126,8 +1632,8 @@ fn render_source_line(
             } else {
                 (pos + 2, annotation.start_col.display.saturating_sub(left))
             };
-            if let Some(ref label) = annotation.label {
-                buffer.puts(line_offset + pos, code_offset + col, label, style);
+            for (i, label) in lines.iter().enumerate() {
+                buffer.puts(line_offset + pos + i, code_offset + col, label, style);
             }
         }
 
@@ -1186,6 +1692,95 @@ fn render_source_line(
             .collect::<Vec<_>>()
     }
 
+    /// Renders a line longer than `MAX_HIGHLIGHTED_LINE_LEN`. Instead of underlining the spans on
+    /// it, only an excerpt of the line starting a little before the left-most annotation is shown,
+    /// with the start of each annotation marked and followed by its label:
+    ///
+    /// ```text
+    /// LL | ...0, "x", 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0...
+    ///    |       ^ expected integer, found `&str`
+    ///    | note: this line is 1048576 bytes long, so only an excerpt is shown
+    /// ```
+    fn render_long_source_line(
+        &self,
+        buffer: &mut StyledBuffer,
+        file: &SourceFile,
+        source_string: &str,
+        line: &Line,
+        width_offset: usize,
+        code_offset: usize,
+        column_width: usize,
+    ) -> Vec<(usize, Style)> {
+        let line_offset = buffer.num_lines();
+        let mut annotations =
+            line.annotations.iter().filter(|ann| !ann.is_line()).collect::<Vec<_>>();
+        annotations.sort_by_key(|ann| ann.start_col.file);
+
+        let left = annotations.first().map_or(0, |ann| ann.start_col.file.saturating_sub(6));
+        let mut chars = source_string.chars().skip(left);
+        let excerpt = chars.by_ref().take(column_width).collect::<String>();
+        let was_cut_right = chars.next().is_some();
+        let excerpt = normalize_whitespace(&excerpt);
+        let excerpt_width = excerpt.chars().count();
+
+        let line_num = self.maybe_anonymized_in(file, line.line_index);
+        buffer.puts(line_offset, 0, &line_num, Style::LineNumber);
+        draw_col_separator_no_space(buffer, line_offset, width_offset - 2);
+        buffer.puts(line_offset, code_offset, &excerpt, Style::Quotation);
+        if left > 0 {
+            buffer.puts(line_offset, code_offset, "...", Style::LineNumber);
+        }
+        if was_cut_right {
+            buffer.puts(
+                line_offset,
+                code_offset + excerpt_width.saturating_sub(3),
+                "...",
+                Style::LineNumber,
+            );
+        }
+
+        for (i, ann) in annotations.iter().enumerate() {
+            let (marker, style) = if ann.is_primary {
+                ("^", Style::UnderlinePrimary)
+            } else {
+                ("-", Style::UnderlineSecondary)
+            };
+            let col = code_offset + min(ann.start_col.file - left, excerpt_width);
+            draw_col_separator(buffer, line_offset + i + 1, width_offset - 2);
+            buffer.puts(line_offset + i + 1, col, marker, style);
+            if let Some(label) = &ann.label {
+                let style =
+                    if ann.is_primary { Style::LabelPrimary } else { Style::LabelSecondary };
+                buffer.puts(line_offset + i + 1, col + 2, label, style);
+            }
+        }
+
+        let note_line = line_offset + annotations.len() + 1;
+        draw_col_separator(buffer, note_line, width_offset - 2);
+        buffer.puts(note_line, code_offset, "note", Style::Level(Level::Note));
+        buffer.puts(
+            note_line,
+            code_offset + 4,
+            &format!(
+                ": this line is {} bytes long, so only an excerpt is shown",
+                source_string.len()
+            ),
+            Style::NoStyle,
+        );
+
+        annotations
+            .iter()
+            .filter_map(|ann| match ann.annotation_type {
+                AnnotationType::MultilineStart(p) | AnnotationType::MultilineEnd(p) => {
+                    let style =
+                        if ann.is_primary { Style::LabelPrimary } else { Style::LabelSecondary };
+                    Some((p, style))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     fn get_multispan_max_line_num(&mut self, msp: &MultiSpan) -> usize {
         let Some(ref sm) = self.sm else {
             return 0;
@@ -1194,7 +1789,11 @@ fn get_multispan_max_line_num(&mut self, msp: &MultiSpan) -> usize {
         let will_be_emitted = |span: Span| {
             !span.is_dummy() && {
                 let file = sm.lookup_source_file(span.hi());
-                should_show_source_code(&self.ignored_directories_in_source_blocks, sm, &file)
+                should_show_source_code(
+                    &self.ignored_directories_in_source_blocks,
+                    self.sources(sm),
+                    &file,
+                )
             }
         };
 
@@ -1254,7 +1853,8 @@ fn msgs_to_buffer(
         //    |  |   length of label
         //    |  magic `3`
         //    `max_line_num_len`
-        let padding = " ".repeat(padding + label.len() + 5);
+        let padding_len = padding + label.len() + 5;
+        let padding = " ".repeat(padding_len);
 
         /// Returns `override` if it is present and `style` is `NoStyle` or `style` otherwise
         fn style_or_override(style: Style, override_: Option<Style>) -> Style {
@@ -1264,7 +1864,10 @@ fn style_or_override(style: Style, override_: Option<Style>) -> Style {
             }
         }
 
+        let wrap_width = self.wrap_width();
         let mut line_number = 0;
+        // Current column in the line being written, used to soft-wrap the message.
+        let mut col = padding_len;
 
         // Provided the following diagnostic message:
         //
@@ -1285,20 +1888,32 @@ fn style_or_override(style: Style, override_: Option<Style>) -> Style {
         //                see how it *looks* with
         //                very *weird* formats
         //                see?
+        //
+        // Lines that don't fit in the rendering width are additionally soft-wrapped and aligned
+        // in the same way.
         for (text, style) in msgs.iter() {
             let text = self.translate_message(text, args).map_err(Report::new).unwrap();
             let text = &normalize_whitespace(&text);
-            let lines = text.split('\n').collect::<Vec<_>>();
-            if lines.len() > 1 {
-                for (i, line) in lines.iter().enumerate() {
-                    if i != 0 {
+            let style = style_or_override(*style, override_style);
+            for (i, line) in text.split('\n').enumerate() {
+                let pieces = match wrap_width {
+                    Some(width) => {
+                        let start = if i == 0 { col } else { padding_len };
+                        let (first_width, width) =
+                            (width.saturating_sub(start), width.saturating_sub(padding_len));
+                        wrap_text(line, first_width, width).into_iter().enumerate()
+                    }
+                    None => vec![line].into_iter().enumerate(),
+                };
+                for (j, piece) in pieces {
+                    if i != 0 || j != 0 {
                         line_number += 1;
                         buffer.append(line_number, &padding, Style::NoStyle);
+                        col = padding_len;
                     }
-                    buffer.append(line_number, line, style_or_override(*style, override_style));
+                    buffer.append(line_number, piece, style);
+                    col += str_width(piece);
                 }
-            } else {
-                buffer.append(line_number, text, style_or_override(*style, override_style));
             }
         }
     }
@@ -1311,9 +1926,11 @@ fn emit_messages_default_inner(
         args: &FluentArgs<'_>,
         code: &Option<String>,
         level: &Level,
+        level_prefix: Option<&str>,
         max_line_num_len: usize,
         is_secondary: bool,
         emitted_at: Option<&DiagnosticLocation>,
+        query_stack: &[String],
     ) -> io::Result<()> {
         let mut buffer = StyledBuffer::new();
 
@@ -1333,8 +1950,12 @@ fn emit_messages_default_inner(
             let mut label_width = 0;
             // The failure note level itself does not provide any useful diagnostic information
             if *level != Level::FailureNote {
-                buffer.append(0, level.to_str(), Style::Level(*level));
-                label_width += level.to_str().len();
+                let level_str = match level_prefix {
+                    Some(prefix) => Cow::Owned(format!("{prefix}-{level}")),
+                    None => Cow::Borrowed(level.to_str()),
+                };
+                buffer.append(0, &level_str, Style::Level(*level));
+                label_width += level_str.len();
             }
             if let Some(code) = code {
                 buffer.append(0, "[", Style::Level(*level));
@@ -1383,7 +2004,13 @@ fn emit_messages_default_inner(
         let primary_span = msp.primary_span().unwrap_or_default();
         let (Some(sm), false) = (self.sm.as_ref(), primary_span.is_dummy()) else {
             // If we don't have span information, emit and exit
-            return emit_to_destination(&buffer.render(), level, &mut self.dst, self.short_message);
+            return emit_to_destination(
+                &buffer.render(),
+                level,
+                &mut self.dst,
+                self.short_message,
+                &self.prof,
+            );
         };
         let primary_lo = sm.lookup_char_pos(primary_span.lo());
         if let Ok(pos) =
@@ -1397,7 +2024,7 @@ fn emit_messages_default_inner(
             // we can't annotate anything if the source is unavailable.
             if !should_show_source_code(
                 &self.ignored_directories_in_source_blocks,
-                sm,
+                self.sources(sm),
                 &annotated_file.file,
             ) {
                 if !self.short_message {
@@ -1416,14 +2043,11 @@ fn emit_messages_default_inner(
                         if annotation_id == 0 || !labels.is_empty() {
                             buffer.append(
                                 line_idx,
-                                &format!(
-                                    "{}:{}:{}",
-                                    sm.filename_for_diagnostics(&annotated_file.file.name),
-                                    sm.doctest_offset_line(
-                                        &annotated_file.file.name,
-                                        line.line_index
-                                    ),
-                                    annotations[0].start_col.file + 1,
+                                &self.display_location(
+                                    sm,
+                                    &annotated_file.file,
+                                    line.line_index,
+                                    Some(annotations[0].start_col.file + 1),
                                 ),
                                 Style::LineAndColumn,
                             );
@@ -1472,12 +2096,7 @@ fn emit_messages_default_inner(
                     buffer.prepend(buffer_msg_line_offset, "--> ", Style::LineNumber);
                     buffer.append(
                         buffer_msg_line_offset,
-                        &format!(
-                            "{}:{}:{}",
-                            sm.filename_for_diagnostics(&loc.file.name),
-                            sm.doctest_offset_line(&loc.file.name, loc.line),
-                            loc.col.0 + 1,
-                        ),
+                        &self.display_location(sm, &loc.file, loc.line, Some(loc.col.0 + 1)),
                         Style::LineAndColumn,
                     );
                     for _ in 0..max_line_num_len {
@@ -1487,10 +2106,8 @@ fn emit_messages_default_inner(
                     buffer.prepend(
                         0,
                         &format!(
-                            "{}:{}:{}: ",
-                            sm.filename_for_diagnostics(&loc.file.name),
-                            sm.doctest_offset_line(&loc.file.name, loc.line),
-                            loc.col.0 + 1,
+                            "{}: ",
+                            self.display_location(sm, &loc.file, loc.line, Some(loc.col.0 + 1))
                         ),
                         Style::LineAndColumn,
                     );
@@ -1509,19 +2126,10 @@ fn emit_messages_default_inner(
                 // Then, the secondary file indicator
                 buffer.prepend(buffer_msg_line_offset + 1, "::: ", Style::LineNumber);
                 let loc = if let Some(first_line) = annotated_file.lines.first() {
-                    let col = if let Some(first_annotation) = first_line.annotations.first() {
-                        format!(":{}", first_annotation.start_col.file + 1)
-                    } else {
-                        String::new()
-                    };
-                    format!(
-                        "{}:{}{}",
-                        sm.filename_for_diagnostics(&annotated_file.file.name),
-                        sm.doctest_offset_line(&annotated_file.file.name, first_line.line_index),
-                        col
-                    )
+                    let col = first_line.annotations.first().map(|a| a.start_col.file + 1);
+                    self.display_location(sm, &annotated_file.file, first_line.line_index, col)
                 } else {
-                    format!("{}", sm.filename_for_diagnostics(&annotated_file.file.name))
+                    self.display_file_name(sm, &annotated_file.file)
                 };
                 buffer.append(buffer_msg_line_offset + 1, &loc, Style::LineAndColumn);
                 for _ in 0..max_line_num_len {
@@ -1544,9 +2152,10 @@ fn emit_messages_default_inner(
                 // Get the left-side margin to remove it
                 let mut whitespace_margin = usize::MAX;
                 for line_idx in 0..annotated_file.lines.len() {
-                    let file = annotated_file.file.clone();
                     let line = &annotated_file.lines[line_idx];
-                    if let Some(source_string) = file.get_line(line.line_index - 1) {
+                    if let Some(source_string) =
+                        self.source_line(&annotated_file.file, line.line_index - 1)
+                    {
                         let leading_whitespace = source_string
                             .chars()
                             .take_while(|c| c.is_whitespace())
@@ -1567,9 +2176,15 @@ fn emit_messages_default_inner(
                     whitespace_margin = 0;
                 }
 
+                // Long lines are rendered on their own, so they don't affect the margins below.
+                let is_long_line = |line: &Line| {
+                    self.source_line(&annotated_file.file, line.line_index - 1)
+                        .is_some_and(|s| s.len() > MAX_HIGHLIGHTED_LINE_LEN)
+                };
+
                 // Left-most column any visible span points at.
                 let mut span_left_margin = usize::MAX;
-                for line in &annotated_file.lines {
+                for line in annotated_file.lines.iter().filter(|line| !is_long_line(line)) {
                     for ann in &line.annotations {
                         span_left_margin = min(span_left_margin, ann.start_col.display);
                         span_left_margin = min(span_left_margin, ann.end_col.display);
@@ -1583,10 +2198,11 @@ fn emit_messages_default_inner(
                 let mut span_right_margin = 0;
                 let mut label_right_margin = 0;
                 let mut max_line_len = 0;
-                for line in &annotated_file.lines {
+                for line in annotated_file.lines.iter().filter(|line| !is_long_line(line)) {
                     max_line_len = max(
                         max_line_len,
-                        annotated_file.file.get_line(line.line_index - 1).map_or(0, |s| s.len()),
+                        self.source_line(&annotated_file.file, line.line_index - 1)
+                            .map_or(0, |s| s.len()),
                     );
                     for ann in &line.annotations {
                         span_right_margin = max(span_right_margin, ann.start_col.display);
@@ -1672,15 +2288,26 @@ fn emit_messages_default_inner(
                                 );
                             }
                         } else if line_idx_delta == 2 {
-                            let unannotated_line = annotated_file
-                                .file
-                                .get_line(annotated_file.lines[line_idx].line_index)
-                                .unwrap_or_else(|| Cow::from(""));
+                            let mut unannotated_line = self
+                                .source_line(
+                                    &annotated_file.file,
+                                    annotated_file.lines[line_idx].line_index,
+                                )
+                                .unwrap_or_else(|| Lrc::from(""));
+                            if unannotated_line.len() > MAX_HIGHLIGHTED_LINE_LEN {
+                                // Only the start of the line fits anyway.
+                                let end = unannotated_line
+                                    .char_indices()
+                                    .nth(margin.column_width + 1)
+                                    .map_or(unannotated_line.len(), |(i, _)| i);
+                                unannotated_line = Lrc::from(&unannotated_line[..end]);
+                            }
 
                             let last_buffer_line_num = buffer.num_lines();
 
                             self.draw_line(
                                 &mut buffer,
+                                &annotated_file.file,
                                 &normalize_whitespace(&unannotated_line),
                                 annotated_file.lines[line_idx + 1].line_index - 1,
                                 last_buffer_line_num,
@@ -1711,10 +2338,21 @@ fn emit_messages_default_inner(
             let track = format!("-Ztrack-diagnostics: created at {tracked}");
             let len = buffer.num_lines();
             buffer.append(len, &track, Style::NoStyle);
+            for (i, query) in query_stack.iter().enumerate() {
+                let track = format!("-Ztrack-diagnostics: in query #{i} {query}");
+                let len = buffer.num_lines();
+                buffer.append(len, &track, Style::NoStyle);
+            }
         }
 
         // final step: take our styled buffer, render it, then output it
-        emit_to_destination(&buffer.render(), level, &mut self.dst, self.short_message)?;
+        emit_to_destination(
+            &buffer.render(),
+            level,
+            &mut self.dst,
+            self.short_message,
+            &self.prof,
+        )?;
 
         Ok(())
     }
@@ -1732,7 +2370,7 @@ fn emit_suggestion_default(
         };
 
         // Render the replacements for each suggestion
-        let suggestions = suggestion.splice_lines(sm);
+        let suggestions = suggestion.splice_lines(sm, self.sources(sm));
         debug!(?suggestions);
 
         if suggestions.is_empty() {
@@ -1760,7 +2398,7 @@ fn emit_suggestion_default(
         draw_col_separator_no_space(&mut buffer, 1, max_line_num_len + 1);
         let mut notice_capitalization = false;
         for (complete, parts, highlights, only_capitalization) in
-            suggestions.iter().take(MAX_SUGGESTIONS)
+            suggestions.iter().take(MAX_SUGGESTIONS).flatten()
         {
             debug!(?complete, ?parts, ?highlights);
             notice_capitalization |= only_capitalization;
@@ -1777,9 +2415,8 @@ fn emit_suggestion_default(
                 if loc.file.name != sm.span_to_filename(span) && loc.file.name.is_real() {
                     let arrow = "--> ";
                     buffer.puts(row_num - 1, 0, arrow, Style::LineNumber);
-                    let filename = sm.filename_for_diagnostics(&loc.file.name);
-                    let offset = sm.doctest_offset_line(&loc.file.name, loc.line);
-                    let message = format!("{}:{}:{}", filename, offset, loc.col.0 + 1);
+                    let message =
+                        self.display_location(sm, &loc.file, loc.line, Some(loc.col.0 + 1));
                     if row_num == 2 {
                         let col = usize::max(max_line_num_len + 1, arrow.len());
                         buffer.puts(1, col, &message, Style::LineAndColumn);
@@ -1840,7 +2477,9 @@ fn emit_suggestion_default(
                     buffer.puts(
                         row_num - 1 + line - line_start,
                         max_line_num_len + 3,
-                        &normalize_whitespace(&file_lines.file.get_line(line - 1).unwrap()),
+                        &normalize_whitespace(
+                            &self.source_line(&file_lines.file, line - 1).unwrap(),
+                        ),
                         Style::Removal,
                     );
                 }
@@ -1953,7 +2592,7 @@ fn emit_suggestion_default(
                     .span_to_lines(parts[0].span.shrink_to_hi())
                     .expect("span_to_lines failed when emitting suggestion");
                 let line_num = sm.lookup_char_pos(parts[0].span.lo()).line;
-                if let Some(line) = file_lines.file.get_line(line_num - 1) {
+                if let Some(line) = self.source_line(&file_lines.file, line_num - 1) {
                     let line = normalize_whitespace(&line);
                     self.draw_code_line(
                         &mut buffer,
@@ -2066,27 +2705,264 @@ fn emit_suggestion_default(
             let msg = "notice the capitalization difference";
             buffer.puts(row_num, max_line_num_len + 3, msg, Style::NoStyle);
         }
-        emit_to_destination(&buffer.render(), level, &mut self.dst, self.short_message)?;
+        emit_to_destination(
+            &buffer.render(),
+            level,
+            &mut self.dst,
+            self.short_message,
+            &self.prof,
+        )?;
+        Ok(())
+    }
+
+    /// Renders each substitution of `suggestion` as a unified diff of the lines it touches:
+    ///
+    /// ```text
+    /// help: consider using a `match`
+    ///    |
+    /// LL - if let Some(x) = opt { x } else { 0 }
+    /// LL + match opt {
+    /// LL +     Some(x) => x,
+    /// LL +     None => 0,
+    /// LL + }
+    ///    |
+    /// ```
+    fn emit_suggestion_diff(
+        &mut self,
+        span: &MultiSpan,
+        suggestion: &CodeSuggestion,
+        args: &FluentArgs<'_>,
+        level: &Level,
+        max_line_num_len: usize,
+    ) -> io::Result<()> {
+        let Some(ref sm) = self.sm else {
+            return Ok(());
+        };
+
+        let mut buffer = StyledBuffer::new();
+
+        // Render the suggestion message
+        buffer.append(0, level.to_str(), Style::Level(*level));
+        buffer.append(0, ": ", Style::HeaderMsg);
+
+        self.msgs_to_buffer(
+            &mut buffer,
+            &[(suggestion.msg.to_owned(), Style::NoStyle)],
+            args,
+            max_line_num_len,
+            "suggestion",
+            Some(Style::HeaderMsg),
+        );
+
+        let mut row_num = 1;
+        // Parts in different files are shown as separate diffs. If any of them can't be shown,
+        // the whole substitution is ignored.
+        let substitutions = suggestion
+            .substitutions
+            .iter()
+            .filter(|substitution| {
+                substitution.parts.iter().all(|part| sm.is_valid_span(part.span).is_ok())
+            })
+            .filter_map(|substitution| {
+                substitution
+                    .split_by_file(sm)
+                    .iter()
+                    .map(|substitution| substitution_line_diff(sm, substitution))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect::<Vec<_>>();
+        if substitutions.is_empty() {
+            // Suggestions coming from macros can have malformed spans. This is a heavy handed
+            // approach to avoid ICEs by ignoring the suggestion outright.
+            return Ok(());
+        }
+        for (file, line_start, diff) in substitutions.iter().take(MAX_SUGGESTIONS).flatten() {
+            draw_col_separator_no_space(&mut buffer, row_num, max_line_num_len + 1);
+            row_num += 1;
+
+            // Point at the file if it isn't the one of the primary span, like for other
+            // suggestions.
+            if let Some(span) = span.primary_span()
+                && file.name != sm.span_to_filename(span)
+                && file.name.is_real()
+            {
+                let location = self.display_location(sm, file, *line_start, None);
+                buffer.puts(row_num, max_line_num_len, "--> ", Style::LineNumber);
+                buffer.append(row_num, &location, Style::LineAndColumn);
+                row_num += 1;
+            }
+
+            self.draw_line_diff(&mut buffer, &mut row_num, *line_start, diff, max_line_num_len);
+        }
+        draw_col_separator_no_space(&mut buffer, row_num, max_line_num_len + 1);
+        row_num += 1;
+        if substitutions.len() > MAX_SUGGESTIONS {
+            let others = substitutions.len() - MAX_SUGGESTIONS;
+            let msg = format!("and {} other candidate{}", others, pluralize!(others));
+            buffer.puts(row_num, max_line_num_len + 3, &msg, Style::NoStyle);
+        }
+        emit_to_destination(
+            &buffer.render(),
+            level,
+            &mut self.dst,
+            self.short_message,
+            &self.prof,
+        )?;
+        Ok(())
+    }
+
+    /// Renders a suggestion for a file that is not in the `SourceMap` as a diff of the lines it
+    /// changes, preceded by the path of the file.
+    fn emit_file_suggestion(
+        &mut self,
+        suggestion: &FileSuggestion,
+        args: &FluentArgs<'_>,
+        level: &Level,
+        max_line_num_len: usize,
+    ) -> io::Result<()> {
+        let mut buffer = StyledBuffer::new();
+
+        buffer.append(0, level.to_str(), Style::Level(*level));
+        buffer.append(0, ": ", Style::HeaderMsg);
+        self.msgs_to_buffer(
+            &mut buffer,
+            &[(suggestion.msg.to_owned(), Style::NoStyle)],
+            args,
+            max_line_num_len,
+            "suggestion",
+            Some(Style::HeaderMsg),
+        );
+
+        let mut row_num = buffer.num_lines();
+        buffer.puts(row_num, max_line_num_len, "--> ", Style::LineNumber);
+        buffer.append(
+            row_num,
+            &format!("{}:{}", suggestion.path.display(), suggestion.line_start),
+            Style::LineAndColumn,
+        );
+        row_num += 1;
+        draw_col_separator_no_space(&mut buffer, row_num, max_line_num_len + 1);
+        row_num += 1;
+
+        let (old, new) = suggestion.lines();
+        let diff = diff_lines(&old.lines().collect::<Vec<_>>(), &new.lines().collect::<Vec<_>>())
+            .into_iter()
+            .map(|(change, line)| (change, line.to_string()))
+            .collect::<Vec<_>>();
+        self.draw_line_diff(
+            &mut buffer,
+            &mut row_num,
+            suggestion.line_start,
+            &diff,
+            max_line_num_len,
+        );
+        draw_col_separator_no_space(&mut buffer, row_num, max_line_num_len + 1);
+
+        emit_to_destination(
+            &buffer.render(),
+            level,
+            &mut self.dst,
+            self.short_message,
+            &self.prof,
+        )?;
         Ok(())
     }
 
-    #[instrument(level = "trace", skip(self, args, code, children, suggestions))]
+    /// Draws the lines of `diff`, starting at line `line_start` of a file, with `-` and `+`
+    /// markers for removed and added lines.
+    fn draw_line_diff(
+        &self,
+        buffer: &mut StyledBuffer,
+        row_num: &mut usize,
+        line_start: usize,
+        diff: &[(LineChange, String)],
+        max_line_num_len: usize,
+    ) {
+        let (mut old_line, mut new_line) = (line_start, line_start);
+        let mut i = 0;
+        while i < diff.len() {
+            let (change, ref line) = diff[i];
+            let line = normalize_whitespace(line);
+            match change {
+                LineChange::Unchanged => {
+                    // Unchanged lines between two changes are shown for context. Since we
+                    // show the first line, "..." line and last line, there is no reason to
+                    // hide them if there are 3 or less lines.
+                    let run = diff[i..]
+                        .iter()
+                        .take_while(|(change, _)| *change == LineChange::Unchanged)
+                        .count();
+                    for (j, (_, line)) in diff[i..i + run].iter().enumerate() {
+                        if run > 3 && j == 1 {
+                            buffer.puts(*row_num, max_line_num_len - 1, "...", Style::LineNumber);
+                            *row_num += 1;
+                        }
+                        if run <= 3 || j == 0 || j == run - 1 {
+                            buffer.puts(
+                                *row_num,
+                                0,
+                                &self.maybe_anonymized(old_line + j),
+                                Style::LineNumber,
+                            );
+                            draw_col_separator(buffer, *row_num, max_line_num_len + 1);
+                            buffer.puts(
+                                *row_num,
+                                max_line_num_len + 3,
+                                &normalize_whitespace(line),
+                                Style::NoStyle,
+                            );
+                            *row_num += 1;
+                        }
+                    }
+                    old_line += run;
+                    new_line += run;
+                    i += run;
+                }
+                LineChange::Removed => {
+                    buffer.puts(*row_num, 0, &self.maybe_anonymized(old_line), Style::LineNumber);
+                    buffer.puts(*row_num, max_line_num_len + 1, "- ", Style::Removal);
+                    buffer.puts(*row_num, max_line_num_len + 3, &line, Style::Removal);
+                    *row_num += 1;
+                    old_line += 1;
+                    i += 1;
+                }
+                LineChange::Added => {
+                    buffer.puts(*row_num, 0, &self.maybe_anonymized(new_line), Style::LineNumber);
+                    buffer.puts(*row_num, max_line_num_len + 1, "+ ", Style::Addition);
+                    buffer.puts(*row_num, max_line_num_len + 3, &line, Style::Addition);
+                    *row_num += 1;
+                    new_line += 1;
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    #[instrument(level = "trace", skip(self, args, code, children, suggestions, file_suggestions))]
     fn emit_messages_default(
         &mut self,
         level: &Level,
+        level_prefix: Option<&str>,
         messages: &[(DiagnosticMessage, Style)],
         args: &FluentArgs<'_>,
         code: &Option<String>,
         span: &MultiSpan,
         children: &[SubDiagnostic],
         suggestions: &[CodeSuggestion],
+        file_suggestions: &[FileSuggestion],
         emitted_at: Option<&DiagnosticLocation>,
+        query_stack: &[String],
     ) {
         let max_line_num_len = if self.ui_testing {
             ANONYMIZED_LINE_NUM.len()
         } else {
             let n = self.get_max_line_num(span, children);
-            num_decimal_digits(n)
+            let file_n = file_suggestions
+                .iter()
+                .map(|sugg| sugg.line_end + sugg.snippet.matches('\n').count())
+                .max()
+                .unwrap_or(0);
+            num_decimal_digits(n.max(file_n))
         };
 
         match self.emit_messages_default_inner(
@@ -2095,13 +2971,16 @@ fn emit_messages_default(
             args,
             code,
             level,
+            level_prefix,
             max_line_num_len,
             false,
             emitted_at,
+            query_stack,
         ) {
             Ok(()) => {
                 if !children.is_empty()
                     || suggestions.iter().any(|s| s.style != SuggestionStyle::CompletelyHidden)
+                    || !file_suggestions.is_empty()
                 {
                     let mut buffer = StyledBuffer::new();
                     if !self.short_message {
@@ -2112,60 +2991,101 @@ fn emit_messages_default(
                         level,
                         &mut self.dst,
                         self.short_message,
+                        &self.prof,
                     ) {
                         panic!("failed to emit error: {e}")
                     }
                 }
                 if !self.short_message {
-                    for child in children {
-                        let span = &child.span;
-                        if let Err(err) = self.emit_messages_default_inner(
-                            span,
-                            &child.messages,
-                            args,
-                            &None,
-                            &child.level,
-                            max_line_num_len,
-                            true,
-                            None,
-                        ) {
-                            panic!("failed to emit error: {err}");
-                        }
-                    }
-                    for sugg in suggestions {
-                        match sugg.style {
-                            SuggestionStyle::CompletelyHidden => {
-                                // do not display this suggestion, it is meant only for tools
+                    for priority in [
+                        SubdiagnosticPriority::First,
+                        SubdiagnosticPriority::Normal,
+                        SubdiagnosticPriority::Last,
+                    ] {
+                        for child in children.iter().filter(|child| child.priority == priority) {
+                            let span = &child.span;
+                            let messages = self.messages_with_url(child);
+                            if let Err(err) = self.emit_messages_default_inner(
+                                span,
+                                &messages,
+                                args,
+                                &None,
+                                &child.level,
+                                None,
+                                max_line_num_len,
+                                true,
+                                None,
+                                &[],
+                            ) {
+                                panic!("failed to emit error: {err}");
                             }
-                            SuggestionStyle::HideCodeAlways => {
-                                if let Err(e) = self.emit_messages_default_inner(
-                                    &MultiSpan::new(),
-                                    &[(sugg.msg.to_owned(), Style::HeaderMsg)],
-                                    args,
-                                    &None,
-                                    &Level::Help,
-                                    max_line_num_len,
-                                    true,
-                                    None,
-                                ) {
-                                    panic!("failed to emit error: {e}");
+                        }
+                        for sugg in suggestions.iter().filter(|sugg| sugg.priority == priority) {
+                            match sugg.style {
+                                SuggestionStyle::CompletelyHidden => {
+                                    // do not display this suggestion, it is meant only for tools
                                 }
-                            }
-                            SuggestionStyle::HideCodeInline
-                            | SuggestionStyle::ShowCode
-                            | SuggestionStyle::ShowAlways => {
-                                if let Err(e) = self.emit_suggestion_default(
-                                    span,
-                                    sugg,
-                                    args,
-                                    &Level::Help,
-                                    max_line_num_len,
-                                ) {
-                                    panic!("failed to emit error: {e}");
+                                SuggestionStyle::HideCodeAlways => {
+                                    if let Err(e) = self.emit_messages_default_inner(
+                                        &MultiSpan::new(),
+                                        &[(sugg.msg.to_owned(), Style::HeaderMsg)],
+                                        args,
+                                        &None,
+                                        &Level::Help,
+                                        None,
+                                        max_line_num_len,
+                                        true,
+                                        None,
+                                        &[],
+                                    ) {
+                                        panic!("failed to emit error: {e}");
+                                    }
+                                }
+                                SuggestionStyle::Diff => {
+                                    if let Err(e) = self.emit_suggestion_diff(
+                                        span,
+                                        sugg,
+                                        args,
+                                        &Level::Help,
+                                        max_line_num_len,
+                                    ) {
+                                        panic!("failed to emit error: {e}");
+                                    }
+                                }
+                                SuggestionStyle::HideCodeInline
+                                | SuggestionStyle::ShowCode
+                                | SuggestionStyle::ShowAlways => {
+                                    let result = if self.diff_suggestions {
+                                        self.emit_suggestion_diff(
+                                            span,
+                                            sugg,
+                                            args,
+                                            &Level::Help,
+                                            max_line_num_len,
+                                        )
+                                    } else {
+                                        self.emit_suggestion_default(
+                                            span,
+                                            sugg,
+                                            args,
+                                            &Level::Help,
+                                            max_line_num_len,
+                                        )
+                                    };
+                                    if let Err(e) = result {
+                                        panic!("failed to emit error: {e}");
+                                    }
                                 }
                             }
                         }
                     }
+                    for sugg in file_suggestions {
+                        if let Err(e) =
+                            self.emit_file_suggestion(sugg, args, &Level::Help, max_line_num_len)
+                        {
+                            panic!("failed to emit error: {e}");
+                        }
+                    }
                 }
             }
             Err(e) => panic!("failed to emit error: {e}"),
@@ -2206,7 +3126,7 @@ fn draw_code_line(
                 );
                 buffer.puts(*row_num - 1, max_line_num_len + 1, "- ", Style::Removal);
                 let line = normalize_whitespace(
-                    &file_lines.file.get_line(line_to_remove.line_index).unwrap(),
+                    &self.source_line(&file_lines.file, line_to_remove.line_index).unwrap(),
                 );
                 buffer.puts(*row_num - 1, max_line_num_len + 3, &line, Style::NoStyle);
                 *row_num += 1;
@@ -2218,8 +3138,8 @@ fn draw_code_line(
             // 3 - if true { true } else { false }
             // If those lines aren't equal, we print their diff
             let last_line_index = file_lines.lines[file_lines.lines.len() - 1].line_index;
-            let last_line = &file_lines.file.get_line(last_line_index).unwrap();
-            if last_line != line_to_add {
+            let last_line = self.source_line(&file_lines.file, last_line_index).unwrap();
+            if &*last_line != line_to_add {
                 buffer.puts(
                     *row_num - 1,
                     0,
@@ -2230,7 +3150,7 @@ fn draw_code_line(
                 buffer.puts(
                     *row_num - 1,
                     max_line_num_len + 3,
-                    &normalize_whitespace(last_line),
+                    &normalize_whitespace(&last_line),
                     Style::NoStyle,
                 );
                 buffer.puts(*row_num, 0, &self.maybe_anonymized(line_num), Style::LineNumber);
@@ -2339,7 +3259,7 @@ fn add_annotation_to_file(
         let mut multiline_annotations = vec![];
 
         if let Some(sm) = emitter.source_map() {
-            for SpanLabel { span, is_primary, label } in msp.span_labels() {
+            for SpanLabel { span, is_primary, label, args: label_args } in msp.span_labels() {
                 // If we don't have a useful span, pick the primary span if that exists.
                 // Worst case we'll just print an error at the top of the main file.
                 let span = match (span.is_dummy(), msp.primary_span()) {
@@ -2360,7 +3280,9 @@ fn add_annotation_to_file(
                     hi.col_display += 1;
                 }
 
+                let label_args = span_label_args(args, &label_args);
                 let label = label.as_ref().map(|m| {
+                    let args = label_args.as_ref().unwrap_or(args);
                     normalize_whitespace(
                         &emitter.translate_message(m, args).map_err(Report::new).unwrap(),
                     )
@@ -2515,6 +3437,202 @@ fn normalize_whitespace(str: &str) -> String {
     s
 }
 
+/// Replaces the parts of the path `name` of `file` that differ between machines. Files of the
+/// local crate are shown relative to the working directory if they are within it, other absolute
+/// paths only with their file name, after `$DIR` for the local crate and `$EXTERNAL` for others.
+fn normalize_file_name(sm: &SourceMap, file: &SourceFile, name: String) -> String {
+    let path = Path::new(&name);
+    if !path.is_absolute() {
+        return name;
+    }
+    // The name is either the local or the remapped path of the file, like the working directory.
+    let working_dir = sm.working_dir();
+    if !file.is_imported()
+        && let Some(relative) =
+            [working_dir.local_path_if_available(), working_dir.remapped_path_if_available()]
+                .into_iter()
+                .find_map(|dir| path.strip_prefix(dir).ok())
+    {
+        return relative.display().to_string();
+    }
+    let prefix = if file.is_imported() { "$EXTERNAL" } else { "$DIR" };
+    match path.file_name() {
+        Some(file_name) => format!("{prefix}/{}", file_name.to_string_lossy()),
+        None => prefix.to_string(),
+    }
+}
+
+/// Replaces the hashes in symbol names, which differ between builds, by `[HASH]`: those at the
+/// end of legacy mangled (`17h<hash>E`) and demangled (`::h<hash>`) names, and the crate
+/// disambiguators of v0 mangled names (`Cs<hash>_`).
+pub(crate) fn normalize_symbol_hashes(text: &str) -> Cow<'_, str> {
+    if !text.contains("_ZN") && !text.contains("_R") && !text.contains("::h") {
+        return Cow::Borrowed(text);
+    }
+    let is_symbol_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '.');
+    let is_legacy_hash = |s: &str| s.len() == 16 && s.bytes().all(|b| b.is_ascii_hexdigit());
+    let mut normalized = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(is_symbol_char) {
+        normalized.push_str(&rest[..start]);
+        let end = rest[start..].find(|c| !is_symbol_char(c)).map_or(rest.len(), |end| start + end);
+        let symbol = &rest[start..end];
+        rest = &rest[end..];
+
+        if normalized.ends_with("::") && symbol.strip_prefix('h').is_some_and(is_legacy_hash) {
+            normalized.push_str("h[HASH]");
+        } else if symbol.starts_with("_ZN")
+            && let Some(hash) = symbol.rfind("17h")
+            && symbol.get(hash + 3..hash + 19).is_some_and(is_legacy_hash)
+        {
+            normalized.push_str(&symbol[..hash + 3]);
+            normalized.push_str("[HASH]");
+            normalized.push_str(&symbol[hash + 19..]);
+        } else if symbol.starts_with("_R") {
+            let mut parts = symbol.split("Cs");
+            normalized.push_str(parts.next().unwrap_or_default());
+            for part in parts {
+                normalized.push_str("Cs");
+                match part.split_once('_') {
+                    Some((_, after)) => {
+                        normalized.push_str("[HASH]_");
+                        normalized.push_str(after);
+                    }
+                    None => normalized.push_str(part),
+                }
+            }
+        } else {
+            normalized.push_str(symbol);
+        }
+    }
+    normalized.push_str(rest);
+    Cow::Owned(normalized)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LineChange {
+    Unchanged,
+    Removed,
+    Added,
+}
+
+/// Applies `substitution`, whose parts must all be in the same file, to the lines of code it
+/// touches. Returns that file and the number of the first of those lines, along with a line diff
+/// between the original and the changed code. Returns `None` if the source code isn't available
+/// or the parts can't be spliced.
+fn substitution_line_diff(
+    sm: &SourceMap,
+    substitution: &Substitution,
+) -> Option<(Lrc<SourceFile>, usize, Vec<(LineChange, String)>)> {
+    let lo = substitution.parts.iter().map(|part| part.span.lo()).min()?;
+    let hi = substitution.parts.iter().map(|part| part.span.hi()).max()?;
+    let lines = sm.span_to_lines(Span::with_root_ctxt(lo, hi)).ok()?;
+    let sf = &lines.file;
+    if !sm.ensure_source_file_source_present(sf) {
+        return None;
+    }
+    let (first, last) = (lines.lines.first()?.line_index, lines.lines.last()?.line_index);
+    let old = (first..=last).map(|line| sf.get_line(line)).collect::<Option<Vec<_>>>()?;
+    let old_text = old.join("\n");
+
+    // Splice the parts into the original lines, relative to the start of the first one.
+    let start = sf.line_bounds(first).start;
+    let mut parts = substitution.parts.iter().collect::<Vec<_>>();
+    parts.sort_by_key(|part| part.span.lo());
+    let mut new_text = String::new();
+    let mut pos = 0;
+    for part in parts {
+        let part_lo = (part.span.lo() - start).to_usize();
+        // Overlapping parts can't be spliced.
+        new_text.push_str(old_text.get(pos..part_lo)?);
+        new_text.push_str(&part.snippet);
+        pos = (part.span.hi() - start).to_usize();
+    }
+    new_text.push_str(old_text.get(pos..)?);
+
+    let old = old.iter().map(|line| line.as_ref()).collect::<Vec<_>>();
+    let new = new_text.split('\n').collect::<Vec<_>>();
+    let diff = diff_lines(&old, &new);
+    let diff = diff.into_iter().map(|(change, line)| (change, line.to_owned())).collect();
+    Some((sf.clone(), first + 1, diff))
+}
+
+/// Computes a line diff between `old` and `new` from their longest common subsequence. Removed
+/// lines come before the added lines that replace them.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(LineChange, &'a str)> {
+    // `lcs[i][j]` is the length of the longest common subsequence of `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push((LineChange::Unchanged, old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push((LineChange::Removed, old[i]));
+            i += 1;
+        } else {
+            diff.push((LineChange::Added, new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+fn str_width(s: &str) -> usize {
+    s.chars().map(|ch| unicode_width::UnicodeWidthChar::width(ch).unwrap_or(1)).sum()
+}
+
+/// Splits `text` at whitespace so that its first line is at most `first_width` columns wide and
+/// every following line is at most `width` columns wide. Words are never split, so a word that
+/// doesn't fit on its own is kept on a line by itself, and the first word always stays on the
+/// first line. Leading whitespace is kept on the first line and dropped on the following ones.
+pub(crate) fn wrap_text(text: &str, first_width: usize, width: usize) -> Vec<&str> {
+    if str_width(text) <= first_width {
+        return vec![text];
+    }
+    let mut lines = vec![];
+    // Byte range of the line being built, which always ends at the end of a word.
+    let mut line: Option<(usize, usize)> = None;
+    let mut offset = 0;
+    for word in text.split(' ') {
+        let word_start = offset;
+        let word_end = word_start + word.len();
+        offset = word_end + 1;
+        if word.is_empty() {
+            continue;
+        }
+        line = match line {
+            None => Some((0, word_end)),
+            Some((start, end)) => {
+                let max_width = if lines.is_empty() { first_width } else { width };
+                if str_width(&text[start..word_end]) <= max_width {
+                    Some((start, word_end))
+                } else {
+                    lines.push(&text[start..end]);
+                    Some((word_start, word_end))
+                }
+            }
+        };
+    }
+    match line {
+        Some((start, end)) => lines.push(&text[start..end]),
+        None => lines.push(text),
+    }
+    lines
+}
+
 fn draw_col_separator(buffer: &mut StyledBuffer, line: usize, col: usize) {
     buffer.puts(line, col, "| ", Style::LineNumber);
 }
@@ -2584,9 +3702,12 @@ fn emit_to_destination(
     lvl: &Level,
     dst: &mut Destination,
     short_message: bool,
+    prof: &SelfProfilerRef,
 ) -> io::Result<()> {
     use crate::lock;
 
+    let _timer = prof.generic_activity("write_diagnostic");
+
     // In order to prevent error message interleaving, where multiple error lines get intermixed
     // when multiple compiler processes error simultaneously, we emit errors with additional
     // steps.
@@ -2724,14 +3845,11 @@ fn color_spec(&self, lvl: Level) -> ColorSpec {
 }
 
 /// Whether the original and suggested code are visually similar enough to warrant extra wording.
-pub fn is_case_difference(sm: &SourceMap, suggested: &str, sp: Span) -> bool {
+pub fn is_case_difference(source: &dyn SourceProvider, suggested: &str, sp: Span) -> bool {
     // FIXME: this should probably be extended to also account for `FO0` → `FOO` and unicode.
-    let found = match sm.span_to_snippet(sp) {
-        Ok(snippet) => snippet,
-        Err(e) => {
-            warn!(error = ?e, "Invalid span {:?}", sp);
-            return false;
-        }
+    let Some(found) = source.snippet(sp) else {
+        warn!("Invalid span {:?}", sp);
+        return false;
     };
     let ascii_confusables = &['c', 'f', 'i', 'k', 'o', 's', 'u', 'v', 'w', 'x', 'y', 'z'];
     // All the chars that differ in capitalization are confusable (above):
@@ -2746,10 +3864,10 @@ pub fn is_case_difference(sm: &SourceMap, suggested: &str, sp: Span) -> bool {
 
 pub(crate) fn should_show_source_code(
     ignored_directories: &[String],
-    sm: &SourceMap,
+    source: &dyn SourceProvider,
     file: &SourceFile,
 ) -> bool {
-    if !sm.ensure_source_file_source_present(file) {
+    if !source.has_source(file) {
         return false;
     }
 
@@ -0,0 +1,375 @@
+use super::*;
+
+use crate::DiagCtxtFlags;
+use rustc_lint_defs::Applicability;
+use rustc_span::source_map::FilePathMapping;
+use rustc_span::BytePos;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn wrap_text_fits() {
+    assert_eq!(wrap_text("a short label", 20, 20), vec!["a short label"]);
+    assert_eq!(wrap_text("", 0, 0), vec![""]);
+}
+
+#[test]
+fn wrap_text_hanging() {
+    assert_eq!(
+        wrap_text("  expected `u32`, found `&str`", 14, 12),
+        vec!["  expected", "`u32`, found", "`&str`"]
+    );
+    // Words that are too long are kept intact.
+    assert_eq!(
+        wrap_text("see `std::collections::HashMap` here", 8, 8),
+        vec!["see", "`std::collections::HashMap`", "here"]
+    );
+    // The first word always stays on the first line.
+    assert_eq!(wrap_text("first second", 0, 6), vec!["first", "second"]);
+}
+
+#[test]
+fn diff_lines_replacement() {
+    use LineChange::*;
+
+    let old = ["if let Some(x) = opt {", "    x", "} else {", "    0", "}"];
+    let new = ["match opt {", "    Some(x) => x,", "    None => 0,", "}"];
+    assert_eq!(
+        diff_lines(&old, &new),
+        vec![
+            (Removed, "if let Some(x) = opt {"),
+            (Removed, "    x"),
+            (Removed, "} else {"),
+            (Removed, "    0"),
+            (Added, "match opt {"),
+            (Added, "    Some(x) => x,"),
+            (Added, "    None => 0,"),
+            (Unchanged, "}"),
+        ]
+    );
+}
+
+#[test]
+fn diff_lines_insertion() {
+    use LineChange::*;
+
+    let old = ["fn foo() {", "}"];
+    let new = ["#[inline]", "fn foo() {", "}"];
+    assert_eq!(
+        diff_lines(&old, &new),
+        vec![(Added, "#[inline]"), (Unchanged, "fn foo() {"), (Unchanged, "}")]
+    );
+}
+
+#[test]
+fn normalize_symbol_hashes_in_text() {
+    assert_eq!(
+        normalize_symbol_hashes("symbol `_ZN3foo3bar17h0123456789abcdefE` is already defined"),
+        "symbol `_ZN3foo3bar17h[HASH]E` is already defined"
+    );
+    assert_eq!(
+        normalize_symbol_hashes("in `foo::bar::h0123456789abcdef`"),
+        "in `foo::bar::h[HASH]`"
+    );
+    assert_eq!(
+        normalize_symbol_hashes("_RNvCs1234abcd_3foo3bar and _RNvCsXyZ_7mycrate4main"),
+        "_RNvCs[HASH]_3foo3bar and _RNvCs[HASH]_7mycrate4main"
+    );
+    // Text without symbol names is left alone, as are hashes that aren't in one.
+    assert_eq!(
+        normalize_symbol_hashes("expected `u32`, found `&str`"),
+        "expected `u32`, found `&str`"
+    );
+    assert_eq!(normalize_symbol_hashes("17h0123456789abcdefE"), "17h0123456789abcdefE");
+}
+
+#[test]
+fn normalize_symbol_hashes_only_in_messages() {
+    let src = "extern \"C\" { fn _ZN3foo3bar17h0123456789abcdefE(); }\n";
+    let output = emit_human(
+        &[("test.rs", src)],
+        |emitter| emitter,
+        |dcx, files| {
+            let span = span_in(&files[0], 16, 47);
+            dcx.struct_span_err(
+                span,
+                "symbol `_ZN3foo3bar17h0123456789abcdefE` is already defined",
+            )
+            .emit();
+        },
+    );
+    assert!(output.contains("symbol `_ZN3foo3bar17h[HASH]E` is already defined"), "{output}");
+    // The source code is shown as it is.
+    assert!(output.contains(src.trim_end()), "{output}");
+}
+
+#[test]
+fn capture_emitter() {
+    crate::testing::with_capture_dcx(None, |dcx, captured| {
+        dcx.struct_warn("first").note("with a note").emit();
+        dcx.struct_warn("second").emit();
+
+        let diags = captured.drain();
+        let messages = diags.iter().map(|diag| diag.messages[0].0.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [Some("first"), Some("second")]);
+        assert_eq!(diags[0].children.len(), 1);
+        assert!(captured.is_empty());
+        assert_eq!(dcx.counts().by_level[&Level::Warning], 2);
+    })
+}
+
+/// Emits the diagnostics of `emit` with a human emitter whose source map has `files`, and returns
+/// the output.
+fn emit_human(
+    files: &[(&str, &str)],
+    configure: impl FnOnce(HumanEmitter) -> HumanEmitter,
+    emit: impl FnOnce(&DiagCtxt, &[Lrc<SourceFile>]),
+) -> String {
+    rustc_span::create_default_session_globals_then(|| {
+        let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let files = files
+            .iter()
+            .map(|&(name, src)| sm.new_source_file(PathBuf::from(name).into(), src.to_owned()))
+            .collect::<Vec<_>>();
+        let (emitter, output) =
+            crate::testing::human_emitter(sm, crate::testing::fallback_bundle());
+        let dcx = DiagCtxt::with_custom_emitter(configure(emitter));
+        emit(&dcx, &files);
+        output.take()
+    })
+}
+
+fn span_in(file: &SourceFile, lo: u32, hi: u32) -> Span {
+    Span::with_root_ctxt(file.start_pos + BytePos(lo), file.start_pos + BytePos(hi))
+}
+
+#[test]
+fn rendering_width_override() {
+    let emit = |width: Option<usize>| {
+        emit_human(
+            &[("test.rs", "let x = foo;\n")],
+            |emitter| emitter.diagnostic_width(Some(20)),
+            |dcx, files| {
+                let span = span_in(&files[0], 8, 11);
+                let mut diag = dcx.struct_span_err(span, "oops");
+                diag.span_label(span, "a label that is far too long");
+                if let Some(width) = width {
+                    diag.rendering_width(width);
+                }
+                diag.emit();
+            },
+        )
+    };
+    // The label is wrapped to `--diagnostic-width`, unless the diagnostic asks for a wider width.
+    assert!(!emit(None).contains("a label that is far too long"));
+    assert!(emit(Some(100)).contains("^^^ a label that is far too long\n"));
+}
+
+#[test]
+fn suggestion_in_other_file() {
+    let output = emit_human(
+        &[("a.rs", "fn foo() {}\n"), ("b.rs", "foo();\n")],
+        |emitter| emitter,
+        |dcx, files| {
+            let call = span_in(&files[1], 0, 3);
+            dcx.struct_span_err(call, "`foo` is private")
+                .with_multipart_suggestion(
+                    "make it public and import it",
+                    vec![
+                        (span_in(&files[0], 0, 0), "pub ".to_owned()),
+                        (call, "a::foo".to_owned()),
+                    ],
+                    Applicability::MachineApplicable,
+                )
+                .emit();
+        },
+    );
+    // Each file is shown as its own snippet, and the one that isn't the file of the error is
+    // pointed at.
+    assert!(output.contains("--> a.rs:1:1"), "{output}");
+    assert!(output.contains("LL | pub fn foo() {}\n"), "{output}");
+    assert!(output.contains("LL | a::foo();\n"), "{output}");
+}
+
+/// The code of a REPL, which the source map only has placeholders for.
+struct ReplSource;
+
+impl SourceProvider for ReplSource {
+    fn has_source(&self, _file: &SourceFile) -> bool {
+        true
+    }
+
+    fn line<'a>(&'a self, _file: &'a SourceFile, line_index: usize) -> Option<Cow<'a, str>> {
+        (line_index == 0).then_some(Cow::Borrowed("let x = Y;"))
+    }
+
+    fn snippet(&self, _span: Span) -> Option<String> {
+        Some("Y".to_string())
+    }
+
+    fn display_name(&self, _file: &SourceFile) -> String {
+        "<repl>".to_string()
+    }
+}
+
+#[test]
+fn suggestions_from_source_provider() {
+    let emit = |verbose: bool| {
+        emit_human(
+            &[("repl.rs", &" ".repeat(10))],
+            |emitter| emitter.source_provider(Some(Lrc::new(ReplSource))),
+            |dcx, files| {
+                let span = span_in(&files[0], 8, 9);
+                let mut diag = dcx.struct_span_err(span, "cannot find value `Y`");
+                if verbose {
+                    diag.span_suggestion_verbose(span, "use", "y", Applicability::MaybeIncorrect);
+                } else {
+                    diag.span_suggestion(span, "use", "y", Applicability::MaybeIncorrect);
+                }
+                diag.emit();
+            },
+        )
+    };
+    // Both the inline suggestion and the spliced one compare with the provider's code.
+    let inline = emit(false);
+    assert!(inline.contains("help: use (notice the capitalization): `y`"), "{inline}");
+    let verbose = emit(true);
+    assert!(verbose.contains("LL | let x = y;\n"), "{verbose}");
+    assert!(verbose.contains("notice the capitalization difference"), "{verbose}");
+}
+
+#[test]
+fn inline_explanation_of_code_block() {
+    let description =
+        "An example.\n\n```compile_fail,E0001\n# fn hidden() {}\nlet x = 1;\nlet y = 2;\n```\n";
+    let emit = |max_lines: usize| {
+        emit_human(
+            &[("test.rs", "let x = 1;\n")],
+            |emitter| {
+                emitter
+                    .explain_inline(Some(max_lines))
+                    .registry(Some(Registry::new(&[("E0001", description)])))
+            },
+            |dcx, _| {
+                let mut diag = dcx.struct_err("oops");
+                diag.code("E0001".to_owned());
+                diag.emit();
+            },
+        )
+        // The lines of the note are indented.
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+    };
+    // The attributes of the code block and its hidden lines are left out.
+    let full = emit(10);
+    assert!(full.contains("An example.\n\n```\nlet x = 1;\nlet y = 2;\n```\n"), "{full}");
+    assert!(!full.contains("hidden") && !full.contains("rustc --explain"), "{full}");
+    // A code block that is cut off is closed before the rest of the explanation is referred to.
+    let cut = emit(4);
+    assert!(cut.contains("```\nlet x = 1;\n```\n...\n"), "{cut}");
+    assert!(cut.contains("the full explanation is shown by `rustc --explain E0001`"), "{cut}");
+}
+
+#[test]
+fn group_diagnostics_by_file() {
+    rustc_span::create_default_session_globals_then(|| {
+        let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let a = sm.new_source_file(PathBuf::from("a.rs").into(), "fn a() {}\nfn b() {}\n".into());
+        let b = sm.new_source_file(PathBuf::from("b.rs").into(), "fn c() {}\n".into());
+        let (emitter, output) =
+            crate::testing::human_emitter(sm, crate::testing::fallback_bundle());
+        let flags = DiagCtxtFlags {
+            can_emit_warnings: true,
+            group_diagnostics_by_file: true,
+            ..Default::default()
+        };
+        let dcx = DiagCtxt::with_custom_emitter(emitter).with_flags(flags);
+
+        dcx.struct_span_warn(span_in(&b, 3, 4), "function `c` is never used").emit();
+        dcx.struct_span_warn(span_in(&a, 13, 14), "function `b` is never used").emit();
+        dcx.struct_span_warn(span_in(&a, 3, 4), "function `a` is never used").emit();
+        // Nothing is printed until the end of the compilation.
+        assert_eq!(output.take(), "");
+
+        dcx.print_error_count(&Registry::new(&[]));
+        let output = output.take();
+        let positions = ["a.rs:\n", "`a`", "`b`", "b.rs:\n", "`c`", "3 warnings emitted"]
+            .map(|s| output.find(s).unwrap_or_else(|| panic!("no `{s}` in {output}")));
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{output}");
+    })
+}
+
+/// An emitter like the ones that tools write outside of this crate: it only uses the public
+/// helpers to find the annotated lines and the suggested code.
+struct LineEmitter {
+    sm: Lrc<SourceMap>,
+    fallback_bundle: LazyFallbackBundle,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Translate for LineEmitter {
+    fn fluent_bundle(&self) -> Option<&Lrc<FluentBundle>> {
+        None
+    }
+
+    fn fallback_fluent_bundle(&self) -> &FluentBundle {
+        &self.fallback_bundle
+    }
+}
+
+impl Emitter for LineEmitter {
+    fn source_map(&self) -> Option<&Lrc<SourceMap>> {
+        Some(&self.sm)
+    }
+
+    fn emit_diagnostic(&mut self, diag: &Diagnostic) {
+        let args = to_fluent_args(diag.args());
+        let mut lines = self.lines.lock().unwrap();
+        for file in FileWithAnnotatedLines::collect_annotations(self, &args, &diag.span) {
+            for line in &file.lines {
+                let labels = line.annotations.iter().filter_map(|ann| ann.label.as_deref());
+                lines.push(format!(
+                    "{}:{}: {}",
+                    file.file.name.prefer_local(),
+                    line.line_index,
+                    labels.collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+        for sugg in diag.suggestions().unwrap_or_default() {
+            for (snippet, ..) in sugg.splice_lines(&self.sm, &*self.sm).into_iter().flatten() {
+                lines.push(format!("help: {snippet}"));
+            }
+        }
+    }
+}
+
+#[test]
+fn custom_emitter_with_public_helpers() {
+    rustc_span::create_default_session_globals_then(|| {
+        let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let file = sm
+            .new_source_file(PathBuf::from("a.rs").into(), "fn a() {\n    let x = 1;\n}\n".into());
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let emitter = LineEmitter {
+            sm: sm.clone(),
+            fallback_bundle: crate::testing::fallback_bundle(),
+            lines: lines.clone(),
+        };
+        let dcx = DiagCtxt::with_custom_emitter(emitter);
+
+        let x = span_in(&file, 17, 18);
+        dcx.struct_span_warn(x, "unused variable: `x`")
+            .with_span_label(x, "never used")
+            .with_span_suggestion(
+                x,
+                "prefix it with an underscore",
+                "_x",
+                Applicability::MachineApplicable,
+            )
+            .emit();
+
+        assert_eq!(*lines.lock().unwrap(), ["a.rs:2: never used", "help:     let _x = 1;"]);
+    })
+}
@@ -0,0 +1,166 @@
+//! Filters that hide diagnostics depending on where they point, see
+//! `DiagCtxt::with_changed_lines` and `DiagCtxt::add_span_filter`.
+
+use rustc_data_structures::fx::FxHashMap;
+use rustc_span::source_map::SourceMap;
+use rustc_span::{FileName, Span};
+use std::ops::RangeInclusive;
+use std::path::{Component, Path, PathBuf};
+
+/// The lines of each file that a patch adds or changes.
+#[derive(Clone, Debug, Default)]
+pub struct ChangedLines {
+    /// The 1-based line ranges of each file, with paths relative to the root of the patch.
+    files: FxHashMap<PathBuf, Vec<RangeInclusive<usize>>>,
+}
+
+impl ChangedLines {
+    /// Reads the changed lines from a patch in the unified diff format, as produced by `diff -u`
+    /// or `git diff`. Removed lines aren't part of the result, as there is no code left on them
+    /// that diagnostics could point at.
+    pub fn from_patch(patch: &str) -> ChangedLines {
+        let mut changed = ChangedLines::default();
+        let mut file = None;
+        let mut line = 0;
+        for text in patch.lines() {
+            if let Some(path) = text.strip_prefix("+++ ") {
+                // Timestamps are separated from the path with a tab.
+                let path = path.split('\t').next().unwrap_or_default().trim_end();
+                file = match path {
+                    "/dev/null" => None,
+                    _ => Some(PathBuf::from(path.strip_prefix("b/").unwrap_or(path))),
+                };
+            } else if let Some(hunk) = text.strip_prefix("@@ ") {
+                // `@@ -l,s +l,s @@`, where the size may be left out if it's 1.
+                line = hunk
+                    .split(' ')
+                    .find_map(|range| range.strip_prefix('+'))
+                    .and_then(|range| range.split(',').next()?.parse().ok())
+                    .unwrap_or(0);
+            } else if let Some(file) = &file
+                && line != 0
+            {
+                match text.as_bytes().first() {
+                    Some(b'+') => {
+                        changed.add(file.clone(), line..=line);
+                        line += 1;
+                    }
+                    Some(b'-' | b'\\') => {}
+                    _ => line += 1,
+                }
+            }
+        }
+        changed
+    }
+
+    /// Marks `lines` of `file` as changed, merging them with the previous range if they follow
+    /// it directly.
+    pub fn add(&mut self, file: PathBuf, lines: RangeInclusive<usize>) {
+        let ranges = self.files.entry(file).or_default();
+        match ranges.last_mut() {
+            Some(last) if *last.end() + 1 == *lines.start() => {
+                *last = *last.start()..=*lines.end();
+            }
+            _ => ranges.push(lines),
+        }
+    }
+
+    /// Whether a line of `path` within `lines` has changed. Paths of the patch are matched
+    /// against the end of `path`, as it may be absolute or relative to another directory.
+    pub(crate) fn contains(&self, path: &Path, lines: RangeInclusive<usize>) -> bool {
+        self.files.iter().any(|(file, ranges)| {
+            path.ends_with(file)
+                && ranges
+                    .iter()
+                    .any(|range| range.start() <= lines.end() && lines.start() <= range.end())
+        })
+    }
+
+    /// Whether a diagnostic pointing at `span` should be shown. Diagnostics without a span are
+    /// always shown, as are those pointing into code that isn't from a local file.
+    pub(crate) fn includes(&self, sm: &SourceMap, span: Span) -> bool {
+        let Some(path) = local_path(sm, span) else { return true };
+        let lines = sm.lookup_char_pos(span.lo()).line..=sm.lookup_char_pos(span.hi()).line;
+        self.contains(&path, lines)
+    }
+}
+
+/// The local path of the file that `span` points into, if it is a local file.
+pub(crate) fn local_path(sm: &SourceMap, span: Span) -> Option<PathBuf> {
+    if span.is_dummy() {
+        return None;
+    }
+    match &sm.lookup_source_file(span.lo()).name {
+        FileName::Real(name) => name.local_path().map(Path::to_owned),
+        _ => None,
+    }
+}
+
+/// A glob pattern matching paths, like `target/generated/**/*.rs`.
+///
+/// `*` matches any part of a path component, `?` matches a single character of one and `**`
+/// matches any number of whole components. Unless the pattern is absolute, it may match the end
+/// of a path, so that it doesn't depend on the directory the paths are relative to.
+#[derive(Clone, Debug)]
+pub struct PathGlob {
+    components: Vec<String>,
+    absolute: bool,
+}
+
+impl PathGlob {
+    pub fn new(pattern: &str) -> PathGlob {
+        let pattern = Path::new(pattern);
+        PathGlob {
+            components: normal_components(pattern).map(String::from).collect(),
+            absolute: pattern.has_root(),
+        }
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = normal_components(path).collect::<Vec<_>>();
+        if self.absolute {
+            return matches_components(&self.components, &path);
+        }
+        (0..=path.len()).any(|start| matches_components(&self.components, &path[start..]))
+    }
+}
+
+/// The components of `path` other than its prefix and root, which aren't part of `PathGlob`s.
+fn normal_components(path: &Path) -> impl Iterator<Item = &str> {
+    path.components().filter_map(|component| match component {
+        Component::Prefix(_) | Component::RootDir => None,
+        // Components that aren't UTF-8 can only be matched by wildcards.
+        _ => Some(component.as_os_str().to_str().unwrap_or("\u{fffd}")),
+    })
+}
+
+fn matches_components(pattern: &[String], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|start| matches_components(rest, &path[start..]))
+        }
+        Some((first, rest)) => path.split_first().is_some_and(|(component, path)| {
+            matches_component(first, component) && matches_components(rest, path)
+        }),
+    }
+}
+
+fn matches_component(pattern: &str, component: &str) -> bool {
+    let mut pattern = pattern.chars();
+    match pattern.next() {
+        None => component.is_empty(),
+        Some('*') => component
+            .char_indices()
+            .map(|(start, _)| start)
+            .chain([component.len()])
+            .any(|start| matches_component(pattern.as_str(), &component[start..])),
+        Some(expected) => {
+            let mut component = component.chars();
+            component.next().is_some_and(|c| {
+                (expected == '?' || expected == c)
+                    && matches_component(pattern.as_str(), component.as_str())
+            })
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Identities of diagnostics that are stable across compilations, see [`fingerprint`].
+
+use crate::Diagnostic;
+use rustc_data_structures::stable_hasher::{Hash128, StableHasher};
+use rustc_error_messages::DiagnosticMessage;
+use rustc_span::source_map::SourceMap;
+use std::hash::Hash;
+
+/// Computes a fingerprint of `diag` that stays the same across compilations as long as the code
+/// it points at doesn't change, even if unrelated changes move that code to a different line.
+/// It is a hash of:
+///
+/// - the error code or the name of the lint,
+/// - the messages of the diagnostic and their arguments, before translation and with whitespace
+///   normalized,
+/// - the path of the file of the primary span, as shown in diagnostics,
+/// - the source code of the lines the primary span is on, and where on those lines it is.
+///
+/// Subdiagnostics are left out, as they tend to change more often than the problem that the
+/// diagnostic is about.
+pub fn fingerprint(diag: &Diagnostic, sm: Option<&SourceMap>) -> String {
+    let mut hasher = StableHasher::new();
+    diag.code.hash(&mut hasher);
+    diag.lint().map(|lint| &lint.name).hash(&mut hasher);
+
+    for (msg, _) in &diag.messages {
+        match msg {
+            DiagnosticMessage::Str(s) | DiagnosticMessage::Eager(s) => {
+                0u8.hash(&mut hasher);
+                s.split_whitespace().for_each(|word| word.hash(&mut hasher));
+            }
+            DiagnosticMessage::FluentIdentifier(id, attr) => {
+                1u8.hash(&mut hasher);
+                (id, attr).hash(&mut hasher);
+            }
+        }
+    }
+    let mut args = diag.args().collect::<Vec<_>>();
+    args.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (name, value) in args {
+        // The `Debug` output of the values is stable, unlike their `Hash` impls, which hash
+        // symbols by their index.
+        (name, format!("{value:?}")).hash(&mut hasher);
+    }
+
+    if let (Some(sm), Some(span)) = (sm, diag.span.primary_span())
+        && !span.is_dummy()
+    {
+        let file = sm.lookup_source_file(span.lo());
+        sm.filename_for_diagnostics(&file.name).to_string().hash(&mut hasher);
+        let lines = sm.span_extend_to_line(span);
+        ((span.lo() - lines.lo()).0, (span.hi() - lines.lo()).0).hash(&mut hasher);
+        sm.span_to_snippet(lines).ok().hash(&mut hasher);
+    }
+
+    let hash: Hash128 = hasher.finish();
+    format!("{:032x}", hash.as_u128())
+}
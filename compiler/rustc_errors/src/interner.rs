@@ -0,0 +1,56 @@
+//! Sharing of the strings of diagnostics that are held on to, see [`DiagnosticInterner`].
+
+use rustc_arena::DroplessArena;
+use rustc_data_structures::fx::FxHashSet;
+use std::borrow::Cow;
+
+/// Deduplicates the owned strings in the messages and arguments of diagnostics that the
+/// `DiagCtxt` keeps around, so that e.g. the thousands of identical "unused variable" warnings
+/// buffered for a large crate share a single allocation per distinct string.
+///
+/// Interned strings are stored in an arena that is freed with the `DiagCtxt`, and borrowed by the
+/// existing `Cow<'static, str>`s of the diagnostics so that emitters see messages and arguments
+/// as before. The `'static` lifetime is a lie: interned diagnostics must only be kept in the
+/// `DiagCtxtInner` that owns the interner, and go through [`DiagnosticInterner::unintern`] before
+/// they are handed to emitters, hooks or callers, which may keep them for longer.
+#[derive(Default)]
+pub(crate) struct DiagnosticInterner {
+    arena: DroplessArena,
+    strings: FxHashSet<&'static str>,
+}
+
+impl DiagnosticInterner {
+    /// Replaces `s` with a borrow of the shared copy of its contents, if it is owned.
+    pub(crate) fn intern(&mut self, s: &mut Cow<'static, str>) {
+        let Cow::Owned(owned) = s else {
+            return;
+        };
+        if owned.is_empty() {
+            *s = Cow::Borrowed("");
+            return;
+        }
+        let interned = match self.strings.get(owned.as_str()) {
+            Some(interned) => *interned,
+            None => {
+                // SAFETY: the arena lives as long as `self`, and the type documentation explains
+                // why the borrows don't outlive `self`.
+                let interned: &'static str =
+                    unsafe { &*(self.arena.alloc_str(owned) as *const str) };
+                self.strings.insert(interned);
+                interned
+            }
+        };
+        *s = Cow::Borrowed(interned);
+    }
+
+    /// Turns `s` back into an owned string if it was interned by this interner, so that it can
+    /// outlive it.
+    pub(crate) fn unintern(&self, s: &mut Cow<'static, str>) {
+        if let Cow::Borrowed(borrowed) = s
+            && let Some(interned) = self.strings.get(*borrowed)
+            && std::ptr::eq(*interned, *borrowed)
+        {
+            *s = Cow::Owned(borrowed.to_string());
+        }
+    }
+}
@@ -14,21 +14,25 @@
 
 use crate::emitter::{should_show_source_code, Emitter, HumanReadableErrorType};
 use crate::registry::Registry;
-use crate::translation::{to_fluent_args, Translate};
+use crate::source_provider::SourceProvider;
+use crate::translation::{span_label_args, to_fluent_args, Translate};
 use crate::{
-    diagnostic::IsLint, CodeSuggestion, FluentBundle, LazyFallbackBundle, MultiSpan, SpanLabel,
-    SubDiagnostic, TerminalUrl,
+    diagnostic::IsLint, CodeSuggestion, DiagnosticArgValue, DiagnosticLocation, FileSuggestion,
+    FluentBundle, LazyFallbackBundle, MacroBacktrace, MultiSpan, SpanLabel, StructuredArg,
+    SubDiagnostic, TerminalUrl, UnfulfilledExpectation,
 };
-use rustc_lint_defs::Applicability;
+use rustc_lint_defs::{Applicability, LintExpectationId};
 
+use rustc_data_structures::profiling::SelfProfilerRef;
 use rustc_data_structures::sync::{IntoDynSyncSend, Lrc};
 use rustc_error_messages::FluentArgs;
 use rustc_span::hygiene::ExpnData;
 use rustc_span::Span;
+use std::collections::BTreeMap;
 use std::error::Report;
 use std::io::{self, Write};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::vec;
 
 use serde::Serialize;
@@ -36,10 +40,16 @@
 #[cfg(test)]
 mod tests;
 
+/// The version of the shape of JSON diagnostics, which is given in their `format_version` field.
+/// Version 1 is the shape from before diagnostics had that field, and is still produced with
+/// `-Zjson-version=1` for tools that can't handle the fields added since.
+pub const JSON_FORMAT_VERSION: u32 = 2;
+
 pub struct JsonEmitter {
     dst: IntoDynSyncSend<Box<dyn Write + Send>>,
     registry: Option<Registry>,
     sm: Lrc<SourceMap>,
+    source_provider: Option<Lrc<dyn SourceProvider>>,
     fluent_bundle: Option<Lrc<FluentBundle>>,
     fallback_bundle: LazyFallbackBundle,
     pretty: bool,
@@ -47,9 +57,17 @@ pub struct JsonEmitter {
     ignored_directories_in_source_blocks: Vec<String>,
     json_rendered: HumanReadableErrorType,
     diagnostic_width: Option<usize>,
-    macro_backtrace: bool,
+    diff_suggestions: bool,
+    macro_backtrace: MacroBacktrace,
     track_diagnostics: bool,
     terminal_url: TerminalUrl,
+    column_convention: ColumnConvention,
+    path_base: Option<PathBuf>,
+    local_paths: bool,
+    normalize_output: bool,
+    format_version: u32,
+    array: Option<JsonArray>,
+    prof: SelfProfilerRef,
 }
 
 impl JsonEmitter {
@@ -61,7 +79,7 @@ pub fn stderr(
         pretty: bool,
         json_rendered: HumanReadableErrorType,
         diagnostic_width: Option<usize>,
-        macro_backtrace: bool,
+        macro_backtrace: MacroBacktrace,
         track_diagnostics: bool,
         terminal_url: TerminalUrl,
     ) -> JsonEmitter {
@@ -69,6 +87,7 @@ pub fn stderr(
             dst: IntoDynSyncSend(Box::new(io::BufWriter::new(io::stderr()))),
             registry,
             sm: source_map,
+            source_provider: None,
             fluent_bundle,
             fallback_bundle,
             pretty,
@@ -76,9 +95,17 @@ pub fn stderr(
             ignored_directories_in_source_blocks: Vec::new(),
             json_rendered,
             diagnostic_width,
+            diff_suggestions: false,
             macro_backtrace,
             track_diagnostics,
             terminal_url,
+            column_convention: ColumnConvention::default(),
+            path_base: None,
+            local_paths: false,
+            normalize_output: false,
+            format_version: JSON_FORMAT_VERSION,
+            array: None,
+            prof: SelfProfilerRef::new(None, None),
         }
     }
 
@@ -88,7 +115,7 @@ pub fn basic(
         fluent_bundle: Option<Lrc<FluentBundle>>,
         fallback_bundle: LazyFallbackBundle,
         diagnostic_width: Option<usize>,
-        macro_backtrace: bool,
+        macro_backtrace: MacroBacktrace,
         track_diagnostics: bool,
         terminal_url: TerminalUrl,
     ) -> JsonEmitter {
@@ -116,7 +143,7 @@ pub fn new(
         pretty: bool,
         json_rendered: HumanReadableErrorType,
         diagnostic_width: Option<usize>,
-        macro_backtrace: bool,
+        macro_backtrace: MacroBacktrace,
         track_diagnostics: bool,
         terminal_url: TerminalUrl,
     ) -> JsonEmitter {
@@ -124,6 +151,7 @@ pub fn new(
             dst: IntoDynSyncSend(dst),
             registry,
             sm: source_map,
+            source_provider: None,
             fluent_bundle,
             fallback_bundle,
             pretty,
@@ -131,9 +159,17 @@ pub fn new(
             ignored_directories_in_source_blocks: Vec::new(),
             json_rendered,
             diagnostic_width,
+            diff_suggestions: false,
             macro_backtrace,
             track_diagnostics,
             terminal_url,
+            column_convention: ColumnConvention::default(),
+            path_base: None,
+            local_paths: false,
+            normalize_output: false,
+            format_version: JSON_FORMAT_VERSION,
+            array: None,
+            prof: SelfProfilerRef::new(None, None),
         }
     }
 
@@ -145,7 +181,67 @@ pub fn ignored_directories_in_source_blocks(self, value: Vec<String>) -> Self {
         Self { ignored_directories_in_source_blocks: value, ..self }
     }
 
+    pub fn diff_suggestions(self, diff_suggestions: bool) -> Self {
+        Self { diff_suggestions, ..self }
+    }
+
+    pub fn column_convention(self, column_convention: ColumnConvention) -> Self {
+        Self { column_convention, ..self }
+    }
+
+    /// Shows file paths relative to `path_base` in the rendered diagnostics. The spans keep the
+    /// full paths.
+    pub fn path_base(self, path_base: Option<PathBuf>) -> Self {
+        Self { path_base, ..self }
+    }
+
+    /// Shows the local paths of files remapped with `--remap-path-prefix` in the rendered
+    /// diagnostics, and adds them to spans next to the remapped paths.
+    pub fn local_paths(self, local_paths: bool) -> Self {
+        Self { local_paths, ..self }
+    }
+
+    /// Replaces the parts of the rendered diagnostics that differ between machines and builds
+    /// by placeholders, like `HumanEmitter::normalize_output`.
+    pub fn normalize_output(self, normalize_output: bool) -> Self {
+        Self { normalize_output, ..self }
+    }
+
+    /// Produces diagnostics in `format_version` of the JSON shape, see `JSON_FORMAT_VERSION`.
+    pub fn format_version(self, format_version: u32) -> Self {
+        Self { format_version, ..self }
+    }
+
+    /// Collects the messages in `array` instead of writing one message per line as they are
+    /// emitted, see `JsonArray`.
+    pub fn array(self, array: Option<JsonArray>) -> Self {
+        if let Some(array) = &array {
+            array.0.lock().unwrap().used = true;
+        }
+        Self { array, ..self }
+    }
+
+    /// Takes the source code and file names of the spans from `source_provider` instead of the
+    /// source map, see `SourceProvider`.
+    pub fn source_provider(self, source_provider: Option<Lrc<dyn SourceProvider>>) -> Self {
+        Self { source_provider, ..self }
+    }
+
+    /// The provider of the source code of the spans, see `JsonEmitter::source_provider`.
+    fn sources(&self) -> &dyn SourceProvider {
+        self.source_provider.as_deref().unwrap_or(&*self.sm)
+    }
+
     fn emit(&mut self, val: EmitTyped<'_>) -> io::Result<()> {
+        let _timer = self.prof.generic_activity("write_diagnostic");
+        if let Some(array) = &self.array {
+            let message = if self.pretty {
+                serde_json::to_vec_pretty(&val)?
+            } else {
+                serde_json::to_vec(&val)?
+            };
+            return array.push(message);
+        }
         if self.pretty {
             serde_json::to_writer_pretty(&mut *self.dst, &val)?
         } else {
@@ -156,13 +252,76 @@ fn emit(&mut self, val: EmitTyped<'_>) -> io::Result<()> {
     }
 }
 
+/// The messages of the `JsonEmitter`s with `JsonEmitter::array`, which `JsonArray::finish`
+/// writes to `dst` as a single JSON array at the end of the compilation. Emitters that share the
+/// array, like those of the driver and of the session, write to the same document.
+#[derive(Clone)]
+pub struct JsonArray(Arc<Mutex<ArrayState>>);
+
+struct ArrayState {
+    dst: IntoDynSyncSend<Box<dyn Write + Send>>,
+    /// The messages so far, or `None` once the array was written.
+    messages: Option<Vec<Vec<u8>>>,
+    /// Whether an emitter writes to the array. Otherwise, nothing is written, not even `[]`.
+    used: bool,
+}
+
+impl JsonArray {
+    pub fn new(dst: Box<dyn Write + Send>) -> JsonArray {
+        let state =
+            ArrayState { dst: IntoDynSyncSend(dst), messages: Some(Vec::new()), used: false };
+        JsonArray(Arc::new(Mutex::new(state)))
+    }
+
+    /// The array on stderr, which is shared by all emitters of the process.
+    pub fn stderr() -> JsonArray {
+        static STDERR: OnceLock<JsonArray> = OnceLock::new();
+        STDERR.get_or_init(|| JsonArray::new(Box::new(io::stderr()))).clone()
+    }
+
+    /// Adds `message` to the array. Messages that come after the array was written, like bugs
+    /// reported while the compiler shuts down, are written on a line of their own.
+    fn push(&self, message: Vec<u8>) -> io::Result<()> {
+        let mut state = self.0.lock().unwrap();
+        let state = &mut *state;
+        match &mut state.messages {
+            Some(messages) => {
+                messages.push(message);
+                Ok(())
+            }
+            None => {
+                state.dst.write_all(&message)?;
+                state.dst.write_all(b"\n")?;
+                state.dst.flush()
+            }
+        }
+    }
+
+    /// Writes the array if an emitter uses it and it wasn't written yet.
+    pub fn finish(&self) -> io::Result<()> {
+        let mut state = self.0.lock().unwrap();
+        if !state.used {
+            return Ok(());
+        }
+        let Some(messages) = state.messages.take() else {
+            return Ok(());
+        };
+        state.dst.write_all(b"[")?;
+        state.dst.write_all(&messages.join(&b",\n"[..]))?;
+        state.dst.write_all(b"]\n")?;
+        state.dst.flush()
+    }
+}
+
 #[derive(Serialize)]
 #[serde(tag = "$message_type", rename_all = "snake_case")]
 enum EmitTyped<'a> {
     Diagnostic(Diagnostic),
     Artifact(ArtifactNotification<'a>),
     FutureIncompat(FutureIncompatReport<'a>),
+    FutureIncompatDiagnostic(FutureIncompatDiagnostic<'a>),
     UnusedExtern(UnusedExterns<'a, 'a, 'a>),
+    UnfulfilledExpectations(UnfulfilledExpectations),
 }
 
 impl Translate for JsonEmitter {
@@ -176,6 +335,10 @@ fn fallback_fluent_bundle(&self) -> &FluentBundle {
 }
 
 impl Emitter for JsonEmitter {
+    fn set_self_profiler(&mut self, prof: SelfProfilerRef) {
+        self.prof = prof;
+    }
+
     fn emit_diagnostic(&mut self, diag: &crate::Diagnostic) {
         let data = Diagnostic::from_errors_diagnostic(diag, self);
         let result = self.emit(EmitTyped::Diagnostic(data));
@@ -213,6 +376,27 @@ fn emit_future_breakage_report(&mut self, diags: Vec<crate::Diagnostic>) {
         }
     }
 
+    fn emit_future_breakage_diagnostic(&mut self, diag: &crate::Diagnostic) {
+        let mut diag = diag.clone();
+        if diag.level == crate::Level::Allow {
+            diag.level = crate::Level::Warning;
+        }
+        let incompat = diag.extras().and_then(|extras| extras.future_incompat.as_ref());
+        let data = FutureIncompatDiagnostic {
+            reference: incompat
+                .map(|incompat| incompat.reference.clone())
+                .filter(|reference| !reference.is_empty()),
+            breaks_in: incompat.map(|incompat| incompat.breaks_in.clone()),
+            diagnostic: Box::new(EmitTyped::Diagnostic(Diagnostic::from_errors_diagnostic(
+                &diag, self,
+            ))),
+        };
+        let result = self.emit(EmitTyped::FutureIncompatDiagnostic(data));
+        if let Err(e) = result {
+            panic!("failed to print future breakage diagnostic: {e:?}");
+        }
+    }
+
     fn emit_unused_externs(&mut self, lint_level: rustc_lint_defs::Level, unused_externs: &[&str]) {
         let lint_level = lint_level.as_str();
         let data = UnusedExterns { lint_level, unused_extern_names: unused_externs };
@@ -222,10 +406,38 @@ fn emit_unused_externs(&mut self, lint_level: rustc_lint_defs::Level, unused_ext
         }
     }
 
+    fn emit_unfulfilled_expectations(&mut self, expectations: &[UnfulfilledExpectation]) {
+        let unfulfilled_expectations = expectations
+            .iter()
+            .map(|expectation| UnfulfilledExpectationItem {
+                id: expectation_id(expectation.id),
+                span: DiagnosticSpan::from_span_etc(expectation.span, true, None, None, self),
+                reason: expectation.reason.map(|reason| reason.to_string()),
+            })
+            .collect();
+        let data = UnfulfilledExpectations { unfulfilled_expectations };
+        let result = self.emit(EmitTyped::UnfulfilledExpectations(data));
+        if let Err(e) = result {
+            panic!("failed to print unfulfilled expectations: {e:?}");
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(array) = &self.array
+            && let Err(e) = array.finish()
+        {
+            panic!("failed to print diagnostics: {e:?}");
+        }
+    }
+
     fn source_map(&self) -> Option<&Lrc<SourceMap>> {
         Some(&self.sm)
     }
 
+    fn source(&self) -> Option<&dyn SourceProvider> {
+        Some(self.sources())
+    }
+
     fn should_show_explain(&self) -> bool {
         !matches!(self.json_rendered, HumanReadableErrorType::Short(_))
     }
@@ -235,6 +447,10 @@ fn should_show_explain(&self) -> bool {
 
 #[derive(Serialize)]
 struct Diagnostic {
+    /// The version of the shape of the diagnostic, see `JSON_FORMAT_VERSION`. Only present on
+    /// top-level diagnostics, and not in version 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format_version: Option<u32>,
     /// The primary error message.
     message: String,
     code: Option<DiagnosticCode>,
@@ -245,19 +461,67 @@ struct Diagnostic {
     children: Vec<Diagnostic>,
     /// The message as rustc would render it.
     rendered: Option<String>,
+    /// Machine-readable facts about the diagnostic, from its structured arguments. Only present
+    /// if it has any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    args: Option<BTreeMap<String, serde_json::Value>>,
+    /// Identifies the diagnostic across compilations, see `crate::fingerprint::fingerprint`.
+    /// Only present on top-level diagnostics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<String>,
+    /// The number of duplicates of the diagnostic that weren't shown, if there were any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicates: Option<usize>,
+    /// The `#[expect]` attribute that the diagnostic is about, on lints that fulfilled it despite
+    /// being force-warned and on the lint about an unfulfilled one. Only present on those.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expectation: Option<DiagnosticExpectation>,
+    /// Where in rustc the diagnostic was created. Only present on top-level diagnostics with
+    /// `-Z track-diagnostics`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    emitted_at: Option<DiagnosticLocation>,
+    /// Documentation that the message points to. Only present on children added with
+    /// `Diagnostic::help_with_url`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    /// The tool that the diagnostic is from, like `clippy`. Only present on top-level diagnostics
+    /// from tools that the driver registered a display prefix for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DiagnosticExpectation {
+    /// Identifies the expectation within the crate, see `expectation_id`.
+    id: String,
+    /// The lint in the `#[expect]` attribute, if known.
+    span: Option<DiagnosticSpan>,
+    /// Whether a lint the attribute expects was emitted.
+    fulfilled: bool,
 }
 
 #[derive(Serialize)]
 struct DiagnosticSpan {
     file_name: String,
+    /// The local path of the file if `file_name` was remapped with `--remap-path-prefix`. Only
+    /// present with `-Zlocal-paths-in-diagnostics`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_file_name: Option<String>,
     byte_start: u32,
     byte_end: u32,
     /// 1-based.
     line_start: usize,
     line_end: usize,
-    /// 1-based, character offset.
+    /// 1-based, character offset, unless another convention was chosen with
+    /// `-Zjson-column-convention`.
     column_start: usize,
     column_end: usize,
+    /// 1-based, offset in UTF-16 code units, as counted by editors using the Language Server
+    /// Protocol. Not present in version 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column_start_utf16: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column_end_utf16: Option<usize>,
     /// Is this a "primary" span -- meaning the point, or one of the points,
     /// where the error occurred?
     is_primary: bool,
@@ -270,6 +534,10 @@ struct DiagnosticSpan {
     suggested_replacement: Option<String>,
     /// If the suggestion is approximate
     suggestion_applicability: Option<Applicability>,
+    /// Rank of the suggestion among the suggestions of the diagnostic, lower is better. Only
+    /// present for ranked suggestions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestion_rank: Option<u32>,
     /// Macro invocations that created the code at this span, if any.
     expansion: Option<Box<DiagnosticSpanMacroExpansion>>,
 }
@@ -278,7 +546,7 @@ struct DiagnosticSpan {
 struct DiagnosticSpanLine {
     text: String,
 
-    /// 1-based, character offset in self.text.
+    /// 1-based, character offset in self.text, counted like the columns of the span.
     highlight_start: usize,
 
     highlight_end: usize,
@@ -305,6 +573,13 @@ struct DiagnosticCode {
     code: String,
     /// An explanation for the code.
     explanation: Option<&'static str>,
+    /// The groups of the lint, like `unused`, if the diagnostic is from a lint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<Vec<String>>,
+    /// The level of the lint unless attributes or flags change it, like `warn`, if the
+    /// diagnostic is from a lint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default_level: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -327,6 +602,30 @@ struct FutureIncompatReport<'a> {
     future_incompat_report: Vec<FutureBreakageItem<'a>>,
 }
 
+#[derive(Serialize)]
+struct FutureIncompatDiagnostic<'a> {
+    // Always EmitTyped::Diagnostic, like `FutureBreakageItem::diagnostic`.
+    diagnostic: Box<EmitTyped<'a>>,
+    /// The tracking issue of the change, if the lint has one.
+    reference: Option<String>,
+    /// When the code is going to break: `future-release` or an edition like `edition-2024`.
+    breaks_in: Option<String>,
+}
+
+#[derive(Serialize)]
+struct UnfulfilledExpectations {
+    unfulfilled_expectations: Vec<UnfulfilledExpectationItem>,
+}
+
+#[derive(Serialize)]
+struct UnfulfilledExpectationItem {
+    /// Identifies the expectation within the crate, like `DiagnosticExpectation::id`.
+    id: String,
+    /// The lint in the `#[expect]` attribute.
+    span: DiagnosticSpan,
+    reason: Option<String>,
+}
+
 // NOTE: Keep this in sync with the equivalent structs in rustdoc's
 // doctest component (as well as cargo).
 // We could unify this struct the one in rustdoc but they have different
@@ -341,17 +640,48 @@ struct UnusedExterns<'a, 'b, 'c> {
 
 impl Diagnostic {
     fn from_errors_diagnostic(diag: &crate::Diagnostic, je: &JsonEmitter) -> Diagnostic {
-        let args = to_fluent_args(diag.args());
-        let sugg = diag.suggestions.iter().flatten().map(|sugg| {
+        // Lists are only capped in the rendered output, tools get to see all of their elements.
+        let uncapped_args =
+            diag.args().map(|(name, value)| (name, value.clone().uncapped())).collect::<Vec<_>>();
+        let args = to_fluent_args(uncapped_args.iter().map(|(name, value)| (*name, value)));
+        let sugg = diag.suggestions().unwrap_or_default().iter().map(|sugg| {
             let translated_message =
                 je.translate_message(&sugg.msg, &args).map_err(Report::new).unwrap();
             Diagnostic {
+                format_version: None,
                 message: translated_message.to_string(),
                 code: None,
                 level: "help",
                 spans: DiagnosticSpan::from_suggestion(sugg, &args, je),
                 children: vec![],
                 rendered: None,
+                args: None,
+                fingerprint: None,
+                duplicates: None,
+                expectation: None,
+                emitted_at: None,
+                url: None,
+                tool: None,
+            }
+        });
+        let file_sugg = diag.file_suggestions().iter().map(|sugg| {
+            let translated_message =
+                je.translate_message(&sugg.msg, &args).map_err(Report::new).unwrap();
+            Diagnostic {
+                format_version: None,
+                message: translated_message.to_string(),
+                code: None,
+                level: "help",
+                spans: vec![DiagnosticSpan::from_file_suggestion(sugg, je)],
+                children: vec![],
+                rendered: None,
+                args: None,
+                fingerprint: None,
+                duplicates: None,
+                expectation: None,
+                emitted_at: None,
+                url: None,
+                tool: None,
             }
         });
 
@@ -387,8 +717,13 @@ fn reset(&mut self) -> io::Result<()> {
         je.json_rendered
             .new_emitter(Box::new(buf), je.fallback_bundle.clone())
             .sm(Some(je.sm.clone()))
+            .source_provider(je.source_provider.clone())
             .fluent_bundle(je.fluent_bundle.clone())
             .diagnostic_width(je.diagnostic_width)
+            .diff_suggestions(je.diff_suggestions)
+            .path_base(je.path_base.clone())
+            .local_paths(je.local_paths)
+            .normalize_output(je.normalize_output)
             .macro_backtrace(je.macro_backtrace)
             .track_diagnostics(je.track_diagnostics)
             .terminal_url(je.terminal_url)
@@ -400,18 +735,46 @@ fn reset(&mut self) -> io::Result<()> {
 
         let translated_message = je.translate_messages(&diag.messages, &args);
 
+        let lint = diag.lint();
+        let groups = lint.map(|lint| lint.groups.clone());
+        let default_level = lint.map(|lint| lint.default_level.clone());
         let code = if let Some(code) = &diag.code {
             Some(DiagnosticCode {
                 code: code.to_string(),
                 explanation: je.registry.as_ref().unwrap().try_find_description(&code).ok(),
+                groups,
+                default_level,
+            })
+        } else if let Some(IsLint { name, .. }) = lint {
+            Some(DiagnosticCode {
+                code: name.to_string(),
+                explanation: None,
+                groups,
+                default_level,
             })
-        } else if let Some(IsLint { name, .. }) = &diag.is_lint {
-            Some(DiagnosticCode { code: name.to_string(), explanation: None })
         } else {
             None
         };
 
-        Diagnostic {
+        let unfulfilled_expectation =
+            diag.extras().and_then(|extras| extras.unfulfilled_expectation);
+        let expectation = match (diag.level, unfulfilled_expectation) {
+            (_, Some(id)) => Some(DiagnosticExpectation {
+                id: expectation_id(id),
+                span: diag
+                    .span
+                    .primary_span()
+                    .map(|span| DiagnosticSpan::from_span_etc(span, true, None, None, je)),
+                fulfilled: false,
+            }),
+            (crate::Level::ForceWarning(Some(id)), None) => {
+                Some(DiagnosticExpectation { id: expectation_id(id), span: None, fulfilled: true })
+            }
+            _ => None,
+        };
+
+        let mut diagnostic = Diagnostic {
+            format_version: Some(je.format_version),
             message: translated_message.to_string(),
             code,
             level: diag.level.to_str(),
@@ -421,9 +784,24 @@ fn reset(&mut self) -> io::Result<()> {
                 .iter()
                 .map(|c| Diagnostic::from_sub_diagnostic(c, &args, je))
                 .chain(sugg)
+                .chain(file_sugg)
                 .collect(),
             rendered: Some(output),
+            args: structured_args(diag),
+            fingerprint: Some(crate::fingerprint::fingerprint(diag, Some(&je.sm))),
+            duplicates: diag.extras().and_then(|extras| extras.duplicates),
+            expectation,
+            emitted_at: je.track_diagnostics.then(|| diag.emitted_at.clone()),
+            url: None,
+            tool: diag
+                .extras()
+                .and_then(|extras| extras.tool.as_ref())
+                .map(|tool| tool.name.clone()),
+        };
+        if je.format_version < 2 {
+            diagnostic.downgrade_to_version_1();
         }
+        diagnostic
     }
 
     fn from_sub_diagnostic(
@@ -433,14 +811,83 @@ fn from_sub_diagnostic(
     ) -> Diagnostic {
         let translated_message = je.translate_messages(&diag.messages, args);
         Diagnostic {
+            format_version: None,
             message: translated_message.to_string(),
             code: None,
             level: diag.level.to_str(),
             spans: DiagnosticSpan::from_multispan(&diag.span, args, je),
             children: vec![],
             rendered: None,
+            args: None,
+            fingerprint: None,
+            duplicates: None,
+            expectation: None,
+            emitted_at: None,
+            url: diag.url.clone(),
+            tool: None,
         }
     }
+
+    /// Removes the fields that were added in version 2 of the JSON shape.
+    fn downgrade_to_version_1(&mut self) {
+        self.format_version = None;
+        self.args = None;
+        self.fingerprint = None;
+        self.duplicates = None;
+        self.expectation = None;
+        self.emitted_at = None;
+        self.url = None;
+        self.tool = None;
+        if let Some(code) = &mut self.code {
+            code.groups = None;
+            code.default_level = None;
+        }
+        self.spans.iter_mut().for_each(DiagnosticSpan::downgrade_to_version_1);
+        self.children.iter_mut().for_each(Diagnostic::downgrade_to_version_1);
+    }
+}
+
+/// Identifies an expectation within the crate: by the owner and local id of the node that the
+/// `#[expect]` attribute is on, the index of the attribute and the index of the lint in it.
+fn expectation_id(id: LintExpectationId) -> String {
+    match id {
+        LintExpectationId::Stable { hir_id, attr_index, lint_index, .. } => format!(
+            "{}.{}.{attr_index}.{}",
+            hir_id.owner.def_id.local_def_index.as_u32(),
+            hir_id.local_id.as_u32(),
+            lint_index.unwrap_or(0),
+        ),
+        // Only early lints have unstable ids, which are made stable before they are emitted.
+        LintExpectationId::Unstable { attr_id, lint_index } => {
+            format!("unstable.{}.{}", attr_id.as_u32(), lint_index.unwrap_or(0))
+        }
+    }
+}
+
+fn structured_args(diag: &crate::Diagnostic) -> Option<BTreeMap<String, serde_json::Value>> {
+    fn to_json(arg: &StructuredArg) -> serde_json::Value {
+        match arg {
+            StructuredArg::Str(s) => serde_json::Value::from(s.as_str()),
+            StructuredArg::Number(n) => match i64::try_from(*n) {
+                Ok(n) => serde_json::Value::from(n),
+                Err(_) => serde_json::Value::from(n.to_string()),
+            },
+            StructuredArg::Bool(b) => serde_json::Value::from(*b),
+            StructuredArg::List(l) => l.iter().map(to_json).collect(),
+            StructuredArg::Map(m) => {
+                m.iter().map(|(key, value)| (key.clone(), to_json(value))).collect()
+            }
+        }
+    }
+
+    let args = diag
+        .args()
+        .filter_map(|(name, value)| match value {
+            DiagnosticArgValue::Structured(arg) => Some((name.to_string(), to_json(arg))),
+            _ => None,
+        })
+        .collect::<BTreeMap<_, _>>();
+    if args.is_empty() { None } else { Some(args) }
 }
 
 impl DiagnosticSpan {
@@ -450,6 +897,8 @@ fn from_span_label(
         args: &FluentArgs<'_>,
         je: &JsonEmitter,
     ) -> DiagnosticSpan {
+        let label_args = span_label_args(args, &span.args);
+        let args = label_args.as_ref().unwrap_or(args);
         Self::from_span_etc(
             span.span,
             span.is_primary,
@@ -505,18 +954,36 @@ fn from_span_full(
             })
         });
 
+        let source = je.sources();
+        let start_line = start.line.checked_sub(1).and_then(|i| source.line(&start.file, i));
+        let end_line = end.line.checked_sub(1).and_then(|i| source.line(&end.file, i));
+        let file_name = source.display_name(&start.file);
+        let local_file_name = je
+            .local_paths
+            .then(|| je.sm.local_path_of_remapped(&start.file))
+            .flatten()
+            .map(|path| path.display().to_string())
+            .filter(|local_file_name| *local_file_name != file_name);
         DiagnosticSpan {
-            file_name: je.sm.filename_for_diagnostics(&start.file.name).to_string(),
+            file_name,
+            local_file_name,
             byte_start: start.file.original_relative_byte_pos(span.lo()).0,
             byte_end: start.file.original_relative_byte_pos(span.hi()).0,
             line_start: start.line,
             line_end: end.line,
-            column_start: start.col.0 + 1,
-            column_end: end.col.0 + 1,
+            column_start: je.column_convention.column(start_line.as_deref(), start.col.0 + 1),
+            column_end: je.column_convention.column(end_line.as_deref(), end.col.0 + 1),
+            column_start_utf16: Some(
+                ColumnConvention::UTF16.column(start_line.as_deref(), start.col.0 + 1),
+            ),
+            column_end_utf16: Some(
+                ColumnConvention::UTF16.column(end_line.as_deref(), end.col.0 + 1),
+            ),
             is_primary,
             text: DiagnosticSpanLine::from_span(span, je),
             suggested_replacement: suggestion.map(|x| x.0.clone()),
             suggestion_applicability: suggestion.map(|x| x.1),
+            suggestion_rank: None,
             expansion: backtrace_step,
             label,
         }
@@ -543,18 +1010,130 @@ fn from_suggestion(
             .iter()
             .flat_map(|substitution| {
                 substitution.parts.iter().map(move |suggestion_inner| {
-                    let span_label =
-                        SpanLabel { span: suggestion_inner.span, is_primary: true, label: None };
-                    DiagnosticSpan::from_span_label(
+                    let span_label = SpanLabel {
+                        span: suggestion_inner.span,
+                        is_primary: true,
+                        label: None,
+                        args: Vec::new(),
+                    };
+                    let mut span = DiagnosticSpan::from_span_label(
                         span_label,
-                        Some((&suggestion_inner.snippet, suggestion.applicability)),
+                        Some((
+                            &suggestion_inner.snippet,
+                            suggestion_inner.applicability.unwrap_or(suggestion.applicability),
+                        )),
                         args,
                         je,
-                    )
+                    );
+                    span.suggestion_rank = suggestion.rank;
+                    span
                 })
             })
             .collect()
     }
+
+    /// Files that are not in the `SourceMap` have no `Span`, so the location of the suggestion
+    /// is taken from the suggestion itself.
+    fn from_file_suggestion(suggestion: &FileSuggestion, je: &JsonEmitter) -> DiagnosticSpan {
+        let (lines, _) = suggestion.lines();
+        let line_count = suggestion.line_end - suggestion.line_start + 1;
+        let first_line = lines.split('\n').next();
+        let last_line = lines.split('\n').nth(line_count - 1);
+        let text = lines
+            .split('\n')
+            .take(line_count)
+            .enumerate()
+            .map(|(i, line)| {
+                let start = if i == 0 { suggestion.column_start } else { 1 };
+                let end = if i == line_count - 1 {
+                    suggestion.column_end
+                } else {
+                    line.chars().count() + 1
+                };
+                DiagnosticSpanLine {
+                    text: line.to_string(),
+                    highlight_start: je.column_convention.column(Some(line), start),
+                    highlight_end: je.column_convention.column(Some(line), end),
+                }
+            })
+            .collect();
+        DiagnosticSpan {
+            file_name: suggestion.path.display().to_string(),
+            local_file_name: None,
+            byte_start: suggestion.byte_start as u32,
+            byte_end: suggestion.byte_end as u32,
+            line_start: suggestion.line_start,
+            line_end: suggestion.line_end,
+            column_start: je.column_convention.column(first_line, suggestion.column_start),
+            column_end: je.column_convention.column(last_line, suggestion.column_end),
+            column_start_utf16: Some(
+                ColumnConvention::UTF16.column(first_line, suggestion.column_start),
+            ),
+            column_end_utf16: Some(
+                ColumnConvention::UTF16.column(last_line, suggestion.column_end),
+            ),
+            is_primary: true,
+            text,
+            label: None,
+            suggested_replacement: Some(suggestion.snippet.clone()),
+            suggestion_applicability: Some(suggestion.applicability),
+            suggestion_rank: None,
+            expansion: None,
+        }
+    }
+
+    /// Removes the fields that were added in version 2 of the JSON shape, see
+    /// `Diagnostic::downgrade_to_version_1`.
+    fn downgrade_to_version_1(&mut self) {
+        self.local_file_name = None;
+        self.column_start_utf16 = None;
+        self.column_end_utf16 = None;
+        self.suggestion_rank = None;
+        if let Some(expansion) = &mut self.expansion {
+            expansion.span.downgrade_to_version_1();
+            expansion.def_site_span.downgrade_to_version_1();
+        }
+    }
+}
+
+/// How the columns of spans are counted in JSON output, see `-Zjson-column-convention`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ColumnConvention {
+    pub unit: ColumnUnit,
+    /// Whether the first column of a line is 0 rather than 1.
+    pub zero_based: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColumnUnit {
+    /// Unicode scalar values, i.e. Rust `char`s.
+    #[default]
+    Char,
+    /// Bytes of the UTF-8 encoding of the line.
+    Utf8,
+    /// Code units of the UTF-16 encoding of the line, as counted by editors using the Language
+    /// Server Protocol.
+    Utf16,
+}
+
+impl ColumnConvention {
+    const UTF16: ColumnConvention = ColumnConvention { unit: ColumnUnit::Utf16, zero_based: false };
+
+    /// Converts a 1-based character column on `line` to this convention. If the text of the line
+    /// isn't available, like for files whose source isn't loaded, the column is assumed to only
+    /// be preceded by characters that are a single unit.
+    pub fn column(self, line: Option<&str>, column: usize) -> usize {
+        let offset = match (self.unit, line) {
+            (ColumnUnit::Char, _) | (_, None) => column - 1,
+            (ColumnUnit::Utf8, Some(line)) => {
+                line.chars().take(column - 1).map(char::len_utf8).sum()
+            }
+            (ColumnUnit::Utf16, Some(line)) => {
+                line.chars().take(column - 1).map(char::len_utf16).sum()
+            }
+        };
+        if self.zero_based { offset } else { offset + 1 }
+    }
 }
 
 impl DiagnosticSpanLine {
@@ -563,11 +1142,13 @@ fn line_from_source_file(
         index: usize,
         h_start: usize,
         h_end: usize,
+        je: &JsonEmitter,
     ) -> DiagnosticSpanLine {
+        let line = je.sources().line(sf, index);
         DiagnosticSpanLine {
-            text: sf.get_line(index).map_or_else(String::new, |l| l.into_owned()),
-            highlight_start: h_start,
-            highlight_end: h_end,
+            highlight_start: je.column_convention.column(line.as_deref(), h_start),
+            highlight_end: je.column_convention.column(line.as_deref(), h_end),
+            text: line.map_or_else(String::new, |l| l.into_owned()),
         }
     }
 
@@ -581,7 +1162,7 @@ fn from_span(span: Span, je: &JsonEmitter) -> Vec<DiagnosticSpanLine> {
                 // We can't get any lines if the source is unavailable.
                 if !should_show_source_code(
                     &je.ignored_directories_in_source_blocks,
-                    &je.sm,
+                    je.sources(),
                     &lines.file,
                 ) {
                     return vec![];
@@ -597,6 +1178,7 @@ fn from_span(span: Span, je: &JsonEmitter) -> Vec<DiagnosticSpanLine> {
                             line.line_index,
                             line.start_col.0 + 1,
                             line.end_col.0 + 1,
+                            je,
                         )
                     })
                     .collect()
@@ -2,8 +2,9 @@
 
 use crate::emitter::ColorConfig;
 use crate::DiagCtxt;
-use rustc_span::BytePos;
+use rustc_span::{BytePos, SourceFile};
 
+use std::borrow::Cow;
 use std::str;
 
 use serde::Deserialize;
@@ -37,8 +38,23 @@ fn flush(&mut self) -> io::Result<()> {
     }
 }
 
-/// Test the span yields correct positions in JSON.
-fn test_positions(code: &str, span: (u32, u32), expected_output: SpanTestData) {
+/// Emits diagnostics for `code`, in a file named `test.rs`, and returns the JSON output.
+fn emit_json(
+    code: &str,
+    track_diagnostics: bool,
+    column_convention: ColumnConvention,
+    emit: impl FnOnce(&DiagCtxt),
+) -> String {
+    emit_json_with(code, track_diagnostics, |je| je.column_convention(column_convention), emit)
+}
+
+/// Like `emit_json`, with the emitter set up further by `configure`.
+fn emit_json_with(
+    code: &str,
+    track_diagnostics: bool,
+    configure: impl FnOnce(JsonEmitter) -> JsonEmitter,
+    emit: impl FnOnce(&DiagCtxt),
+) -> String {
     rustc_span::create_default_session_globals_then(|| {
         let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
         sm.new_source_file(Path::new("test.rs").to_owned().into(), code.to_owned());
@@ -55,25 +71,185 @@ fn test_positions(code: &str, span: (u32, u32), expected_output: SpanTestData) {
             true,
             HumanReadableErrorType::Short(ColorConfig::Never),
             None,
-            false,
-            false,
+            MacroBacktrace::No,
+            track_diagnostics,
             TerminalUrl::No,
         );
 
-        let span = Span::with_root_ctxt(BytePos(span.0), BytePos(span.1));
-        let dcx = DiagCtxt::with_emitter(Box::new(je));
-        dcx.span_err(span, "foo");
+        let dcx = DiagCtxt::with_emitter(Box::new(configure(je)));
+        emit(&dcx);
+        drop(dcx);
 
         let bytes = output.lock().unwrap();
-        let actual_output = str::from_utf8(&bytes).unwrap();
-        let actual_output: TestData = serde_json::from_str(actual_output).unwrap();
-        let spans = actual_output.spans;
-        assert_eq!(spans.len(), 1);
-
-        assert_eq!(expected_output, spans[0])
+        str::from_utf8(&bytes).unwrap().to_owned()
     })
 }
 
+/// Test the span yields correct positions in JSON.
+fn test_positions(code: &str, span: (u32, u32), expected_output: SpanTestData) {
+    let output = emit_json(code, false, ColumnConvention::default(), |dcx| {
+        dcx.span_err(Span::with_root_ctxt(BytePos(span.0), BytePos(span.1)), "foo");
+    });
+    let actual_output: TestData = serde_json::from_str(&output).unwrap();
+    let spans = actual_output.spans;
+    assert_eq!(spans.len(), 1);
+
+    assert_eq!(expected_output, spans[0])
+}
+
+#[test]
+fn utf16_columns() {
+    // The emoji is a single character, but two UTF-16 code units and four bytes.
+    let output = emit_json("'\u{1f600}' foo", false, ColumnConvention::default(), |dcx| {
+        dcx.span_err(Span::with_root_ctxt(BytePos(7), BytePos(10)), "foo");
+    });
+    let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let span = &output["spans"][0];
+    assert_eq!(span["byte_start"], 7);
+    assert_eq!(span["column_start"], 5);
+    assert_eq!(span["column_end"], 8);
+    assert_eq!(span["column_start_utf16"], 6);
+    assert_eq!(span["column_end_utf16"], 9);
+}
+
+#[test]
+fn column_conventions() {
+    let columns = |unit, zero_based| {
+        let output =
+            emit_json("'\u{1f600}' foo", false, ColumnConvention { unit, zero_based }, |dcx| {
+                dcx.span_err(Span::with_root_ctxt(BytePos(7), BytePos(10)), "foo")
+            });
+        let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+        let span = &output["spans"][0];
+        let text = &span["text"][0];
+        assert_eq!(span["column_start"], text["highlight_start"]);
+        assert_eq!(span["column_end"], text["highlight_end"]);
+        (span["column_start"].as_u64().unwrap(), span["column_end"].as_u64().unwrap())
+    };
+    assert_eq!(columns(ColumnUnit::Char, false), (5, 8));
+    assert_eq!(columns(ColumnUnit::Char, true), (4, 7));
+    assert_eq!(columns(ColumnUnit::Utf8, false), (8, 11));
+    assert_eq!(columns(ColumnUnit::Utf8, true), (7, 10));
+    assert_eq!(columns(ColumnUnit::Utf16, false), (6, 9));
+    assert_eq!(columns(ColumnUnit::Utf16, true), (5, 8));
+}
+
+#[test]
+fn emitted_at() {
+    let mut line = 0;
+    let output = emit_json("foo", true, ColumnConvention::default(), |dcx| {
+        line = line!() + 1;
+        dcx.span_err(Span::with_root_ctxt(BytePos(0), BytePos(3)), "foo");
+    });
+    let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let emitted_at = &output["emitted_at"];
+    assert!(emitted_at["file"].as_str().unwrap().ends_with("tests.rs"));
+    assert_eq!(emitted_at["line"], line);
+    assert!(emitted_at["col"].is_u64());
+
+    let output = emit_json("foo", false, ColumnConvention::default(), |dcx| {
+        dcx.span_err(Span::with_root_ctxt(BytePos(0), BytePos(3)), "foo");
+    });
+    let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+    assert!(output.get("emitted_at").is_none());
+}
+
+#[test]
+fn help_url() {
+    let output = emit_json("foo", false, ColumnConvention::default(), |dcx| {
+        dcx.struct_span_err(Span::with_root_ctxt(BytePos(0), BytePos(3)), "foo")
+            .with_help("bar")
+            .with_help_with_url("see the reference", "https://doc.rust-lang.org/reference/")
+            .emit();
+    });
+    let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let children = output["children"].as_array().unwrap();
+    assert!(children[0].get("url").is_none());
+    assert_eq!(children[1]["message"], "see the reference");
+    assert_eq!(children[1]["url"], "https://doc.rust-lang.org/reference/");
+}
+
+#[test]
+fn format_version() {
+    let emit = |format_version| {
+        let output = emit_json_with(
+            "foo",
+            true,
+            |je| je.format_version(format_version),
+            |dcx| {
+                dcx.struct_span_err(Span::with_root_ctxt(BytePos(0), BytePos(3)), "foo")
+                    .with_help_with_url("see the reference", "https://doc.rust-lang.org/reference/")
+                    .emit();
+            },
+        );
+        serde_json::from_str::<serde_json::Value>(&output).unwrap()
+    };
+
+    let output = emit(JSON_FORMAT_VERSION);
+    assert_eq!(output["format_version"], JSON_FORMAT_VERSION);
+    assert!(output.get("fingerprint").is_some());
+    assert!(output.get("emitted_at").is_some());
+    assert!(output["spans"][0].get("column_start_utf16").is_some());
+    assert!(output["children"][0].get("format_version").is_none());
+
+    let output = emit(1);
+    let mut fields = output.as_object().unwrap().keys().map(String::as_str).collect::<Vec<_>>();
+    fields.sort();
+    assert_eq!(
+        fields,
+        ["$message_type", "children", "code", "level", "message", "rendered", "spans"]
+    );
+    assert!(output["spans"][0].get("column_start_utf16").is_none());
+    assert!(output["children"][0].get("url").is_none());
+}
+
+#[test]
+fn array() {
+    let array_output = Arc::new(Mutex::new(Vec::new()));
+    let array = JsonArray::new(Box::new(Shared { data: array_output.clone() }));
+    let output = emit_json_with(
+        "foo",
+        false,
+        |je| je.array(Some(array.clone())),
+        |dcx| {
+            dcx.span_err(Span::with_root_ctxt(BytePos(0), BytePos(3)), "foo");
+            dcx.span_warn(Span::with_root_ctxt(BytePos(0), BytePos(3)), "bar");
+            assert!(array_output.lock().unwrap().is_empty());
+            dcx.finish();
+        },
+    );
+    assert_eq!(output, "");
+    let output = String::from_utf8(array_output.lock().unwrap().clone()).unwrap();
+    let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let messages = output.as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0]["message"], "foo");
+    assert_eq!(messages[1]["message"], "bar");
+}
+
+#[test]
+fn tool_display_prefix() {
+    let output = emit_json("foo", false, ColumnConvention::default(), |dcx| {
+        dcx.set_tool_display_prefix("clippy", "clippy");
+        dcx.struct_span_warn(Span::with_root_ctxt(BytePos(0), BytePos(3)), "boxed collection")
+            .with_is_lint("clippy::box_collection".to_string(), rustc_lint_defs::Level::Warn, false)
+            .emit();
+        dcx.struct_span_warn(Span::with_root_ctxt(BytePos(0), BytePos(3)), "unused variable")
+            .with_is_lint("unused_variables".to_string(), rustc_lint_defs::Level::Warn, false)
+            .emit();
+    });
+    let mut messages = serde_json::Deserializer::from_str(&output).into_iter::<serde_json::Value>();
+    let clippy = messages.next().unwrap().unwrap();
+    assert_eq!(clippy["tool"], "clippy");
+    assert_eq!(clippy["level"], "warning");
+    let rendered = clippy["rendered"].as_str().unwrap();
+    assert!(rendered.contains("clippy-warning: boxed collection"), "{rendered}");
+    let rustc = messages.next().unwrap().unwrap();
+    assert!(rustc.get("tool").is_none());
+    let rendered = rustc["rendered"].as_str().unwrap();
+    assert!(rendered.contains(": warning: unused variable"), "{rendered}");
+}
+
 #[test]
 fn empty() {
     test_positions(
@@ -201,3 +377,140 @@ fn span_after_crlf() {
         },
     )
 }
+
+/// The code of a REPL, which the source map only has placeholders for.
+struct ReplSource;
+
+impl SourceProvider for ReplSource {
+    fn has_source(&self, _file: &SourceFile) -> bool {
+        true
+    }
+
+    fn line<'a>(&'a self, _file: &'a SourceFile, line_index: usize) -> Option<Cow<'a, str>> {
+        (line_index == 0).then_some(Cow::Borrowed("let x = y;"))
+    }
+
+    fn snippet(&self, _span: Span) -> Option<String> {
+        Some("y".to_string())
+    }
+
+    fn display_name(&self, _file: &SourceFile) -> String {
+        "<repl>".to_string()
+    }
+}
+
+#[test]
+fn source_provider() {
+    let output = rustc_span::create_default_session_globals_then(|| {
+        let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        sm.new_source_file(Path::new("repl.rs").to_owned().into(), " ".repeat(10));
+        let fallback_bundle =
+            crate::fallback_fluent_bundle(vec![crate::DEFAULT_LOCALE_RESOURCE], false);
+
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let je = JsonEmitter::new(
+            Box::new(Shared { data: output.clone() }),
+            None,
+            sm,
+            None,
+            fallback_bundle,
+            false,
+            HumanReadableErrorType::Default(ColorConfig::Never),
+            None,
+            MacroBacktrace::No,
+            false,
+            TerminalUrl::No,
+        )
+        .source_provider(Some(Lrc::new(ReplSource)));
+
+        let dcx = DiagCtxt::with_emitter(Box::new(je));
+        dcx.span_err(Span::with_root_ctxt(BytePos(8), BytePos(9)), "cannot find value `y`");
+
+        let bytes = output.lock().unwrap();
+        str::from_utf8(&bytes).unwrap().to_owned()
+    });
+    let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let span = &output["spans"][0];
+    assert_eq!(span["file_name"], "<repl>");
+    assert_eq!(span["text"][0]["text"], "let x = y;");
+    let rendered = output["rendered"].as_str().unwrap();
+    assert!(rendered.contains("--> <repl>:1:9"), "{rendered}");
+    assert!(rendered.contains("1 | let x = y;"), "{rendered}");
+}
+
+#[test]
+fn part_applicability() {
+    let output = emit_json("let x = foo(a, b);", false, ColumnConvention::default(), |dcx| {
+        let span = |lo, hi| Span::with_root_ctxt(BytePos(lo), BytePos(hi));
+        dcx.struct_span_err(span(8, 11), "foo")
+            .with_multipart_suggestion_with_part_applicability(
+                "use `bar`",
+                vec![
+                    (span(8, 11), "bar".to_owned(), Applicability::MachineApplicable),
+                    (span(15, 16), "/* value */".to_owned(), Applicability::HasPlaceholders),
+                ],
+            )
+            .emit();
+    });
+    let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let spans = output["children"][0]["spans"].as_array().unwrap();
+    let applicabilities = spans
+        .iter()
+        .map(|span| {
+            let replacement = span["suggested_replacement"].as_str().unwrap();
+            (replacement, span["suggestion_applicability"].as_str().unwrap())
+        })
+        .collect::<Vec<_>>();
+    // Each part keeps its own applicability, so that the first one can still be applied.
+    assert_eq!(applicabilities, [("bar", "MachineApplicable"), ("/* value */", "HasPlaceholders")]);
+}
+
+#[test]
+fn fingerprint() {
+    let fingerprint = |code: &str, lo: u32| {
+        let output = emit_json(code, false, ColumnConvention::default(), |dcx| {
+            dcx.span_err(Span::with_root_ctxt(BytePos(lo), BytePos(lo + 1)), "unused variable");
+        });
+        let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+        output["fingerprint"].as_str().unwrap().to_owned()
+    };
+    let code = "fn main() {\n    let x = 1;\n}\n";
+    let original = fingerprint(code, 20);
+    assert_eq!(original.len(), 32);
+    // Unrelated changes that move the code to another line keep the fingerprint.
+    assert_eq!(fingerprint(&format!("// comment\n{code}"), 31), original);
+    // Changes to the line that the diagnostic points at don't.
+    assert_ne!(fingerprint("fn main() {\n    let x = 2;\n}\n", 20), original);
+}
+
+#[test]
+fn unfulfilled_expectations() {
+    let id = LintExpectationId::Stable {
+        hir_id: rustc_hir::CRATE_HIR_ID,
+        attr_index: 1,
+        lint_index: Some(2),
+        attr_id: None,
+    };
+    let span = Span::with_root_ctxt(BytePos(9), BytePos(18));
+    let output = emit_json("#[expect(dead_code)]", false, ColumnConvention::default(), |dcx| {
+        let mut diag = dcx.struct_span_warn(span, "this lint expectation is unfulfilled");
+        diag.unfulfilled_expectation(id);
+        diag.emit();
+        dcx.emit_unfulfilled_expectations(&[UnfulfilledExpectation { id, span, reason: None }]);
+    });
+    let output = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect::<Vec<serde_json::Value>>();
+    assert_eq!(output.len(), 2);
+
+    let expectation = &output[0]["expectation"];
+    assert_eq!(expectation["id"], "0.0.1.2");
+    assert_eq!(expectation["fulfilled"], false);
+    assert_eq!(expectation["span"]["byte_start"], 9);
+
+    let unfulfilled = &output[1]["unfulfilled_expectations"][0];
+    assert_eq!(unfulfilled["id"], "0.0.1.2");
+    assert_eq!(unfulfilled["span"]["byte_end"], 18);
+    assert!(unfulfilled["reason"].is_null());
+}
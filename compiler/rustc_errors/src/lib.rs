@@ -5,7 +5,6 @@
 #![doc(html_root_url = "https://doc.rust-lang.org/nightly/nightly-rustc/")]
 #![doc(rust_logo)]
 #![feature(rustdoc_internals)]
-#![feature(array_windows)]
 #![feature(associated_type_defaults)]
 #![feature(box_into_inner)]
 #![feature(box_patterns)]
@@ -29,40 +28,51 @@
 extern crate self as rustc_errors;
 
 pub use diagnostic::{
-    AddToDiagnostic, DecorateLint, Diagnostic, DiagnosticArg, DiagnosticArgValue,
-    DiagnosticStyledString, IntoDiagnosticArg, SubDiagnostic,
+    AddToDiagnostic, AddToDiagnosticShared, AsDiagnosticArg, DecorateLint, Diagnostic,
+    DiagnosticArg, DiagnosticArgName, DiagnosticArgValue, DiagnosticExtras, DiagnosticFloat,
+    DiagnosticLocation, DiagnosticStyledString, DiagnosticTool, FutureIncompat, IntoDiagnosticArg,
+    LazyDiagnosticArg, StructuredArg, SubDiagnostic, SubdiagnosticPriority,
 };
 pub use diagnostic_builder::{
-    BugAbort, DiagnosticBuilder, EmissionGuarantee, FatalAbort, IntoDiagnostic,
+    BugAbort, DiagnosticBatch, DiagnosticBuilder, EmissionGuarantee, FatalAbort, IntoDiagnostic,
 };
 pub use diagnostic_impls::{
-    DiagnosticArgFromDisplay, DiagnosticSymbolList, ExpectedLifetimeParameter,
-    IndicateAnonymousLifetime, InvalidFlushedDelayedDiagnosticLevel, SingleLabelManySpans,
+    suggest_similar, ConfusableCharNote, DiagnosticArgFromDisplay, DiagnosticArgList,
+    DiagnosticArgSnippet, DiagnosticSymbolList, ExpectedLifetimeParameter,
+    IndicateAnonymousLifetime, InvalidFlushedDelayedDiagnosticLevel, ManyLabelsManySpans,
+    SimilarName, SingleLabelManySpans,
 };
 pub use emitter::ColorConfig;
+pub use filter::{ChangedLines, PathGlob};
 pub use rustc_error_messages::{
-    fallback_fluent_bundle, fluent_bundle, DelayDm, DiagnosticMessage, FluentBundle,
-    LanguageIdentifier, LazyFallbackBundle, MultiSpan, SpanLabel, SubdiagnosticMessage,
+    fallback_fluent_bundle, fallback_fluent_bundle_with_options, fluent_bundle, localize_numbers,
+    pseudo_localize_bundle, DelayDm, DiagnosticMessage, FluentBundle, LanguageIdentifier,
+    LazyFallbackBundle, MultiSpan, SpanLabel, SpanLabelArgs, SubdiagnosticMessage,
 };
 pub use rustc_lint_defs::{pluralize, Applicability};
 pub use rustc_span::fatal_error::{FatalError, FatalErrorMarker};
 pub use rustc_span::ErrorGuaranteed;
 pub use snippet::Style;
+pub use source_provider::SourceProvider;
 
 // Used by external projects such as `rust-gpu`.
 // See https://github.com/rust-lang/rust/pull/115393.
 pub use termcolor::{Color, ColorSpec, WriteColor};
 
 use crate::diagnostic_impls::{DelayedAtWithNewline, DelayedAtWithoutNewline};
+use crate::interner::DiagnosticInterner;
 use emitter::{is_case_difference, DynEmitter, Emitter, HumanEmitter};
 use registry::Registry;
-use rustc_data_structures::fx::{FxHashSet, FxIndexMap, FxIndexSet};
+use rustc_data_structures::fx::{FxHashMap, FxHashSet, FxIndexMap, FxIndexSet};
+use rustc_data_structures::profiling::SelfProfilerRef;
 use rustc_data_structures::stable_hasher::{Hash128, StableHasher};
-use rustc_data_structures::sync::{Lock, Lrc};
+use rustc_data_structures::sharded::Sharded;
+use rustc_data_structures::sync::{is_known_dyn_thread_safe, DynSend, Lock, Lrc, OnceLock};
 use rustc_data_structures::AtomicRef;
 use rustc_lint_defs::LintExpectationId;
+use rustc_serialize::opaque::FileEncodeResult;
 use rustc_span::source_map::SourceMap;
-use rustc_span::{Loc, Span, DUMMY_SP};
+use rustc_span::{BytePos, Loc, Span, Symbol, DUMMY_SP};
 use std::backtrace::{Backtrace, BacktraceStatus};
 use std::borrow::Cow;
 use std::error::Report;
@@ -72,6 +82,7 @@
 use std::num::NonZeroUsize;
 use std::panic;
 use std::path::{Path, PathBuf};
+use std::thread::{self, ThreadId};
 
 use Level::*;
 
@@ -81,19 +92,39 @@
 mod diagnostic_impls;
 pub mod emitter;
 pub mod error;
+mod filter;
+pub mod fingerprint;
+mod interner;
 pub mod json;
 mod lock;
 pub mod markdown;
+pub mod persist;
 pub mod registry;
-mod snippet;
+pub mod snippet;
+mod source_provider;
 mod styled_buffer;
+pub mod testing;
 #[cfg(test)]
 mod tests;
 pub mod translation;
+mod verify;
 
 pub type PErr<'a> = DiagnosticBuilder<'a>;
 pub type PResult<'a, T> = Result<T, PErr<'a>>;
 
+/// Decides whether to hide the warnings pointing into a file, see `DiagCtxt::add_span_filter`.
+type SpanFilter = dyn Fn(&Path) -> bool + DynSend;
+
+/// Changes the diagnostics of the future breakage report before it is emitted, see
+/// `DiagCtxt::add_future_breakage_hook`.
+type FutureBreakageHook = dyn FnMut(&mut Vec<Diagnostic>) + DynSend;
+
+/// Sees each diagnostic before it is emitted, see `DiagCtxt::add_observer`.
+type DiagnosticObserver = dyn Fn(&Diagnostic) + DynSend;
+
+/// Describes the active queries, see `DiagCtxt::set_query_stack_hook`.
+type QueryStackHook = dyn Fn() -> Vec<String> + DynSend;
+
 rustc_fluent_macro::fluent_messages! { "../messages.ftl" }
 
 // `PResult` is used a lot. Make sure it doesn't unintentionally get bigger.
@@ -117,6 +148,8 @@ pub enum SuggestionStyle {
     ShowCode,
     /// Always show the suggested code independently.
     ShowAlways,
+    /// Always show the suggested code independently, as a unified diff of the lines it changes.
+    Diff,
 }
 
 impl SuggestionStyle {
@@ -158,6 +191,11 @@ pub struct CodeSuggestion {
     /// which are useful for users but not useful for
     /// tools like rustfix
     pub applicability: Applicability,
+    /// How this suggestion ranks among the other suggestions of the diagnostic, lower is better.
+    /// This lets tools preselect the most likely fix.
+    pub rank: Option<u32>,
+    /// Where this suggestion is rendered, see `SubdiagnosticPriority`.
+    pub priority: SubdiagnosticPriority,
 }
 
 #[derive(Clone, Debug, PartialEq, Hash, Encodable, Decodable)]
@@ -170,14 +208,19 @@ pub struct Substitution {
 pub struct SubstitutionPart {
     pub span: Span,
     pub snippet: String,
+    /// The applicability of this part on its own, if it can differ from the applicability of the
+    /// whole suggestion. This lets tools apply the machine applicable parts of a suggestion even
+    /// if some other part has placeholders.
+    pub applicability: Option<Applicability>,
 }
 
 /// Used to translate between `Span`s and byte positions within a single output line in highlighted
 /// code of structured suggestions.
 #[derive(Debug, Clone, Copy)]
-pub(crate) struct SubstitutionHighlight {
-    start: usize,
-    end: usize,
+pub struct SubstitutionHighlight {
+    /// The byte range of the replaced code within the line.
+    pub start: usize,
+    pub end: usize,
 }
 
 impl SubstitutionPart {
@@ -199,13 +242,91 @@ fn replaces_meaningful_content(&self, sm: &SourceMap) -> bool {
     }
 }
 
+/// A suggested edit to a file that is not part of the `SourceMap`, such as a `Cargo.toml` that
+/// needs a feature or edition change. Since there is no `Span` to refer to, the location of the
+/// edit is recorded directly, computed from the file's contents when the suggestion is made.
+#[derive(Clone, Debug, PartialEq, Hash, Encodable, Decodable)]
+pub struct FileSuggestion {
+    pub msg: DiagnosticMessage,
+    pub path: PathBuf,
+    /// Byte offsets of the replaced text in the file.
+    pub byte_start: usize,
+    pub byte_end: usize,
+    /// 1-based.
+    pub line_start: usize,
+    pub line_end: usize,
+    /// 1-based, character offset.
+    pub column_start: usize,
+    pub column_end: usize,
+    /// The text currently at `byte_start..byte_end`.
+    pub original: String,
+    /// The text that replaces `original`.
+    pub snippet: String,
+    /// The rest of the first and last lines of the edit, before and after `original`.
+    pub prefix: String,
+    pub suffix: String,
+    pub applicability: Applicability,
+}
+
+impl FileSuggestion {
+    /// The lines of the file touched by this suggestion, before and after the edit.
+    pub(crate) fn lines(&self) -> (String, String) {
+        (
+            format!("{}{}{}", self.prefix, self.original, self.suffix),
+            format!("{}{}{}", self.prefix, self.snippet, self.suffix),
+        )
+    }
+}
+
+impl Substitution {
+    /// Sorts the parts and drops the ones that overlap an earlier part, so that the substitution
+    /// can be applied. Parts that are identical to an earlier part are merged into it, the spans
+    /// of the other conflicting parts are returned.
+    pub(crate) fn remove_overlapping_parts(&mut self) -> Vec<Span> {
+        self.parts.sort_by_key(|part| part.span);
+        let mut conflicts = vec![];
+        let mut parts: Vec<SubstitutionPart> = Vec::with_capacity(self.parts.len());
+        for part in self.parts.drain(..) {
+            if let Some(prev) = parts.last()
+                && prev.span.overlaps(part.span)
+            {
+                if prev.span != part.span || prev.snippet != part.snippet {
+                    conflicts.push(prev.span);
+                    conflicts.push(part.span);
+                }
+                continue;
+            }
+            parts.push(part);
+        }
+        self.parts = parts;
+        conflicts
+    }
+
+    /// Splits this substitution into one substitution per file that its parts are in, in the
+    /// order in which the files first appear.
+    pub fn split_by_file(&self, sm: &SourceMap) -> Vec<Substitution> {
+        let mut files: FxIndexMap<BytePos, Vec<SubstitutionPart>> = FxIndexMap::default();
+        for part in &self.parts {
+            let file = sm.lookup_source_file(part.span.lo());
+            files.entry(file.start_pos).or_default().push(part.clone());
+        }
+        files.into_values().map(|parts| Substitution { parts }).collect()
+    }
+}
+
 impl CodeSuggestion {
     /// Returns the assembled code suggestions, whether they should be shown with an underline
     /// and whether the substitution only differs in capitalization.
-    pub(crate) fn splice_lines(
+    ///
+    /// There is one entry per substitution, with one assembled snippet per file that the
+    /// substitution touches. Emitters outside of this crate can use this to show suggestions the
+    /// same way the human-readable emitter does. The code around the substitutions is taken from
+    /// `source`, while `sm` maps their spans to files and lines.
+    pub fn splice_lines(
         &self,
         sm: &SourceMap,
-    ) -> Vec<(String, Vec<SubstitutionPart>, Vec<Vec<SubstitutionHighlight>>, bool)> {
+        source: &dyn SourceProvider,
+    ) -> Vec<Vec<(String, Vec<SubstitutionPart>, Vec<Vec<SubstitutionHighlight>>, bool)>> {
         // For the `Vec<Vec<SubstitutionHighlight>>` value, the first level of the vector
         // corresponds to the output snippet's lines, while the second level corresponds to the
         // substrings within that line that should be highlighted.
@@ -258,142 +379,147 @@ fn push_trailing(
             line_count
         }
 
-        assert!(!self.substitutions.is_empty());
-
-        self.substitutions
-            .iter()
-            .filter(|subst| {
-                // Suggestions coming from macros can have malformed spans. This is a heavy
-                // handed approach to avoid ICEs by ignoring the suggestion outright.
-                let invalid = subst.parts.iter().any(|item| sm.is_valid_span(item.span).is_err());
-                if invalid {
-                    debug!("splice_lines: suggestion contains an invalid span: {:?}", subst);
-                }
-                !invalid
-            })
-            .cloned()
-            .filter_map(|mut substitution| {
-                // Assumption: all spans are in the same file, and all spans
-                // are disjoint. Sort in ascending order.
-                substitution.parts.sort_by_key(|part| part.span.lo());
-
-                // Find the bounding span.
-                let lo = substitution.parts.iter().map(|part| part.span.lo()).min()?;
-                let hi = substitution.parts.iter().map(|part| part.span.hi()).max()?;
-                let bounding_span = Span::with_root_ctxt(lo, hi);
-                // The different spans might belong to different contexts, if so ignore suggestion.
-                let lines = sm.span_to_lines(bounding_span).ok()?;
-                assert!(!lines.lines.is_empty() || bounding_span.is_dummy());
-
-                // We can't splice anything if the source is unavailable.
-                if !sm.ensure_source_file_source_present(&lines.file) {
-                    return None;
-                }
+        let splice_substitution = |mut substitution: Substitution| {
+            // All spans are in the same file at this point, and are assumed
+            // to be disjoint. Sort in ascending order.
+            substitution.parts.sort_by_key(|part| part.span.lo());
+
+            // Find the bounding span.
+            let lo = substitution.parts.iter().map(|part| part.span.lo()).min()?;
+            let hi = substitution.parts.iter().map(|part| part.span.hi()).max()?;
+            let bounding_span = Span::with_root_ctxt(lo, hi);
+            // The different spans might belong to different contexts, if so ignore suggestion.
+            let lines = sm.span_to_lines(bounding_span).ok()?;
+            assert!(!lines.lines.is_empty() || bounding_span.is_dummy());
+
+            // We can't splice anything if the source is unavailable.
+            if !source.has_source(&lines.file) {
+                return None;
+            }
 
-                let mut highlights = vec![];
-                // To build up the result, we do this for each span:
-                // - push the line segment trailing the previous span
-                //   (at the beginning a "phantom" span pointing at the start of the line)
-                // - push lines between the previous and current span (if any)
-                // - if the previous and current span are not on the same line
-                //   push the line segment leading up to the current span
-                // - splice in the span substitution
-                //
-                // Finally push the trailing line segment of the last span
-                let sf = &lines.file;
-                let mut prev_hi = sm.lookup_char_pos(bounding_span.lo());
-                prev_hi.col = CharPos::from_usize(0);
-                let mut prev_line =
-                    lines.lines.get(0).and_then(|line0| sf.get_line(line0.line_index));
-                let mut buf = String::new();
-
-                let mut line_highlight = vec![];
-                // We need to keep track of the difference between the existing code and the added
-                // or deleted code in order to point at the correct column *after* substitution.
-                let mut acc = 0;
-                for part in &substitution.parts {
-                    let cur_lo = sm.lookup_char_pos(part.span.lo());
-                    if prev_hi.line == cur_lo.line {
-                        let mut count =
-                            push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, Some(&cur_lo));
-                        while count > 0 {
-                            highlights.push(std::mem::take(&mut line_highlight));
-                            acc = 0;
-                            count -= 1;
-                        }
-                    } else {
+            let mut highlights = vec![];
+            // To build up the result, we do this for each span:
+            // - push the line segment trailing the previous span
+            //   (at the beginning a "phantom" span pointing at the start of the line)
+            // - push lines between the previous and current span (if any)
+            // - if the previous and current span are not on the same line
+            //   push the line segment leading up to the current span
+            // - splice in the span substitution
+            //
+            // Finally push the trailing line segment of the last span
+            let sf = &lines.file;
+            let mut prev_hi = sm.lookup_char_pos(bounding_span.lo());
+            prev_hi.col = CharPos::from_usize(0);
+            let mut prev_line =
+                lines.lines.get(0).and_then(|line0| source.line(sf, line0.line_index));
+            let mut buf = String::new();
+
+            let mut line_highlight = vec![];
+            // We need to keep track of the difference between the existing code and the added
+            // or deleted code in order to point at the correct column *after* substitution.
+            let mut acc = 0;
+            for part in &substitution.parts {
+                let cur_lo = sm.lookup_char_pos(part.span.lo());
+                if prev_hi.line == cur_lo.line {
+                    let mut count =
+                        push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, Some(&cur_lo));
+                    while count > 0 {
+                        highlights.push(std::mem::take(&mut line_highlight));
                         acc = 0;
+                        count -= 1;
+                    }
+                } else {
+                    acc = 0;
+                    highlights.push(std::mem::take(&mut line_highlight));
+                    let mut count = push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, None);
+                    while count > 0 {
                         highlights.push(std::mem::take(&mut line_highlight));
-                        let mut count = push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, None);
-                        while count > 0 {
+                        count -= 1;
+                    }
+                    // push lines between the previous and current span (if any)
+                    for idx in prev_hi.line..(cur_lo.line - 1) {
+                        if let Some(line) = source.line(sf, idx) {
+                            buf.push_str(line.as_ref());
+                            buf.push('\n');
                             highlights.push(std::mem::take(&mut line_highlight));
-                            count -= 1;
-                        }
-                        // push lines between the previous and current span (if any)
-                        for idx in prev_hi.line..(cur_lo.line - 1) {
-                            if let Some(line) = sf.get_line(idx) {
-                                buf.push_str(line.as_ref());
-                                buf.push('\n');
-                                highlights.push(std::mem::take(&mut line_highlight));
-                            }
-                        }
-                        if let Some(cur_line) = sf.get_line(cur_lo.line - 1) {
-                            let end = match cur_line.char_indices().nth(cur_lo.col.to_usize()) {
-                                Some((i, _)) => i,
-                                None => cur_line.len(),
-                            };
-                            buf.push_str(&cur_line[..end]);
                         }
                     }
-                    // Add a whole line highlight per line in the snippet.
-                    let len: isize = part
-                        .snippet
-                        .split('\n')
-                        .next()
-                        .unwrap_or(&part.snippet)
+                    if let Some(cur_line) = source.line(sf, cur_lo.line - 1) {
+                        let end = match cur_line.char_indices().nth(cur_lo.col.to_usize()) {
+                            Some((i, _)) => i,
+                            None => cur_line.len(),
+                        };
+                        buf.push_str(&cur_line[..end]);
+                    }
+                }
+                // Add a whole line highlight per line in the snippet.
+                let len: isize = part
+                    .snippet
+                    .split('\n')
+                    .next()
+                    .unwrap_or(&part.snippet)
+                    .chars()
+                    .map(|c| match c {
+                        '\t' => 4,
+                        _ => 1,
+                    })
+                    .sum();
+                line_highlight.push(SubstitutionHighlight {
+                    start: (cur_lo.col.0 as isize + acc) as usize,
+                    end: (cur_lo.col.0 as isize + acc + len) as usize,
+                });
+                buf.push_str(&part.snippet);
+                let cur_hi = sm.lookup_char_pos(part.span.hi());
+                // Account for the difference between the width of the current code and the
+                // snippet being suggested, so that the *later* suggestions are correctly
+                // aligned on the screen. Note that cur_hi and cur_lo can be on different
+                // lines, so cur_hi.col can be smaller than cur_lo.col
+                acc += len - (cur_hi.col.0 as isize - cur_lo.col.0 as isize);
+                prev_hi = cur_hi;
+                prev_line = source.line(sf, prev_hi.line - 1);
+                for line in part.snippet.split('\n').skip(1) {
+                    acc = 0;
+                    highlights.push(std::mem::take(&mut line_highlight));
+                    let end: usize = line
                         .chars()
                         .map(|c| match c {
                             '\t' => 4,
                             _ => 1,
                         })
                         .sum();
-                    line_highlight.push(SubstitutionHighlight {
-                        start: (cur_lo.col.0 as isize + acc) as usize,
-                        end: (cur_lo.col.0 as isize + acc + len) as usize,
-                    });
-                    buf.push_str(&part.snippet);
-                    let cur_hi = sm.lookup_char_pos(part.span.hi());
-                    // Account for the difference between the width of the current code and the
-                    // snippet being suggested, so that the *later* suggestions are correctly
-                    // aligned on the screen. Note that cur_hi and cur_lo can be on different
-                    // lines, so cur_hi.col can be smaller than cur_lo.col
-                    acc += len - (cur_hi.col.0 as isize - cur_lo.col.0 as isize);
-                    prev_hi = cur_hi;
-                    prev_line = sf.get_line(prev_hi.line - 1);
-                    for line in part.snippet.split('\n').skip(1) {
-                        acc = 0;
-                        highlights.push(std::mem::take(&mut line_highlight));
-                        let end: usize = line
-                            .chars()
-                            .map(|c| match c {
-                                '\t' => 4,
-                                _ => 1,
-                            })
-                            .sum();
-                        line_highlight.push(SubstitutionHighlight { start: 0, end });
-                    }
-                }
-                highlights.push(std::mem::take(&mut line_highlight));
-                let only_capitalization = is_case_difference(sm, &buf, bounding_span);
-                // if the replacement already ends with a newline, don't print the next line
-                if !buf.ends_with('\n') {
-                    push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, None);
+                    line_highlight.push(SubstitutionHighlight { start: 0, end });
                 }
-                // remove trailing newlines
-                while buf.ends_with('\n') {
-                    buf.pop();
+            }
+            highlights.push(std::mem::take(&mut line_highlight));
+            let only_capitalization = is_case_difference(source, &buf, bounding_span);
+            // if the replacement already ends with a newline, don't print the next line
+            if !buf.ends_with('\n') {
+                push_trailing(&mut buf, prev_line.as_ref(), &prev_hi, None);
+            }
+            // remove trailing newlines
+            while buf.ends_with('\n') {
+                buf.pop();
+            }
+            Some((buf, substitution.parts, highlights, only_capitalization))
+        };
+
+        assert!(!self.substitutions.is_empty());
+
+        self.substitutions
+            .iter()
+            .filter(|subst| {
+                // Suggestions coming from macros can have malformed spans. This is a heavy
+                // handed approach to avoid ICEs by ignoring the suggestion outright.
+                let invalid = subst.parts.iter().any(|item| sm.is_valid_span(item.span).is_err());
+                if invalid {
+                    debug!("splice_lines: suggestion contains an invalid span: {:?}", subst);
                 }
-                Some((buf, substitution.parts, highlights, only_capitalization))
+                !invalid
+            })
+            .filter_map(|subst| {
+                // Parts in different files are spliced separately. If any of them can't be
+                // spliced, the whole substitution is ignored.
+                subst.split_by_file(sm).into_iter().map(&splice_substitution).collect()
             })
             .collect()
     }
@@ -405,13 +531,30 @@ fn push_trailing(
 
 /// Signifies that the compiler died with an explicit call to `.delay_*_bug`
 /// rather than a failed assertion, etc.
-pub struct DelayedBugPanic;
+pub struct DelayedBugPanic {
+    /// The messages of the delayed bugs that were reported, each with where it was delayed, for
+    /// machine-readable ICE reports.
+    pub bugs: Vec<(String, DiagnosticLocation)>,
+}
+
+/// How many warnings and notes a thread of the parallel compiler buffers at most while another
+/// thread is emitting diagnostics, see `DiagCtxt::pending`.
+const MAX_PENDING_DIAGNOSTICS: usize = 32;
 
 /// A `DiagCtxt` deals with errors and other compiler output.
 /// Certain errors (fatal, bug, unimpl) may cause immediate exit,
 /// others log errors for later reporting.
 pub struct DiagCtxt {
     inner: Lock<DiagCtxtInner>,
+    /// Warnings and notes emitted by the parallel compiler while another thread holds `inner`,
+    /// buffered per thread to avoid waiting for it. They are merged into `inner`, which
+    /// deduplicates and counts them, as soon as their thread finds `inner` free, when
+    /// `MAX_PENDING_DIAGNOSTICS` of them are buffered, before anything else is emitted and
+    /// whenever the results of emission are observed.
+    ///
+    /// This is created on first use: `Sharded` needs to know whether the compiler is parallel,
+    /// which isn't known yet when the `DiagCtxt` of the session is created.
+    pending: OnceLock<Sharded<Vec<Diagnostic>>>,
 }
 
 /// This inner struct exists to keep it all behind a single lock;
@@ -429,12 +572,19 @@ struct DiagCtxtInner {
     deduplicated_err_count: usize,
     /// The warning count shown to the user at the end.
     deduplicated_warn_count: usize,
+    /// The count of performance notes shown to the user at the end.
+    deduplicated_perf_note_count: usize,
 
     /// Has this diagnostic context printed any diagnostics? (I.e. has
     /// `self.emitter.emit_diagnostic()` been called?
     has_printed: bool,
 
     emitter: Box<DynEmitter>,
+    /// Writes the diagnostics that aren't errors instead of `emitter`, see
+    /// `DiagCtxt::with_warning_emitter`.
+    warning_emitter: Option<Box<DynEmitter>>,
+    /// Also writes every diagnostic that is emitted, see `DiagCtxt::with_log_emitter`.
+    log_emitter: Option<Box<DynEmitter>>,
     span_delayed_bugs: Vec<DelayedDiagnostic>,
     good_path_delayed_bugs: Vec<DelayedDiagnostic>,
     /// This flag indicates that an expected diagnostic was emitted and suppressed.
@@ -461,6 +611,62 @@ struct DiagCtxtInner {
     stashed_diagnostics: FxIndexMap<(Span, StashKey), Diagnostic>,
 
     future_breakage_diagnostics: Vec<Diagnostic>,
+    /// Run on the future breakage report before it is emitted, in the order they were added.
+    future_breakage_hooks: Vec<Box<FutureBreakageHook>>,
+
+    /// Diagnostics that are ready to be printed, along with their stable hashes, but are held back
+    /// until the next flush point so they can be printed in a deterministic order, with
+    /// [`DiagCtxtFlags::deterministic_diagnostics`] and
+    /// [`DiagCtxtFlags::group_diagnostics_by_file`], or with the number of their duplicates, with
+    /// [`DiagCtxtFlags::deduplicate_diagnostics`].
+    ordered_diagnostics: Vec<(Hash128, Diagnostic)>,
+
+    /// Shares the strings of the future breakage and ordered diagnostics, which often repeat the
+    /// same messages and arguments many times. Stashed diagnostics aren't interned, as callers can
+    /// look at them with `DiagCtxt::for_each_stashed_diagnostic`.
+    interner: DiagnosticInterner,
+
+    /// Records how long emitting diagnostics takes with `-Zself-profile`.
+    prof: SelfProfilerRef,
+
+    /// The diagnostics printed since `DiagCtxt::record_diagnostics` was called, other than errors.
+    recorded_diagnostics: Option<Vec<Diagnostic>>,
+
+    /// The fingerprints of known diagnostics that aren't shown, see `DiagCtxt::with_baseline`.
+    baseline: FxHashSet<String>,
+    /// The number of diagnostics that weren't shown because they are in `baseline`.
+    baseline_suppressed_count: usize,
+
+    /// Only the diagnostics pointing at these lines are shown, see `DiagCtxt::with_changed_lines`.
+    changed_lines: Option<ChangedLines>,
+    /// The number of diagnostics that weren't shown because they point outside `changed_lines`.
+    unchanged_lines_count: usize,
+
+    /// Warnings pointing into files whose path matches one of these aren't shown, see
+    /// `DiagCtxt::add_span_filter`.
+    span_filters: Vec<Box<SpanFilter>>,
+    /// The number of warnings that weren't shown because of `span_filters`.
+    span_filtered_count: usize,
+    /// Run on each diagnostic that is emitted, see `DiagCtxt::add_observer`.
+    observers: Vec<Box<DiagnosticObserver>>,
+    /// The groups that each lint is in, by the name of the lint, see `DiagCtxt::set_lint_groups`.
+    lint_groups: FxHashMap<String, Vec<String>>,
+    /// What the levels of diagnostics from each tool are prefixed with, by the name of the tool,
+    /// see `DiagCtxt::set_tool_display_prefix`.
+    tool_display_prefixes: FxHashMap<String, String>,
+    /// Describes the active queries for `-Z track-diagnostics`, see
+    /// `DiagCtxt::set_query_stack_hook`.
+    query_stack_hook: Option<Box<QueryStackHook>>,
+    /// The number of errors that weren't shown because of `DiagCtxtFlags::error_limit`.
+    errors_over_limit: usize,
+
+    /// The diagnostics emitted so far, see `DiagCtxt::counts`.
+    counts: DiagnosticCounts,
+
+    /// How many duplicates `DiagCtxtFlags::deduplicate_diagnostics` didn't show of each of the
+    /// `ordered_diagnostics`, by their hashes. The count is added to the diagnostic when it is
+    /// printed.
+    duplicate_counts: FxHashMap<Hash128, usize>,
 
     /// The [`Self::unstable_expect_diagnostics`] should be empty when this struct is
     /// dropped. However, it can have values if the compilation is stopped early
@@ -488,6 +694,43 @@ struct DiagCtxtInner {
     /// The file where the ICE information is stored. This allows delayed_span_bug backtraces to be
     /// stored along side the main panic backtrace.
     ice_file: Option<PathBuf>,
+
+    /// The snapshots taken with `DiagCtxt::snapshot` that haven't been committed or rolled back
+    /// yet, by the thread that took them. While a thread has any, the diagnostics it emits are
+    /// held back.
+    speculations: FxHashMap<ThreadId, Speculation>,
+
+    /// The number of `ErrorGuaranteed`s handed out so far. A snapshot can't be rolled back once
+    /// this changed, see `DiagCtxt::rollback`.
+    guarantees: usize,
+}
+
+/// The snapshots taken by one thread, see `DiagCtxt::snapshot`.
+#[derive(Default)]
+struct Speculation {
+    /// The state when each of the snapshots was taken, innermost last.
+    snapshots: Vec<SnapshotState>,
+    /// The diagnostics held back since the outermost snapshot was taken.
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// What `DiagCtxt::rollback` goes back to.
+struct SnapshotState {
+    /// The length of `Speculation::diagnostics`.
+    held_back: usize,
+    /// The length of `DiagCtxtInner::stashed_diagnostics`.
+    stashed: usize,
+    /// The length of `DiagCtxtInner::good_path_delayed_bugs`.
+    good_path_delayed_bugs: usize,
+    /// The value of `DiagCtxtInner::guarantees`.
+    guarantees: usize,
+}
+
+/// The diagnostics of a `DiagCtxt` at some point of the compilation, see `DiagCtxt::snapshot`.
+#[must_use = "snapshots must be committed or rolled back"]
+pub struct DiagCtxtSnapshot {
+    thread: ThreadId,
+    depth: usize,
 }
 
 /// A key denoting where from a diagnostic was stashed.
@@ -509,12 +752,39 @@ pub enum StashKey {
     MaybeForgetReturn,
     /// Query cycle detected, stashing in favor of a better error.
     Cycle,
+    /// Stashed by code outside of the compiler, such as a driver or a tool, under a name of its
+    /// choosing, which should be specific enough not to clash with the names of other tools.
+    Custom(&'static str),
+}
+
+/// What to do when a diagnostic is stashed with the same span and key as one that is already
+/// stashed, see `DiagCtxt::stash_diagnostic_with`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum StashMergePolicy {
+    /// Drop the diagnostic that was stashed before.
+    Replace,
+    /// Drop the new diagnostic.
+    KeepFirst,
+    /// Keep the diagnostic that was stashed before, adding the notes, help messages and other
+    /// subdiagnostics of the new one to it.
+    CombineNotes,
 }
 
 fn default_track_diagnostic(diag: Diagnostic, f: &mut dyn FnMut(Diagnostic)) {
     (*f)(diag)
 }
 
+/// Records where `diagnostic` was created and its primary message in a guarantee handed out for
+/// it, which is shown if it turns out that no error was emitted.
+#[cfg(debug_assertions)]
+fn claimed_for(guar: ErrorGuaranteed, diagnostic: &Diagnostic) -> ErrorGuaranteed {
+    let message = match &diagnostic.messages[0].0 {
+        DiagnosticMessage::FluentIdentifier(id, _) => id.to_string(),
+        DiagnosticMessage::Str(message) | DiagnosticMessage::Eager(message) => message.to_string(),
+    };
+    guar.claimed_for(diagnostic.emitted_at.to_string(), message)
+}
+
 pub static TRACK_DIAGNOSTIC: AtomicRef<fn(Diagnostic, &mut dyn FnMut(Diagnostic))> =
     AtomicRef::new(&(default_track_diagnostic as _));
 
@@ -535,18 +805,84 @@ pub struct DiagCtxtFlags {
     /// Eagerly emit delayed bugs as errors, so that the compiler debugger may
     /// see all of the errors being emitted at once.
     pub eagerly_emit_delayed_bugs: bool,
-    /// Show macro backtraces.
+    /// How much of macro backtraces to show.
     /// (rustc: see `-Z macro-backtrace`)
-    pub macro_backtrace: bool,
-    /// If true, identical diagnostics are reported only once.
+    pub macro_backtrace: MacroBacktrace,
+    /// If true, identical diagnostics are reported only once, with the number of duplicates that
+    /// weren't shown. They are held back until the next flush point to count the duplicates.
     pub deduplicate_diagnostics: bool,
     /// Track where errors are created. Enabled with `-Ztrack-diagnostics`.
     pub track_diagnostics: bool,
+    /// Check that machine-applicable suggestions produce code that lexes.
+    /// (rustc: see `-Z verify-suggestions`)
+    pub verify_suggestions: bool,
+    /// Buffer diagnostics and print them sorted by their spans at flush points, so that the
+    /// output doesn't depend on the order in which threads emit them.
+    /// (rustc: see `-Z deterministic-diagnostics`)
+    pub deterministic_diagnostics: bool,
+    /// Like `deterministic_diagnostics`, but also print the name of the file before the
+    /// diagnostics pointing into it, and only print them when the compilation ends or aborts.
+    /// (rustc: see `-Z group-diagnostics-by-file`)
+    pub group_diagnostics_by_file: bool,
+    /// List how many times each lint was emitted in the summary at the end of the compilation.
+    /// (rustc: see `-Z lint-summary`)
+    pub lint_summary: bool,
+    /// If Some, errors after the Nth one are counted but not shown.
+    /// (rustc: see `-Z error-limit`)
+    pub error_limit: Option<NonZeroUsize>,
+    /// Emit each future breakage diagnostic to the emitter as soon as it is emitted, in addition
+    /// to the future breakage report at the end of the compilation.
+    /// (rustc: see `-Z stream-future-incompat`)
+    pub stream_future_incompat: bool,
+    /// For which delayed bugs a backtrace is captured.
+    /// (rustc: see `-Z track-delayed-bugs`)
+    pub track_delayed_bugs: DelayedBugBacktraces,
+}
+
+/// The number of diagnostics emitted so far, grouped in different ways, see `DiagCtxt::counts`.
+/// Diagnostics that were hidden, for example by `#[allow]`, aren't counted.
+#[derive(Clone, Debug, Default)]
+pub struct DiagnosticCounts {
+    /// The number of diagnostics of each level.
+    pub by_level: FxIndexMap<Level, usize>,
+    /// The number of diagnostics emitted by each lint, by the name of the lint.
+    pub by_lint: FxIndexMap<String, usize>,
+    /// The number of diagnostics with each error code.
+    pub by_code: FxIndexMap<String, usize>,
+}
+
+impl DiagnosticCounts {
+    fn add(&mut self, diagnostic: &Diagnostic) {
+        *self.by_level.entry(diagnostic.level).or_default() += 1;
+        if let Some(lint) = diagnostic.lint() {
+            *self.by_lint.entry(lint.name.clone()).or_default() += 1;
+        }
+        if let Some(code) = &diagnostic.code {
+            *self.by_code.entry(code.clone()).or_default() += 1;
+        }
+    }
+}
+
+/// An `#[expect]` attribute whose lint was never emitted, see
+/// `DiagCtxt::emit_unfulfilled_expectations`.
+#[derive(Clone, Debug)]
+pub struct UnfulfilledExpectation {
+    pub id: LintExpectationId,
+    /// The lint in the attribute.
+    pub span: Span,
+    /// The reason given in the attribute, if any.
+    pub reason: Option<Symbol>,
 }
 
 impl Drop for DiagCtxtInner {
     fn drop(&mut self) {
+        // The diagnostics of snapshots that were never committed or rolled back, like when the
+        // code that took them panicked, are kept as they may explain what went wrong.
+        for (_, speculation) in std::mem::take(&mut self.speculations) {
+            self.emit_held_back(speculation.diagnostics);
+        }
         self.emit_stashed_diagnostics();
+        self.flush_ordered_diagnostics();
 
         if !self.has_errors() {
             self.flush_delayed(DelayedBugKind::Normal)
@@ -570,6 +906,12 @@ fn drop(&mut self) {
     }
 }
 
+impl Drop for DiagCtxt {
+    fn drop(&mut self) {
+        self.flush_pending();
+    }
+}
+
 impl DiagCtxt {
     pub fn with_tty_emitter(
         sm: Option<Lrc<SourceMap>>,
@@ -593,6 +935,66 @@ pub fn with_ice_file(mut self, ice_file: PathBuf) -> Self {
         self
     }
 
+    /// Records the time spent emitting diagnostics with `prof`, see `Emitter::set_self_profiler`.
+    pub fn with_self_profiler(mut self, prof: SelfProfilerRef) -> Self {
+        let inner = self.inner.get_mut();
+        inner.emitter.set_self_profiler(prof.clone());
+        for emitter in inner.warning_emitter.iter_mut().chain(&mut inner.log_emitter) {
+            emitter.set_self_profiler(prof.clone());
+        }
+        inner.prof = prof;
+        self
+    }
+
+    /// Writes warnings, and notes and help messages that aren't part of an error, with `emitter`,
+    /// so that they can go to another output than errors, like a file that a build system keeps
+    /// while errors go to stderr. The summary at the end of the compilation, like "aborting due
+    /// to previous error", and everything else that isn't a diagnostic is still written by the
+    /// main emitter.
+    pub fn with_warning_emitter(mut self, mut emitter: Box<DynEmitter>) -> Self {
+        let inner = self.inner.get_mut();
+        emitter.set_self_profiler(inner.prof.clone());
+        inner.warning_emitter = Some(emitter);
+        self
+    }
+
+    /// Writes every diagnostic that is emitted with `emitter` too, in addition to the emitters
+    /// that show it, like to keep a log of the diagnostics of a build in a file.
+    pub fn with_log_emitter(mut self, mut emitter: Box<DynEmitter>) -> Self {
+        let inner = self.inner.get_mut();
+        emitter.set_self_profiler(inner.prof.clone());
+        inner.log_emitter = Some(emitter);
+        self
+    }
+
+    /// Hides the diagnostics whose [`fingerprint::fingerprint`] is in `fingerprints`, so that
+    /// existing warnings and lints can be accepted while new ones are still shown. Errors other
+    /// than lints are always shown.
+    pub fn with_baseline(mut self, fingerprints: FxHashSet<String>) -> Self {
+        self.inner.get_mut().baseline = fingerprints;
+        self
+    }
+
+    /// Hides the warnings and lints whose primary span doesn't point at one of `changed_lines`, so
+    /// that only the diagnostics introduced by a patch are shown. Like with `with_baseline`,
+    /// errors other than lints are always shown.
+    pub fn with_changed_lines(mut self, changed_lines: ChangedLines) -> Self {
+        self.inner.get_mut().changed_lines = Some(changed_lines);
+        self
+    }
+
+    /// Creates a `DiagCtxt` that emits diagnostics through an emitter implemented outside of the
+    /// compiler, such as by a driver that shows diagnostics in its own way.
+    ///
+    /// Such emitters implement `Translate` with the bundles from `fallback_fluent_bundle` and
+    /// `fluent_bundle`, and can reuse the rendering of the compiler with
+    /// `FileWithAnnotatedLines::collect_annotations`, which groups the labels of a diagnostic by
+    /// file and line, and `CodeSuggestion::splice_lines`, which applies suggestions to the lines
+    /// they change.
+    pub fn with_custom_emitter(emitter: impl Emitter + DynSend + 'static) -> Self {
+        Self::with_emitter(Box::new(emitter))
+    }
+
     pub fn with_emitter(emitter: Box<DynEmitter>) -> Self {
         Self {
             inner: Lock::new(DiagCtxtInner {
@@ -601,8 +1003,11 @@ pub fn with_emitter(emitter: Box<DynEmitter>) -> Self {
                 err_count: 0,
                 deduplicated_err_count: 0,
                 deduplicated_warn_count: 0,
+                deduplicated_perf_note_count: 0,
                 has_printed: false,
                 emitter,
+                warning_emitter: None,
+                log_emitter: None,
                 span_delayed_bugs: Vec::new(),
                 good_path_delayed_bugs: Vec::new(),
                 suppressed_expected_diag: false,
@@ -611,11 +1016,32 @@ pub fn with_emitter(emitter: Box<DynEmitter>) -> Self {
                 emitted_diagnostics: Default::default(),
                 stashed_diagnostics: Default::default(),
                 future_breakage_diagnostics: Vec::new(),
+                future_breakage_hooks: Vec::new(),
+                ordered_diagnostics: Vec::new(),
+                interner: Default::default(),
+                prof: SelfProfilerRef::new(None, None),
+                recorded_diagnostics: None,
+                baseline: Default::default(),
+                baseline_suppressed_count: 0,
+                changed_lines: None,
+                unchanged_lines_count: 0,
+                span_filters: Vec::new(),
+                observers: Vec::new(),
+                lint_groups: Default::default(),
+                tool_display_prefixes: Default::default(),
+                query_stack_hook: None,
+                span_filtered_count: 0,
+                errors_over_limit: 0,
+                counts: Default::default(),
+                duplicate_counts: Default::default(),
                 check_unstable_expect_diagnostics: false,
                 unstable_expect_diagnostics: Vec::new(),
                 fulfilled_expectations: Default::default(),
                 ice_file: None,
+                speculations: Default::default(),
+                guarantees: 0,
             }),
+            pending: OnceLock::new(),
         }
     }
 
@@ -639,6 +1065,26 @@ pub fn eagerly_translate_to_string<'a>(
         inner.emitter.translate_message(&message, &args).map_err(Report::new).unwrap().to_string()
     }
 
+    /// Whether `diag` could be rendered if it was emitted, judging by its level. Diagnostics that
+    /// can't be rendered don't need their messages to be translated, which is expensive enough to
+    /// matter for crates with many allowed lints.
+    pub(crate) fn may_render(&self, diag: &Diagnostic) -> bool {
+        // Future breakages are rendered in the future incompatibility report at any level.
+        if diag.has_future_breakage() {
+            return true;
+        }
+        match diag.level {
+            Allow | Expect(_) => false,
+            Warning | PerfNote => self.inner.borrow().flags.can_emit_warnings,
+            _ => true,
+        }
+    }
+
+    /// The source map of the emitter, if it has one.
+    pub(crate) fn source_map(&self) -> Option<Lrc<SourceMap>> {
+        self.inner.borrow().emitter.source_map().cloned()
+    }
+
     // This is here to not allow mutation of flags;
     // as of this writing it's only used in tests in librustc_middle.
     pub fn can_emit_warnings(&self) -> bool {
@@ -651,11 +1097,13 @@ pub fn can_emit_warnings(&self) -> bool {
     /// tools that want to reuse a `Parser` cleaning the previously emitted diagnostics as well as
     /// the overall count of emitted error diagnostics.
     pub fn reset_err_count(&self) {
+        self.flush_pending();
         let mut inner = self.inner.borrow_mut();
         inner.lint_err_count = 0;
         inner.err_count = 0;
         inner.deduplicated_err_count = 0;
         inner.deduplicated_warn_count = 0;
+        inner.deduplicated_perf_note_count = 0;
         inner.has_printed = false;
 
         // actually free the underlying memory (which `clear` would not do)
@@ -670,12 +1118,34 @@ pub fn reset_err_count(&self) {
     /// Stash a given diagnostic with the given `Span` and [`StashKey`] as the key.
     /// Retrieve a stashed diagnostic with `steal_diagnostic`.
     pub fn stash_diagnostic(&self, span: Span, key: StashKey, diag: Diagnostic) {
+        self.stash_diagnostic_with(span, key, diag, StashMergePolicy::Replace)
+    }
+
+    /// Like `stash_diagnostic`, but with `policy` deciding what happens if a diagnostic is already
+    /// stashed with the same `Span` and [`StashKey`].
+    pub fn stash_diagnostic_with(
+        &self,
+        span: Span,
+        key: StashKey,
+        diag: Diagnostic,
+        policy: StashMergePolicy,
+    ) {
         let mut inner = self.inner.borrow_mut();
 
         let key = (span.with_parent(None), key);
+        if let Some(stashed) = inner.stashed_diagnostics.get_mut(&key) {
+            match policy {
+                StashMergePolicy::Replace => {}
+                StashMergePolicy::KeepFirst => return,
+                StashMergePolicy::CombineNotes => {
+                    stashed.children.extend(diag.children);
+                    return;
+                }
+            }
+        }
 
         if diag.is_error() {
-            if diag.is_lint.is_some() {
+            if diag.lint().is_some() {
                 inner.lint_err_count += 1;
             } else {
                 inner.err_count += 1;
@@ -694,7 +1164,7 @@ pub fn steal_diagnostic(&self, span: Span, key: StashKey) -> Option<DiagnosticBu
         let key = (span.with_parent(None), key);
         let diag = inner.stashed_diagnostics.remove(&key)?;
         if diag.is_error() {
-            if diag.is_lint.is_some() {
+            if diag.lint().is_some() {
                 inner.lint_err_count -= 1;
             } else {
                 inner.err_count -= 1;
@@ -707,8 +1177,60 @@ pub fn has_stashed_diagnostic(&self, span: Span, key: StashKey) -> bool {
         self.inner.borrow().stashed_diagnostics.get(&(span.with_parent(None), key)).is_some()
     }
 
+    /// Calls `f` with each diagnostic that is currently stashed, along with its `Span` and
+    /// [`StashKey`], in the order they were stashed. `f` must not use the `DiagCtxt`.
+    pub fn for_each_stashed_diagnostic(&self, mut f: impl FnMut(Span, StashKey, &Diagnostic)) {
+        for (&(span, key), diag) in &self.inner.borrow().stashed_diagnostics {
+            f(span, key, diag);
+        }
+    }
+
+    /// Starts holding back the diagnostics emitted on the current thread from now on, for code
+    /// that tries something that may not work out, like parsing the code in one of several ways.
+    /// The diagnostics are then either emitted with `commit` or dropped with `rollback`.
+    ///
+    /// Held back errors are counted by `err_count` and `has_errors` right away. Diagnostics that
+    /// abort the compilation and delayed bugs are never held back. Snapshots can be nested, and
+    /// must be committed or rolled back in the reverse order in which they were taken, on the
+    /// thread that took them.
+    pub fn snapshot(&self) -> DiagCtxtSnapshot {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        let speculation = inner.speculations.entry(thread::current().id()).or_default();
+        speculation.snapshots.push(SnapshotState {
+            held_back: speculation.diagnostics.len(),
+            stashed: inner.stashed_diagnostics.len(),
+            good_path_delayed_bugs: inner.good_path_delayed_bugs.len(),
+            guarantees: inner.guarantees,
+        });
+        DiagCtxtSnapshot { thread: thread::current().id(), depth: speculation.snapshots.len() }
+    }
+
+    /// Discards everything recorded since `snapshot` was taken: the diagnostics held back, and
+    /// the diagnostics stashed and good path delayed bugs along with what they were counted as.
+    ///
+    /// Panics if an `ErrorGuaranteed` was handed out since, like for an error that was emitted:
+    /// it may have been used already, so the snapshot must be committed instead.
+    pub fn rollback(&self, snapshot: DiagCtxtSnapshot) {
+        let mut inner = self.inner.borrow_mut();
+        let state = inner.pop_snapshot(snapshot);
+        if inner.guarantees != state.guarantees {
+            panic!("cannot roll back a snapshot after an `ErrorGuaranteed` was handed out");
+        }
+        inner.rollback(state);
+    }
+
+    /// Keeps the diagnostics held back since `snapshot` was taken. They are emitted now, or when
+    /// the snapshot around it is committed if there is one.
+    pub fn commit(&self, snapshot: DiagCtxtSnapshot) {
+        let mut inner = self.inner.borrow_mut();
+        inner.pop_snapshot(snapshot);
+        inner.end_speculation();
+    }
+
     /// Emit all stashed diagnostics.
     pub fn emit_stashed_diagnostics(&self) -> Option<ErrorGuaranteed> {
+        self.flush_pending();
         self.inner.borrow_mut().emit_stashed_diagnostics()
     }
 
@@ -926,6 +1448,13 @@ pub fn bug(&self, msg: impl Into<DiagnosticMessage>) -> ! {
         self.struct_bug(msg).emit()
     }
 
+    /// Returns the number of diagnostics emitted so far by level, lint and error code, so that
+    /// drivers can decide what to do depending on which diagnostics were emitted.
+    pub fn counts(&self) -> DiagnosticCounts {
+        self.flush_pending();
+        self.inner.borrow().counts.clone()
+    }
+
     /// This excludes lint errors and delayed bugs.
     #[inline]
     pub fn err_count(&self) -> usize {
@@ -933,45 +1462,80 @@ pub fn err_count(&self) -> usize {
     }
 
     /// This excludes lint errors and delayed bugs.
+    #[track_caller]
     pub fn has_errors(&self) -> Option<ErrorGuaranteed> {
-        self.inner.borrow().has_errors().then(|| {
-            #[allow(deprecated)]
-            ErrorGuaranteed::unchecked_claim_error_was_emitted()
-        })
+        let mut inner = self.inner.borrow_mut();
+        if !inner.has_errors() {
+            return None;
+        }
+        Some(inner.hand_out_guarantee())
     }
 
     /// This excludes delayed bugs. Unless absolutely necessary, prefer
     /// `has_errors` to this method.
+    #[track_caller]
     pub fn has_errors_or_lint_errors(&self) -> Option<ErrorGuaranteed> {
-        let inner = self.inner.borrow();
-        let result = inner.has_errors() || inner.lint_err_count > 0;
-        result.then(|| {
-            #[allow(deprecated)]
-            ErrorGuaranteed::unchecked_claim_error_was_emitted()
-        })
+        let mut inner = self.inner.borrow_mut();
+        if !inner.has_errors() && inner.lint_err_count == 0 {
+            return None;
+        }
+        Some(inner.hand_out_guarantee())
     }
 
     /// Unless absolutely necessary, prefer `has_errors` or
     /// `has_errors_or_lint_errors` to this method.
+    #[track_caller]
     pub fn has_errors_or_lint_errors_or_delayed_bugs(&self) -> Option<ErrorGuaranteed> {
-        let inner = self.inner.borrow();
-        let result =
-            inner.has_errors() || inner.lint_err_count > 0 || !inner.span_delayed_bugs.is_empty();
-        result.then(|| {
-            #[allow(deprecated)]
-            ErrorGuaranteed::unchecked_claim_error_was_emitted()
-        })
+        let mut inner = self.inner.borrow_mut();
+        if !inner.has_errors() && inner.lint_err_count == 0 && inner.span_delayed_bugs.is_empty() {
+            return None;
+        }
+        Some(inner.hand_out_guarantee())
     }
 
     pub fn print_error_count(&self, registry: &Registry) {
+        self.flush_pending();
         let mut inner = self.inner.borrow_mut();
 
         inner.emit_stashed_diagnostics();
+        inner.flush_ordered_diagnostics();
 
         if inner.treat_err_as_bug() {
             return;
         }
 
+        match inner.baseline_suppressed_count {
+            0 => {}
+            1 => inner.failure_note("1 diagnostic was suppressed by the diagnostic baseline"),
+            count => inner.failure_note(format!(
+                "{count} diagnostics were suppressed by the diagnostic baseline"
+            )),
+        }
+        match inner.unchanged_lines_count {
+            0 => {}
+            1 => inner.failure_note("1 diagnostic outside of the changed lines was hidden"),
+            count => inner.failure_note(format!(
+                "{count} diagnostics outside of the changed lines were hidden"
+            )),
+        }
+        match inner.span_filtered_count {
+            0 => {}
+            1 => inner.failure_note("1 warning in a filtered path was hidden"),
+            count => inner.failure_note(format!("{count} warnings in filtered paths were hidden")),
+        }
+
+        // Performance notes aren't warnings, so they are counted on their own.
+        match inner.deduplicated_perf_note_count {
+            0 => {}
+            1 => inner
+                .emitter
+                .emit_diagnostic(&Diagnostic::new(PerfNote, "1 performance note emitted")),
+            count => inner.emitter.emit_diagnostic(&Diagnostic::new(
+                PerfNote,
+                format!("{count} performance notes emitted"),
+            )),
+        }
+
         let warnings = match inner.deduplicated_warn_count {
             0 => Cow::from(""),
             1 => Cow::from("1 warning emitted"),
@@ -996,6 +1560,31 @@ pub fn print_error_count(&self, registry: &Registry) {
             }
         }
 
+        match inner.errors_over_limit {
+            0 => {}
+            1 => inner.failure_note("1 more error was not shown because of `-Z error-limit`"),
+            count => inner.failure_note(format!(
+                "{count} more errors were not shown because of `-Z error-limit`"
+            )),
+        }
+
+        if inner.flags.lint_summary && !inner.counts.by_lint.is_empty() {
+            let mut lints = inner.counts.by_lint.clone().into_iter().collect::<Vec<_>>();
+            lints.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+            inner.failure_note("lints emitted, most frequent first:");
+            let width = lints[0].1.to_string().len();
+            for (name, count) in &lints {
+                inner.failure_note(format!("{count:>width$} {name}"));
+            }
+            let (name, count) = &lints[0];
+            inner.failure_note(format!(
+                "`{name}` was emitted most often ({count} time{s}): allow it in the whole crate \
+                with `#![allow({name})]` in the crate root, or try `cargo fix` to apply its \
+                suggestions",
+                s = pluralize!(*count),
+            ));
+        }
+
         let can_show_explain = inner.emitter.should_show_explain();
         let are_there_diagnostics = !inner.emitted_diagnostic_codes.is_empty();
         if can_show_explain && are_there_diagnostics {
@@ -1020,12 +1609,14 @@ pub fn print_error_count(&self, registry: &Registry) {
                         if error_codes.len() > 9 { "..." } else { "." }
                     ));
                     inner.failure_note(format!(
-                        "For more information about an error, try `rustc --explain {}`.",
+                        "For more information about an error, try `{} {}`.",
+                        registry.explain_command(&error_codes[0]),
                         &error_codes[0]
                     ));
                 } else {
                     inner.failure_note(format!(
-                        "For more information about this error, try `rustc --explain {}`.",
+                        "For more information about this error, try `{} {}`.",
+                        registry.explain_command(&error_codes[0]),
                         &error_codes[0]
                     ));
                 }
@@ -1033,13 +1624,107 @@ pub fn print_error_count(&self, registry: &Registry) {
         }
     }
 
+    /// Hides the warnings whose primary span points into a file for which `filter` returns true,
+    /// such as generated code that `#[allow]` attributes can't be added to. See `PathGlob` for
+    /// matching paths with a glob pattern.
+    pub fn add_span_filter(&self, filter: impl Fn(&Path) -> bool + DynSend + 'static) {
+        self.inner.borrow_mut().span_filters.push(Box::new(filter));
+    }
+
+    /// Calls `observer` with each diagnostic that is emitted from now on, once its level is final
+    /// and before it is rendered, so that drivers can record or count diagnostics without
+    /// replacing the emitter. Diagnostics that aren't shown, like allowed lints, aren't observed.
+    ///
+    /// `observer` is called while the `DiagCtxt` is borrowed, so it must not use it.
+    pub fn add_observer(&self, observer: impl Fn(&Diagnostic) + DynSend + 'static) {
+        self.inner.borrow_mut().observers.push(Box::new(observer));
+    }
+
+    /// Sets the groups that lints are in, by the names of the lints, so that machine-readable
+    /// output can show them for lint diagnostics. Lints are registered after the `DiagCtxt` is
+    /// created, by drivers as well, so they can't be known before.
+    pub fn set_lint_groups(&self, lint_groups: FxHashMap<String, Vec<String>>) {
+        self.inner.borrow_mut().lint_groups = lint_groups;
+    }
+
+    /// Makes the lints of `tool`, like `clippy::box_collection`, show their level with `prefix`,
+    /// like `clippy-warning`, so that they can be told apart from the lints of rustc. Machine-
+    /// readable output has the tool in a field of its own instead.
+    pub fn set_tool_display_prefix(&self, tool: impl Into<String>, prefix: impl Into<String>) {
+        self.inner.borrow_mut().tool_display_prefixes.insert(tool.into(), prefix.into());
+    }
+
+    /// Sets how the active queries are described, for `-Z track-diagnostics` to show the queries
+    /// that each diagnostic was emitted in along with where in rustc it was created. `hook`
+    /// returns a line for each active query, innermost first.
+    ///
+    /// `hook` is called while the `DiagCtxt` is borrowed, so it must not use it.
+    pub fn set_query_stack_hook(&self, hook: impl Fn() -> Vec<String> + DynSend + 'static) {
+        self.inner.borrow_mut().query_stack_hook = Some(Box::new(hook));
+    }
+
+    /// Lets drivers change the future breakage report before it is emitted: `hook` can remove
+    /// diagnostics from it, add notes to them, such as how to fix the code with the driver, or
+    /// take them all to report them in its own way, in which case no report is emitted.
+    pub fn add_future_breakage_hook(
+        &self,
+        hook: impl FnMut(&mut Vec<Diagnostic>) + DynSend + 'static,
+    ) {
+        self.inner.borrow_mut().future_breakage_hooks.push(Box::new(hook));
+    }
+
     pub fn take_future_breakage_diagnostics(&self) -> Vec<Diagnostic> {
-        std::mem::take(&mut self.inner.borrow_mut().future_breakage_diagnostics)
+        self.flush_pending();
+        let mut inner = self.inner.borrow_mut();
+        let mut diags = std::mem::take(&mut inner.future_breakage_diagnostics);
+        diags.iter_mut().for_each(|diag| diag.unintern_strings(&inner.interner));
+        diags
+    }
+
+    /// Starts recording the diagnostics that are printed from now on, other than errors, so that
+    /// they can be saved with `DiagCtxt::save_recorded_diagnostics`.
+    pub fn record_diagnostics(&self) {
+        self.inner.borrow_mut().recorded_diagnostics.get_or_insert_with(Vec::new);
+    }
+
+    /// Writes the diagnostics recorded since `DiagCtxt::record_diagnostics` was called to `path`,
+    /// so that a later session can emit them again with `DiagCtxt::emit_cached_diagnostics`, e.g.
+    /// when incremental compilation skips the code they were emitted for. Does nothing if the
+    /// emitter has no source map, as the spans of the diagnostics couldn't be saved then.
+    pub fn save_recorded_diagnostics(&self, path: &Path) -> FileEncodeResult {
+        self.flush_pending();
+        let Some(sm) = self.source_map() else {
+            return Ok(0);
+        };
+        let inner = self.inner.borrow();
+        let recorded = inner.recorded_diagnostics.as_deref().unwrap_or_default();
+        persist::encode_diagnostics(path, &sm, recorded)
+    }
+
+    /// Emits the diagnostics saved by `DiagCtxt::save_recorded_diagnostics` in a previous session,
+    /// mapping their spans to the source map of the emitter. Diagnostics pointing into files that
+    /// have changed since are skipped, they are expected to be emitted again anyway. Returns the
+    /// number of diagnostics that were emitted.
+    pub fn emit_cached_diagnostics(&self, data: &[u8]) -> usize {
+        let Some(sm) = self.source_map() else {
+            return 0;
+        };
+        let diagnostics = persist::decode_diagnostics(data, &sm).unwrap_or_default();
+        let count = diagnostics.len();
+        for diagnostic in diagnostics {
+            self.emit_diagnostic(diagnostic);
+        }
+        count
     }
 
     pub fn abort_if_errors(&self) {
+        self.flush_pending();
         let mut inner = self.inner.borrow_mut();
         inner.emit_stashed_diagnostics();
+        // Grouped diagnostics are kept until the end, unless the compilation is about to abort.
+        if !inner.flags.group_diagnostics_by_file || inner.has_errors() {
+            inner.flush_ordered_diagnostics();
+        }
         if inner.has_errors() {
             FatalError.raise();
         }
@@ -1055,13 +1740,65 @@ pub fn must_teach(&self, code: &str) -> bool {
     }
 
     pub fn force_print_diagnostic(&self, db: Diagnostic) {
-        self.inner.borrow_mut().emitter.emit_diagnostic(&db);
+        self.inner.borrow_mut().write_diagnostic(&db);
     }
 
+    /// In debug builds, the `ErrorGuaranteed` of an error records the caller of this method.
+    #[track_caller]
     pub fn emit_diagnostic(&self, diagnostic: Diagnostic) -> Option<ErrorGuaranteed> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            // Diagnostics that abort the compilation can't wait for the speculation to end, and
+            // delayed bugs are only emitted if nothing else is.
+            if inner.speculations.contains_key(&thread::current().id())
+                && !matches!(diagnostic.level, Fatal | Bug | DelayedBug(_))
+            {
+                return inner.hold_back_speculative(diagnostic);
+            }
+        }
+        if is_known_dyn_thread_safe()
+            && matches!(diagnostic.level, Warning | PerfNote | Note | Help)
+        {
+            let pending = self.pending.get_or_init(Default::default);
+            let mut buffer = pending.lock_shard_by_value(&thread::current().id());
+            // Diagnostics are only buffered while another thread is emitting, and written as soon
+            // as `inner` is free or enough of them are buffered, so that they still show up as the
+            // compilation goes on.
+            let inner = self.inner.try_lock();
+            if inner.is_none() && buffer.len() + 1 < MAX_PENDING_DIAGNOSTICS {
+                buffer.push(diagnostic);
+                return None;
+            }
+            let buffered = std::mem::take(&mut *buffer);
+            drop(buffer);
+            let mut inner = inner.unwrap_or_else(|| self.inner.borrow_mut());
+            for diagnostic in buffered.into_iter().chain([diagnostic]) {
+                inner.emit_diagnostic(diagnostic);
+            }
+            return None;
+        }
+        self.flush_pending();
         self.inner.borrow_mut().emit_diagnostic(diagnostic)
     }
 
+    /// Merges the diagnostics buffered by each thread into `inner`.
+    fn flush_pending(&self) {
+        let Some(pending) = self.pending.get() else {
+            return;
+        };
+        let pending = pending
+            .lock_shards()
+            .flat_map(|mut shard| std::mem::take(&mut *shard))
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return;
+        }
+        let mut inner = self.inner.borrow_mut();
+        for diagnostic in pending {
+            inner.emit_diagnostic(diagnostic);
+        }
+    }
+
     #[track_caller]
     pub fn emit_err<'a>(&'a self, err: impl IntoDiagnostic<'a>) -> ErrorGuaranteed {
         self.create_err(err).emit()
@@ -1072,6 +1809,34 @@ pub fn create_err<'a>(&'a self, err: impl IntoDiagnostic<'a>) -> DiagnosticBuild
         err.into_diagnostic(self, Error)
     }
 
+    /// Emits every diagnostic in `diags` at `level`, returning the result of each emission, like
+    /// the `ErrorGuaranteed` of each error. Warnings and other diagnostics without a guarantee
+    /// are batched with `G = ()`.
+    #[track_caller]
+    pub fn emit_all<'a, G: EmissionGuarantee>(
+        &'a self,
+        level: Level,
+        diags: impl IntoIterator<Item = impl IntoDiagnostic<'a, G>>,
+    ) -> Vec<G::EmitResult> {
+        diags.into_iter().map(|diag| diag.into_diagnostic(self, level).emit()).collect()
+    }
+
+    /// Emits `diags` ordered by their primary spans, for passes which create diagnostics in an
+    /// unspecified order, e.g. while iterating over a hash map. Diagnostics without a primary span
+    /// are emitted last, in their original order. Returns the result of the last emission.
+    #[track_caller]
+    pub fn emit_sorted_batch<'a, G: EmissionGuarantee>(
+        &'a self,
+        diags: impl IntoIterator<Item = DiagnosticBuilder<'a, G>>,
+    ) -> Option<G::EmitResult> {
+        let mut diags = diags.into_iter().collect::<Vec<_>>();
+        diags.sort_by_key(|diag| {
+            let span = diag.span.primary_span();
+            (span.is_none(), span)
+        });
+        diags.into_iter().fold(None, |_, diag| Some(diag.emit()))
+    }
+
     #[track_caller]
     pub fn create_warn<'a>(
         &'a self,
@@ -1140,12 +1905,34 @@ pub fn create_note<'a>(
         note.into_diagnostic(self, Note)
     }
 
+    /// Lets the emitters write what they held back until the end of the compilation, see
+    /// `Emitter::finish`.
+    pub fn finish(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let inner = &mut *inner;
+        inner.emitter.finish();
+        for emitter in inner.warning_emitter.iter_mut().chain(&mut inner.log_emitter) {
+            emitter.finish();
+        }
+    }
+
     pub fn emit_artifact_notification(&self, path: &Path, artifact_type: &str) {
         self.inner.borrow_mut().emitter.emit_artifact_notification(path, artifact_type);
     }
 
-    pub fn emit_future_breakage_report(&self, diags: Vec<Diagnostic>) {
-        self.inner.borrow_mut().emitter.emit_future_breakage_report(diags)
+    pub fn emit_future_breakage_report(&self, mut diags: Vec<Diagnostic>) {
+        // The hooks may use the `DiagCtxt` themselves, so it must not be borrowed while they run.
+        let mut hooks = std::mem::take(&mut self.inner.borrow_mut().future_breakage_hooks);
+        for hook in &mut hooks {
+            hook(&mut diags);
+        }
+        let mut inner = self.inner.borrow_mut();
+        hooks.append(&mut inner.future_breakage_hooks);
+        inner.future_breakage_hooks = hooks;
+        if diags.is_empty() {
+            return;
+        }
+        inner.emitter.emit_future_breakage_report(diags)
     }
 
     pub fn emit_unused_externs(
@@ -1164,6 +1951,12 @@ pub fn emit_unused_externs(
         inner.emitter.emit_unused_externs(lint_level, unused_externs)
     }
 
+    /// Reports all `#[expect]` attributes of the crate whose lints were never emitted, including
+    /// those whose `unfulfilled_lint_expectations` lint is allowed, for tools that manage them.
+    pub fn emit_unfulfilled_expectations(&self, expectations: &[UnfulfilledExpectation]) {
+        self.inner.borrow_mut().emitter.emit_unfulfilled_expectations(expectations)
+    }
+
     pub fn update_unstable_expectation_id(
         &self,
         unstable_to_stable: &FxIndexMap<LintExpectationId, LintExpectationId>,
@@ -1206,6 +1999,7 @@ pub fn steal_fulfilled_expectation_ids(&self) -> FxHashSet<LintExpectationId> {
     }
 
     pub fn flush_delayed(&self) {
+        self.flush_pending();
         self.inner.borrow_mut().flush_delayed(DelayedBugKind::Normal);
     }
 }
@@ -1223,7 +2017,7 @@ fn emit_stashed_diagnostics(&mut self) -> Option<ErrorGuaranteed> {
         for diag in diags {
             // Decrement the count tracking the stash; emitting will increment it.
             if diag.is_error() {
-                if diag.is_lint.is_some() {
+                if diag.lint().is_some() {
                     self.lint_err_count -= 1;
                 } else {
                     self.err_count -= 1;
@@ -1242,7 +2036,11 @@ fn emit_stashed_diagnostics(&mut self) -> Option<ErrorGuaranteed> {
         reported
     }
 
+    #[track_caller]
     fn emit_diagnostic(&mut self, mut diagnostic: Diagnostic) -> Option<ErrorGuaranteed> {
+        let prof = self.prof.clone();
+        let _timer = prof.generic_activity("emit_diagnostic");
+
         // The `LintExpectationId` can be stable or unstable depending on when it was created.
         // Diagnostics created before the definition of `HirId`s are unstable and can not yet
         // be stored. Instead, they are buffered until the `LintExpectationId` is replaced by
@@ -1252,6 +2050,25 @@ fn emit_diagnostic(&mut self, mut diagnostic: Diagnostic) -> Option<ErrorGuarant
             return None;
         }
 
+        // Overlapping parts can't be applied or rendered. They are common with spans coming from
+        // macro expansions, so rather than failing to emit the diagnostic, they are dropped and
+        // reported as a bug.
+        if let Some(suggestions) = diagnostic.suggestions_mut() {
+            let conflicts = suggestions
+                .iter_mut()
+                .flat_map(|suggestion| &mut suggestion.substitutions)
+                .flat_map(|substitution| substitution.remove_overlapping_parts())
+                .collect::<Vec<_>>();
+            if !conflicts.is_empty() {
+                let mut bug = Diagnostic::new(
+                    DelayedBug(DelayedBugKind::Normal),
+                    "suggestion must not have overlapping parts",
+                );
+                bug.span(conflicts);
+                self.emit_diagnostic(bug);
+            }
+        }
+
         // FIXME(eddyb) this should check for `has_errors` and stop pushing
         // once *any* errors were emitted (and truncate `span_delayed_bugs`
         // when an error is first emitted, also), but maybe there's a case
@@ -1261,15 +2078,17 @@ fn emit_diagnostic(&mut self, mut diagnostic: Diagnostic) -> Option<ErrorGuarant
                 diagnostic.level = Error;
             }
             DelayedBug(DelayedBugKind::Normal) => {
-                let backtrace = std::backtrace::Backtrace::capture();
+                let backtrace = self.delayed_bug_backtrace();
                 self.span_delayed_bugs
                     .push(DelayedDiagnostic::with_backtrace(diagnostic.clone(), backtrace));
 
-                #[allow(deprecated)]
-                return Some(ErrorGuaranteed::unchecked_claim_error_was_emitted());
+                let guar = self.hand_out_guarantee();
+                #[cfg(debug_assertions)]
+                let guar = claimed_for(guar, &diagnostic);
+                return Some(guar);
             }
             DelayedBug(DelayedBugKind::GoodPath) => {
-                let backtrace = std::backtrace::Backtrace::capture();
+                let backtrace = self.delayed_bug_backtrace();
                 self.good_path_delayed_bugs
                     .push(DelayedDiagnostic::with_backtrace(diagnostic.clone(), backtrace));
 
@@ -1289,7 +2108,17 @@ fn emit_diagnostic(&mut self, mut diagnostic: Diagnostic) -> Option<ErrorGuarant
             // but they still need to be constructed and stashed below,
             // so they'll trigger the good-path bug check.
             self.suppressed_expected_diag = true;
-            self.future_breakage_diagnostics.push(diagnostic.clone());
+            let mut diagnostic = diagnostic.clone();
+            {
+                let _timer = prof.generic_activity("translate_diagnostic");
+                diagnostic.render_snippets(self.emitter.source());
+                diagnostic.translate_scoped_messages(&*self.emitter);
+            }
+            if self.flags.stream_future_incompat {
+                self.emitter.emit_future_breakage_diagnostic(&diagnostic);
+            }
+            diagnostic.intern_strings(&mut self.interner);
+            self.future_breakage_diagnostics.push(diagnostic);
         }
 
         if let Some(expectation_id) = diagnostic.level.get_expectation_id() {
@@ -1297,7 +2126,7 @@ fn emit_diagnostic(&mut self, mut diagnostic: Diagnostic) -> Option<ErrorGuarant
             self.fulfilled_expectations.insert(expectation_id.normalize());
         }
 
-        if diagnostic.level == Warning && !self.flags.can_emit_warnings {
+        if matches!(diagnostic.level, Warning | PerfNote) && !self.flags.can_emit_warnings {
             if diagnostic.has_future_breakage() {
                 (*TRACK_DIAGNOSTIC)(diagnostic, &mut |_| {});
             }
@@ -1309,24 +2138,99 @@ fn emit_diagnostic(&mut self, mut diagnostic: Diagnostic) -> Option<ErrorGuarant
             return None;
         }
 
+        // Lints are hidden by the baseline and the changed lines filter even if they are denied,
+        // so that they can be used to adopt stricter lint levels. Other errors are always shown,
+        // as the compilation would then fail without saying why.
+        if diagnostic.lint().is_some() || !diagnostic.is_error() {
+            let sm = self.emitter.source_map().map(|sm| &**sm);
+            if !self.baseline.is_empty()
+                && self.baseline.contains(&fingerprint::fingerprint(&diagnostic, sm))
+            {
+                self.baseline_suppressed_count += 1;
+                (*TRACK_DIAGNOSTIC)(diagnostic, &mut |_| {});
+                return None;
+            }
+            if let (Some(changed_lines), Some(sm), Some(span)) =
+                (&self.changed_lines, sm, diagnostic.span.primary_span())
+                && !changed_lines.includes(sm, span)
+            {
+                self.unchanged_lines_count += 1;
+                (*TRACK_DIAGNOSTIC)(diagnostic, &mut |_| {});
+                return None;
+            }
+        }
+
+        if !self.span_filters.is_empty()
+            && !diagnostic.is_error()
+            && let (Some(sm), Some(span)) =
+                (self.emitter.source_map(), diagnostic.span.primary_span())
+            && let Some(path) = filter::local_path(sm, span)
+            && self.span_filters.iter().any(|filter| filter(&path))
+        {
+            self.span_filtered_count += 1;
+            (*TRACK_DIAGNOSTIC)(diagnostic, &mut |_| {});
+            return None;
+        }
+
+        if self.flags.verify_suggestions {
+            self.verify_suggestions(&diagnostic);
+        }
+
+        // Claimed outside of the closure, so that it records the caller in debug builds.
+        #[allow(deprecated)]
+        let guar = ErrorGuaranteed::unchecked_claim_error_was_emitted();
         let mut guaranteed = None;
         (*TRACK_DIAGNOSTIC)(diagnostic, &mut |mut diagnostic| {
+            if self.flags.track_diagnostics
+                && let Some(hook) = &self.query_stack_hook
+            {
+                diagnostic.extras_mut().query_stack = hook();
+            }
+            // Lints always have their extras allocated already.
+            if diagnostic.lint().is_some() {
+                let extras = diagnostic.extras_mut();
+                if let Some(lint) = &mut extras.is_lint
+                    && let Some(groups) = self.lint_groups.get(&lint.name)
+                {
+                    lint.groups = groups.clone();
+                }
+                if extras.tool.is_none()
+                    && let Some(lint) = &extras.is_lint
+                    && let Some((tool, _)) = lint.name.split_once("::")
+                    && let Some(prefix) = self.tool_display_prefixes.get(tool)
+                {
+                    let display_prefix = prefix.clone();
+                    extras.tool = Some(DiagnosticTool { name: tool.to_string(), display_prefix });
+                }
+            }
             if let Some(ref code) = diagnostic.code {
                 self.emitted_diagnostic_codes.insert(code.clone());
             }
 
-            let already_emitted = {
+            let diagnostic_hash = {
                 let mut hasher = StableHasher::new();
                 diagnostic.hash(&mut hasher);
-                let diagnostic_hash = hasher.finish();
-                !self.emitted_diagnostics.insert(diagnostic_hash)
+                hasher.finish()
             };
+            let already_emitted = !self.emitted_diagnostics.insert(diagnostic_hash);
 
             // Only emit the diagnostic if we've been asked to deduplicate or
-            // haven't already emitted an equivalent diagnostic.
-            if !(self.flags.deduplicate_diagnostics && already_emitted) {
+            // haven't already emitted an equivalent diagnostic. Errors over the
+            // limit are still counted below, so that the compilation fails.
+            let error_limit = self.flags.error_limit.map_or(usize::MAX, NonZeroUsize::get);
+            if diagnostic.level == Error && self.deduplicated_err_count >= error_limit {
+                self.errors_over_limit += 1;
+            } else if !(self.flags.deduplicate_diagnostics && already_emitted) {
                 debug!(?diagnostic);
                 debug!(?self.emitted_diagnostics);
+                // Only the diagnostics that are shown are counted and observed, not the
+                // duplicates and the errors over the limit.
+                if diagnostic.level != FailureNote {
+                    self.counts.add(&diagnostic);
+                }
+                for observer in &self.observers {
+                    observer(&diagnostic);
+                }
                 let already_emitted_sub = |sub: &mut SubDiagnostic| {
                     debug!(?sub);
                     if sub.level != OnceNote && sub.level != OnceHelp {
@@ -1346,32 +2250,198 @@ fn emit_diagnostic(&mut self, mut diagnostic: Diagnostic) -> Option<ErrorGuarant
                     );
                 }
 
-                self.emitter.emit_diagnostic(&diagnostic);
+                {
+                    let _timer = prof.generic_activity("translate_diagnostic");
+                    diagnostic.render_snippets(self.emitter.source());
+                    diagnostic.translate_scoped_messages(&*self.emitter);
+                }
+                if let Some(recorded) = &mut self.recorded_diagnostics
+                    && !diagnostic.is_error()
+                    && diagnostic.level != FailureNote
+                {
+                    recorded.push(diagnostic.clone());
+                }
+                if (self.flags.deterministic_diagnostics
+                    || self.flags.group_diagnostics_by_file
+                    || self.flags.deduplicate_diagnostics)
+                    && !matches!(diagnostic.level, Fatal | Bug | FailureNote)
+                {
+                    if self.flags.deduplicate_diagnostics {
+                        self.duplicate_counts.insert(diagnostic_hash, 0);
+                    }
+                    let mut diagnostic = diagnostic.clone();
+                    diagnostic.intern_strings(&mut self.interner);
+                    self.ordered_diagnostics.push((diagnostic_hash, diagnostic));
+                } else {
+                    // Diagnostics that abort compilation and the notes printed at the end must
+                    // come after everything emitted before them.
+                    self.flush_ordered_diagnostics();
+                    let _timer = prof.generic_activity("render_diagnostic");
+                    self.write_diagnostic(&diagnostic);
+                }
                 if diagnostic.is_error() {
                     self.deduplicated_err_count += 1;
                 } else if matches!(diagnostic.level, ForceWarning(_) | Warning) {
                     self.deduplicated_warn_count += 1;
+                } else if diagnostic.level == PerfNote {
+                    self.deduplicated_perf_note_count += 1;
                 }
                 self.has_printed = true;
+            } else if let Some(duplicates) = self.duplicate_counts.get_mut(&diagnostic_hash) {
+                // Duplicates of a diagnostic that was already printed aren't counted anymore.
+                *duplicates += 1;
             }
             if diagnostic.is_error() {
-                if diagnostic.is_lint.is_some() {
+                if diagnostic.lint().is_some() {
                     self.lint_err_count += 1;
                 } else {
                     self.err_count += 1;
                 }
                 self.panic_if_treat_err_as_bug();
-
-                #[allow(deprecated)]
-                {
-                    guaranteed = Some(ErrorGuaranteed::unchecked_claim_error_was_emitted());
-                }
+                #[cfg(debug_assertions)]
+                let guar = claimed_for(guar, &diagnostic);
+                self.guarantees += 1;
+                guaranteed = Some(guar);
             }
         });
 
         guaranteed
     }
 
+    /// Captures the backtrace of a delayed bug that is about to be stored, unless
+    /// `-Z track-delayed-bugs` says otherwise.
+    fn delayed_bug_backtrace(&self) -> Backtrace {
+        let delayed = self.span_delayed_bugs.len() + self.good_path_delayed_bugs.len();
+        match self.flags.track_delayed_bugs {
+            DelayedBugBacktraces::Always => Backtrace::capture(),
+            DelayedBugBacktraces::First(n) if delayed < n => Backtrace::capture(),
+            DelayedBugBacktraces::First(_) | DelayedBugBacktraces::Never => Backtrace::disabled(),
+        }
+    }
+
+    /// Holds back a diagnostic emitted while the current thread has snapshots, see
+    /// `DiagCtxt::snapshot`.
+    #[track_caller]
+    fn hold_back_speculative(&mut self, diagnostic: Diagnostic) -> Option<ErrorGuaranteed> {
+        let is_error = diagnostic.is_error();
+        // Held back errors are counted like stashed ones, as they are emitted eventually.
+        if is_error {
+            if diagnostic.lint().is_some() {
+                self.lint_err_count += 1;
+            } else {
+                self.err_count += 1;
+            }
+        }
+        let guar = if is_error {
+            let guar = self.hand_out_guarantee();
+            #[cfg(debug_assertions)]
+            let guar = claimed_for(guar, &diagnostic);
+            Some(guar)
+        } else {
+            None
+        };
+        let speculation = self.speculations.get_mut(&thread::current().id()).unwrap();
+        speculation.diagnostics.push(diagnostic);
+        guar
+    }
+
+    /// Hands out an `ErrorGuaranteed`, after which the current snapshots can't be rolled back.
+    #[track_caller]
+    fn hand_out_guarantee(&mut self) -> ErrorGuaranteed {
+        self.guarantees += 1;
+        #[allow(deprecated)]
+        ErrorGuaranteed::unchecked_claim_error_was_emitted()
+    }
+
+    fn pop_snapshot(&mut self, snapshot: DiagCtxtSnapshot) -> SnapshotState {
+        assert_eq!(snapshot.thread, thread::current().id(), "snapshot taken by another thread");
+        let speculation = self.speculations.get_mut(&snapshot.thread).unwrap();
+        assert_eq!(
+            snapshot.depth,
+            speculation.snapshots.len(),
+            "snapshots must be committed or rolled back in reverse order",
+        );
+        speculation.snapshots.pop().unwrap()
+    }
+
+    /// Goes back to `state`, see `DiagCtxt::rollback`.
+    fn rollback(&mut self, state: SnapshotState) {
+        let speculation = self.speculations.get_mut(&thread::current().id()).unwrap();
+        speculation.diagnostics.truncate(state.held_back);
+        self.end_speculation();
+        self.good_path_delayed_bugs.truncate(state.good_path_delayed_bugs);
+        let stashed = state.stashed.min(self.stashed_diagnostics.len());
+        for (_, diag) in self.stashed_diagnostics.drain(stashed..) {
+            if diag.is_error() {
+                if diag.lint().is_some() {
+                    self.lint_err_count -= 1;
+                } else {
+                    self.err_count -= 1;
+                }
+            }
+        }
+    }
+
+    /// Emits the diagnostics held back on the current thread once it has no snapshots left.
+    fn end_speculation(&mut self) {
+        let thread = thread::current().id();
+        if self.speculations.get(&thread).is_some_and(|s| !s.snapshots.is_empty()) {
+            return;
+        }
+        if let Some(speculation) = self.speculations.remove(&thread) {
+            self.emit_held_back(speculation.diagnostics);
+        }
+    }
+
+    fn emit_held_back(&mut self, diagnostics: Vec<Diagnostic>) {
+        for diagnostic in diagnostics {
+            // Decrement the count tracking the held back error; emitting will increment it.
+            if diagnostic.is_error() {
+                if diagnostic.lint().is_some() {
+                    self.lint_err_count -= 1;
+                } else {
+                    self.err_count -= 1;
+                }
+            }
+            self.emit_diagnostic(diagnostic);
+        }
+    }
+
+    /// Delays a bug for every machine-applicable suggestion of `diagnostic` that produces code
+    /// that doesn't lex, see `-Zverify-suggestions`.
+    fn verify_suggestions(&mut self, diagnostic: &Diagnostic) {
+        let (Some(sm), Ok(suggestions)) =
+            (self.emitter.source_map().cloned(), diagnostic.suggestions())
+        else {
+            return;
+        };
+        for suggestion in suggestions {
+            for substitution in &suggestion.substitutions {
+                let machine_applicable = substitution.parts.iter().all(|part| {
+                    part.applicability.unwrap_or(suggestion.applicability)
+                        == Applicability::MachineApplicable
+                });
+                if !machine_applicable
+                    || substitution.parts.iter().any(|part| sm.is_valid_span(part.span).is_err())
+                {
+                    continue;
+                }
+                for substitution in substitution.split_by_file(&sm) {
+                    if let Err(problem) = verify::check_substitution(&sm, &substitution) {
+                        let mut bug = Diagnostic::new(
+                            DelayedBug(DelayedBugKind::Normal),
+                            format!("machine-applicable suggestion {problem}"),
+                        );
+                        bug.span(
+                            substitution.parts.iter().map(|part| part.span).collect::<Vec<_>>(),
+                        );
+                        self.emit_diagnostic(bug);
+                    }
+                }
+            }
+        }
+    }
+
     fn treat_err_as_bug(&self) -> bool {
         self.flags.treat_err_as_bug.is_some_and(|c| self.err_count + self.lint_err_count >= c.get())
     }
@@ -1391,6 +2461,73 @@ fn failure_note(&mut self, msg: impl Into<DiagnosticMessage>) {
         self.emit_diagnostic(Diagnostic::new(FailureNote, msg));
     }
 
+    /// Prints the diagnostics held back for [`DiagCtxtFlags::deterministic_diagnostics`], sorted
+    /// by their sort spans. Diagnostics with the same span are ordered by their stable hashes,
+    /// which don't depend on when they were emitted. With
+    /// [`DiagCtxtFlags::group_diagnostics_by_file`], each file gets a header.
+    fn flush_ordered_diagnostics(&mut self) {
+        let mut diagnostics = std::mem::take(&mut self.ordered_diagnostics);
+        // Sorting by span also groups the diagnostics by file, as each file has its own range of
+        // positions in the source map.
+        if self.flags.deterministic_diagnostics || self.flags.group_diagnostics_by_file {
+            diagnostics.sort_by_key(|(hash, diagnostic)| (diagnostic.sort_span, *hash));
+        }
+        // The groups of each output, the warning emitter's second.
+        let mut groups = [None, None];
+        for (hash, mut diagnostic) in diagnostics {
+            diagnostic.unintern_strings(&self.interner);
+            if let Some(duplicates) = self.duplicate_counts.remove(&hash)
+                && duplicates > 0
+            {
+                let suffix = format!(" (and {duplicates} duplicate{})", pluralize!(duplicates));
+                diagnostic.messages.push((DiagnosticMessage::from(suffix), Style::NoStyle));
+                diagnostic.extras_mut().duplicates = Some(duplicates);
+            }
+            let group = &mut groups[usize::from(self.is_warning_output(diagnostic.level))];
+            if self.flags.group_diagnostics_by_file
+                && let Some(sm) = self.emitter.source_map().cloned()
+                && !diagnostic.sort_span.is_dummy()
+            {
+                let file = sm.lookup_source_file(diagnostic.sort_span.lo());
+                if group.as_ref() != Some(&file.name) {
+                    let header = format!("{}:", sm.filename_for_diagnostics(&file.name));
+                    self.emitter_for(diagnostic.level).emit_group_header(&header);
+                    *group = Some(file.name.clone());
+                }
+            }
+            let _timer = self.prof.generic_activity("render_diagnostic");
+            self.write_diagnostic(&diagnostic);
+        }
+    }
+
+    /// Writes `diagnostic` with the emitter for its level, and to the log if there is one.
+    fn write_diagnostic(&mut self, diagnostic: &Diagnostic) {
+        self.emitter_for(diagnostic.level).emit_diagnostic(diagnostic);
+        if let Some(log_emitter) = &mut self.log_emitter {
+            log_emitter.emit_diagnostic(diagnostic);
+        }
+    }
+
+    /// Whether diagnostics of `level` are written by the warning emitter, see
+    /// `DiagCtxt::with_warning_emitter`.
+    fn is_warning_output(&self, level: Level) -> bool {
+        self.warning_emitter.is_some()
+            && matches!(
+                level,
+                ForceWarning(_) | Warning | PerfNote | Note | OnceNote | Help | OnceHelp
+            )
+    }
+
+    /// The emitter that writes diagnostics of `level`.
+    fn emitter_for(&mut self, level: Level) -> &mut DynEmitter {
+        if self.is_warning_output(level)
+            && let Some(warning_emitter) = &mut self.warning_emitter
+        {
+            return &mut **warning_emitter;
+        }
+        &mut *self.emitter
+    }
+
     fn flush_delayed(&mut self, kind: DelayedBugKind) {
         let (bugs, note1) = match kind {
             DelayedBugKind::Normal => (
@@ -1408,9 +2545,36 @@ fn flush_delayed(&mut self, kind: DelayedBugKind) {
             return;
         }
 
+        // Bugs delayed by the same code with the same message tend to be the same problem, so
+        // only the first of them is shown, with how many there are and where the others are.
+        let mut groups: FxIndexMap<_, (DelayedDiagnostic, usize, Vec<Span>)> =
+            FxIndexMap::default();
+        for bug in bugs {
+            let message = bug
+                .inner
+                .messages()
+                .iter()
+                .map(|(msg, _)| msg.as_str().map_or_else(|| format!("{msg:?}"), str::to_owned))
+                .collect::<String>();
+            let key = (message, bug.inner.emitted_at.clone());
+            if let Some((_, count, others)) = groups.get_mut(&key) {
+                *count += 1;
+                others.extend(bug.inner.span.primary_span().filter(|span| !span.is_dummy()));
+            } else {
+                groups.insert(key, (bug, 1, Vec::new()));
+            }
+        }
+
         // If backtraces are enabled, also print the query stack
         let backtrace = std::env::var_os("RUST_BACKTRACE").map_or(true, |x| &x != "0");
-        for (i, bug) in bugs.into_iter().enumerate() {
+        let mut reported = Vec::with_capacity(groups.len());
+        // The path of the ICE file may be a template with directories that don't exist yet.
+        if let Some(dir) = self.ice_file.as_ref().and_then(|file| file.parent()) {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        for (i, ((message, emitted_at), (bug, count, others))) in groups.into_iter().enumerate() {
+            reported.push((message, emitted_at));
+
             if let Some(file) = self.ice_file.as_ref()
                 && let Ok(mut out) = std::fs::File::options().create(true).append(true).open(file)
             {
@@ -1448,12 +2612,21 @@ fn flush_delayed(&mut self, kind: DelayedBugKind) {
                 });
             }
             bug.level = Bug;
+            if count > 1 {
+                bug.note(format!(
+                    "this bug was delayed {count} times with the same message from the same \
+                     location, only the first one is shown"
+                ));
+            }
+            if !others.is_empty() {
+                bug.span_note(others, "the other bugs were delayed here");
+            }
 
             self.emit_diagnostic(bug);
         }
 
         // Panic with `DelayedBugPanic` to avoid "unexpected panic" messages.
-        panic::panic_any(DelayedBugPanic);
+        panic::panic_any(DelayedBugPanic { bugs: reported });
     }
 
     fn panic_if_treat_err_as_bug(&self) {
@@ -1489,14 +2662,13 @@ fn decorate(mut self) -> Diagnostic {
                     note: self.note,
                 });
             }
-            // Avoid the needless newline when no backtrace has been captured,
-            // the display impl should just be a single line.
+            // When no backtrace has been captured, whether because of `RUST_BACKTRACE` or
+            // `-Z track-delayed-bugs`, only show where the bug was delayed, on a single line.
             _ => {
                 let inner = &self.inner;
                 self.inner.subdiagnostic(DelayedAtWithoutNewline {
                     span: inner.span.primary_span().unwrap_or(DUMMY_SP),
                     emitted_at: inner.emitted_at.clone(),
-                    note: self.note,
                 });
             }
         }
@@ -1546,6 +2718,13 @@ pub enum Level {
     /// Its `EmissionGuarantee` is `()`.
     Warning,
 
+    /// A hint about the performance of the code being compiled, from warning lints that are
+    /// declared with `perf_note`. It is counted apart from warnings, and `-D warnings` doesn't
+    /// turn it into an error unless `-Z perf-notes-as-warnings` makes such lints warn as usual.
+    ///
+    /// Its `EmissionGuarantee` is `()`.
+    PerfNote,
+
     /// A message giving additional context. Rare, because notes are more commonly attached to other
     /// diagnostics such as errors.
     ///
@@ -1600,6 +2779,9 @@ fn color(self) -> ColorSpec {
             ForceWarning(_) | Warning => {
                 spec.set_fg(Some(Color::Yellow)).set_intense(cfg!(windows));
             }
+            PerfNote => {
+                spec.set_fg(Some(Color::Magenta)).set_intense(true);
+            }
             Note | OnceNote => {
                 spec.set_fg(Some(Color::Green)).set_intense(true);
             }
@@ -1617,6 +2799,7 @@ pub fn to_str(self) -> &'static str {
             Bug | DelayedBug(_) => "error: internal compiler error",
             Fatal | Error => "error",
             ForceWarning(_) | Warning => "warning",
+            PerfNote => "perf-note",
             Note | OnceNote => "note",
             Help | OnceHelp => "help",
             FailureNote => "failure-note",
@@ -1645,7 +2828,10 @@ pub fn add_elided_lifetime_in_path_suggestion(
     incl_angl_brckt: bool,
     insertion_span: Span,
 ) {
-    diag.subdiagnostic(ExpectedLifetimeParameter { span: path_span, count: n });
+    diag.subdiagnostic_once(
+        (path_span, n),
+        ExpectedLifetimeParameter { span: path_span, count: n },
+    );
     if !source_map.is_span_accessible(insertion_span) {
         // Do not try to suggest anything if generated by a proc-macro.
         return;
@@ -1683,3 +2869,35 @@ pub enum TerminalUrl {
     Yes,
     Auto,
 }
+
+/// For which delayed bugs a backtrace is captured, to be shown if they are reported. Capturing
+/// them is expensive, and most delayed bugs are never reported.
+/// (rustc: see `-Z track-delayed-bugs`)
+#[derive(Clone, Copy, PartialEq, Hash, Debug, Default)]
+pub enum DelayedBugBacktraces {
+    /// No backtraces are captured, only where the bug was delayed is shown.
+    Never,
+    /// Backtraces are only captured for the given number of delayed bugs, the first ones.
+    First(usize),
+    /// A backtrace is captured for every delayed bug. Like for panics, this also depends on
+    /// `RUST_BACKTRACE` being set.
+    #[default]
+    Always,
+}
+
+/// How much of the macro backtrace of a diagnostic is shown.
+/// (rustc: see `-Z macro-backtrace`)
+#[derive(Clone, Copy, PartialEq, Hash, Debug, Default)]
+pub enum MacroBacktrace {
+    /// Only the outermost expansion is pointed at, with a note on how to see more.
+    #[default]
+    No,
+    /// The outermost and innermost expansions are shown, along with those of macros defined
+    /// in the current crate. The number of frames left out is noted.
+    Collapsed,
+    /// Only the given number of expansions is shown, starting with the outermost one. The number
+    /// of frames left out is noted.
+    Depth(usize),
+    /// Every expansion is shown.
+    Yes,
+}
@@ -0,0 +1,243 @@
+//! Encoding of diagnostics that can be decoded in a later session, for drivers that want to store
+//! diagnostics and emit them again, possibly through a different emitter.
+//!
+//! The `BytePos`s of a span are only meaningful within the `SourceMap` they come from, so spans
+//! are encoded as the name and hash of their file along with their offsets within it. When
+//! decoding, spans are mapped to the same file in the current `SourceMap`, which is loaded from
+//! disk if needed. Diagnostics pointing into files that have changed since are dropped, as their
+//! spans would point at the wrong code.
+
+use crate::Diagnostic;
+use rustc_serialize::opaque::{FileEncodeResult, FileEncoder, MemDecoder};
+use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
+use rustc_span::def_id::{CrateNum, DefId, DefIndex};
+use rustc_span::source_map::SourceMap;
+use rustc_span::{
+    AttrId, BytePos, ExpnId, FileName, RelativeBytePos, SourceFileHash, Span, SpanDecoder,
+    SpanEncoder, Symbol, SyntaxContext, DUMMY_SP,
+};
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+/// Written at the start of encoded diagnostics, to recognize data in a different format.
+const HEADER: &[u8] = b"rustc-diagnostics-1";
+/// Written at the end of encoded diagnostics, after the length of the data in between as a
+/// little-endian `u64`, to recognize data that was cut short.
+const FOOTER: &[u8] = b"rustc-diagnostics-end";
+
+/// Writes `diagnostics` to `path`, so that they can be read back with `decode_diagnostics`.
+pub fn encode_diagnostics(
+    path: &Path,
+    sm: &SourceMap,
+    diagnostics: &[Diagnostic],
+) -> FileEncodeResult {
+    let mut encoder = match FileEncoder::new(path) {
+        Ok(encoder) => encoder,
+        Err(err) => return Err((path.to_owned(), err)),
+    };
+    encoder.emit_raw_bytes(HEADER);
+    let mut encoder = DiagnosticEncoder { opaque: encoder, sm };
+    diagnostics.encode(&mut encoder);
+    let len = encoder.opaque.position() - HEADER.len();
+    encoder.opaque.write_array((len as u64).to_le_bytes());
+    encoder.opaque.emit_raw_bytes(FOOTER);
+    encoder.opaque.finish()
+}
+
+/// Reads diagnostics written by `encode_diagnostics`, mapping their spans to `sm` and skipping
+/// those that point into files that have changed. Returns `None` if `data` wasn't written by
+/// `encode_diagnostics`, or was cut short, like when the compiler was killed while writing it.
+pub fn decode_diagnostics(data: &[u8], sm: &SourceMap) -> Option<Vec<Diagnostic>> {
+    let data = data.strip_prefix(HEADER)?.strip_suffix(FOOTER)?;
+    let (data, len) = data.split_at(data.len().checked_sub(8)?);
+    if u64::from_le_bytes(len.try_into().unwrap()) != data.len() as u64 {
+        return None;
+    }
+    let mut decoder = DiagnosticDecoder { opaque: MemDecoder::new(data, 0), sm, stale: false };
+    let len = decoder.read_usize();
+    // Every diagnostic takes at least a byte, so a larger count can only come from corrupt data.
+    if len > decoder.opaque.remaining() {
+        return None;
+    }
+    let diagnostics = (0..len)
+        .filter_map(|_| {
+            decoder.stale = false;
+            let diagnostic = Diagnostic::decode(&mut decoder);
+            (!decoder.stale).then_some(diagnostic)
+        })
+        .collect();
+    Some(diagnostics)
+}
+
+struct DiagnosticEncoder<'a> {
+    opaque: FileEncoder,
+    sm: &'a SourceMap,
+}
+
+macro_rules! encoder_methods {
+    ($($name:ident($ty:ty);)*) => {
+        $(fn $name(&mut self, value: $ty) {
+            self.opaque.$name(value)
+        })*
+    }
+}
+
+impl Encoder for DiagnosticEncoder<'_> {
+    encoder_methods! {
+        emit_usize(usize);
+        emit_u128(u128);
+        emit_u64(u64);
+        emit_u32(u32);
+        emit_u16(u16);
+        emit_u8(u8);
+
+        emit_isize(isize);
+        emit_i128(i128);
+        emit_i64(i64);
+        emit_i32(i32);
+        emit_i16(i16);
+
+        emit_raw_bytes(&[u8]);
+    }
+}
+
+impl SpanEncoder for DiagnosticEncoder<'_> {
+    fn encode_span(&mut self, span: Span) {
+        let span = span.data();
+        if span.is_dummy() {
+            return self.emit_u8(0);
+        }
+        let file = self.sm.lookup_source_file(span.lo);
+        if !file.contains(span.hi) {
+            return self.emit_u8(0);
+        }
+        self.emit_u8(1);
+        file.name.encode(self);
+        file.src_hash.encode(self);
+        file.relative_position(span.lo).encode(self);
+        file.relative_position(span.hi).encode(self);
+    }
+
+    fn encode_symbol(&mut self, symbol: Symbol) {
+        self.emit_str(symbol.as_str());
+    }
+
+    fn encode_expn_id(&mut self, _expn_id: ExpnId) {
+        panic!("cannot encode `ExpnId` with `DiagnosticEncoder`");
+    }
+
+    fn encode_syntax_context(&mut self, _syntax_context: SyntaxContext) {
+        panic!("cannot encode `SyntaxContext` with `DiagnosticEncoder`");
+    }
+
+    fn encode_crate_num(&mut self, _crate_num: CrateNum) {
+        panic!("cannot encode `CrateNum` with `DiagnosticEncoder`");
+    }
+
+    fn encode_def_index(&mut self, _def_index: DefIndex) {
+        panic!("cannot encode `DefIndex` with `DiagnosticEncoder`");
+    }
+
+    fn encode_def_id(&mut self, _def_id: DefId) {
+        panic!("cannot encode `DefId` with `DiagnosticEncoder`");
+    }
+}
+
+struct DiagnosticDecoder<'a, 'sm> {
+    opaque: MemDecoder<'a>,
+    sm: &'sm SourceMap,
+    /// Whether a span of the diagnostic being decoded couldn't be mapped to `sm`.
+    stale: bool,
+}
+
+macro_rules! decoder_methods {
+    ($($name:ident -> $ty:ty;)*) => {
+        $(fn $name(&mut self) -> $ty {
+            self.opaque.$name()
+        })*
+    }
+}
+
+impl<'a> Decoder for DiagnosticDecoder<'a, '_> {
+    decoder_methods! {
+        read_usize -> usize;
+        read_u128 -> u128;
+        read_u64 -> u64;
+        read_u32 -> u32;
+        read_u16 -> u16;
+        read_u8 -> u8;
+
+        read_isize -> isize;
+        read_i128 -> i128;
+        read_i64 -> i64;
+        read_i32 -> i32;
+        read_i16 -> i16;
+    }
+
+    fn read_raw_bytes(&mut self, len: usize) -> &[u8] {
+        self.opaque.read_raw_bytes(len)
+    }
+
+    fn peek_byte(&self) -> u8 {
+        self.opaque.peek_byte()
+    }
+
+    fn position(&self) -> usize {
+        self.opaque.position()
+    }
+}
+
+impl SpanDecoder for DiagnosticDecoder<'_, '_> {
+    fn decode_span(&mut self) -> Span {
+        if self.read_u8() == 0 {
+            return DUMMY_SP;
+        }
+        let name = FileName::decode(self);
+        let hash = SourceFileHash::decode(self);
+        let lo = RelativeBytePos::decode(self);
+        let hi = RelativeBytePos::decode(self);
+        let file = self.sm.get_source_file(&name).or_else(|| match &name {
+            FileName::Real(path) => self.sm.load_file(path.local_path()?).ok(),
+            _ => None,
+        });
+        match file {
+            Some(file) if file.src_hash == hash && hi <= file.source_len => {
+                Span::with_root_ctxt(file.absolute_position(lo), file.absolute_position(hi))
+            }
+            _ => {
+                self.stale = true;
+                DUMMY_SP
+            }
+        }
+    }
+
+    fn decode_symbol(&mut self) -> Symbol {
+        Symbol::intern(self.read_str())
+    }
+
+    fn decode_expn_id(&mut self) -> ExpnId {
+        panic!("cannot decode `ExpnId` with `DiagnosticDecoder`");
+    }
+
+    fn decode_syntax_context(&mut self) -> SyntaxContext {
+        panic!("cannot decode `SyntaxContext` with `DiagnosticDecoder`");
+    }
+
+    fn decode_crate_num(&mut self) -> CrateNum {
+        panic!("cannot decode `CrateNum` with `DiagnosticDecoder`");
+    }
+
+    fn decode_def_index(&mut self) -> DefIndex {
+        panic!("cannot decode `DefIndex` with `DiagnosticDecoder`");
+    }
+
+    fn decode_def_id(&mut self) -> DefId {
+        panic!("cannot decode `DefId` with `DiagnosticDecoder`");
+    }
+
+    fn decode_attr_id(&mut self) -> AttrId {
+        panic!("cannot decode `AttrId` with `DiagnosticDecoder`");
+    }
+}
@@ -0,0 +1,82 @@
+use super::{decode_diagnostics, encode_diagnostics};
+use crate::{Diagnostic, Level};
+use rustc_span::source_map::{FilePathMapping, SourceMap};
+use rustc_span::{BytePos, Span, DUMMY_SP};
+use std::path::PathBuf;
+
+const SOURCE: &str = "fn main() {\n    let x = 1;\n}\n";
+
+fn source_map(files: &[(&str, &str)]) -> SourceMap {
+    let sm = SourceMap::new(FilePathMapping::empty());
+    for &(name, src) in files {
+        sm.new_source_file(PathBuf::from(name).into(), src.to_owned());
+    }
+    sm
+}
+
+/// Encodes `diagnostics` with the spans of `from` and decodes them with the spans of `to`.
+fn round_trip(
+    name: &str,
+    diagnostics: &[Diagnostic],
+    from: &SourceMap,
+    to: &SourceMap,
+) -> Vec<Diagnostic> {
+    let path = std::env::temp_dir().join(format!("rustc-{name}-{}.diags", std::process::id()));
+    encode_diagnostics(&path, from, diagnostics).unwrap();
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    decode_diagnostics(&data, to).unwrap()
+}
+
+#[test]
+fn spans_are_mapped_by_file() {
+    rustc_span::create_default_session_globals_then(|| {
+        let from = source_map(&[("main.rs", SOURCE)]);
+        let mut diag = Diagnostic::new(Level::Warning, "unused variable: `x`");
+        diag.span(Span::with_root_ctxt(BytePos(20), BytePos(21)));
+        diag.arg("name", "x");
+
+        // The same file, at a different position in the source map.
+        let to = source_map(&[("lib.rs", "mod main;\n"), ("main.rs", SOURCE)]);
+        let decoded = round_trip("mapped", &[diag.clone()], &from, &to);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].messages[0].0.as_str(), Some("unused variable: `x`"));
+        assert_eq!(decoded[0].args().collect::<Vec<_>>(), diag.args().collect::<Vec<_>>());
+        let span = decoded[0].span.primary_span().unwrap();
+        assert_eq!(to.span_to_snippet(span).unwrap(), "x");
+
+        // The file has changed since the diagnostic was encoded, so the diagnostic is dropped, but
+        // dummy spans don't point into any file.
+        let changed = source_map(&[("main.rs", "fn main() {}\n")]);
+        let mut dummy = Diagnostic::new(Level::Warning, "unused crate");
+        dummy.span(DUMMY_SP);
+        let decoded = round_trip("changed", &[diag, dummy], &from, &changed);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].messages[0].0.as_str(), Some("unused crate"));
+    })
+}
+
+#[test]
+fn foreign_data() {
+    let sm = source_map(&[]);
+    assert!(decode_diagnostics(b"not diagnostics", &sm).is_none());
+}
+
+#[test]
+fn truncated_data() {
+    rustc_span::create_default_session_globals_then(|| {
+        let sm = source_map(&[("main.rs", SOURCE)]);
+        let mut diag = Diagnostic::new(Level::Warning, "unused variable: `x`");
+        diag.span(Span::with_root_ctxt(BytePos(20), BytePos(21)));
+        let path =
+            std::env::temp_dir().join(format!("rustc-truncated-{}.diags", std::process::id()));
+        encode_diagnostics(&path, &sm, &[diag]).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(decode_diagnostics(&data, &sm).unwrap().len(), 1);
+        for len in 0..data.len() {
+            assert!(decode_diagnostics(&data[..len], &sm).is_none(), "truncated to {len} bytes");
+        }
+    })
+}
@@ -3,14 +3,65 @@
 #[derive(Debug)]
 pub struct InvalidErrorCode;
 
+/// The explanations of error codes, for `--explain` and the `explanation` of codes in the JSON
+/// output.
+///
+/// Besides the `EXXXX` codes of rustc, drivers can register codes of their own, in a namespace
+/// of the tool, like `CLIPPY0042` or `MIRI0007`.
 #[derive(Clone)]
 pub struct Registry {
     long_descriptions: FxHashMap<&'static str, &'static str>,
+    /// The namespaces registered by tools, see `Registry::register_namespace`.
+    namespaces: Vec<ErrorCodeNamespace>,
+}
+
+/// Error codes of a tool that all start with the same prefix, followed by four digits.
+#[derive(Clone, Debug)]
+struct ErrorCodeNamespace {
+    prefix: &'static str,
+    /// The command that explains the codes of the namespace, like `cargo clippy --explain`.
+    explain_command: &'static str,
 }
 
 impl Registry {
     pub fn new(long_descriptions: &[(&'static str, &'static str)]) -> Registry {
-        Registry { long_descriptions: long_descriptions.iter().copied().collect() }
+        Registry {
+            long_descriptions: long_descriptions.iter().copied().collect(),
+            namespaces: Vec::new(),
+        }
+    }
+
+    /// Registers the error codes of a tool, which all start with `prefix` followed by four
+    /// digits, like `CLIPPY0042` for the prefix `CLIPPY`. `explain_command` is the command that
+    /// diagnostics suggest for learning more about one of the codes, like
+    /// `cargo clippy --explain`, which can use this registry to look the code up.
+    ///
+    /// Panics if a code isn't in the namespace, or if the prefix overlaps with the prefix of a
+    /// namespace that is already registered.
+    pub fn register_namespace(
+        &mut self,
+        prefix: &'static str,
+        explain_command: &'static str,
+        long_descriptions: &[(&'static str, &'static str)],
+    ) -> &mut Registry {
+        assert!(
+            prefix.len() > 1
+                && prefix.bytes().all(|b| b.is_ascii_uppercase())
+                && self.namespaces.iter().all(|namespace| {
+                    !namespace.prefix.starts_with(prefix) && !prefix.starts_with(namespace.prefix)
+                }),
+            "invalid or overlapping error code prefix `{prefix}`",
+        );
+        for &(code, description) in long_descriptions {
+            assert!(
+                code.strip_prefix(prefix)
+                    .is_some_and(|number| number.bytes().all(|b| b.is_ascii_digit())),
+                "error code `{code}` isn't in the namespace `{prefix}`",
+            );
+            self.long_descriptions.insert(code, description);
+        }
+        self.namespaces.push(ErrorCodeNamespace { prefix, explain_command });
+        self
     }
 
     /// Returns `InvalidErrorCode` if the code requested does not exist in the
@@ -18,4 +69,34 @@ pub fn new(long_descriptions: &[(&'static str, &'static str)]) -> Registry {
     pub fn try_find_description(&self, code: &str) -> Result<&'static str, InvalidErrorCode> {
         self.long_descriptions.get(code).copied().ok_or(InvalidErrorCode)
     }
+
+    /// Turns a code as it may be given to `--explain` into the form it is registered as: the
+    /// prefix is uppercased and the number padded to four digits, so `e382` and `382` are
+    /// `E0382` and `clippy42` is `CLIPPY0042`. A code without a prefix is one of rustc's.
+    ///
+    /// Returns `None` if the code has no number, or if its prefix is neither rustc's `E` nor the
+    /// prefix of a registered namespace.
+    pub fn normalize_code(&self, code: &str) -> Option<String> {
+        let code = code.to_ascii_uppercase();
+        let digits = code.len() - code.bytes().rev().take_while(u8::is_ascii_digit).count();
+        let (prefix, number) = code.split_at(digits);
+        let prefix = if prefix.is_empty() { "E" } else { prefix };
+        if number.is_empty()
+            || (prefix != "E"
+                && !self.namespaces.iter().any(|namespace| namespace.prefix == prefix))
+        {
+            return None;
+        }
+        Some(format!("{prefix}{number:0>4}"))
+    }
+
+    /// The command that explains `code`, `rustc --explain` unless the code is in the namespace
+    /// of a tool.
+    pub fn explain_command(&self, code: &str) -> &'static str {
+        self.namespace(code).map_or("rustc --explain", |namespace| namespace.explain_command)
+    }
+
+    fn namespace(&self, code: &str) -> Option<&ErrorCodeNamespace> {
+        self.namespaces.iter().find(|namespace| code.starts_with(namespace.prefix))
+    }
 }
@@ -0,0 +1,44 @@
+//! Access of emitters to the source code that diagnostics point at, see `SourceProvider`.
+
+use rustc_data_structures::sync::{DynSend, DynSync};
+use rustc_span::source_map::SourceMap;
+use rustc_span::{SourceFile, Span};
+use std::borrow::Cow;
+
+/// Where emitters get the source code and file names that they show from. The positions of spans
+/// are still looked up in the `SourceMap`, but the text of its files may come from elsewhere, so
+/// that embedders can render diagnostics against in-memory or virtual sources, like the input of
+/// a REPL, generated code, or files on another machine that the source map has no source for.
+///
+/// `SourceMap` itself is the provider that is used unless an emitter is given another one.
+pub trait SourceProvider: DynSend + DynSync {
+    /// Whether the source code of `file` is available, which lines and snippets of it require.
+    fn has_source(&self, file: &SourceFile) -> bool;
+
+    /// The line of `file` at `line_index` (0-indexed), without its line terminator.
+    fn line<'a>(&'a self, file: &'a SourceFile, line_index: usize) -> Option<Cow<'a, str>>;
+
+    /// The code that `span` points to.
+    fn snippet(&self, span: Span) -> Option<String>;
+
+    /// The name of `file` as shown in diagnostics.
+    fn display_name(&self, file: &SourceFile) -> String;
+}
+
+impl SourceProvider for SourceMap {
+    fn has_source(&self, file: &SourceFile) -> bool {
+        self.ensure_source_file_source_present(file)
+    }
+
+    fn line<'a>(&'a self, file: &'a SourceFile, line_index: usize) -> Option<Cow<'a, str>> {
+        file.get_line(line_index)
+    }
+
+    fn snippet(&self, span: Span) -> Option<String> {
+        self.span_to_snippet(span).ok()
+    }
+
+    fn display_name(&self, file: &SourceFile) -> String {
+        self.filename_for_diagnostics(&file.name).to_string()
+    }
+}
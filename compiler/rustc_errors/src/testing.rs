@@ -0,0 +1,203 @@
+//! Helpers for testing the diagnostics of drivers and lints outside of the compiler's own test
+//! suite: emitters whose output is the same on every machine, helpers to normalize the parts of
+//! the output that they can't, and a check of which diagnostics were emitted.
+
+use crate::emitter::{
+    self, CaptureEmitter, CapturedDiagnostics, ColorConfig, HumanEmitter, HumanReadableErrorType,
+};
+use crate::json::JsonEmitter;
+use crate::translation::{to_fluent_args, Translate};
+use crate::{DiagCtxt, Diagnostic, LazyFallbackBundle, Level, MacroBacktrace, TerminalUrl};
+use rustc_data_structures::sync::Lrc;
+use rustc_span::source_map::SourceMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use termcolor::{ColorSpec, WriteColor};
+
+#[cfg(test)]
+mod tests;
+
+/// The output of the emitters of this module, which can be taken while they are still in use.
+#[derive(Clone, Default)]
+pub struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    /// Takes the output written since the last call.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+        String::from_utf8(bytes).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl WriteColor for Output {
+    fn supports_color(&self) -> bool {
+        false
+    }
+
+    fn set_color(&mut self, _spec: &ColorSpec) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A human-readable emitter with the settings of UI tests: no colors, line numbers replaced by
+/// `LL` and no wrapping that depends on the terminal. The other parts that differ between
+/// machines are normalized as with `-Znormalize-diagnostics`.
+pub fn human_emitter(
+    sm: Lrc<SourceMap>,
+    fallback_bundle: LazyFallbackBundle,
+) -> (HumanEmitter, Output) {
+    let output = Output::default();
+    let emitter = HumanEmitter::new(Box::new(output.clone()), fallback_bundle)
+        .sm(Some(sm))
+        .ui_testing(true)
+        .normalize_output(true);
+    (emitter, output)
+}
+
+/// A JSON emitter with the same settings as `human_emitter` for the rendered diagnostics. Each
+/// diagnostic is written on its own line.
+pub fn json_emitter(
+    sm: Lrc<SourceMap>,
+    fallback_bundle: LazyFallbackBundle,
+) -> (JsonEmitter, Output) {
+    let output = Output::default();
+    let emitter = JsonEmitter::new(
+        Box::new(output.clone()),
+        None,
+        sm,
+        None,
+        fallback_bundle,
+        false,
+        HumanReadableErrorType::Default(ColorConfig::Never),
+        None,
+        MacroBacktrace::No,
+        false,
+        TerminalUrl::No,
+    )
+    .ui_testing(true)
+    .normalize_output(true);
+    (emitter, output)
+}
+
+/// Runs `f` with a `DiagCtxt` whose diagnostics are kept by a `CaptureEmitter`, in fresh session
+/// globals and with the fallback bundle of the compiler's own messages.
+pub fn with_capture_dcx<R>(
+    sm: Option<Lrc<SourceMap>>,
+    f: impl FnOnce(DiagCtxt, CapturedDiagnostics) -> R,
+) -> R {
+    rustc_span::create_default_session_globals_then(|| {
+        let (emitter, captured) = CaptureEmitter::new(sm, None, fallback_bundle());
+        f(DiagCtxt::with_custom_emitter(emitter), captured)
+    })
+}
+
+/// The fallback bundle of the compiler's own messages, for the emitters of this module.
+pub fn fallback_bundle() -> LazyFallbackBundle {
+    crate::fallback_fluent_bundle(vec![crate::DEFAULT_LOCALE_RESOURCE], false)
+}
+
+/// Replaces `path` in `output` by `placeholder`, like UI tests replace the directory of a test by
+/// `$DIR`. On Windows, the path is also replaced if it is written with forward slashes.
+pub fn normalize_path(output: &str, path: &Path, placeholder: &str) -> String {
+    let path = path.display().to_string();
+    let normalized = output.replace(&path, placeholder);
+    if cfg!(windows) {
+        normalized.replace(&path.replace('\\', "/"), placeholder)
+    } else {
+        normalized
+    }
+}
+
+/// Replaces the hashes in symbol names by `[HASH]`, as `-Znormalize-diagnostics` does.
+pub fn normalize_symbol_hashes(output: &str) -> String {
+    emitter::normalize_symbol_hashes(output).into_owned()
+}
+
+/// A diagnostic that a test expects, which is matched like the `//~ ERROR` annotations of UI
+/// tests: by its level, a part of its message and, if given, the line of its primary span.
+#[derive(Clone, Debug)]
+pub struct ExpectedDiagnostic {
+    pub level: Level,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+impl ExpectedDiagnostic {
+    pub fn new(level: Level, message: impl Into<String>) -> ExpectedDiagnostic {
+        ExpectedDiagnostic { level, message: message.into(), line: None }
+    }
+
+    /// Only matches diagnostics whose primary span starts on `line`, which is 1-based.
+    pub fn at_line(self, line: usize) -> ExpectedDiagnostic {
+        ExpectedDiagnostic { line: Some(line), ..self }
+    }
+
+    fn matches(&self, level: Level, message: &str, line: Option<usize>) -> bool {
+        self.level.to_str() == level.to_str()
+            && message.contains(&self.message)
+            && (self.line.is_none() || self.line == line)
+    }
+}
+
+/// Checks that `diagnostics`, such as those taken from a `CaptureEmitter`, are the `expected`
+/// ones, in any order, and panics with the unexpected and the missing diagnostics otherwise. The
+/// messages are translated with `translator` and the lines of the spans looked up in `sm`.
+pub fn assert_diagnostics(
+    translator: &impl Translate,
+    sm: Option<&SourceMap>,
+    diagnostics: &[Diagnostic],
+    expected: &[ExpectedDiagnostic],
+) {
+    let mut missing = expected.iter().collect::<Vec<_>>();
+    let mut unexpected = Vec::new();
+    for diag in diagnostics {
+        let args = to_fluent_args(diag.args());
+        let message = translator.translate_messages(&diag.messages, &args);
+        let line = match (sm, diag.span.primary_span()) {
+            (Some(sm), Some(span)) if !span.is_dummy() => Some(sm.lookup_char_pos(span.lo()).line),
+            _ => None,
+        };
+        match missing.iter().position(|expected| expected.matches(diag.level(), &message, line)) {
+            Some(i) => {
+                missing.remove(i);
+            }
+            None => unexpected.push(describe(diag.level(), &message, line)),
+        }
+    }
+    if missing.is_empty() && unexpected.is_empty() {
+        return;
+    }
+
+    let mut report = String::new();
+    for diag in unexpected {
+        writeln!(report, "unexpected {diag}").unwrap();
+    }
+    for expected in missing {
+        writeln!(report, "missing {}", describe(expected.level, &expected.message, expected.line))
+            .unwrap();
+    }
+    panic!("the emitted diagnostics aren't the expected ones:\n{report}");
+}
+
+fn describe(level: Level, message: &str, line: Option<usize>) -> String {
+    match line {
+        Some(line) => format!("{} on line {line}: {message}", level.to_str()),
+        None => format!("{}: {message}", level.to_str()),
+    }
+}
@@ -0,0 +1,90 @@
+use super::*;
+
+use crate::emitter::CaptureEmitter;
+use crate::DiagCtxt;
+use rustc_span::source_map::FilePathMapping;
+use rustc_span::{BytePos, Span};
+
+fn source_map() -> Lrc<SourceMap> {
+    let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    sm.new_source_file(Path::new("test.rs").to_owned().into(), "fn main() {}\n".to_owned());
+    sm
+}
+
+fn with_source_map(f: impl FnOnce(Lrc<SourceMap>, LazyFallbackBundle)) {
+    rustc_span::create_default_session_globals_then(|| f(source_map(), fallback_bundle()))
+}
+
+#[test]
+fn human_output() {
+    with_source_map(|sm, fallback_bundle| {
+        let (emitter, output) = human_emitter(sm, fallback_bundle);
+        let dcx = DiagCtxt::with_custom_emitter(emitter);
+        dcx.span_err(Span::with_root_ctxt(BytePos(3), BytePos(7)), "oops");
+        assert_eq!(
+            output.take(),
+            "error: oops\n  --> test.rs:1:4\n   |\nLL | fn main() {}\n   |    ^^^^\n\n"
+        );
+        assert_eq!(output.take(), "");
+    })
+}
+
+#[test]
+fn json_output() {
+    with_source_map(|sm, fallback_bundle| {
+        let (emitter, output) = json_emitter(sm, fallback_bundle);
+        let dcx = DiagCtxt::with_custom_emitter(emitter);
+        dcx.span_warn(Span::with_root_ctxt(BytePos(3), BytePos(7)), "first");
+        dcx.span_warn(Span::with_root_ctxt(BytePos(3), BytePos(7)), "second");
+        let output = output.take();
+        let messages = output
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["message"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["first", "second"]);
+    })
+}
+
+#[test]
+fn normalize() {
+    let dir = Path::new("/home/user/project");
+    assert_eq!(
+        normalize_path("--> /home/user/project/src/main.rs:1:4", dir, "$DIR"),
+        "--> $DIR/src/main.rs:1:4"
+    );
+    assert_eq!(normalize_symbol_hashes("`foo::h0123456789abcdef`"), "`foo::h[HASH]`");
+}
+
+#[test]
+fn expected_diagnostics() {
+    let sm = source_map();
+    with_capture_dcx(Some(sm.clone()), |dcx, captured| {
+        dcx.span_err(Span::with_root_ctxt(BytePos(3), BytePos(7)), "cannot find `main`");
+        dcx.struct_warn("unused import").emit();
+        let diagnostics = captured.drain();
+
+        let (translator, _) = CaptureEmitter::new(None, None, fallback_bundle());
+        assert_diagnostics(
+            &translator,
+            Some(&sm),
+            &diagnostics,
+            &[
+                ExpectedDiagnostic::new(Level::Warning, "unused"),
+                ExpectedDiagnostic::new(Level::Error, "cannot find").at_line(1),
+            ],
+        );
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            assert_diagnostics(
+                &translator,
+                Some(&sm),
+                &diagnostics,
+                &[ExpectedDiagnostic::new(Level::Error, "cannot find").at_line(2)],
+            )
+        }));
+        let report = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(report.contains("unexpected error on line 1: cannot find `main`"));
+        assert!(report.contains("unexpected warning: unused import"));
+        assert!(report.contains("missing error on line 2: cannot find"));
+    })
+}
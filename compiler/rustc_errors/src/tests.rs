@@ -1,11 +1,33 @@
+use crate::emitter::CaptureEmitter;
 use crate::error::{TranslateError, TranslateErrorKind};
 use crate::fluent_bundle::*;
-use crate::translation::Translate;
-use crate::FluentBundle;
-use rustc_data_structures::sync::{IntoDynSyncSend, Lrc};
+use crate::interner::DiagnosticInterner;
+use crate::registry::Registry;
+use crate::testing::{fallback_bundle, with_capture_dcx};
+use crate::translation::{span_label_args, Translate};
+use crate::{
+    AddToDiagnostic, AsDiagnosticArg, ChangedLines, DiagCtxt, DiagCtxtFlags, Diagnostic,
+    DiagnosticArgList, DiagnosticArgSnippet, DiagnosticArgValue, DiagnosticBatch, DiagnosticBuilder,
+    DiagnosticSymbolList, FluentBundle, IntoDiagnostic, IntoDiagnosticArg, Level,
+    ManyLabelsManySpans, MultiSpan, PathGlob, SimilarName, StashKey, StashMergePolicy,
+    SubdiagnosticMessage, SubdiagnosticPriority, Substitution, SubstitutionPart,
+};
+use rustc_data_structures::sync::Lrc;
 use rustc_error_messages::fluent_bundle::resolver::errors::{ReferenceKind, ResolverError};
-use rustc_error_messages::langid;
+use rustc_error_messages::{langid, LanguageIdentifier};
 use rustc_error_messages::DiagnosticMessage;
+use rustc_lint_defs::Applicability;
+use rustc_span::source_map::{FilePathMapping, SourceMap};
+use rustc_span::symbol::kw;
+use rustc_span::{BytePos, SourceFile, Span, Symbol, DUMMY_SP};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 struct Dummy {
     bundle: FluentBundle,
@@ -22,20 +44,13 @@ fn fallback_fluent_bundle(&self) -> &FluentBundle {
 }
 
 fn make_dummy(ftl: &'static str) -> Dummy {
-    let resource = FluentResource::try_new(ftl.into()).expect("Failed to parse an FTL string.");
-
-    let langid_en = langid!("en-US");
-
-    #[cfg(parallel_compiler)]
-    let mut bundle: FluentBundle =
-        IntoDynSyncSend(crate::fluent_bundle::bundle::FluentBundle::new_concurrent(vec![
-            langid_en,
-        ]));
+    make_dummy_in(langid!("en-US"), ftl)
+}
 
-    #[cfg(not(parallel_compiler))]
-    let mut bundle: FluentBundle =
-        IntoDynSyncSend(crate::fluent_bundle::bundle::FluentBundle::new(vec![langid_en]));
+fn make_dummy_in(lang: LanguageIdentifier, ftl: &'static str) -> Dummy {
+    let resource = FluentResource::try_new(ftl.into()).expect("Failed to parse an FTL string.");
 
+    let mut bundle = FluentBundle::new(vec![lang]);
     bundle.add_resource(resource).expect("Failed to add FTL resources to the bundle.");
 
     Dummy { bundle }
@@ -194,3 +209,1155 @@ fn misformed_fluent() {
         );
     }
 }
+
+#[test]
+fn overlapping_substitution_parts() {
+    rustc_span::create_default_session_globals_then(|| {
+        let span = |lo, hi| Span::with_root_ctxt(BytePos(lo), BytePos(hi));
+        let part = |lo, hi, snippet: &str| SubstitutionPart {
+            span: span(lo, hi),
+            snippet: snippet.to_string(),
+            applicability: None,
+        };
+        let mut substitution = Substitution {
+            parts: vec![part(8, 12, "b"), part(0, 4, "a"), part(0, 4, "a"), part(2, 6, "c")],
+        };
+
+        let conflicts = substitution.remove_overlapping_parts();
+
+        assert_eq!(substitution.parts, vec![part(0, 4, "a"), part(8, 12, "b")]);
+        assert_eq!(conflicts, vec![span(0, 4), span(2, 6)]);
+    });
+}
+
+#[test]
+fn suggest_similar_name() {
+    rustc_span::create_default_session_globals_then(|| {
+        let candidates = [Symbol::intern("foo"), Symbol::intern("bar"), Symbol::intern("baz")];
+        assert_eq!(
+            crate::suggest_similar(Symbol::intern("fo"), candidates),
+            Some(Symbol::intern("foo"))
+        );
+        assert_eq!(crate::suggest_similar(Symbol::intern("foo"), candidates), None);
+        assert_eq!(crate::suggest_similar(Symbol::intern("quux"), candidates), None);
+
+        // Keywords are suggested as raw identifiers.
+        let similar = SimilarName::new(DUMMY_SP, "a field", Symbol::intern("typ"), [kw::Type]);
+        assert_eq!(similar.unwrap().candidate.to_string(), "r#type");
+    });
+}
+
+#[test]
+fn float_and_duration_args() {
+    let dummy = make_dummy("errors_limit = took {$time} instead of {$limit}");
+
+    let mut args = FluentArgs::new();
+    args.set("time", Duration::from_millis(1250).into_diagnostic_arg());
+    args.set("limit", 0.1f32.into_diagnostic_arg());
+    let message = DiagnosticMessage::FluentIdentifier("errors_limit".into(), None);
+
+    assert_eq!(
+        dummy.translate_message(&message, &args).unwrap(),
+        "took \u{2068}1.25s\u{2069} instead of \u{2068}0.1\u{2069}"
+    );
+}
+
+#[test]
+fn span_label_args() {
+    let dummy = make_dummy("errors_moved = `{$name}` moved here, in iteration {$iteration}");
+
+    let mut args = FluentArgs::new();
+    args.set("name", "x");
+    args.set("iteration", 0);
+    let message = DiagnosticMessage::FluentIdentifier("errors_moved".into(), None);
+
+    let mut span = MultiSpan::new();
+    span.push_span_label(DUMMY_SP, message.clone());
+    span.push_span_label_with_args(
+        DUMMY_SP,
+        message,
+        vec![("iteration".into(), 2.into_diagnostic_arg())],
+    );
+    let labels = span
+        .span_labels()
+        .into_iter()
+        .map(|label| {
+            let label_args = span_label_args(&args, &label.args);
+            let args = label_args.as_ref().unwrap_or(&args);
+            dummy.translate_message(label.label.as_ref().unwrap(), args).unwrap().into_owned()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        labels,
+        [
+            "`\u{2068}x\u{2069}` moved here, in iteration \u{2068}0\u{2069}",
+            "`\u{2068}x\u{2069}` moved here, in iteration \u{2068}2\u{2069}",
+        ]
+    );
+}
+
+#[test]
+fn pseudo_localization() {
+    let mut dummy = make_dummy("errors_mismatch = expected {$expected}, found `{$found}`");
+    crate::pseudo_localize_bundle(&mut dummy.bundle);
+
+    let mut args = FluentArgs::new();
+    args.set("expected", "u32");
+    args.set("found", 3);
+    let message = DiagnosticMessage::FluentIdentifier("errors_mismatch".into(), None);
+
+    assert_eq!(
+        dummy.translate_message(&message, &args).unwrap(),
+        "ḗḗẋƥḗḗƈŧḗḗḓ \u{2068}[u32]\u{2069}, ƒǿǿŭŭƞḓ `\u{2068}[3]\u{2069}`"
+    );
+    // Messages that aren't translated stay as they are.
+    let message = DiagnosticMessage::Str("expected `u32`".into());
+    assert_eq!(dummy.translate_message(&message, &args).unwrap(), "expected `u32`");
+}
+
+#[test]
+fn locale_chain() {
+    let resource = |ftl: &str| FluentResource::try_new(ftl.into()).unwrap();
+    let (pt_br, pt) = (langid!("pt-BR"), langid!("pt"));
+    let mut bundle = FluentBundle::new(vec![pt_br.clone(), pt.clone()]);
+    bundle.add_locale_resource(resource("errors_a = a do Brasil"), &pt_br).unwrap();
+    bundle.add_locale_resource(resource("errors_a = a\nerrors_b = b"), &pt).unwrap();
+
+    let format = |id: &str| {
+        let pattern = bundle.get_message(id).unwrap().value().unwrap();
+        bundle.format_pattern(pattern, None, &mut vec![]).into_owned()
+    };
+    assert_eq!(format("errors_a"), "a do Brasil");
+    assert_eq!(format("errors_b"), "b");
+    assert_eq!(bundle.message_locale("errors_a"), Some(&pt_br));
+    assert_eq!(bundle.message_locale("errors_b"), Some(&pt));
+    assert_eq!(bundle.message_locale("errors_c"), None);
+
+    // Resources of the same locale still can't define a message twice.
+    assert!(bundle.add_locale_resource(resource("errors_b = b de novo"), &pt).is_err());
+}
+
+#[test]
+fn ordinal_and_range_selectors() {
+    let bundle = crate::fallback_fluent_bundle(
+        vec![
+            "errors_nth = { NUMBER($n, type: \"ordinal\") ->
+                [one] {$n}st
+                [two] {$n}nd
+                [few] {$n}rd
+               *[other] {$n}th
+            }
+errors_count = { RANGE($n, 2, 4) ->
+                [below] few
+                [within] some
+               *[above] many
+            }",
+        ],
+        false,
+    );
+    let format = |id: &str, n: usize| {
+        let mut args = FluentArgs::new();
+        args.set("n", n);
+        let pattern = bundle.get_message(id).unwrap().value().unwrap();
+        let mut errs = vec![];
+        let formatted = bundle.format_pattern(pattern, Some(&args), &mut errs).into_owned();
+        assert!(errs.is_empty(), "{errs:?}");
+        formatted
+    };
+
+    let ordinals = [1, 2, 3, 4, 11, 12, 13, 21, 22, 23, 101].map(|n| format("errors_nth", n));
+    assert_eq!(
+        ordinals,
+        ["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "23rd", "101st"]
+    );
+    let counts = [1, 2, 4, 5].map(|n| format("errors_count", n));
+    assert_eq!(counts, ["few", "some", "some", "many"]);
+}
+
+#[test]
+fn localized_numbers() {
+    let format = |locale, ftl: &str, n: DiagnosticArgValue<'static>| {
+        let mut bundle = FluentBundle::new(vec![locale]);
+        crate::localize_numbers(&mut bundle);
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(FluentResource::try_new(format!("errors_size = {ftl}")).unwrap())
+            .unwrap();
+        let mut args = FluentArgs::new();
+        args.set("n", n);
+        let pattern = bundle.get_message("errors_size").unwrap().value().unwrap();
+        bundle.format_pattern(pattern, Some(&args), &mut vec![]).into_owned()
+    };
+
+    assert_eq!(format(langid!("en-US"), "{$n}", 1_048_576.into_diagnostic_arg()), "1,048,576");
+    assert_eq!(format(langid!("de"), "{$n}", (-65_536).into_diagnostic_arg()), "-65.536");
+    assert_eq!(format(langid!("fr"), "{$n}", 1000.into_diagnostic_arg()), "1\u{202f}000");
+    assert_eq!(format(langid!("de"), "{$n}", 999.into_diagnostic_arg()), "999");
+    // Numbers that are code keep their digits together.
+    let ungrouped = "{ NUMBER($n, useGrouping: \"false\") }";
+    assert_eq!(format(langid!("de"), ungrouped, 4096.into_diagnostic_arg()), "4096");
+    // Numbers that a `f64` can't represent exactly are passed to Fluent as strings.
+    let large = DiagnosticArgValue::Number(i128::MAX);
+    assert_eq!(format(langid!("de"), "{$n}", large), i128::MAX.to_string());
+}
+
+#[test]
+fn list_args() {
+    let dummy = make_dummy("errors_expected = expected {$or}, found {$custom}");
+
+    let mut args = FluentArgs::new();
+    args.set(
+        "or",
+        DiagnosticArgValue::StrListSepByOr(vec!["`a`".into(), "`b`".into(), "`c`".into()]),
+    );
+    args.set(
+        "custom",
+        DiagnosticArgValue::StrListSepByCustom(vec!["x".into(), "y".into()], " | ".into()),
+    );
+    let message = DiagnosticMessage::FluentIdentifier("errors_expected".into(), None);
+
+    assert_eq!(
+        dummy.translate_message(&message, &args).unwrap(),
+        "expected \u{2068}`a`, `b`, or `c`\u{2069}, found \u{2068}x | y\u{2069}"
+    );
+}
+
+#[test]
+fn or_list_falls_back_to_english() {
+    let dummy = make_dummy_in(langid!("fr"), "errors_expected = attendu {$and} ou {$or}");
+
+    let list = || vec!["`a`".into(), "`b`".into(), "`c`".into()];
+    let mut args = FluentArgs::new();
+    args.set("and", DiagnosticArgValue::StrListSepByAnd(list()));
+    args.set("or", DiagnosticArgValue::StrListSepByOr(list()));
+    let message = DiagnosticMessage::FluentIdentifier("errors_expected".into(), None);
+
+    // There is no ICU data for "or" lists, unlike for "and" lists.
+    assert_eq!(
+        dummy.translate_message(&message, &args).unwrap(),
+        "attendu \u{2068}`a`, `b` et `c`\u{2069} ou \u{2068}`a`, `b`, or `c`\u{2069}"
+    );
+}
+
+#[test]
+fn capped_symbol_list() {
+    rustc_span::create_default_session_globals_then(|| {
+        let dummy = make_dummy(
+            "errors_candidates = candidates are {$list}\nerrors_list_more = {$count} more",
+        );
+
+        let list = vec![Symbol::intern("a"), Symbol::intern("b"), Symbol::intern("c")];
+        let mut args = FluentArgs::new();
+        args.set("list", DiagnosticSymbolList::from(list).with_cap(1).into_diagnostic_arg());
+        let message = DiagnosticMessage::FluentIdentifier("errors_candidates".into(), None);
+
+        assert_eq!(
+            dummy.translate_message(&message, &args).unwrap(),
+            "candidates are \u{2068}`a` and \u{2068}2\u{2069} more\u{2069}"
+        );
+
+        // "2 more" is translated too.
+        let dummy = make_dummy_in(
+            langid!("fr"),
+            "errors_candidates = candidats : {$list}\nerrors_list_more = {$count} de plus",
+        );
+        assert_eq!(
+            dummy.translate_message(&message, &args).unwrap(),
+            "candidats : \u{2068}`a` et \u{2068}2\u{2069} de plus\u{2069}"
+        );
+
+        // Without a translation of "2 more", the list ends with an ellipsis instead.
+        let dummy = make_dummy("errors_candidates = candidates are {$list}");
+        assert_eq!(
+            dummy.translate_message(&message, &args).unwrap(),
+            "candidates are \u{2068}`a` and …\u{2069}"
+        );
+    });
+}
+
+#[test]
+fn symbol_arg() {
+    rustc_span::create_default_session_globals_then(|| {
+        let dummy = make_dummy("errors_name = expected {$name}");
+
+        let arg = Symbol::intern("fn").into_diagnostic_arg();
+        assert_eq!(arg, DiagnosticArgValue::Symbol(Symbol::intern("fn")));
+        let mut args = FluentArgs::new();
+        args.set("name", arg);
+        let message = DiagnosticMessage::FluentIdentifier("errors_name".into(), None);
+
+        assert_eq!(
+            dummy.translate_message(&message, &args).unwrap(),
+            "expected \u{2068}r#fn\u{2069}"
+        );
+    });
+}
+
+#[test]
+fn static_str_args_are_borrowed() {
+    let args = [
+        rustc_lint_defs::Level::Warn.into_diagnostic_arg(),
+        rustc_target::spec::SplitDebuginfo::Packed.into_diagnostic_arg(),
+        rustc_target::spec::PanicStrategy::Abort.into_diagnostic_arg(),
+    ];
+    for arg in args {
+        assert!(matches!(arg, DiagnosticArgValue::Str(Cow::Borrowed(_))), "{arg:?}");
+    }
+}
+
+#[test]
+fn lazy_arg_is_computed_once_on_use() {
+    let computed = Arc::new(AtomicUsize::new(0));
+    let mut diag = Diagnostic::new(Level::Warning, "unused");
+    diag.arg_lazy("ty", {
+        let computed = computed.clone();
+        move || {
+            computed.fetch_add(1, Ordering::Relaxed);
+            "Vec<u8>"
+        }
+    });
+
+    // Hashing for deduplication and cloning don't compute the argument.
+    diag.hash(&mut DefaultHasher::new());
+    let clone = diag.clone();
+    assert_eq!(computed.load(Ordering::Relaxed), 0);
+
+    let args = diag.args().map(|(name, value)| (name.clone(), value.clone())).collect::<Vec<_>>();
+    assert_eq!(args, [("ty".into(), DiagnosticArgValue::Str("Vec<u8>".into()))]);
+    assert_eq!(clone.args().count(), 1);
+    assert_eq!(computed.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn set_args() {
+    let mut diag = Diagnostic::new(Level::Warning, "unused");
+    diag.set_args([("a", 1.into_diagnostic_arg()), ("b", "x".into_diagnostic_arg())]);
+    diag.set_args(vec![("c".to_string(), true)]);
+
+    let mut args =
+        diag.args().map(|(name, value)| (name.clone(), value.clone())).collect::<Vec<_>>();
+    args.sort_by(|(a, _), (b, _)| a.cmp(b));
+    assert_eq!(
+        args,
+        [
+            ("a".into(), DiagnosticArgValue::Number(1)),
+            ("b".into(), DiagnosticArgValue::Str("x".into())),
+            ("c".into(), DiagnosticArgValue::Str("true".into())),
+        ]
+    );
+}
+
+/// A value that can't be cloned, but only needs a reference to be an argument.
+struct NameList(Vec<String>);
+
+impl AsDiagnosticArg for NameList {
+    fn as_diagnostic_arg(&self) -> DiagnosticArgValue<'static> {
+        self.0.join(", ").into_diagnostic_arg()
+    }
+}
+
+#[test]
+fn borrowed_args() {
+    let names = NameList(vec!["a".to_string(), "b".to_string()]);
+    let name = "c".to_string();
+    let path = PathBuf::from("d");
+    let mut diag = Diagnostic::new(Level::Warning, "unused");
+    diag.arg("names", &names).arg("name", &name).arg("path", &path).arg("count", &3usize);
+
+    let arg = |name| diag.args().find(|(n, _)| *n == name).map(|(_, value)| value.clone());
+    assert_eq!(arg("names"), Some(DiagnosticArgValue::Str("a, b".into())));
+    assert_eq!(arg("name"), Some(DiagnosticArgValue::Str("c".into())));
+    assert_eq!(arg("path"), Some(DiagnosticArgValue::Str("d".into())));
+    assert_eq!(arg("count"), Some(DiagnosticArgValue::Number(3)));
+}
+
+struct CountNote(usize);
+
+impl AddToDiagnostic for CountNote {
+    fn add_to_diagnostic_with<F>(self, diag: &mut Diagnostic, f: F)
+    where
+        F: Fn(&mut Diagnostic, SubdiagnosticMessage) -> SubdiagnosticMessage,
+    {
+        diag.arg("count", self.0);
+        let msg = f(diag, SubdiagnosticMessage::FluentIdentifier("errors_count".into()));
+        diag.note(msg);
+    }
+}
+
+#[test]
+fn subdiagnostic_args_are_scoped() {
+    let dummy = make_dummy("errors_count = {$count} items");
+    let mut diag = Diagnostic::new(Level::Warning, "unused");
+    diag.subdiagnostic(CountNote(1));
+    diag.subdiagnostic(CountNote(2));
+    diag.translate_scoped_messages(&dummy);
+
+    let notes = diag.children.iter().map(|child| &child.messages[0].0).collect::<Vec<_>>();
+    assert_eq!(
+        notes,
+        [
+            &DiagnosticMessage::Eager("\u{2068}1\u{2069} items".into()),
+            &DiagnosticMessage::Eager("\u{2068}2\u{2069} items".into()),
+        ]
+    );
+}
+
+#[test]
+fn many_labels_many_spans() {
+    rustc_span::create_default_session_globals_then(|| {
+        let dummy = make_dummy("errors_use = use number {$n}");
+        let span = |lo| Span::with_root_ctxt(BytePos(lo), BytePos(lo + 1));
+        let msg = DiagnosticMessage::FluentIdentifier("errors_use".into(), None);
+
+        let mut diag = Diagnostic::new(Level::Error, "used twice");
+        diag.subdiagnostic(ManyLabelsManySpans {
+            labels: vec![(span(0), msg.clone()), (span(4), msg.clone())],
+            args: vec![
+                vec![("n".into(), 1.into_diagnostic_arg())],
+                vec![("n".into(), 2.into_diagnostic_arg())],
+            ],
+        });
+        diag.translate_scoped_messages(&dummy);
+
+        let labels = diag.span.span_labels();
+        let labels =
+            labels.iter().map(|label| (label.span, label.label.clone())).collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                (span(0), Some(DiagnosticMessage::Eager("use number \u{2068}1\u{2069}".into()))),
+                (span(4), Some(DiagnosticMessage::Eager("use number \u{2068}2\u{2069}".into()))),
+            ]
+        );
+    });
+}
+
+#[test]
+fn subdiagnostic_once() {
+    let mut diag = Diagnostic::new(Level::Warning, "unused");
+    for count in [1, 2, 1] {
+        diag.subdiagnostic_once(count, CountNote(count));
+    }
+    assert_eq!(diag.children.len(), 2);
+}
+
+#[test]
+fn move_last_child() {
+    let mut diag = Diagnostic::new(Level::Warning, "unused");
+    diag.note("first").note("second").help("third").move_last_child(0);
+    let children =
+        diag.children.iter().map(|child| child.messages[0].0.as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(children, ["third", "first", "second"]);
+
+    diag.move_last_child(10);
+    let children =
+        diag.children.iter().map(|child| child.messages[0].0.as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(children, ["third", "first", "second"]);
+}
+
+#[test]
+fn reorder_without_children() {
+    let mut diag = Diagnostic::new(Level::Warning, "unused");
+    diag.move_last_child(0)
+        .prioritize_last_child(SubdiagnosticPriority::First)
+        .prioritize_last_suggestion(SubdiagnosticPriority::First);
+    assert!(diag.children.is_empty());
+}
+
+#[test]
+fn intern_strings() {
+    let mut interner = DiagnosticInterner::default();
+    let mut diags = [0, 1].map(|_| {
+        let mut diag = Diagnostic::new(Level::Warning, format!("unused variable: `{}`", "x"));
+        diag.arg("name", "x".to_string());
+        diag
+    });
+    diags.iter_mut().for_each(|diag| diag.intern_strings(&mut interner));
+    let [a, b] = [&diags[0], &diags[1]].map(|diag| match diag.messages[0].0 {
+        DiagnosticMessage::Str(Cow::Borrowed(s)) => s,
+        _ => panic!("message was not interned"),
+    });
+    assert_eq!(a, "unused variable: `x`");
+    assert!(std::ptr::eq(a, b));
+
+    // Diagnostics that leave the `DiagCtxt` own their strings again, unlike other borrowed ones.
+    let [mut a, _] = diags;
+    a.arg("level", rustc_lint_defs::Level::Warn);
+    a.unintern_strings(&interner);
+    assert!(matches!(a.messages[0].0, DiagnosticMessage::Str(Cow::Owned(_))));
+    let args = a.args().map(|(name, value)| (name.as_ref(), value)).collect::<Vec<_>>();
+    assert!(args.iter().any(|arg| matches!(arg, ("name", DiagnosticArgValue::Str(Cow::Owned(_))))));
+    assert!(
+        args.iter()
+            .any(|arg| matches!(arg, ("level", DiagnosticArgValue::Str(Cow::Borrowed("warn")))))
+    );
+}
+
+#[test]
+fn changed_lines_from_patch() {
+    let patch = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,5 @@
+ fn main() {
+-    let x = 1;
++    let x = 2;
++    let y = 3;
+     println!(\"{x}\");
+ }
+@@ -10 +11 @@ fn other() {
+-old
++new
+--- a/src/removed.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+    let changed = ChangedLines::from_patch(patch);
+    let path = Path::new("/home/user/project/src/lib.rs");
+    assert!(!changed.contains(path, 1..=1));
+    assert!(changed.contains(path, 2..=2));
+    assert!(changed.contains(path, 3..=3));
+    assert!(!changed.contains(path, 4..=10));
+    assert!(changed.contains(path, 4..=11));
+    assert!(!changed.contains(Path::new("src/removed.rs"), 1..=1));
+    assert!(!changed.contains(Path::new("lib.rs"), 2..=2));
+}
+
+#[test]
+fn path_glob() {
+    let glob = PathGlob::new("target/generated/**/*.rs");
+    assert!(glob.matches(Path::new("target/generated/foo.rs")));
+    assert!(glob.matches(Path::new("/home/user/project/target/generated/a/b/foo.rs")));
+    assert!(!glob.matches(Path::new("target/generated/foo.txt")));
+    assert!(!glob.matches(Path::new("target/foo.rs")));
+
+    let glob = PathGlob::new("/src/gen_?.rs");
+    assert!(glob.matches(Path::new("/src/gen_a.rs")));
+    assert!(!glob.matches(Path::new("/src/gen_ab.rs")));
+    assert!(!glob.matches(Path::new("/project/src/gen_a.rs")));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn error_guaranteed_records_where_it_was_claimed() {
+    with_capture_dcx(None, |dcx, _captured| {
+        let line = line!() + 1;
+        let guar = dcx.struct_err("the error").emit();
+        let debug = format!("{guar:?}");
+        assert!(
+            debug.contains(&format!("for `the error` emitted at {}:{line}:", file!())),
+            "{debug}"
+        );
+        assert_eq!(Some(guar), dcx.has_errors());
+        let debug = format!("{:?}", dcx.has_errors().unwrap());
+        assert!(debug.contains("claimed at") && !debug.contains("emitted at"), "{debug}");
+    })
+}
+
+#[test]
+fn snapshot_rollback_and_commit() {
+    with_capture_dcx(None, |dcx, captured| {
+        let outer = dcx.snapshot();
+        dcx.struct_warn("kept").emit();
+        let inner = dcx.snapshot();
+        dcx.struct_warn("dropped").emit();
+        dcx.rollback(inner);
+        assert!(captured.is_empty());
+        dcx.commit(outer);
+
+        let diags = captured.drain();
+        let messages = diags.iter().map(|diag| diag.messages[0].0.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [Some("kept")]);
+    })
+}
+
+#[test]
+fn snapshot_rollback_drops_stashed_error() {
+    with_capture_dcx(None, |dcx, captured| {
+        let snapshot = dcx.snapshot();
+        let error = Diagnostic::new(Level::Error, "error");
+        dcx.stash_diagnostic(DUMMY_SP, StashKey::ItemNoType, error);
+        dcx.good_path_delayed_bug("good path");
+        assert_eq!(dcx.err_count(), 1);
+        dcx.rollback(snapshot);
+
+        assert_eq!(dcx.err_count(), 0);
+        assert!(!dcx.has_stashed_diagnostic(DUMMY_SP, StashKey::ItemNoType));
+        assert_eq!(dcx.has_errors(), None);
+        dcx.abort_if_errors();
+        assert!(captured.is_empty());
+    })
+}
+
+#[test]
+#[should_panic = "cannot roll back a snapshot after an `ErrorGuaranteed` was handed out"]
+fn snapshot_rollback_after_error() {
+    with_capture_dcx(None, |dcx, _captured| {
+        let snapshot = dcx.snapshot();
+        dcx.struct_err("error").emit();
+        dcx.rollback(snapshot);
+    })
+}
+
+#[test]
+fn warning_emitter() {
+    with_capture_dcx(None, |dcx, errors| {
+        let (warning_emitter, warnings) = CaptureEmitter::new(None, None, fallback_bundle());
+        let dcx = dcx.with_warning_emitter(Box::new(warning_emitter));
+
+        dcx.struct_warn("warning").emit();
+        dcx.struct_err("error").emit();
+        dcx.struct_note("note").emit();
+
+        let (errors, warnings) = (errors.drain(), warnings.drain());
+        let messages = errors.iter().map(|diag| diag.messages[0].0.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [Some("error")]);
+        let messages = warnings.iter().map(|diag| diag.messages[0].0.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [Some("warning"), Some("note")]);
+    })
+}
+
+struct TestError(&'static str);
+
+impl<'a> IntoDiagnostic<'a> for TestError {
+    fn into_diagnostic(self, dcx: &'a DiagCtxt, level: Level) -> DiagnosticBuilder<'a> {
+        DiagnosticBuilder::new(dcx, level, self.0)
+    }
+}
+
+struct TestWarning(&'static str);
+
+impl<'a> IntoDiagnostic<'a, ()> for TestWarning {
+    fn into_diagnostic(self, dcx: &'a DiagCtxt, level: Level) -> DiagnosticBuilder<'a, ()> {
+        DiagnosticBuilder::new(dcx, level, self.0)
+    }
+}
+
+#[test]
+fn emit_batch() {
+    assert!(DiagnosticBatch::<TestError>::from_vec(vec![]).is_none());
+
+    with_capture_dcx(None, |dcx, captured| {
+        let mut batch = DiagnosticBatch::from_vec(vec![TestError("first")]).unwrap();
+        batch.push(TestError("second"));
+        dcx.emit_err(batch);
+        assert!(dcx.emit_all(Level::Error, Vec::<TestError>::new()).is_empty());
+        assert_eq!(dcx.emit_all(Level::Error, [TestError("third")]).len(), 1);
+        let warnings = [TestWarning("fourth"), TestWarning("fifth")];
+        assert_eq!(dcx.emit_all(Level::Warning, warnings).len(), 2);
+
+        let diags = captured.drain();
+        let messages =
+            diags.iter().map(|diag| (diag.level, diag.messages[0].0.as_str())).collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                (Level::Error, Some("first")),
+                (Level::Error, Some("second")),
+                (Level::Error, Some("third")),
+                (Level::Warning, Some("fourth")),
+                (Level::Warning, Some("fifth")),
+            ]
+        );
+        assert_eq!(dcx.err_count(), 3);
+    })
+}
+
+#[test]
+fn deterministic_diagnostics() {
+    with_capture_dcx(None, |dcx, captured| {
+        let flags = DiagCtxtFlags {
+            can_emit_warnings: true,
+            deterministic_diagnostics: true,
+            ..Default::default()
+        };
+        let dcx = dcx.with_flags(flags);
+        let span = |lo| Span::with_root_ctxt(BytePos(lo), BytePos(lo + 1));
+
+        dcx.struct_span_warn(span(10), "second").emit();
+        dcx.struct_span_warn(span(0), "first").emit();
+        // Held back until the next flush point.
+        assert!(captured.drain().is_empty());
+
+        dcx.abort_if_errors();
+        let diags = captured.drain();
+        let messages = diags.iter().map(|diag| diag.messages[0].0.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [Some("first"), Some("second")]);
+    })
+}
+
+#[test]
+fn replay_cached_diagnostics() {
+    rustc_span::create_default_session_globals_then(|| {
+        let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+        let file = sm.new_source_file(
+            Path::new("main.rs").to_owned().into(),
+            "fn main() {\n    let x = 1;\n}\n".into(),
+        );
+        let span = Span::with_root_ctxt(file.start_pos + BytePos(20), file.start_pos + BytePos(21));
+        let path = std::env::temp_dir().join(format!("rustc-replay-{}.diags", std::process::id()));
+
+        let (emitter, _) = CaptureEmitter::new(Some(sm.clone()), None, fallback_bundle());
+        let dcx = DiagCtxt::with_custom_emitter(emitter);
+        dcx.struct_span_warn(span, "before recording").emit();
+        dcx.record_diagnostics();
+        dcx.struct_span_warn(span, "unused variable: `x`").emit();
+        // Errors aren't recorded.
+        dcx.struct_span_err(span, "mismatched types").emit();
+        dcx.save_recorded_diagnostics(&path).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (emitter, replayed) = CaptureEmitter::new(Some(sm), None, fallback_bundle());
+        let dcx = DiagCtxt::with_custom_emitter(emitter);
+        assert_eq!(dcx.emit_cached_diagnostics(&data), 1);
+        let diags = replayed.drain();
+        let messages = diags.iter().map(|diag| diag.messages[0].0.as_str()).collect::<Vec<_>>();
+        assert_eq!(messages, [Some("unused variable: `x`")]);
+        assert_eq!(diags[0].span.primary_span(), Some(span));
+    })
+}
+
+#[test]
+fn diagnostic_baseline() {
+    with_capture_dcx(None, |dcx, captured| {
+        let known = Diagnostic::new(Level::Warning, "known warning");
+        let baseline = [crate::fingerprint::fingerprint(&known, None)].into_iter().collect();
+        let dcx = dcx.with_baseline(baseline);
+
+        dcx.struct_warn("known warning").emit();
+        dcx.struct_warn("new warning").emit();
+        dcx.print_error_count(&Registry::new(&[]));
+
+        let diags = captured.drain();
+        let messages = diags
+            .iter()
+            .map(|diag| (diag.level, diag.messages[0].0.as_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                (Level::Warning, "new warning"),
+                (Level::FailureNote, "1 diagnostic was suppressed by the diagnostic baseline"),
+                (Level::Warning, "1 warning emitted"),
+            ]
+        );
+    })
+}
+
+#[test]
+fn future_breakage_hooks() {
+    with_capture_dcx(None, |dcx, captured| {
+        dcx.add_future_breakage_hook(|diags| {
+            diags.retain(|diag| diag.messages[0].0.as_str() != Some("hidden"))
+        });
+        dcx.add_future_breakage_hook(|diags| {
+            for diag in diags {
+                diag.note("run `cargo fix` to update the code");
+            }
+        });
+        let report = ["shown", "hidden"].map(|msg| Diagnostic::new(Level::Warning, msg));
+        dcx.emit_future_breakage_report(report.into());
+        let report = captured.drain_future_breakage();
+        let report = report
+            .iter()
+            .map(|diag| (diag.messages[0].0.as_str(), diag.children.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(report, [(Some("shown"), 1)]);
+
+        // Hooks can also take the whole report, to report it their own way.
+        let taken = Arc::new(AtomicUsize::new(0));
+        let count = taken.clone();
+        dcx.add_future_breakage_hook(move |diags| {
+            count.fetch_add(std::mem::take(diags).len(), Ordering::Relaxed);
+        });
+        dcx.emit_future_breakage_report(vec![Diagnostic::new(Level::Warning, "shown")]);
+        assert_eq!(taken.load(Ordering::Relaxed), 1);
+        assert!(captured.drain_future_breakage().is_empty());
+    })
+}
+
+#[test]
+fn observers() {
+    with_capture_dcx(None, |dcx, captured| {
+        let flags = DiagCtxtFlags {
+            can_emit_warnings: true,
+            deduplicate_diagnostics: true,
+            ..Default::default()
+        };
+        let dcx = dcx.with_flags(flags);
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        dcx.add_observer({
+            let observed = observed.clone();
+            move |diag| {
+                let message = diag.messages[0].0.as_str().unwrap().to_owned();
+                observed.lock().unwrap().push((diag.level, message));
+            }
+        });
+
+        dcx.struct_warn("unused variable").emit();
+        DiagnosticBuilder::<()>::new(&dcx, Level::Allow, "allowed lint").emit();
+        dcx.struct_err("mismatched types").emit();
+        // Duplicates aren't shown, so they aren't observed either.
+        dcx.struct_warn("unused variable").emit();
+
+        assert_eq!(
+            *observed.lock().unwrap(),
+            [
+                (Level::Warning, "unused variable".to_owned()),
+                (Level::Error, "mismatched types".to_owned())
+            ]
+        );
+        drop(dcx);
+        assert_eq!(captured.drain().len(), 2);
+    })
+}
+
+#[test]
+fn custom_stash_keys() {
+    with_capture_dcx(None, |dcx, captured| {
+        let key = StashKey::Custom("my-tool::unused-import");
+        let with_note = |msg: &'static str| {
+            let mut diag = Diagnostic::new(Level::Warning, msg);
+            diag.note("a note");
+            diag
+        };
+        let stashed = || {
+            let mut stashed = vec![];
+            dcx.for_each_stashed_diagnostic(|_, stashed_key, diag| {
+                assert!(stashed_key == key);
+                stashed
+                    .push((diag.messages[0].0.as_str().unwrap().to_owned(), diag.children.len()));
+            });
+            stashed
+        };
+
+        dcx.stash_diagnostic(DUMMY_SP, key, Diagnostic::new(Level::Warning, "first"));
+        dcx.stash_diagnostic_with(DUMMY_SP, key, with_note("second"), StashMergePolicy::KeepFirst);
+        assert_eq!(stashed(), [("first".to_owned(), 0)]);
+        dcx.stash_diagnostic_with(
+            DUMMY_SP,
+            key,
+            with_note("third"),
+            StashMergePolicy::CombineNotes,
+        );
+        assert_eq!(stashed(), [("first".to_owned(), 1)]);
+        dcx.stash_diagnostic(DUMMY_SP, key, Diagnostic::new(Level::Warning, "replaced"));
+        assert_eq!(stashed(), [("replaced".to_owned(), 0)]);
+
+        // Custom keys don't clash with the keys of the compiler.
+        assert!(!dcx.has_stashed_diagnostic(DUMMY_SP, StashKey::Cycle));
+        dcx.steal_diagnostic(DUMMY_SP, key).unwrap().emit();
+        let diags = captured.drain();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].messages[0].0.as_str(), Some("replaced"));
+        assert!(stashed().is_empty());
+    })
+}
+
+#[test]
+fn dcx_before_thread_safe_mode() {
+    // The test harness never sets whether the compiler is parallel, like drivers that create the
+    // `DiagCtxt` of the session before the `-Zthreads` option is parsed.
+    with_capture_dcx(None, |dcx, captured| {
+        dcx.struct_warn("unused variable").emit();
+        dcx.struct_note("note").emit();
+        assert_eq!(captured.drain().len(), 2);
+        assert_eq!(dcx.counts().by_level[&Level::Warning], 1);
+    })
+}
+
+#[test]
+fn counts_without_duplicates() {
+    with_capture_dcx(None, |dcx, captured| {
+        let flags = DiagCtxtFlags {
+            can_emit_warnings: true,
+            deduplicate_diagnostics: true,
+            ..Default::default()
+        };
+        let dcx = dcx.with_flags(flags);
+        dcx.struct_warn("unused variable").emit();
+        dcx.struct_warn("unused variable").emit();
+        dcx.abort_if_errors();
+        assert_eq!(captured.drain().len(), 1);
+        assert_eq!(dcx.counts().by_level[&Level::Warning], 1);
+    })
+}
+
+#[test]
+fn downgrade_to_warning() {
+    with_capture_dcx(None, |dcx, captured| {
+        let mut err = dcx.struct_err("unused import");
+        err.note("the import is allowed");
+        err.downgrade_to_warning().emit();
+
+        let diags = captured.drain();
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].level, Level::Warning);
+        assert_eq!(diags[0].children.len(), 1);
+        assert!(dcx.has_errors().is_none());
+        assert_eq!(dcx.counts().by_level[&Level::Warning], 1);
+    })
+}
+
+#[test]
+fn duplicate_counts() {
+    with_capture_dcx(None, |dcx, captured| {
+        let flags = DiagCtxtFlags {
+            can_emit_warnings: true,
+            deduplicate_diagnostics: true,
+            ..Default::default()
+        };
+        let dcx = dcx.with_flags(flags);
+        for _ in 0..3 {
+            dcx.struct_err("cannot find macro `m` in this scope").emit();
+        }
+        dcx.struct_warn("unused variable").emit();
+        // Held back until the next flush point, to count the duplicates.
+        assert!(captured.drain().is_empty());
+        let aborted = std::panic::catch_unwind(AssertUnwindSafe(|| dcx.abort_if_errors()));
+        assert!(aborted.is_err());
+
+        let diags = captured.drain();
+        let duplicates = diags
+            .iter()
+            .map(|diag| {
+                let message = diag.messages.iter().map(|(msg, _)| msg.as_str().unwrap());
+                (message.collect::<String>(), diag.extras().and_then(|extras| extras.duplicates))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            duplicates,
+            [
+                ("cannot find macro `m` in this scope (and 2 duplicates)".to_owned(), Some(2)),
+                ("unused variable".to_owned(), None),
+            ]
+        );
+    })
+}
+
+#[test]
+fn flush_delayed_groups_bugs() {
+    with_capture_dcx(None, |dcx, captured| {
+        let spans = (0..3).map(|i| Span::with_root_ctxt(BytePos(i), BytePos(i + 1)));
+        for span in spans.clone() {
+            dcx.span_delayed_bug(span, "delayed");
+        }
+        let flushed = std::panic::catch_unwind(AssertUnwindSafe(|| dcx.flush_delayed()));
+        assert!(flushed.is_err());
+
+        let diags = captured.drain();
+        let bugs = diags.iter().filter(|diag| diag.level == Level::Bug).collect::<Vec<_>>();
+        assert_eq!(bugs.len(), 1);
+        assert_eq!(bugs[0].span.primary_spans(), &spans.clone().take(1).collect::<Vec<_>>()[..]);
+        let count = bugs[0]
+            .children
+            .iter()
+            .find(|child| child.messages[0].0.as_str().is_some_and(|msg| msg.contains("times")))
+            .unwrap();
+        assert_eq!(
+            count.messages[0].0.as_str(),
+            Some(
+                "this bug was delayed 3 times with the same message from the same location, only \
+                 the first one is shown"
+            )
+        );
+        let others = bugs[0]
+            .children
+            .iter()
+            .find(|child| child.messages[0].0.as_str() == Some("the other bugs were delayed here"))
+            .unwrap();
+        assert_eq!(others.span.primary_spans(), &spans.skip(1).collect::<Vec<_>>()[..]);
+    })
+}
+
+#[test]
+fn perf_notes() {
+    with_capture_dcx(None, |dcx, captured| {
+        dcx.struct_warn("warning").emit();
+        DiagnosticBuilder::<()>::new(&dcx, Level::PerfNote, "large enum variant").emit();
+        dcx.print_error_count(&Registry::new(&[]));
+
+        let diags = captured.drain();
+        let messages = diags
+            .iter()
+            .map(|diag| (diag.level, diag.messages[0].0.as_str().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                (Level::Warning, "warning"),
+                (Level::PerfNote, "large enum variant"),
+                (Level::PerfNote, "1 performance note emitted"),
+                (Level::Warning, "1 warning emitted"),
+            ]
+        );
+    })
+}
+
+#[test]
+fn error_code_namespaces() {
+    let mut registry = Registry::new(&[("E0382", "use of a moved value")]);
+    registry.register_namespace("CLIPPY", "cargo clippy --explain", &[("CLIPPY0042", "a lint")]);
+
+    assert_eq!(registry.normalize_code("e382").as_deref(), Some("E0382"));
+    assert_eq!(registry.normalize_code("382").as_deref(), Some("E0382"));
+    assert_eq!(registry.normalize_code("clippy42").as_deref(), Some("CLIPPY0042"));
+    assert_eq!(registry.normalize_code("clippy"), None);
+    assert_eq!(registry.normalize_code("e"), None);
+    assert_eq!(registry.normalize_code("miri7"), None);
+    assert_eq!(registry.normalize_code("clip42"), None);
+    assert_eq!(registry.try_find_description("CLIPPY0042").ok(), Some("a lint"));
+    assert_eq!(registry.explain_command("E0382"), "rustc --explain");
+    assert_eq!(registry.explain_command("CLIPPY0042"), "cargo clippy --explain");
+}
+
+#[test]
+#[should_panic = "invalid or overlapping error code prefix `CLIP`"]
+fn overlapping_error_code_namespaces() {
+    let mut registry = Registry::new(&[]);
+    registry.register_namespace("CLIPPY", "cargo clippy --explain", &[]);
+    registry.register_namespace("CLIP", "clip --explain", &[]);
+}
+
+#[test]
+fn snippet_args() {
+    let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    let file =
+        sm.new_source_file(Path::new("test.rs").to_owned().into(), "let x = foo.bar() ;".into());
+    with_capture_dcx(Some(sm), |dcx, captured| {
+        let span = |lo, hi| {
+            Span::with_root_ctxt(file.start_pos + BytePos(lo), file.start_pos + BytePos(hi))
+        };
+        dcx.struct_warn("snippets")
+            .with_arg("expr", DiagnosticArgSnippet(span(7, 18)))
+            .with_arg("empty", DiagnosticArgSnippet(span(18, 18)))
+            .emit();
+
+        let diags = captured.drain();
+        let arg = |name| diags[0].args().find(|(n, _)| *n == name).map(|(_, value)| value.clone());
+        assert_eq!(arg("expr"), Some(DiagnosticArgValue::Str("`foo.bar()`".into())));
+        assert_eq!(arg("empty"), Some(DiagnosticArgValue::Str("`...`".into())));
+    })
+}
+
+#[test]
+#[should_panic = "snippets can't be elements of a `DiagnosticArgList`"]
+fn snippet_in_list_arg() {
+    DiagnosticArgList(vec![DiagnosticArgSnippet(DUMMY_SP)]).into_diagnostic_arg();
+}
+
+#[test]
+fn suggestion_block() {
+    let sm = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+    let file = sm.new_source_file(
+        Path::new("test.rs").to_owned().into(),
+        "fn main() {\n    if true {\n        foo();\n    }\n}\n".into(),
+    );
+    with_capture_dcx(Some(sm), |dcx, captured| {
+        let span = |lo, hi| {
+            Span::with_root_ctxt(file.start_pos + BytePos(lo), file.start_pos + BytePos(hi))
+        };
+        let block = ["let x = 1;", "", "if x > 0 {", "    bar(x);", "}"];
+        // Only whitespace before the span: the block goes on the lines before it.
+        dcx.struct_warn("before")
+            .with_span_suggestion_block(span(34, 40), "add", block, Applicability::HasPlaceholders)
+            .emit();
+        // Code before the span: the block goes on new lines after it.
+        dcx.struct_warn("after")
+            .with_span_suggestion_block(span(24, 25), "add", block, Applicability::HasPlaceholders)
+            .emit();
+
+        let diags = captured.drain();
+        let part = |diag: &Diagnostic| {
+            let part = &diag.suggestions().unwrap()[0].substitutions[0].parts[0];
+            (part.span, part.snippet.clone())
+        };
+        assert_eq!(
+            part(&diags[0]),
+            (
+                span(26, 26),
+                "        let x = 1;\n\n        if x > 0 {\n            bar(x);\n        }\n"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            part(&diags[1]),
+            (span(25, 25), "\n    let x = 1;\n\n    if x > 0 {\n        bar(x);\n    }".to_owned())
+        );
+    })
+}
+
+#[test]
+fn substitution_split_by_file() {
+    rustc_span::create_default_session_globals_then(|| {
+        let sm = SourceMap::new(FilePathMapping::empty());
+        let a = sm.new_source_file(Path::new("a.rs").to_owned().into(), "fn foo() {}\n".into());
+        let b = sm.new_source_file(Path::new("b.rs").to_owned().into(), "foo();\n".into());
+        let part = |file: &SourceFile, lo, snippet: &str| SubstitutionPart {
+            span: Span::with_root_ctxt(file.start_pos + BytePos(lo), file.start_pos + BytePos(lo)),
+            snippet: snippet.to_owned(),
+            applicability: None,
+        };
+        let substitution =
+            Substitution { parts: vec![part(&b, 0, "a::"), part(&a, 0, "pub "), part(&b, 6, ";")] };
+
+        let snippets = substitution
+            .split_by_file(&sm)
+            .into_iter()
+            .map(|substitution| {
+                substitution.parts.into_iter().map(|part| part.snippet).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(snippets, [vec!["a::", ";"], vec!["pub "]]);
+    })
+}
+
+#[test]
+fn file_suggestion_range() {
+    let contents = "[package]\nname = \"föo\"\nedition = \"2015\"\n";
+    let mut diag = Diagnostic::new(Level::Error, "edition");
+    let applicability = Applicability::MachineApplicable;
+    // Ranges that aren't in the file or split a `char` are ignored.
+    diag.file_suggestion("Cargo.toml", contents, 40..50, "update", "2021", applicability);
+    diag.file_suggestion("Cargo.toml", contents, 20..21, "update", "2021", applicability);
+    assert!(diag.file_suggestions().is_empty());
+
+    diag.file_suggestion("Cargo.toml", contents, 35..39, "update", "2021", applicability);
+    let [sugg] = diag.file_suggestions() else { panic!() };
+    assert_eq!((sugg.line_start, sugg.line_end), (3, 3));
+    assert_eq!((sugg.column_start, sugg.column_end), (12, 16));
+    assert_eq!((sugg.prefix.as_str(), sugg.original.as_str()), ("edition = \"", "2015"));
+}
+
+#[test]
+fn lazy_extras() {
+    let mut diag = Diagnostic::new(Level::Warning, "unused variable: `x`");
+    diag.arg("name", "x").note("a note");
+    diag.replace_args(Default::default());
+    assert!(diag.extras().is_none());
+    assert!(diag.suggestions().unwrap().is_empty());
+
+    diag.disable_suggestions();
+    assert!(diag.extras().is_some());
+    assert!(diag.suggestions().is_err());
+    assert!(diag.suggestions_mut().is_none());
+}
+
+#[test]
+fn rank_last_suggestion() {
+    let span = Span::with_root_ctxt(BytePos(0), BytePos(3));
+    let mut diag = Diagnostic::new(Level::Error, "cannot find `fo`");
+    // Without a suggestion, there is nothing to rank.
+    diag.rank_last_suggestion(0);
+    assert!(diag.extras().is_none());
+    diag.span_suggestion(span, "a similar name exists", "foo", Applicability::MaybeIncorrect)
+        .span_suggestion(span, "a similar name exists", "for", Applicability::MaybeIncorrect)
+        .rank_last_suggestion(0);
+
+    let suggestions = diag.suggestions().unwrap();
+    let ranks = suggestions.iter().map(|sugg| sugg.rank).collect::<Vec<_>>();
+    assert_eq!(ranks, [None, Some(0)]);
+}
@@ -2,7 +2,7 @@
 use crate::snippet::Style;
 use crate::{DiagnosticArg, DiagnosticMessage, FluentBundle};
 use rustc_data_structures::sync::Lrc;
-use rustc_error_messages::FluentArgs;
+use rustc_error_messages::{CappedStrList, FluentArgs, FluentValue, SpanLabelArgs};
 use std::borrow::Cow;
 use std::env;
 use std::error::Report;
@@ -28,6 +28,63 @@ pub fn to_fluent_args<'iter, 'arg: 'iter>(
     args
 }
 
+/// The arguments to translate a span label with, if it has arguments of its own: those of its
+/// diagnostic, with the label's arguments replacing any of them that have the same name.
+pub(crate) fn span_label_args<'arg>(
+    args: &FluentArgs<'arg>,
+    label_args: &'arg SpanLabelArgs,
+) -> Option<FluentArgs<'arg>> {
+    if label_args.is_empty() {
+        return None;
+    }
+    let mut merged = FluentArgs::with_capacity(args.iter().count() + label_args.len());
+    for (name, value) in args.iter() {
+        merged.set(name.to_owned(), value.clone());
+    }
+    for (name, value) in label_args {
+        merged.set(name.clone(), value.clone());
+    }
+    Some(merged)
+}
+
+/// The arguments with the lists that were capped by `DiagnosticArgValue::StrListSepByAndCapped`
+/// resolved, translating the "N more" that ends them with the first of `bundles` that has
+/// `errors_list_more`, if `args` contains any such list.
+fn resolve_capped_lists<'arg>(
+    bundles: [&FluentBundle; 2],
+    args: &'arg FluentArgs<'_>,
+) -> Option<FluentArgs<'arg>> {
+    let capped = |value: &FluentValue<'_>| match value {
+        FluentValue::Custom(value) => value.as_any().downcast_ref::<CappedStrList>().cloned(),
+        _ => None,
+    };
+    if !args.iter().any(|(_, value)| capped(value).is_some()) {
+        return None;
+    }
+    let list_more = |more: usize| {
+        let mut more_args = FluentArgs::with_capacity(1);
+        more_args.set("count", more);
+        bundles.into_iter().find_map(|bundle| {
+            let value = bundle.get_message("errors_list_more")?.value()?;
+            let mut errs = vec![];
+            let translated = bundle.format_pattern(value, Some(&more_args), &mut errs);
+            errs.is_empty().then(|| translated.into_owned())
+        })
+    };
+    let mut resolved = FluentArgs::with_capacity(args.iter().count());
+    for (name, value) in args.iter() {
+        let value = match capped(value) {
+            Some(list) => match list_more(list.more) {
+                Some(more) => list.resolve(Cow::Owned(more)),
+                None => value.clone(),
+            },
+            None => value.clone(),
+        };
+        resolved.set(name.to_owned(), value);
+    }
+    Some(resolved)
+}
+
 pub trait Translate {
     /// Return `FluentBundle` with localized diagnostics for the locale requested by the user. If no
     /// language was requested by the user then this will be `None` and `fallback_fluent_bundle`
@@ -53,6 +110,13 @@ fn translate_messages(
         )
     }
 
+    /// Adjusts a message after its translation, like `HumanEmitter` does with
+    /// `-Znormalize-diagnostics`. Only messages go through this, not the source code shown with
+    /// them.
+    fn finish_message<'a>(&self, message: Cow<'a, str>) -> Cow<'a, str> {
+        message
+    }
+
     /// Convert a `DiagnosticMessage` to a string, performing translation if necessary.
     fn translate_message<'a>(
         &'a self,
@@ -62,7 +126,7 @@ fn translate_message<'a>(
         trace!(?message, ?args);
         let (identifier, attr) = match message {
             DiagnosticMessage::Str(msg) | DiagnosticMessage::Eager(msg) => {
-                return Ok(Cow::Borrowed(msg));
+                return Ok(self.finish_message(Cow::Borrowed(msg)));
             }
             DiagnosticMessage::FluentIdentifier(identifier, attr) => (identifier, attr),
         };
@@ -78,10 +142,16 @@ fn translate_message<'a>(
                         .value(),
                     None => message.value().ok_or(TranslateError::value(identifier, args))?,
                 };
-                debug!(?message, ?value);
+                // Bundles for a chain of locales may take the message from any of them.
+                debug!(?message, ?value, locale = ?bundle.message_locale(identifier));
 
+                let resolved = resolve_capped_lists([bundle, self.fallback_fluent_bundle()], args);
                 let mut errs = vec![];
-                let translated = bundle.format_pattern(value, Some(args), &mut errs);
+                let translated = bundle.format_pattern(
+                    value,
+                    Some(resolved.as_ref().unwrap_or(args)),
+                    &mut errs,
+                );
                 debug!(?translated, ?errs);
                 if errs.is_empty() {
                     Ok(translated)
@@ -90,17 +160,23 @@ fn translate_message<'a>(
                 }
             };
 
-        try {
+        let translated: Result<Cow<'_, str>, TranslateError<'_>> = try {
             match self.fluent_bundle().map(|b| translate_with_bundle(b)) {
                 // The primary bundle was present and translation succeeded
                 Some(Ok(t)) => t,
 
                 // If `translate_with_bundle` returns `Err` with the primary bundle, this is likely
                 // just that the primary bundle doesn't contain the message being translated, so
-                // proceed to the fallback bundle.
+                // proceed to the fallback bundle. Overlays from `-Ztranslate-additional-ftl` may
+                // also override only the value or some attributes of a message, in which case
+                // the rest of it comes from the fallback bundle too.
                 Some(Err(
                     primary @ TranslateError::One {
-                        kind: TranslateErrorKind::MessageMissing, ..
+                        kind:
+                            TranslateErrorKind::MessageMissing
+                            | TranslateErrorKind::AttributeMissing { .. }
+                            | TranslateErrorKind::ValueMissing,
+                        ..
                     },
                 )) => translate_with_bundle(self.fallback_fluent_bundle())
                     .map_err(|fallback| primary.and(fallback))?,
@@ -125,6 +201,7 @@ fn translate_message<'a>(
                 None => translate_with_bundle(self.fallback_fluent_bundle())
                     .map_err(|fallback| TranslateError::primary(identifier, args).and(fallback))?,
             }
-        }
+        };
+        translated.map(|translated| self.finish_message(translated))
     }
 }
@@ -0,0 +1,107 @@
+//! Checks that suggestions produce code that still lexes, for `-Zverify-suggestions`.
+//!
+//! Parsing the suggested code isn't possible from here, so this is an approximation: the lines
+//! that a substitution changes are lexed before and after applying it, and the substitution is
+//! rejected if it introduces lexer errors or changes how delimiters are balanced.
+
+use crate::Substitution;
+use rustc_lexer::{LiteralKind, TokenKind};
+use rustc_span::source_map::SourceMap;
+
+#[cfg(test)]
+mod tests;
+
+/// Checks the code produced by applying `substitution`, whose parts must all be in the same file.
+/// On failure, returns a description of the problem.
+pub(crate) fn check_substitution(
+    sm: &SourceMap,
+    substitution: &Substitution,
+) -> Result<(), &'static str> {
+    let mut parts = substitution.parts.iter().collect::<Vec<_>>();
+    parts.sort_by_key(|part| part.span.lo());
+    let (Some(first), Some(last)) = (parts.first(), parts.last()) else {
+        return Ok(());
+    };
+    let lines = sm.span_extend_to_line(first.span.to(last.span));
+    let Ok(old) = sm.span_to_snippet(lines) else {
+        return Ok(());
+    };
+
+    let mut new = String::with_capacity(old.len());
+    let mut pos = 0;
+    for part in parts {
+        let lo = (part.span.lo() - lines.lo()).0 as usize;
+        let hi = (part.span.hi() - lines.lo()).0 as usize;
+        if lo < pos {
+            // Overlapping parts can't be applied, which is checked elsewhere.
+            return Ok(());
+        }
+        new.push_str(&old[pos..lo]);
+        new.push_str(&part.snippet);
+        pos = hi;
+    }
+    new.push_str(&old[pos..]);
+
+    check_lines(&old, &new)
+}
+
+/// Compares the lexed `old` and `new` code, see the module documentation.
+pub(crate) fn check_lines(old: &str, new: &str) -> Result<(), &'static str> {
+    let old = LexSummary::of(old);
+    // If the original code already doesn't lex, there is nothing to compare against.
+    if old.errors > 0 {
+        return Ok(());
+    }
+    let new = LexSummary::of(new);
+    if new.errors > 0 {
+        Err("produces code that does not lex")
+    } else if new.delimiters != old.delimiters {
+        Err("changes the balance of delimiters")
+    } else {
+        Ok(())
+    }
+}
+
+struct LexSummary {
+    /// Number of invalid or unterminated tokens.
+    errors: usize,
+    /// Number of opening minus closing parentheses, braces and brackets.
+    delimiters: [isize; 3],
+}
+
+impl LexSummary {
+    fn of(src: &str) -> LexSummary {
+        let mut summary = LexSummary { errors: 0, delimiters: [0; 3] };
+        for token in rustc_lexer::tokenize(src) {
+            match token.kind {
+                TokenKind::OpenParen => summary.delimiters[0] += 1,
+                TokenKind::CloseParen => summary.delimiters[0] -= 1,
+                TokenKind::OpenBrace => summary.delimiters[1] += 1,
+                TokenKind::CloseBrace => summary.delimiters[1] -= 1,
+                TokenKind::OpenBracket => summary.delimiters[2] += 1,
+                TokenKind::CloseBracket => summary.delimiters[2] -= 1,
+                TokenKind::Unknown
+                | TokenKind::InvalidIdent
+                | TokenKind::UnknownPrefix
+                | TokenKind::BlockComment { terminated: false, .. } => summary.errors += 1,
+                TokenKind::Literal { kind, .. } if !is_terminated(kind) => summary.errors += 1,
+                _ => {}
+            }
+        }
+        summary
+    }
+}
+
+fn is_terminated(kind: LiteralKind) -> bool {
+    match kind {
+        LiteralKind::Char { terminated }
+        | LiteralKind::Byte { terminated }
+        | LiteralKind::Str { terminated }
+        | LiteralKind::ByteStr { terminated }
+        | LiteralKind::CStr { terminated } => terminated,
+        LiteralKind::RawStr { n_hashes }
+        | LiteralKind::RawByteStr { n_hashes }
+        | LiteralKind::RawCStr { n_hashes } => n_hashes.is_some(),
+        LiteralKind::Int { .. } | LiteralKind::Float { .. } => true,
+    }
+}
@@ -0,0 +1,23 @@
+use super::check_lines;
+
+#[test]
+fn valid_replacement() {
+    assert_eq!(check_lines("    let x = foo(a, b);", "    let _x = foo(&a, b);"), Ok(()));
+}
+
+#[test]
+fn unterminated_literal() {
+    assert!(check_lines(r#"    println!("{}", x);"#, r#"    println!("{x}, );"#).is_err());
+}
+
+#[test]
+fn unbalanced_delimiters() {
+    assert!(check_lines("    foo(bar(x));", "    foo(bar(x);").is_err());
+    // Lines that were unbalanced to begin with are fine as long as that doesn't change.
+    assert_eq!(check_lines("fn main() {", "fn main() -> () {"), Ok(()));
+}
+
+#[test]
+fn original_does_not_lex() {
+    assert_eq!(check_lines("    let s = \"foo;", "    let s = \"bar;"), Ok(()));
+}
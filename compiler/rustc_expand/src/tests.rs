@@ -1056,3 +1056,29 @@ fn foo() {
 "#,
     );
 }
+
+#[test]
+fn long_line_excerpt() {
+    let long_line = format!("    let x = [{}X{}];", "0, ".repeat(30000), "0, ".repeat(40000));
+    test_harness(
+        &format!("\nfn foo() {{\n{long_line}\n}}\n"),
+        vec![SpanLabel {
+            start: Position { string: "X", count: 1 },
+            end: Position { string: "X", count: 1 },
+            label: "`X` is a good letter",
+        }],
+        &format!(
+            r#"
+error: foo
+ --> test.rs:3:90014
+  |
+3 | ...0, X{}...
+  |       ^ `X` is a good letter
+  | note: this line is {} bytes long, so only an excerpt is shown
+
+"#,
+            "0, ".repeat(42),
+            long_line.len(),
+        ),
+    );
+}
@@ -1,3 +1,4 @@
+use rustc_errors::{ErrorGuaranteed, Level};
 use rustc_hir as hir;
 use rustc_hir::Node;
 use rustc_infer::infer::TyCtxtInferExt;
@@ -101,8 +102,10 @@ fn main_fn_return_type_span(tcx: TyCtxt<'_>, def_id: DefId) -> Option<Span> {
         error = true;
     }
 
-    for attr in tcx.get_attrs(main_def_id, sym::track_caller) {
-        tcx.dcx().emit_err(errors::TrackCallerOnMain { span: attr.span, annotated: main_span });
+    let track_caller = tcx
+        .get_attrs(main_def_id, sym::track_caller)
+        .map(|attr| errors::TrackCallerOnMain { span: attr.span, annotated: main_span });
+    if !tcx.dcx().emit_all::<ErrorGuaranteed>(Level::Error, track_caller).is_empty() {
         error = true;
     }
 
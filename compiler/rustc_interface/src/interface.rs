@@ -14,7 +14,7 @@
 use rustc_middle::util::Providers;
 use rustc_parse::maybe_new_parser_from_source_str;
 use rustc_query_impl::QueryCtxt;
-use rustc_query_system::query::print_query_stack;
+use rustc_query_system::query::{describe_query_stack, print_query_stack};
 use rustc_session::config::{self, Cfg, CheckCfg, ExpectedValues, Input, OutFileName};
 use rustc_session::filesearch::sysroot_candidates;
 use rustc_session::parse::ParseSess;
@@ -327,7 +327,10 @@ pub fn run_compiler<R: Send>(config: Config, f: impl FnOnce(&Compiler) -> R + Se
     rustc_data_structures::sync::set_dyn_thread_safe_mode(config.opts.unstable_opts.threads > 1);
 
     // Check jobserver before run_in_thread_pool_with_globals, which call jobserver::acquire_thread
-    let early_dcx = EarlyDiagCtxt::new(config.opts.error_format);
+    let early_dcx = EarlyDiagCtxt::with_json_array(
+        config.opts.error_format,
+        config.opts.unstable_opts.json_array,
+    );
     early_dcx.initialize_checked_jobserver();
 
     util::run_in_thread_pool_with_globals(
@@ -336,7 +339,10 @@ pub fn run_compiler<R: Send>(config: Config, f: impl FnOnce(&Compiler) -> R + Se
         || {
             crate::callbacks::setup_callbacks();
 
-            let early_dcx = EarlyDiagCtxt::new(config.opts.error_format);
+            let early_dcx = EarlyDiagCtxt::with_json_array(
+                config.opts.error_format,
+                config.opts.unstable_opts.json_array,
+            );
 
             let codegen_backend = if let Some(make_codegen_backend) = config.make_codegen_backend {
                 make_codegen_backend(&config.opts)
@@ -356,6 +362,7 @@ pub fn run_compiler<R: Send>(config: Config, f: impl FnOnce(&Compiler) -> R + Se
                 config.opts.unstable_opts.translate_lang.clone(),
                 config.opts.unstable_opts.translate_additional_ftl.as_deref(),
                 config.opts.unstable_opts.translate_directionality_markers,
+                config.opts.unstable_opts.translate_pseudo,
             ) {
                 Ok(bundle) => bundle,
                 Err(e) => {
@@ -392,6 +399,8 @@ pub fn run_compiler<R: Send>(config: Config, f: impl FnOnce(&Compiler) -> R + Se
 
             codegen_backend.init(&sess);
 
+            sess.dcx().set_query_stack_hook(try_describe_query_stack);
+
             let cfg = parse_cfg(&sess.dcx(), config.crate_cfg);
             let mut cfg = config::build_configuration(&sess, cfg);
             util::add_configuration(&mut cfg, &mut sess, &*codegen_backend);
@@ -419,6 +428,16 @@ pub fn run_compiler<R: Send>(config: Config, f: impl FnOnce(&Compiler) -> R + Se
                 register_lints(&sess, &mut lint_store);
                 sess.registered_lints = true;
             }
+            // The groups are in no particular order, they are sorted by name for stable output.
+            let mut groups = lint_store.get_lint_groups().collect::<Vec<_>>();
+            groups.sort_by_key(|&(group, ..)| group);
+            let mut lint_groups = FxHashMap::<String, Vec<String>>::default();
+            for (group, lints, _) in groups {
+                for lint in lints {
+                    lint_groups.entry(lint.to_string()).or_default().push(group.to_string());
+                }
+            }
+            sess.dcx().set_lint_groups(lint_groups);
             sess.lint_store = Some(Lrc::new(lint_store));
 
             let compiler =
@@ -442,10 +461,22 @@ pub fn run_compiler<R: Send>(config: Config, f: impl FnOnce(&Compiler) -> R + Se
     )
 }
 
+/// Describes the queries that are active on the current thread, innermost first, for
+/// `-Z track-diagnostics` and machine-readable ICE reports.
+pub fn try_describe_query_stack() -> Vec<String> {
+    ty::tls::with_context_opt(|icx| match icx {
+        Some(icx) => {
+            ty::print::with_no_queries!(describe_query_stack(QueryCtxt::new(icx.tcx), icx.query))
+        }
+        None => vec![],
+    })
+}
+
 pub fn try_print_query_stack(
     dcx: &DiagCtxt,
     num_frames: Option<usize>,
     file: Option<std::fs::File>,
+    file_frames: Option<usize>,
 ) {
     eprintln!("query stack during panic:");
 
@@ -460,6 +491,7 @@ pub fn try_print_query_stack(
                 dcx,
                 num_frames,
                 file,
+                file_frames,
             ))
         } else {
             0
@@ -1,14 +1,18 @@
 #![allow(rustc::bad_opt_access)]
 use crate::interface::parse_cfg;
 use rustc_data_structures::profiling::TimePassesFormat;
-use rustc_errors::{emitter::HumanReadableErrorType, registry, ColorConfig};
+use rustc_errors::json::{ColumnConvention, ColumnUnit};
+use rustc_errors::{
+    emitter::HumanReadableErrorType, registry, ColorConfig, DelayedBugBacktraces, MacroBacktrace,
+};
 use rustc_session::config::{
     build_configuration, build_session_options, rustc_optgroups, BranchProtection, CFGuard, Cfg,
-    CollapseMacroDebuginfo, DebugInfo, DumpMonoStatsFormat, ErrorOutputType, ExternEntry,
-    ExternLocation, Externs, FunctionReturn, InliningThreshold, Input, InstrumentCoverage,
-    InstrumentXRay, LinkSelfContained, LinkerPluginLto, LocationDetail, LtoCli, NextSolverConfig,
-    OomStrategy, Options, OutFileName, OutputType, OutputTypes, PAuthKey, PacRet, Passes, Polonius,
-    ProcMacroExecutionStrategy, Strip, SwitchWithOptPath, SymbolManglingVersion, WasiExecModel,
+    CollapseMacroDebuginfo, DebugInfo, DiagnosticFilter, DumpMonoStatsFormat, ErrorOutputType,
+    ExternEntry, ExternLocation, Externs, FunctionReturn, InliningThreshold, Input,
+    InstrumentCoverage, InstrumentXRay, LinkSelfContained, LinkerPluginLto, LocationDetail, LtoCli,
+    NextSolverConfig, OomStrategy, Options, OutFileName, OutputType, OutputTypes, PAuthKey, PacRet,
+    Passes, Polonius, ProcMacroExecutionStrategy, Strip, SwitchWithOptPath, SymbolManglingVersion,
+    WasiExecModel,
 };
 use rustc_session::lint::Level;
 use rustc_session::search_paths::SearchPath;
@@ -660,6 +664,15 @@ macro_rules! untracked {
     // tidy-alphabetical-start
     untracked!(assert_incr_state, Some(String::from("loaded")));
     untracked!(deduplicate_diagnostics, false);
+    untracked!(deterministic_diagnostics, true);
+    untracked!(diagnostic_baseline, Some(PathBuf::from("baseline.txt")));
+    untracked!(
+        diagnostic_filter,
+        Some(DiagnosticFilter::ChangedLines(PathBuf::from("changes.patch")))
+    );
+    untracked!(diagnostic_path_base, Some(PathBuf::from("workspace")));
+    untracked!(diagnostics_log, Some(PathBuf::from("diagnostics.jsonl")));
+    untracked!(diff_suggestions, true);
     untracked!(dump_dep_graph, true);
     untracked!(dump_mir, Some(String::from("abc")));
     untracked!(dump_mir_dataflow, true);
@@ -670,22 +683,35 @@ macro_rules! untracked {
     untracked!(dump_mono_stats_format, DumpMonoStatsFormat::Json);
     untracked!(dylib_lto, true);
     untracked!(emit_stack_sizes, true);
+    untracked!(error_limit, NonZeroUsize::new(10));
+    untracked!(explain_inline, Some(5));
     untracked!(future_incompat_test, true);
+    untracked!(group_diagnostics_by_file, true);
+    untracked!(hide_warnings_in, vec![String::from("target/generated/**")]);
     untracked!(hir_stats, true);
     untracked!(identify_regions, true);
     untracked!(incremental_info, true);
     untracked!(incremental_verify_ich, true);
     untracked!(input_stats, true);
+    untracked!(json_array, true);
+    untracked!(
+        json_column_convention,
+        ColumnConvention { unit: ColumnUnit::Utf8, zero_based: true }
+    );
+    untracked!(json_version, Some(1));
     untracked!(link_native_libraries, false);
+    untracked!(lint_summary, true);
     untracked!(llvm_time_trace, true);
+    untracked!(local_paths_in_diagnostics, true);
     untracked!(ls, vec!["all".to_owned()]);
-    untracked!(macro_backtrace, true);
+    untracked!(macro_backtrace, MacroBacktrace::Yes);
     untracked!(meta_stats, true);
     untracked!(mir_include_spans, true);
     untracked!(nll_facts, true);
     untracked!(no_analysis, true);
     untracked!(no_leak_check, true);
     untracked!(no_parallel_llvm, true);
+    untracked!(normalize_diagnostics, true);
     untracked!(parse_only, true);
     // `pre_link_arg` is omitted because it just forwards to `pre_link_args`.
     untracked!(pre_link_args, vec![String::from("abc"), String::from("def")]);
@@ -702,18 +728,22 @@ macro_rules! untracked {
     untracked!(shell_argfiles, true);
     untracked!(span_debug, true);
     untracked!(span_free_formats, true);
+    untracked!(stream_future_incompat, true);
     untracked!(temps_dir, Some(String::from("abc")));
     untracked!(threads, 99);
     untracked!(time_llvm_passes, true);
     untracked!(time_passes, true);
     untracked!(time_passes_format, TimePassesFormat::Json);
     untracked!(trace_macros, true);
+    untracked!(track_delayed_bugs, DelayedBugBacktraces::Never);
     untracked!(track_diagnostics, true);
     untracked!(trim_diagnostic_paths, false);
     untracked!(ui_testing, true);
     untracked!(unpretty, Some("expanded".to_string()));
     untracked!(unstable_options, true);
     untracked!(validate_mir, true);
+    untracked!(verify_suggestions, true);
+    untracked!(warnings_output, Some(PathBuf::from("warnings.txt")));
     untracked!(write_long_types_to_disk, false);
     // tidy-alphabetical-end
 
@@ -752,6 +782,7 @@ macro_rules! tracked {
     tracked!(dual_proc_macros, true);
     tracked!(dwarf_version, Some(5));
     tracked!(emit_thin_lto, false);
+    tracked!(explain_lint_levels, true);
     tracked!(export_executable_symbols, true);
     tracked!(fewer_names, Some(true));
     tracked!(flatten_format_args, false);
@@ -795,6 +826,7 @@ macro_rules! tracked {
     tracked!(packed_bundled_libs, true);
     tracked!(panic_abort_tests, true);
     tracked!(panic_in_drop, PanicStrategy::Abort);
+    tracked!(perf_notes_as_warnings, true);
     tracked!(plt, Some(true));
     tracked!(polonius, Polonius::Legacy);
     tracked!(precise_enum_drop_elaboration, false);
@@ -819,11 +851,14 @@ macro_rules! tracked {
     tracked!(split_lto_unit, Some(true));
     tracked!(src_hash_algorithm, Some(SourceFileHashAlgorithm::Sha1));
     tracked!(stack_protector, StackProtector::All);
+    tracked!(suggest_allow_lints, true);
     tracked!(teach, true);
     tracked!(thinlto, Some(true));
     tracked!(thir_unsafeck, false);
     tracked!(tiny_const_eval_limit, true);
     tracked!(tls_model, Some(TlsModel::GeneralDynamic));
+    tracked!(translate_additional_ftl, Some(PathBuf::from("overlay.ftl")));
+    tracked!(translate_pseudo, true);
     tracked!(translate_remapped_path_to_local_path, false);
     tracked!(trap_unreachable, Some(false));
     tracked!(treat_err_as_bug, NonZeroUsize::new(1));
@@ -303,13 +303,21 @@ lint_improper_ctypes_union_layout_help = consider adding a `#[repr(C)]` or `#[re
 lint_improper_ctypes_union_layout_reason = this union has unspecified layout
 lint_improper_ctypes_union_non_exhaustive = this union is non-exhaustive
 
-# FIXME: we should ordinalize $valid_up_to when we add support for doing so
 lint_invalid_from_utf8_checked = calls to `{$method}` with a invalid literal always return an error
-    .label = the literal was valid UTF-8 up to the {$valid_up_to} bytes
+    .label = the literal was valid UTF-8 up to the { NUMBER($valid_up_to, type: "ordinal") ->
+        [one] {$valid_up_to}st
+        [two] {$valid_up_to}nd
+        [few] {$valid_up_to}rd
+       *[other] {$valid_up_to}th
+    } byte
 
-# FIXME: we should ordinalize $valid_up_to when we add support for doing so
 lint_invalid_from_utf8_unchecked = calls to `{$method}` with a invalid literal are undefined behavior
-    .label = the literal was valid UTF-8 up to the {$valid_up_to} bytes
+    .label = the literal was valid UTF-8 up to the { NUMBER($valid_up_to, type: "ordinal") ->
+        [one] {$valid_up_to}st
+        [two] {$valid_up_to}nd
+        [few] {$valid_up_to}rd
+       *[other] {$valid_up_to}th
+    } byte
 
 lint_invalid_nan_comparisons_eq_ne = incorrect NaN comparison, NaN cannot be directly compared to itself
     .suggestion = use `f32::is_nan()` or `f64::is_nan()` instead
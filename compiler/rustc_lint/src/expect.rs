@@ -1,4 +1,5 @@
 use crate::lints::{Expectation, ExpectationNote};
+use rustc_errors::{DecorateLint, UnfulfilledExpectation};
 use rustc_middle::query::Providers;
 use rustc_middle::ty::TyCtxt;
 use rustc_session::lint::builtin::UNFULFILLED_LINT_EXPECTATIONS;
@@ -10,6 +11,7 @@ pub(crate) fn provide(providers: &mut Providers) {
     *providers = Providers { check_expectations, ..*providers };
 }
 
+#[allow(rustc::diagnostic_outside_of_impl)]
 fn check_expectations(tcx: TyCtxt<'_>, tool_filter: Option<Symbol>) {
     if !tcx.features().active(sym::lint_reasons) {
         return;
@@ -20,6 +22,7 @@ fn check_expectations(tcx: TyCtxt<'_>, tool_filter: Option<Symbol>) {
 
     tracing::debug!(?lint_expectations, ?fulfilled_expectations);
 
+    let mut unfulfilled = Vec::new();
     for (id, expectation) in lint_expectations {
         // This check will always be true, since `lint_expectations` only
         // holds stable ids
@@ -29,15 +32,29 @@ fn check_expectations(tcx: TyCtxt<'_>, tool_filter: Option<Symbol>) {
             {
                 let rationale = expectation.reason.map(|rationale| ExpectationNote { rationale });
                 let note = expectation.is_unfulfilled_lint_expectations.then_some(());
-                tcx.emit_node_span_lint(
+                let decorator = Expectation { rationale, note };
+                tcx.node_span_lint(
                     UNFULFILLED_LINT_EXPECTATIONS,
                     *hir_id,
                     expectation.emission_span,
-                    Expectation { rationale, note },
+                    decorator.msg(),
+                    |diag| {
+                        decorator.decorate_lint(diag);
+                        diag.unfulfilled_expectation(*id);
+                    },
                 );
+                unfulfilled.push(UnfulfilledExpectation {
+                    id: *id,
+                    span: expectation.emission_span,
+                    reason: expectation.reason,
+                });
             }
         } else {
             unreachable!("at this stage all `LintExpectationId`s are stable");
         }
     }
+
+    if tcx.sess.opts.json_unfulfilled_expectations {
+        tcx.dcx().emit_unfulfilled_expectations(&unfulfilled);
+    }
 }
@@ -107,12 +107,14 @@
     ///
     /// This lint is "allow" by default because it can be noisy, and may not be
     /// an actual problem. Decisions about this should be guided with
-    /// profiling and benchmarking.
+    /// profiling and benchmarking. When it warns, it is shown as a
+    /// performance note, which `-D warnings` doesn't turn into an error.
     ///
     /// [`Box`]: https://doc.rust-lang.org/std/boxed/index.html
     VARIANT_SIZE_DIFFERENCES,
     Allow,
-    "detects enums with widely varying variant sizes"
+    "detects enums with widely varying variant sizes",
+    perf_note
 }
 
 declare_lint! {
@@ -329,6 +329,12 @@ pub struct Lint {
     pub feature_gate: Option<Symbol>,
 
     pub crate_level_only: bool,
+
+    /// `true` if this lint is a hint about performance, which is shown as a
+    /// `rustc_errors::Level::PerfNote` instead of a warning when the lint warns.
+    /// Set with `perf_note` in `declare_lint!` and `@perf_note = true;` in
+    /// `declare_tool_lint!`.
+    pub perf_note: bool,
 }
 
 /// Extra information for a future incompatibility lint.
@@ -473,6 +479,7 @@ pub const fn default_fields_for_macro() -> Self {
             future_incompatible: None,
             feature_gate: None,
             crate_level_only: false,
+            perf_note: false,
         }
     }
 
@@ -796,6 +803,9 @@ pub fn buffer_lint_with_diagnostic(
 /// If you have already built the compiler, and you want to make changes to
 /// just the doc comments, then use the `--keep-stage=0` flag with the above
 /// commands to avoid rebuilding the compiler.
+///
+/// The flags of [`Lint`], like `report_in_external_macro`, `crate_level_only`
+/// and `perf_note`, are set by listing them after the description.
 #[macro_export]
 macro_rules! declare_lint {
     ($(#[$attr:meta])* $vis: vis $NAME: ident, $Level: ident, $desc: expr) => (
@@ -835,20 +845,29 @@ macro_rules! declare_tool_lint {
     (
         $(#[$attr:meta])* $vis:vis $tool:ident ::$NAME:ident, $Level: ident, $desc: expr
         $(, @feature_gate = $gate:expr;)?
+        $(, @perf_note = $perf_note:expr;)?
     ) => (
-        $crate::declare_tool_lint!{$(#[$attr])* $vis $tool::$NAME, $Level, $desc, false $(, @feature_gate = $gate;)?}
+        $crate::declare_tool_lint!{
+            $(#[$attr])* $vis $tool::$NAME, $Level, $desc, false
+            $(, @feature_gate = $gate;)? $(, @perf_note = $perf_note;)?
+        }
     );
     (
         $(#[$attr:meta])* $vis:vis $tool:ident ::$NAME:ident, $Level:ident, $desc:expr,
         report_in_external_macro: $rep:expr
         $(, @feature_gate = $gate:expr;)?
+        $(, @perf_note = $perf_note:expr;)?
     ) => (
-         $crate::declare_tool_lint!{$(#[$attr])* $vis $tool::$NAME, $Level, $desc, $rep $(, @feature_gate = $gate;)?}
+        $crate::declare_tool_lint!{
+            $(#[$attr])* $vis $tool::$NAME, $Level, $desc, $rep
+            $(, @feature_gate = $gate;)? $(, @perf_note = $perf_note;)?
+        }
     );
     (
         $(#[$attr:meta])* $vis:vis $tool:ident ::$NAME:ident, $Level:ident, $desc:expr,
         $external:expr
         $(, @feature_gate = $gate:expr;)?
+        $(, @perf_note = $perf_note:expr;)?
     ) => (
         $(#[$attr])*
         $vis static $NAME: &$crate::Lint = &$crate::Lint {
@@ -861,6 +880,7 @@ macro_rules! declare_tool_lint {
             is_loaded: true,
             $(feature_gate: Some($gate),)?
             crate_level_only: false,
+            $(perf_note: $perf_note,)?
             ..$crate::Lint::default_fields_for_macro()
         };
     );
@@ -4,7 +4,8 @@
 
 use crate::diagnostics::diagnostic_builder::DiagnosticDeriveKind;
 use crate::diagnostics::error::{span_err, DiagnosticDeriveError};
-use crate::diagnostics::utils::SetOnce;
+use crate::diagnostics::fluent::check_arguments;
+use crate::diagnostics::utils::{add_arg_bounds, SetOnce};
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::spanned::Spanned;
@@ -22,6 +23,7 @@ pub(crate) fn new(structure: Structure<'a>) -> Self {
 
     pub(crate) fn into_tokens(self) -> TokenStream {
         let DiagnosticDerive { mut structure } = self;
+        add_arg_bounds(&mut structure);
         let kind = DiagnosticDeriveKind::Diagnostic;
         let slugs = RefCell::new(Vec::new());
         let implementation = kind.each_variant(&mut structure, |mut builder, variant| {
@@ -49,6 +51,7 @@ pub(crate) fn into_tokens(self) -> TokenStream {
                     return DiagnosticDeriveError::ErrorHandled.to_compile_error();
                 }
                 Some(slug) => {
+                    check_arguments(slug, variant);
                     slugs.borrow_mut().push(slug.clone());
                     quote! {
                         let mut diag = rustc_errors::DiagnosticBuilder::new(
@@ -107,6 +110,7 @@ pub(crate) fn new(structure: Structure<'a>) -> Self {
 
     pub(crate) fn into_tokens(self) -> TokenStream {
         let LintDiagnosticDerive { mut structure } = self;
+        add_arg_bounds(&mut structure);
         let kind = DiagnosticDeriveKind::LintDiagnostic;
         let implementation = kind.each_variant(&mut structure, |mut builder, variant| {
             let preamble = builder.preamble(variant);
@@ -147,6 +151,7 @@ pub(crate) fn into_tokens(self) -> TokenStream {
                     DiagnosticDeriveError::ErrorHandled.to_compile_error()
                 }
                 Some(slug) => {
+                    check_arguments(slug, variant);
                     slugs.borrow_mut().push(slug.clone());
                     quote! {
                         crate::fluent_generated::#slug.into()
@@ -203,12 +208,13 @@ fn check(slug: &syn::Path) -> Option<Mismatch> {
 }
 
 /// Generates a `#[test]` that verifies that all referenced variables
-/// exist on this structure.
+/// exist on this structure. Crates built by Cargo are already checked by `check_arguments`, this
+/// covers those that aren't, like tests of the derives.
 fn generate_test(slug: &syn::Path, structure: &Structure<'_>) -> TokenStream {
     // FIXME: We can't identify variables in a subdiagnostic
     for field in structure.variants().iter().flat_map(|v| v.ast().fields.iter()) {
         for attr_name in field.attrs.iter().filter_map(|at| at.path().get_ident()) {
-            if attr_name == "subdiagnostic" {
+            if attr_name == "subdiagnostic" || attr_name == "diag_flatten" {
                 return quote!();
             }
         }
@@ -4,14 +4,14 @@
     span_err, throw_invalid_attr, throw_span_err, DiagnosticDeriveError,
 };
 use crate::diagnostics::utils::{
-    build_field_mapping, is_doc_comment, report_error_if_not_applied_to_span, report_type_error,
-    should_generate_arg, type_is_bool, type_is_unit, type_matches_path, FieldInfo, FieldInnerTy,
-    FieldMap, HasFieldMap, SetOnce, SpannedOption, SubdiagnosticKind,
+    FieldInfo, FieldInnerTy, FieldMap, HasFieldMap, SetOnce, SpannedOption, SubdiagnosticKind,
+    build_field_mapping, is_doc_comment, is_label_with_arg, report_error_if_not_applied_to_span,
+    report_type_error, should_generate_arg, type_is_bool, type_is_unit, type_matches_path,
 };
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote, quote_spanned};
 use syn::Token;
-use syn::{parse_quote, spanned::Spanned, Attribute, Meta, Path, Type};
+use syn::{parse_quote, spanned::Spanned, Attribute, LitStr, Meta, Path, Type};
 use synstructure::{BindingInfo, Structure, VariantInfo};
 
 use super::utils::SubdiagnosticVariant;
@@ -314,6 +314,10 @@ fn generate_inner_field_code(
             // Don't need to do anything - by virtue of the attribute existing, the
             // `arg` call will not be generated.
             (Meta::Path(_), "skip_arg") => return Ok(quote! {}),
+            // Flattened fields add their own arguments and subdiagnostics.
+            (Meta::Path(_), "diag_flatten") => {
+                return Ok(quote! { diag.subdiagnostic(#binding); });
+            }
             (Meta::Path(_), "primary_span") => {
                 match self.kind {
                     DiagnosticDeriveKind::Diagnostic => {
@@ -345,30 +349,68 @@ fn generate_inner_field_code(
                 let err = || {
                     span_err(
                         meta_list.span().unwrap(),
-                        "`eager` is the only supported nested attribute for `subdiagnostic`",
+                        "`eager` and `if = \"...\"` are the only supported nested attributes for \
+                         `subdiagnostic`",
                     )
                     .emit();
                 };
 
-                let Ok(p): Result<Path, _> = meta_list.parse_args() else {
+                let mut eager = false;
+                let mut condition = None;
+                let parsed = meta_list.parse_nested_meta(|nested| {
+                    if nested.path.is_ident("eager") {
+                        eager = true;
+                        Ok(())
+                    } else if nested.path.is_ident("if") {
+                        condition = Some(nested.value()?.parse::<LitStr>()?);
+                        Ok(())
+                    } else {
+                        Err(nested.error("unsupported nested attribute"))
+                    }
+                });
+                if parsed.is_err() {
                     err();
                     return Ok(quote! {});
-                };
+                }
 
-                if !p.is_ident("eager") {
-                    err();
-                    return Ok(quote! {});
+                if eager {
+                    match &self.kind {
+                        DiagnosticDeriveKind::Diagnostic => {}
+                        DiagnosticDeriveKind::LintDiagnostic => {
+                            throw_invalid_attr!(attr, |diag| {
+                                diag.help("eager subdiagnostics are not supported on lints")
+                            })
+                        }
+                    };
+                } else if FieldInnerTy::from_type(&info.binding.ast().ty).will_iterate() {
+                    // Same as without nested attributes.
+                    eager = self.kind == DiagnosticDeriveKind::Diagnostic;
                 }
+                let add = if eager {
+                    quote! { diag.eager_subdiagnostic(dcx, #binding); }
+                } else {
+                    quote! { diag.subdiagnostic(#binding); }
+                };
 
-                match &self.kind {
-                    DiagnosticDeriveKind::Diagnostic => {}
-                    DiagnosticDeriveKind::LintDiagnostic => {
-                        throw_invalid_attr!(attr, |diag| {
-                            diag.help("eager subdiagnostics are not supported on lints")
-                        })
-                    }
+                // The condition names a `bool` field, optionally prefixed with `self.`.
+                let Some(condition) = condition else {
+                    return Ok(add);
                 };
-                return Ok(quote! { diag.eager_subdiagnostic(dcx, #binding); });
+                let value = condition.value();
+                let field = value.strip_prefix("self.").unwrap_or(&value);
+                let Some(condition_binding) = self.get_field_binding(&field.to_string()) else {
+                    span_err(
+                        condition.span().unwrap(),
+                        format!("`{field}` doesn't refer to a field on this type"),
+                    )
+                    .emit();
+                    return Ok(quote! {});
+                };
+                return Ok(quote! {
+                    if #condition_binding {
+                        #add
+                    }
+                });
             }
             _ => (),
         }
@@ -381,6 +423,22 @@ fn generate_inner_field_code(
         let fn_ident = format_ident!("{}", subdiag);
         match subdiag {
             SubdiagnosticKind::Label => {
+                let field = info.binding.ast();
+                if is_label_with_arg(field)
+                    && let Some(name) = &field.ident
+                {
+                    let name = name.to_string();
+                    return Ok(quote! {
+                        diag.span_label_with_args(
+                            #binding.0,
+                            crate::fluent_generated::#slug,
+                            [(
+                                std::borrow::Cow::Borrowed(#name),
+                                rustc_errors::IntoDiagnosticArg::into_diagnostic_arg(#binding.1),
+                            )],
+                        );
+                    });
+                }
                 report_error_if_not_applied_to_span(attr, &info)?;
                 Ok(self.add_spanned_subdiagnostic(binding, &fn_ident, slug))
             }
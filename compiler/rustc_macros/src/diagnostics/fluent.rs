@@ -0,0 +1,223 @@
+//! Checks of the arguments of derived diagnostics against the variables that their Fluent
+//! messages reference, so that mismatches are reported when the crate is built instead of only
+//! showing up as missing arguments in the translated output.
+
+use crate::diagnostics::error::span_err;
+use crate::diagnostics::utils::{is_label_with_arg, should_generate_arg};
+use fluent_syntax::ast::{
+    CallArguments, Entry, Expression, InlineExpression, Pattern, PatternElement,
+};
+use proc_macro::{Diagnostic, Level};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use syn::{Ident, LitStr, Path};
+use synstructure::VariantInfo;
+
+#[cfg(test)]
+mod tests;
+
+/// The variables that each message of a Fluent resource references, in its value or attributes.
+type MessageVariables = HashMap<String, BTreeSet<String>>;
+
+/// Returns the variables of the messages in the Fluent resource of the crate being built, or
+/// `None` if it can't be found, like when the derive isn't used from a crate built by Cargo.
+/// Resources are only read and parsed once for all derives of a crate.
+fn message_variables() -> Option<Arc<MessageVariables>> {
+    static RESOURCES: Mutex<Option<HashMap<PathBuf, Option<Arc<MessageVariables>>>>> =
+        Mutex::new(None);
+
+    // Every crate using the derives has its messages in `messages.ftl` at its root.
+    let path = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR")?).join("messages.ftl");
+    let mut resources = RESOURCES.lock().unwrap();
+    let resources = resources.get_or_insert_with(HashMap::new);
+    resources
+        .entry(path)
+        .or_insert_with_key(|path| {
+            let source = std::fs::read_to_string(path).ok()?;
+            Some(Arc::new(parse_message_variables(&source)))
+        })
+        .clone()
+}
+
+fn parse_message_variables(source: &str) -> MessageVariables {
+    // Syntax errors are reported by `fluent_messages!`, messages that could be parsed are still
+    // checked.
+    let resource = match fluent_syntax::parser::parse(source) {
+        Ok(resource) => resource,
+        Err((resource, _)) => resource,
+    };
+    let mut messages = MessageVariables::new();
+    for entry in resource.body {
+        let Entry::Message(message) = entry else { continue };
+        let mut variables = BTreeSet::new();
+        let patterns = message.value.iter().chain(message.attributes.iter().map(|a| &a.value));
+        for pattern in patterns {
+            pattern_variables(pattern, &mut variables);
+        }
+        messages.insert(message.id.name.to_string(), variables);
+    }
+    messages
+}
+
+fn pattern_variables(pattern: &Pattern<&str>, variables: &mut BTreeSet<String>) {
+    for element in &pattern.elements {
+        let PatternElement::Placeable { expression } = element else { continue };
+        expression_variables(expression, variables);
+    }
+}
+
+fn expression_variables(expression: &Expression<&str>, variables: &mut BTreeSet<String>) {
+    match expression {
+        Expression::Select { selector, variants } => {
+            inline_expression_variables(selector, variables);
+            for variant in variants {
+                pattern_variables(&variant.value, variables);
+            }
+        }
+        Expression::Inline(expression) => inline_expression_variables(expression, variables),
+    }
+}
+
+fn inline_expression_variables(
+    expression: &InlineExpression<&str>,
+    variables: &mut BTreeSet<String>,
+) {
+    match expression {
+        InlineExpression::VariableReference { id } => {
+            variables.insert(id.name.to_string());
+        }
+        InlineExpression::FunctionReference {
+            arguments: CallArguments { positional, named },
+            ..
+        } => {
+            for argument in positional.iter().chain(named.iter().map(|named| &named.value)) {
+                inline_expression_variables(argument, variables);
+            }
+        }
+        InlineExpression::Placeable { expression } => expression_variables(expression, variables),
+        // Variables of referenced messages and terms aren't arguments of this message.
+        InlineExpression::StringLiteral { .. }
+        | InlineExpression::NumberLiteral { .. }
+        | InlineExpression::MessageReference { .. }
+        | InlineExpression::TermReference { .. } => {}
+    }
+}
+
+/// Checks that the fields of `variant` provide every variable that its message `slug`
+/// references, and warns about fields that are added as arguments but neither that message nor
+/// the other messages and code suggestions of the variant use.
+pub(crate) fn check_arguments(slug: &Path, variant: &VariantInfo<'_>) {
+    let Some(messages) = message_variables() else { return };
+    let Some(slug) = slug.get_ident() else { return };
+    let Some((missing, unused)) = mismatched_arguments(&messages, slug, variant) else { return };
+
+    for variable in missing {
+        span_err(
+            slug.span().unwrap(),
+            format!("`{slug}` references `${variable}`, which isn't an argument of the diagnostic"),
+        )
+        .help(format!("add a field named `{variable}` without attributes"))
+        .emit();
+    }
+    for arg in unused {
+        Diagnostic::spanned(
+            arg.span().unwrap(),
+            Level::Warning,
+            format!("argument `{arg}` isn't used by the Fluent messages of the diagnostic"),
+        )
+        .help("remove the field, or add `#[skip_arg]` if it's only used elsewhere")
+        .emit();
+    }
+}
+
+/// Returns the variables that the message `slug` references but no field of `variant` provides,
+/// and the fields that are added as arguments but that no message or code suggestion of the
+/// variant uses, or `None` if the arguments of `variant` can't be checked.
+fn mismatched_arguments<'v>(
+    messages: &MessageVariables,
+    slug: &Ident,
+    variant: &VariantInfo<'v>,
+) -> Option<(Vec<String>, Vec<&'v Ident>)> {
+    // Unknown slugs are an error where the constant for them is used.
+    let referenced = messages.get(&slug.to_string())?;
+
+    let fields = &variant.ast().fields;
+    // Subdiagnostics and flattened fields add arguments of their own, and their messages may use
+    // those of the diagnostic.
+    if fields
+        .iter()
+        .flat_map(|field| &field.attrs)
+        .any(|attr| attr.path().is_ident("subdiagnostic") || attr.path().is_ident("diag_flatten"))
+    {
+        return None;
+    }
+    let args: Vec<_> = fields
+        .iter()
+        .filter(|field| should_generate_arg(field))
+        .filter_map(|field| field.ident.as_ref())
+        .collect();
+    // Labels with arguments of their own provide them for the messages of the variant, too.
+    let label_args: Vec<_> = fields
+        .iter()
+        .filter(|field| is_label_with_arg(field))
+        .filter_map(|field| field.ident.as_ref())
+        .collect();
+
+    let missing = referenced
+        .iter()
+        .filter(|variable| !args.iter().chain(&label_args).any(|arg| arg == variable))
+        .cloned()
+        .collect();
+
+    let mut used = referenced.clone();
+    let attrs = variant.ast().attrs.iter().chain(fields.iter().flat_map(|field| &field.attrs));
+    for attr in attrs {
+        attribute_variables(attr.to_token_stream(), messages, &mut used);
+    }
+    let unused = args.into_iter().filter(|arg| !used.contains(&arg.to_string())).collect();
+    Some((missing, unused))
+}
+
+/// Adds the variables that the messages named in an attribute reference and the arguments that
+/// the format strings of its code suggestions use.
+fn attribute_variables(
+    tokens: TokenStream,
+    messages: &MessageVariables,
+    variables: &mut BTreeSet<String>,
+) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => attribute_variables(group.stream(), messages, variables),
+            TokenTree::Ident(ident) => {
+                if let Some(referenced) = messages.get(&ident.to_string()) {
+                    variables.extend(referenced.iter().cloned());
+                }
+            }
+            TokenTree::Literal(literal) => {
+                let Ok(literal) = syn::parse2::<LitStr>(TokenTree::Literal(literal).into()) else {
+                    continue;
+                };
+                format_string_arguments(&literal.value(), variables);
+            }
+            TokenTree::Punct(_) => {}
+        }
+    }
+}
+
+/// Adds the names of the arguments of a format string like `{name}` or `{name:?}`.
+fn format_string_arguments(format: &str, variables: &mut BTreeSet<String>) {
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        if let Some(escaped) = rest.strip_prefix('{') {
+            rest = escaped;
+            continue;
+        }
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        variables.insert(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+}
@@ -0,0 +1,49 @@
+use super::*;
+
+const MESSAGES: &str = r#"
+diag_message = {$missing} and {$used} with {NUMBER($count)}
+    .label = {$kind ->
+        [struct] a struct
+       *[other] {other_message}
+    }
+other_message = {$unrelated}
+diag_label_message = {$from_label}
+"#;
+
+#[test]
+fn message_variables_of_values_and_attributes() {
+    let messages = parse_message_variables(MESSAGES);
+    let variables =
+        |message: &str| messages[message].iter().map(String::as_str).collect::<Vec<_>>();
+    // Variables of referenced messages aren't arguments of the message that references them.
+    assert_eq!(variables("diag_message"), ["count", "kind", "missing", "used"]);
+    assert_eq!(variables("other_message"), ["unrelated"]);
+}
+
+#[test]
+fn mismatched_arguments_of_variant() {
+    let messages = parse_message_variables(MESSAGES);
+    let input: syn::DeriveInput = syn::parse_quote! {
+        #[diag(diag_message)]
+        struct Diag {
+            #[primary_span]
+            #[label(diag_label_message)]
+            span: Span,
+            used: u32,
+            count: u32,
+            kind: &'static str,
+            from_label: u32,
+            unused: u32,
+            #[skip_arg]
+            skipped: u32,
+        }
+    };
+    let structure = synstructure::Structure::new(&input);
+    let slug: Ident = syn::parse_quote!(diag_message);
+    let (missing, unused) =
+        mismatched_arguments(&messages, &slug, &structure.variants()[0]).unwrap();
+    // `from_label` is used by the message of the label.
+    assert_eq!(missing, ["missing"]);
+    let unused = unused.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(unused, ["unused"]);
+}
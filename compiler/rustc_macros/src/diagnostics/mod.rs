@@ -1,6 +1,7 @@
 mod diagnostic;
 mod diagnostic_builder;
 mod error;
+mod fluent;
 mod subdiagnostic;
 mod utils;
 
@@ -53,6 +54,14 @@
 /// });
 /// ```
 ///
+/// A `#[label]` on a field of type `(Span, T)`, or a `Vec` or `Option` of it, adds the `T` as an
+/// argument of the label only, named after the field, so that every label can have its own
+/// value.
+///
+/// When the crate is built by Cargo, the variables that the message references are checked
+/// against the fields that are added as arguments while it is built, see
+/// `fluent::check_arguments`.
+///
 /// See rustc dev guide for more examples on using the `#[derive(Diagnostic)]`:
 /// <https://rustc-dev-guide.rust-lang.org/diagnostics/diagnostic-structs.html>
 pub fn session_diagnostic_derive(s: Structure<'_>) -> TokenStream {
@@ -4,7 +4,7 @@
     invalid_attr, span_err, throw_invalid_attr, throw_span_err, DiagnosticDeriveError,
 };
 use crate::diagnostics::utils::{
-    build_field_mapping, build_suggestion_code, is_doc_comment, new_code_ident,
+    add_arg_bounds, build_field_mapping, build_suggestion_code, is_doc_comment, new_code_ident,
     report_error_if_not_applied_to_applicability, report_error_if_not_applied_to_span,
     should_generate_arg, AllowMultipleAlternatives, FieldInfo, FieldInnerTy, FieldMap, HasFieldMap,
     SetOnce, SpannedOption, SubdiagnosticKind,
@@ -20,16 +20,19 @@
 pub(crate) struct SubdiagnosticDeriveBuilder {
     diag: syn::Ident,
     f: syn::Ident,
+    /// Set to true for enums with `#[shared]`, whose variants use the primary span of the
+    /// subdiagnostic containing them.
+    shared: bool,
 }
 
 impl SubdiagnosticDeriveBuilder {
     pub(crate) fn new() -> Self {
         let diag = format_ident!("diag");
         let f = format_ident!("f");
-        Self { diag, f }
+        Self { diag, f, shared: false }
     }
 
-    pub(crate) fn into_tokens(self, mut structure: Structure<'_>) -> TokenStream {
+    pub(crate) fn into_tokens(mut self, mut structure: Structure<'_>) -> TokenStream {
         let implementation = {
             let ast = structure.ast();
             let span = ast.span().unwrap();
@@ -52,6 +55,13 @@ pub(crate) fn into_tokens(self, mut structure: Structure<'_>) -> TokenStream {
                         continue;
                     }
 
+                    if let Meta::Path(path) = &attr.meta
+                        && path.is_ident("shared")
+                    {
+                        self.shared = true;
+                        continue;
+                    }
+
                     span_err(
                         attr.span().unwrap(),
                         "unsupported type attribute for subdiagnostic enum",
@@ -60,6 +70,7 @@ pub(crate) fn into_tokens(self, mut structure: Structure<'_>) -> TokenStream {
                 }
             }
 
+            add_arg_bounds(&mut structure);
             structure.bind_with(|_| synstructure::BindStyle::Move);
             let variants_ = structure.each_variant(|variant| {
                 let mut builder = SubdiagnosticDeriveVariantBuilder {
@@ -69,9 +80,12 @@ pub(crate) fn into_tokens(self, mut structure: Structure<'_>) -> TokenStream {
                     formatting_init: TokenStream::new(),
                     fields: build_field_mapping(variant),
                     span_field: None,
+                    span_field_is_vec: false,
                     applicability: None,
                     has_suggestion_parts: false,
                     is_enum,
+                    flatten: None,
+                    shared_fields: vec![],
                 };
                 builder.into_tokens().unwrap_or_else(|v| v.to_compile_error())
             });
@@ -85,6 +99,27 @@ pub(crate) fn into_tokens(self, mut structure: Structure<'_>) -> TokenStream {
 
         let diag = &self.diag;
         let f = &self.f;
+        if self.shared {
+            return structure.gen_impl(quote! {
+                gen impl rustc_errors::AddToDiagnosticShared for @Self {
+                    fn add_to_diagnostic_shared<__F>(
+                        self,
+                        #diag: &mut rustc_errors::Diagnostic,
+                        __shared_span: rustc_span::Span,
+                        #f: __F,
+                    )
+                    where
+                        __F: core::ops::Fn(
+                            &mut rustc_errors::Diagnostic,
+                            rustc_errors::SubdiagnosticMessage
+                        ) -> rustc_errors::SubdiagnosticMessage,
+                    {
+                        #implementation
+                    }
+                }
+            });
+        }
+
         let ret = structure.gen_impl(quote! {
             gen impl rustc_errors::AddToDiagnostic for @Self {
                 fn add_to_diagnostic_with<__F>(self, #diag: &mut rustc_errors::Diagnostic, #f: __F)
@@ -125,6 +160,10 @@ struct SubdiagnosticDeriveVariantBuilder<'parent, 'a> {
     /// Identifier for the binding to the `#[primary_span]` field.
     span_field: SpannedOption<proc_macro2::Ident>,
 
+    /// Set to true when the `#[primary_span]` field is a `Vec<Span>`, in which case labels are
+    /// applied to each of the spans.
+    span_field_is_vec: bool,
+
     /// The binding to the `#[applicability]` field, if present.
     applicability: SpannedOption<TokenStream>,
 
@@ -134,6 +173,14 @@ struct SubdiagnosticDeriveVariantBuilder<'parent, 'a> {
 
     /// Set to true when this variant is an enum variant rather than just the body of a struct.
     is_enum: bool,
+
+    /// Span of the `#[diag_flatten]` attribute, if present. Flattened subdiagnostics have no
+    /// message of their own and only add their fields to the diagnostic.
+    flatten: Option<proc_macro::Span>,
+
+    /// Bindings to `#[shared]` fields, which are added after the other fields using the primary
+    /// span of this subdiagnostic.
+    shared_fields: Vec<proc_macro2::Ident>,
 }
 
 impl<'parent, 'a> HasFieldMap for SubdiagnosticDeriveVariantBuilder<'parent, 'a> {
@@ -187,6 +234,13 @@ fn identify_kind(
         let mut kind_slugs = vec![];
 
         for attr in self.variant.ast().attrs {
+            if let Meta::Path(path) = &attr.meta
+                && path.is_ident("diag_flatten")
+            {
+                self.flatten = Some(attr.span().unwrap());
+                continue;
+            }
+
             let Some(SubdiagnosticVariant { kind, slug, no_span }) =
                 SubdiagnosticVariant::from_attr(attr, self)?
             else {
@@ -210,6 +264,12 @@ fn identify_kind(
             kind_slugs.push((kind, slug, no_span));
         }
 
+        if let Some(span) = self.flatten
+            && !kind_slugs.is_empty()
+        {
+            throw_span_err!(span, "`#[diag_flatten]` cannot be combined with a subdiagnostic kind");
+        }
+
         Ok(kind_slugs)
     }
 
@@ -258,6 +318,11 @@ fn generate_field_attr_code(
                     .generate_field_code_inner(kind_stats, attr, info, inner_ty.will_iterate())
                     .unwrap_or_else(|v| v.to_compile_error());
 
+                // `Vec<Span>` primary spans are used as a whole, so don't iterate over them.
+                if attr.path().is_ident("primary_span") || attr.path().is_ident("shared") {
+                    return generated;
+                }
+
                 inner_ty.with(binding, generated)
             })
             .collect()
@@ -300,6 +365,18 @@ fn generate_field_code_inner_path(
 
         match name {
             "skip_arg" => Ok(quote! {}),
+            "diag_flatten" => {
+                let diag = &self.parent.diag;
+                let binding = &info.binding.binding;
+                Ok(quote! { #diag.subdiagnostic(#binding); })
+            }
+            "shared" => {
+                if !matches!(info.ty, FieldInnerTy::Plain(_)) {
+                    throw_invalid_attr!(attr);
+                }
+                self.shared_fields.push(info.binding.binding.clone());
+                Ok(quote! {})
+            }
             "primary_span" => {
                 if kind_stats.has_multipart_suggestion {
                     invalid_attr(attr)
@@ -314,7 +391,10 @@ fn generate_field_code_inner_path(
                     let binding = info.binding.binding.clone();
                     // FIXME(#100717): support `Option<Span>` on `primary_span` like in the
                     // diagnostic derive
-                    if !matches!(info.ty, FieldInnerTy::Plain(_)) {
+                    let is_vec = matches!(info.ty, FieldInnerTy::Vec(_));
+                    if !matches!(info.ty, FieldInnerTy::Plain(_))
+                        && !(is_vec && !kind_stats.has_normal_suggestion)
+                    {
                         throw_invalid_attr!(attr, |diag| {
                             let diag = diag.note("there must be exactly one primary span");
 
@@ -330,6 +410,7 @@ fn generate_field_code_inner_path(
                     }
 
                     self.span_field.set_once(binding, span);
+                    self.span_field_is_vec = is_vec;
                 }
 
                 Ok(quote! {})
@@ -479,7 +560,7 @@ fn generate_field_code_inner_list(
 
     pub(crate) fn into_tokens(&mut self) -> Result<TokenStream, DiagnosticDeriveError> {
         let kind_slugs = self.identify_kind()?;
-        if kind_slugs.is_empty() {
+        if kind_slugs.is_empty() && self.flatten.is_none() {
             if self.is_enum {
                 // It's okay for a variant to not be a subdiagnostic at all..
                 return Ok(quote! {});
@@ -509,7 +590,16 @@ pub(crate) fn into_tokens(&mut self) -> Result<TokenStream, DiagnosticDeriveErro
             .map(|binding| self.generate_field_attr_code(binding, kind_stats))
             .collect();
 
-        let span_field = self.span_field.value_ref();
+        let shared_span = format_ident!("__shared_span");
+        let span_field = self.span_field.value_ref().or(self.parent.shared.then_some(&shared_span));
+        // A `Vec<Span>` can be used for more than one message, so it needs to be cloned.
+        let span_arg = span_field.map(|span| {
+            if self.span_field_is_vec {
+                quote! { #span.clone() }
+            } else {
+                quote! { #span }
+            }
+        });
 
         let diag = &self.parent.diag;
         let f = &self.parent.f;
@@ -568,7 +658,15 @@ pub(crate) fn into_tokens(&mut self) -> Result<TokenStream, DiagnosticDeriveErro
                     quote! { #diag.#name(#message, suggestions, #applicability, #style); }
                 }
                 SubdiagnosticKind::Label => {
-                    if let Some(span) = span_field {
+                    if let Some(span) = span_field
+                        && self.span_field_is_vec
+                    {
+                        quote! {
+                            for __span in #span.iter() {
+                                #diag.#name(*__span, #message.clone());
+                            }
+                        }
+                    } else if let Some(span) = span_field {
                         quote! { #diag.#name(#span, #message); }
                     } else {
                         span_err(self.span, "label without `#[primary_span]` field").emit();
@@ -576,7 +674,7 @@ pub(crate) fn into_tokens(&mut self) -> Result<TokenStream, DiagnosticDeriveErro
                     }
                 }
                 _ => {
-                    if let Some(span) = span_field
+                    if let Some(span) = &span_arg
                         && !no_span
                     {
                         quote! { #diag.#name(#span, #message); }
@@ -597,6 +695,26 @@ pub(crate) fn into_tokens(&mut self) -> Result<TokenStream, DiagnosticDeriveErro
             .map(|binding| self.generate_field_arg(binding))
             .collect();
 
+        let shared_calls: TokenStream = self
+            .shared_fields
+            .iter()
+            .map(|binding| match span_field {
+                Some(span) if !self.span_field_is_vec => quote! {
+                    rustc_errors::AddToDiagnosticShared::add_to_diagnostic_shared(
+                        #binding,
+                        #diag,
+                        #span,
+                        &#f,
+                    );
+                },
+                _ => {
+                    span_err(self.span, "`#[shared]` field without a `#[primary_span]` field")
+                        .emit();
+                    quote! { unreachable!(); }
+                }
+            })
+            .collect();
+
         let formatting_init = &self.formatting_init;
         Ok(quote! {
             #init
@@ -604,6 +722,7 @@ pub(crate) fn into_tokens(&mut self) -> Result<TokenStream, DiagnosticDeriveErro
             #attr_args
             #plain_args
             #calls
+            #shared_calls
         })
     }
 }
@@ -2,17 +2,17 @@
     span_err, throw_invalid_attr, throw_span_err, DiagnosticDeriveError,
 };
 use proc_macro::Span;
-use proc_macro2::{Ident, TokenStream};
+use proc_macro2::{Ident, TokenStream, TokenTree};
 use quote::{format_ident, quote, ToTokens};
 use std::cell::RefCell;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::str::FromStr;
 use syn::meta::ParseNestedMeta;
 use syn::punctuated::Punctuated;
-use syn::{parenthesized, LitStr, Path, Token};
+use syn::{parenthesized, parse_quote, LitStr, Path, Token, WherePredicate};
 use syn::{spanned::Spanned, Attribute, Field, Meta, Type, TypeTuple};
-use synstructure::{BindingInfo, VariantInfo};
+use synstructure::{AddBounds, BindingInfo, Structure, VariantInfo};
 
 use super::error::invalid_attr;
 
@@ -121,6 +121,17 @@ pub(crate) fn report_error_if_not_applied_to_span(
     Ok(())
 }
 
+/// Whether the field is a `#[label]` on a `(Span, T)`, whose `T` is an argument that only the
+/// label uses, named after the field. This allows a `Vec` of labels with different values.
+pub(crate) fn is_label_with_arg(field: &syn::Field) -> bool {
+    let Type::Tuple(tuple) = FieldInnerTy::from_type(&field.ty).inner_type() else {
+        return false;
+    };
+    field.attrs.iter().any(|attr| attr.path().is_ident("label"))
+        && tuple.elems.len() == 2
+        && type_matches_path(&tuple.elems[0], &["rustc_span", "Span"])
+}
+
 /// Inner type of a field and type of wrapper.
 #[derive(Copy, Clone)]
 pub(crate) enum FieldInnerTy<'ty> {
@@ -507,6 +518,7 @@ pub(super) enum SuggestionKind {
     Hidden,
     Verbose,
     ToolOnly,
+    Diff,
 }
 
 impl FromStr for SuggestionKind {
@@ -519,6 +531,7 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
             "hidden" => Ok(SuggestionKind::Hidden),
             "verbose" => Ok(SuggestionKind::Verbose),
             "tool-only" => Ok(SuggestionKind::ToolOnly),
+            "diff" => Ok(SuggestionKind::Diff),
             _ => Err(()),
         }
     }
@@ -532,6 +545,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             SuggestionKind::Hidden => write!(f, "hidden"),
             SuggestionKind::Verbose => write!(f, "verbose"),
             SuggestionKind::ToolOnly => write!(f, "tool-only"),
+            SuggestionKind::Diff => write!(f, "diff"),
         }
     }
 }
@@ -554,6 +568,9 @@ pub(crate) fn to_suggestion_style(&self) -> TokenStream {
             SuggestionKind::ToolOnly => {
                 quote! { rustc_errors::SuggestionStyle::CompletelyHidden }
             }
+            SuggestionKind::Diff => {
+                quote! { rustc_errors::SuggestionStyle::Diff }
+            }
         }
     }
 
@@ -771,7 +788,7 @@ macro_rules! get_string {
 
                     let value = value.value().parse().unwrap_or_else(|()| {
                         span_err(value.span().unwrap(), "invalid suggestion style")
-                            .help("valid styles are `normal`, `short`, `hidden`, `verbose` and `tool-only`")
+                            .help("valid styles are `normal`, `short`, `hidden`, `verbose`, `tool-only` and `diff`")
                             .emit();
                         has_errors = true;
                         SuggestionKind::Normal
@@ -873,3 +890,40 @@ pub(super) fn should_generate_arg(field: &Field) -> bool {
 pub(super) fn is_doc_comment(attr: &Attribute) -> bool {
     attr.path().segments.last().unwrap().ident == "doc"
 }
+
+/// Replaces the bounds that `synstructure` would add by default (the derived trait on every
+/// generic parameter) with `IntoDiagnosticArg` bounds on the types of fields that are added as
+/// arguments and mention a generic type parameter.
+pub(super) fn add_arg_bounds(structure: &mut Structure<'_>) {
+    structure.add_bounds(AddBounds::None);
+
+    let params: Vec<Ident> =
+        structure.ast().generics.type_params().map(|param| param.ident.clone()).collect();
+    if params.is_empty() {
+        return;
+    }
+
+    let mut seen = HashSet::new();
+    let mut predicates: Vec<WherePredicate> = vec![];
+    for binding in structure.variants().iter().flat_map(|variant| variant.bindings()) {
+        let ty = &binding.ast().ty;
+        if should_generate_arg(binding.ast())
+            && mentions_any(ty.to_token_stream(), &params)
+            && seen.insert(ty.to_token_stream().to_string())
+        {
+            predicates.push(parse_quote! { #ty: rustc_errors::IntoDiagnosticArg });
+        }
+    }
+    for predicate in predicates {
+        structure.add_where_predicate(predicate);
+    }
+}
+
+/// Returns `true` if any of `idents` occurs in `tokens`.
+fn mentions_any(tokens: TokenStream, idents: &[Ident]) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Ident(ident) => idents.contains(&ident),
+        TokenTree::Group(group) => mentions_any(group.stream(), idents),
+        _ => false,
+    })
+}
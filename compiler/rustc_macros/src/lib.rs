@@ -94,6 +94,7 @@ pub fn symbols(input: TokenStream) -> TokenStream {
         warning,
         // field attributes
         skip_arg,
+        diag_flatten,
         primary_span,
         label,
         subdiagnostic,
@@ -111,6 +112,7 @@ pub fn symbols(input: TokenStream) -> TokenStream {
         warning,
         // field attributes
         skip_arg,
+        diag_flatten,
         primary_span,
         label,
         subdiagnostic,
@@ -122,6 +124,8 @@ pub fn symbols(input: TokenStream) -> TokenStream {
 decl_derive!(
     [Subdiagnostic, attributes(
         // struct/variant attributes
+        diag_flatten,
+        shared,
         label,
         help,
         note,
@@ -136,6 +140,7 @@ pub fn symbols(input: TokenStream) -> TokenStream {
         multipart_suggestion_verbose,
         // field attributes
         skip_arg,
+        diag_flatten,
         primary_span,
         suggestion_part,
         applicability)] => diagnostics::session_subdiagnostic_derive
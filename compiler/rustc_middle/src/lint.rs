@@ -86,10 +86,16 @@ pub fn reveal_actual_level(
     // triggers in cases (like #80988) where you have `forbid(warnings)`,
     // and so if we turned that into an error, it'd defeat the purpose of the
     // future compatibility warning.
+    //
+    // Performance notes aren't warnings, so `deny(warnings)` doesn't turn them into errors, but
+    // `allow(warnings)` still silences them.
     if level == Level::Warn && lint != LintId::of(FORBIDDEN_LINT_GROUPS) {
         let (warnings_level, warnings_src) = probe_for_lint_level(LintId::of(builtin::WARNINGS));
         if let Some(configured_warning_level) = warnings_level {
-            if configured_warning_level != Level::Warn {
+            let promoted = matches!(configured_warning_level, Level::Deny | Level::Forbid);
+            if configured_warning_level != Level::Warn
+                && !(promoted && is_perf_note(sess, lint.lint))
+            {
                 level = configured_warning_level;
                 *src = warnings_src;
             }
@@ -111,6 +117,12 @@ pub fn reveal_actual_level(
     level
 }
 
+/// Whether `lint` is shown as a performance note rather than a warning when it warns, see
+/// `rustc_errors::Level::PerfNote`.
+fn is_perf_note(sess: &Session, lint: &Lint) -> bool {
+    lint.perf_note && !sess.opts.unstable_opts.perf_notes_as_warnings
+}
+
 impl ShallowLintLevelMap {
     /// Perform a deep probe in the HIR tree looking for the actual level for the lint.
     /// This lint level is not usable for diagnostics, it needs to be corrected by
@@ -243,6 +255,54 @@ pub fn explain_lint_level_source(
     }
 }
 
+/// Explains step by step how the level of `lint` was decided, for `-Z explain-lint-levels`: its
+/// default level, the command-line flags that set it, the attribute that set it and the caps
+/// that limit it.
+pub fn explain_lint_level_provenance(
+    sess: &Session,
+    lint: &'static Lint,
+    level: Level,
+    src: LintLevelSource,
+    err: &mut Diagnostic,
+) {
+    let name = lint.name_lower();
+    let default_level = lint.default_level(sess.edition());
+    err.note(format!("`{name}` is `{}` by default", default_level.as_str()));
+
+    // Flags naming a lint group only matter if they are what decided the level in the end, as
+    // the groups of the lint aren't known here.
+    let src_flag_val = match src {
+        LintLevelSource::CommandLine(lint_flag_val, _) => Some(lint_flag_val),
+        _ => None,
+    };
+    for (lint_flag_val, flag_level) in &sess.opts.lint_opts {
+        if *lint_flag_val == name || src_flag_val.is_some_and(|val| val.as_str() == lint_flag_val) {
+            let flag = flag_level.to_cmd_flag();
+            let hyphen_case_flag_val = lint_flag_val.replace('_', "-");
+            err.note(format!(
+                "then set to `{}` on the command line with `{flag} {hyphen_case_flag_val}`",
+                flag_level.as_str(),
+            ));
+        }
+    }
+
+    if let LintLevelSource::Node { span, .. } = src {
+        err.span_note(span, "then set by this attribute");
+    }
+
+    if !matches!(src, LintLevelSource::CommandLine(_, Level::ForceWarn(_)))
+        && let Some(cap) = sess.opts.lint_cap
+    {
+        let cap = cap.as_str();
+        err.note(format!("then capped at `{cap}` by `--cap-lints {cap}`"));
+    }
+    if let Some(driver_level) = sess.driver_lint_caps.get(&LintId::of(lint)) {
+        err.note(format!("then capped at `{}` by the compiler driver", driver_level.as_str()));
+    }
+
+    err.note(format!("so `{name}` is `{}` here", level.as_str()));
+}
+
 /// The innermost function for emitting lints.
 ///
 /// If you are looking to implement a lint, look for higher level functions,
@@ -314,6 +374,7 @@ fn lint_level_impl(
             }
             Level::ForceWarn(Some(expect_id)) => rustc_errors::Level::ForceWarning(Some(expect_id)),
             Level::ForceWarn(None) => rustc_errors::Level::ForceWarning(None),
+            Level::Warn if is_perf_note(sess, lint) => rustc_errors::Level::PerfNote,
             Level::Warn => rustc_errors::Level::Warning,
             Level::Deny | Level::Forbid => rustc_errors::Level::Error,
         };
@@ -349,7 +410,7 @@ fn lint_level_impl(
         // suppressed the lint due to macros.
         err.primary_message(msg);
 
-        err.is_lint(lint.name_lower(), has_future_breakage);
+        err.is_lint(lint.name_lower(), lint.default_level(sess.edition()), has_future_breakage);
 
         // Lint diagnostics that are covered by the expect level will not be emitted outside
         // the compiler. It is therefore not necessary to add any information for the user.
@@ -392,11 +453,19 @@ fn lint_level_impl(
                     format!("for more information, see {}", future_incompatible.reference);
                 err.note(citation);
             }
+            let breaks_in = match future_incompatible.reason.edition() {
+                Some(edition) => format!("edition-{edition}"),
+                None => "future-release".to_owned(),
+            };
+            err.future_incompat(future_incompatible.reference.to_owned(), breaks_in);
         }
 
         // Finally, run `decorate`.
         decorate(&mut err);
         explain_lint_level_source(lint, level, src, &mut *err);
+        if sess.opts.unstable_opts.explain_lint_levels {
+            explain_lint_level_provenance(sess, lint, level, src, &mut *err);
+        }
         err.emit()
     }
     lint_level_impl(sess, lint, level, src, span, msg, Box::new(decorate))
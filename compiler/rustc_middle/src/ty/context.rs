@@ -44,11 +44,12 @@
 use rustc_data_structures::sync::{DynSend, DynSync};
 use rustc_data_structures::unord::UnordSet;
 use rustc_errors::{
-    DecorateLint, DiagCtxt, DiagnosticBuilder, DiagnosticMessage, ErrorGuaranteed, MultiSpan,
+    Applicability, DecorateLint, DiagCtxt, Diagnostic, DiagnosticBuilder, DiagnosticMessage,
+    ErrorGuaranteed, MultiSpan,
 };
 use rustc_hir as hir;
 use rustc_hir::def::DefKind;
-use rustc_hir::def_id::{CrateNum, DefId, LocalDefId, LOCAL_CRATE};
+use rustc_hir::def_id::{CrateNum, DefId, LocalDefId, CRATE_DEF_ID, LOCAL_CRATE};
 use rustc_hir::definitions::Definitions;
 use rustc_hir::intravisit::Visitor;
 use rustc_hir::lang_items::LangItem;
@@ -60,7 +61,7 @@
 use rustc_serialize::opaque::{FileEncodeResult, FileEncoder};
 use rustc_session::config::CrateType;
 use rustc_session::cstore::{CrateStoreDyn, Untracked};
-use rustc_session::lint::Lint;
+use rustc_session::lint::{Level, Lint};
 use rustc_session::{Limit, MetadataKind, Session};
 use rustc_span::def_id::{DefPathHash, StableCrateId};
 use rustc_span::symbol::{kw, sym, Ident, Symbol};
@@ -2088,6 +2089,7 @@ pub fn emit_node_span_lint(
         let (level, src) = self.lint_level_at_node(lint, hir_id);
         lint_level(self.sess, lint, level, src, Some(span.into()), msg, |diag| {
             decorator.decorate_lint(diag);
+            self.suggest_allow_lint(diag, lint, level, hir_id);
         })
     }
 
@@ -2105,7 +2107,10 @@ pub fn node_span_lint(
         decorate: impl for<'a, 'b> FnOnce(&'b mut DiagnosticBuilder<'a, ()>),
     ) {
         let (level, src) = self.lint_level_at_node(lint, hir_id);
-        lint_level(self.sess, lint, level, src, Some(span.into()), msg, decorate);
+        lint_level(self.sess, lint, level, src, Some(span.into()), msg, |diag| {
+            decorate(diag);
+            self.suggest_allow_lint(diag, lint, level, hir_id);
+        });
     }
 
     /// Emit a lint from a lint struct (some type that implements `DecorateLint`, typically
@@ -2135,7 +2140,76 @@ pub fn node_lint(
         decorate: impl for<'a, 'b> FnOnce(&'b mut DiagnosticBuilder<'a, ()>),
     ) {
         let (level, src) = self.lint_level_at_node(lint, id);
-        lint_level(self.sess, lint, level, src, None, msg, decorate);
+        lint_level(self.sess, lint, level, src, None, msg, |diag| {
+            decorate(diag);
+            self.suggest_allow_lint(diag, lint, level, id);
+        });
+    }
+
+    /// With `-Z suggest-allow-lints`, suggests allowing `lint` on the innermost item around
+    /// `hir_id`, by adding it to an `#[allow]` attribute of the item if there is one, or by
+    /// adding a new attribute otherwise. The suggestion is only shown to tools.
+    fn suggest_allow_lint(
+        self,
+        diag: &mut Diagnostic,
+        lint: &'static Lint,
+        level: Level,
+        hir_id: HirId,
+    ) {
+        // Forbidden and force-warned lints can't be allowed.
+        if !self.sess.opts.unstable_opts.suggest_allow_lints
+            || matches!(level, Level::Forbid | Level::ForceWarn(_))
+        {
+            return;
+        }
+        // Opaque types are owners, but attributes go on the item they are part of.
+        let mut owner = hir_id.owner.def_id;
+        while self.def_kind(owner) == DefKind::OpaqueTy {
+            owner = self.local_parent(owner);
+        }
+        // `#![allow]` on the crate root is better left to the user.
+        if owner == CRATE_DEF_ID {
+            return;
+        }
+        let owner = HirId::make_owner(owner);
+        let name = lint.name_lower();
+        let msg = format!("allow `{name}` on the enclosing item");
+
+        let last_allowed = self
+            .hir()
+            .attrs(owner)
+            .iter()
+            .filter(|attr| attr.has_name(sym::allow))
+            .find_map(|attr| {
+                attr.meta_item_list()?.into_iter().filter(|item| !item.has_name(sym::reason)).last()
+            });
+        if let Some(last_allowed) = last_allowed {
+            let span = last_allowed.span();
+            if !span.from_expansion() {
+                diag.tool_only_span_suggestion(
+                    span.shrink_to_hi(),
+                    msg,
+                    format!(", {name}"),
+                    Applicability::MachineApplicable,
+                );
+            }
+            return;
+        }
+
+        // Outer attributes aren't part of the span of an item, so this inserts the new attribute
+        // after the existing ones.
+        let span = self.hir().span_with_body(owner);
+        if span.from_expansion() {
+            return;
+        }
+        if let Some(indent) = self.sess.source_map().indentation_before(span) {
+            diag.tool_only_span_suggestion(
+                span.shrink_to_lo(),
+                msg,
+                format!("#[allow({name})]\n{indent}"),
+                Applicability::MachineApplicable,
+            );
+        }
     }
 
     pub fn in_scope_traits(self, id: HirId) -> Option<&'tcx [TraitCandidate]> {
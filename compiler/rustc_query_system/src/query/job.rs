@@ -605,12 +605,35 @@ pub fn report_cycle<'a>(
     sess.dcx().create_err(cycle_diag)
 }
 
+/// Describes `current_query` and the queries it was called from, innermost first, as
+/// `[name] description`.
+pub fn describe_query_stack<Qcx: QueryContext>(
+    qcx: Qcx,
+    mut current_query: Option<QueryJobId>,
+) -> Vec<String> {
+    let query_map = qcx.collect_active_jobs();
+    let mut stack = Vec::new();
+    while let Some(query) = current_query {
+        let Some(query_info) = query_map.get(&query) else {
+            break;
+        };
+        stack.push(format!(
+            "[{}] {}",
+            qcx.dep_context().dep_kind_info(query_info.query.dep_kind).name,
+            query_info.query.description
+        ));
+        current_query = query_info.job.parent;
+    }
+    stack
+}
+
 pub fn print_query_stack<Qcx: QueryContext>(
     qcx: Qcx,
     mut current_query: Option<QueryJobId>,
     dcx: &DiagCtxt,
     num_frames: Option<usize>,
     mut file: Option<std::fs::File>,
+    file_frames: Option<usize>,
 ) -> usize {
     // Be careful relying on global state here: this code is called from
     // a panic hook, which means that the global `DiagCtxt` may be in a weird
@@ -640,7 +663,9 @@ pub fn print_query_stack<Qcx: QueryContext>(
             count_printed += 1;
         }
 
-        if let Some(ref mut file) = file {
+        if let Some(ref mut file) = file
+            && (Some(count_total) < file_frames || file_frames.is_none())
+        {
             let _ = writeln!(
                 file,
                 "#{} [{}] {}",
@@ -5,7 +5,8 @@
 #[cfg(parallel_compiler)]
 pub use self::job::deadlock;
 pub use self::job::{
-    print_query_stack, report_cycle, QueryInfo, QueryJob, QueryJobId, QueryJobInfo, QueryMap,
+    describe_query_stack, print_query_stack, report_cycle, QueryInfo, QueryJob, QueryJobId,
+    QueryJobInfo, QueryMap,
 };
 
 mod caches;
@@ -3821,7 +3821,7 @@ fn smart_resolve_path_fragment(
             swap(&mut err.span, &mut parent_err.span);
             err.children = take(&mut parent_err.children);
             err.sort_span = parent_err.sort_span;
-            err.is_lint = parent_err.is_lint.clone();
+            err.extras_mut().is_lint = parent_err.lint().cloned();
 
             // merge the parent's suggestions with the typo suggestions
             fn append_result<T, E>(res1: &mut Result<Vec<T>, E>, res2: Result<Vec<T>, E>) {
@@ -3833,7 +3833,8 @@ fn append_result<T, E>(res1: &mut Result<Vec<T>, E>, res2: Result<Vec<T>, E>) {
                     Err(_) => (),
                 };
             }
-            append_result(&mut err.suggestions, parent_err.suggestions.clone());
+            let parent_suggestions = parent_err.suggestions().map(<[_]>::to_vec);
+            append_result(&mut err.extras_mut().suggestions, parent_suggestions);
 
             parent_err.cancel();
 
@@ -1084,6 +1084,7 @@ fn default() -> Options {
             json_artifact_notifications: false,
             json_unused_externs: JsonUnusedExterns::No,
             json_future_incompat: false,
+            json_unfulfilled_expectations: false,
             pretty: None,
             working_dir: RealFileName::LocalPath(std::env::current_dir().unwrap()),
             color: ColorConfig::Auto,
@@ -1136,6 +1137,13 @@ pub fn dcx_flags(&self, can_emit_warnings: bool) -> DiagCtxtFlags {
             macro_backtrace: self.macro_backtrace,
             deduplicate_diagnostics: self.deduplicate_diagnostics,
             track_diagnostics: self.track_diagnostics,
+            verify_suggestions: self.verify_suggestions,
+            deterministic_diagnostics: self.deterministic_diagnostics,
+            group_diagnostics_by_file: self.group_diagnostics_by_file,
+            lint_summary: self.lint_summary,
+            error_limit: self.error_limit,
+            stream_future_incompat: self.stream_future_incompat,
+            track_delayed_bugs: self.track_delayed_bugs,
         }
     }
 }
@@ -1872,6 +1880,7 @@ pub struct JsonConfig {
     pub json_artifact_notifications: bool,
     pub json_unused_externs: JsonUnusedExterns,
     pub json_future_incompat: bool,
+    pub json_unfulfilled_expectations: bool,
 }
 
 /// Report unused externs in event stream
@@ -1912,6 +1921,7 @@ pub fn parse_json(early_dcx: &EarlyDiagCtxt, matches: &getopts::Matches) -> Json
     let mut json_artifact_notifications = false;
     let mut json_unused_externs = JsonUnusedExterns::No;
     let mut json_future_incompat = false;
+    let mut json_unfulfilled_expectations = false;
     for option in matches.opt_strs("json") {
         // For now conservatively forbid `--color` with `--json` since `--json`
         // won't actually be emitting any colors and anything colorized is
@@ -1928,6 +1938,7 @@ pub fn parse_json(early_dcx: &EarlyDiagCtxt, matches: &getopts::Matches) -> Json
                 "unused-externs" => json_unused_externs = JsonUnusedExterns::Loud,
                 "unused-externs-silent" => json_unused_externs = JsonUnusedExterns::Silent,
                 "future-incompat" => json_future_incompat = true,
+                "unfulfilled-expectations" => json_unfulfilled_expectations = true,
                 s => early_dcx.early_fatal(format!("unknown `--json` option `{s}`")),
             }
         }
@@ -1938,6 +1949,7 @@ pub fn parse_json(early_dcx: &EarlyDiagCtxt, matches: &getopts::Matches) -> Json
         json_artifact_notifications,
         json_unused_externs,
         json_future_incompat,
+        json_unfulfilled_expectations,
     }
 }
 
@@ -2612,6 +2624,7 @@ pub fn build_session_options(early_dcx: &mut EarlyDiagCtxt, matches: &getopts::M
         json_artifact_notifications,
         json_unused_externs,
         json_future_incompat,
+        json_unfulfilled_expectations,
     } = parse_json(early_dcx, matches);
 
     let error_format = parse_error_format(early_dcx, matches, color, json_rendered);
@@ -2627,6 +2640,19 @@ pub fn build_session_options(early_dcx: &mut EarlyDiagCtxt, matches: &getopts::M
         .unwrap_or_else(|e| early_dcx.early_fatal(e));
 
     let mut unstable_opts = UnstableOptions::build(early_dcx, matches);
+    // Overlays of Fluent messages can also be given in the environment, so that they apply to
+    // every crate of a build without changing how the compiler is invoked. Like the flag, this
+    // is only available on nightly. The variable is read into the tracked option right away, so
+    // that it is part of the dep-tracking hash and changing it invalidates incremental results.
+    if unstable_opts.translate_additional_ftl.is_none()
+        && nightly_options::match_is_nightly_build(matches)
+        && let Some(path) = std::env::var_os("RUSTC_TRANSLATE_ADDITIONAL_FTL")
+    {
+        unstable_opts.translate_additional_ftl = Some(PathBuf::from(path));
+    }
+    if unstable_opts.json_array {
+        early_dcx.abort_if_error_and_set_json_array(error_format);
+    }
     let (lint_opts, describe_lints, lint_cap) = get_cmd_lint_options(early_dcx, matches);
 
     check_error_format_stability(early_dcx, &unstable_opts, error_format);
@@ -2935,6 +2961,7 @@ pub fn build_session_options(early_dcx: &mut EarlyDiagCtxt, matches: &getopts::M
         json_artifact_notifications,
         json_unused_externs,
         json_future_incompat,
+        json_unfulfilled_expectations,
         pretty,
         working_dir,
         color,
@@ -3490,6 +3517,13 @@ pub fn extension(self) -> &'static str {
     }
 }
 
+/// Which diagnostics to hide with `-Z diagnostic-filter`.
+#[derive(Clone, PartialEq, Hash, Debug)]
+pub enum DiagnosticFilter {
+    /// Hide the warnings that don't point at a line added or changed by the patch in this file.
+    ChangedLines(PathBuf),
+}
+
 /// `-Zpolonius` values, enabling the borrow checker polonius analysis, and which version: legacy,
 /// or future prototype.
 #[derive(Clone, Copy, PartialEq, Hash, Debug, Default)]
@@ -6,8 +6,9 @@
 use rustc_data_structures::fx::FxIndexMap;
 use rustc_data_structures::profiling::TimePassesFormat;
 use rustc_data_structures::stable_hasher::Hash64;
+use rustc_errors::json::{ColumnConvention, ColumnUnit};
 use rustc_errors::ColorConfig;
-use rustc_errors::{LanguageIdentifier, TerminalUrl};
+use rustc_errors::{DelayedBugBacktraces, LanguageIdentifier, MacroBacktrace, TerminalUrl};
 use rustc_target::spec::{CodeModel, LinkerFlavorCli, MergeFunctions, PanicStrategy, SanitizerSet};
 use rustc_target::spec::{
     RelocModel, RelroLevel, SplitDebuginfo, StackProtector, TargetTriple, TlsModel,
@@ -218,6 +219,9 @@ pub struct Options {
         /// `true` if we're emitting a JSON job containing a future-incompat report for lints
         json_future_incompat: bool [TRACKED],
 
+        /// `true` if we're emitting a JSON blob listing the unfulfilled `#[expect]` attributes
+        json_unfulfilled_expectations: bool [UNTRACKED],
+
         pretty: Option<PpMode> [UNTRACKED],
 
         /// The (potentially remapped) working directory
@@ -366,7 +370,8 @@ mod desc {
     pub const parse_string: &str = "a string";
     pub const parse_opt_string: &str = parse_string;
     pub const parse_string_push: &str = parse_string;
-    pub const parse_opt_langid: &str = "a language identifier";
+    pub const parse_langid_list: &str =
+        "a comma-separated list of language identifiers, most preferred first";
     pub const parse_opt_pathbuf: &str = "a path";
     pub const parse_list: &str = "a space-separated list of strings";
     pub const parse_list_with_polarity: &str =
@@ -393,11 +398,14 @@ mod desc {
     pub const parse_linker_flavor: &str = ::rustc_target::spec::LinkerFlavorCli::one_of();
     pub const parse_optimization_fuel: &str = "crate=integer";
     pub const parse_dump_mono_stats: &str = "`markdown` (default) or `json`";
+    pub const parse_diagnostic_filter: &str = "`changed-lines:<patch file>`";
     pub const parse_instrument_coverage: &str =
         "`all` (default), `branch`, `except-unused-generics`, `except-unused-functions`, or `off`";
     pub const parse_instrument_xray: &str = "either a boolean (`yes`, `no`, `on`, `off`, etc), or a comma separated list of settings: `always` or `never` (mutually exclusive), `ignore-loops`, `instruction-threshold=N`, `skip-entry`, `skip-exit`";
     pub const parse_unpretty: &str = "`string` or `string=string`";
     pub const parse_treat_err_as_bug: &str = "either no value or a non-negative number";
+    pub const parse_error_limit: &str = "a non-negative number";
+    pub const parse_explain_inline: &str = "either no value or a number of lines";
     pub const parse_next_solver_config: &str = "a comma separated list of solver configurations: `globally` (default), `coherence`, `dump-tree`, `dump-tree-on-error";
     pub const parse_lto: &str =
         "either a boolean (`yes`, `no`, `on`, `off`, etc), `thin`, `fat`, or omitted";
@@ -415,6 +423,12 @@ mod desc {
     pub const parse_code_model: &str = "one of supported code models (`rustc --print code-models`)";
     pub const parse_tls_model: &str = "one of supported TLS models (`rustc --print tls-models`)";
     pub const parse_target_feature: &str = parse_string;
+    pub const parse_macro_backtrace: &str =
+        "either a boolean (`yes`, `no`, `on`, `off`, etc), `collapsed`, or a number of frames";
+    pub const parse_json_column_convention: &str =
+        "`char`, `utf8` or `utf16`, followed by `-0based` or `-1based`";
+    pub const parse_track_delayed_bugs: &str =
+        "either a boolean (`yes`, `no`, `on`, `off`, etc), or a number of delayed bugs";
     pub const parse_terminal_url: &str =
         "either a boolean (`yes`, `no`, `on`, `off`, etc), or `auto`";
     pub const parse_wasi_exec_model: &str = "either `command` or `reactor`";
@@ -523,14 +537,15 @@ pub(crate) fn parse_opt_string(slot: &mut Option<String>, v: Option<&str>) -> bo
         }
     }
 
-    /// Parse an optional language identifier, e.g. `en-US` or `zh-CN`.
-    pub(crate) fn parse_opt_langid(slot: &mut Option<LanguageIdentifier>, v: Option<&str>) -> bool {
-        match v {
-            Some(s) => {
-                *slot = rustc_errors::LanguageIdentifier::from_str(s).ok();
+    /// Parse a list of language identifiers, e.g. `pt-BR,pt` or `zh-CN`.
+    pub(crate) fn parse_langid_list(slot: &mut Vec<LanguageIdentifier>, v: Option<&str>) -> bool {
+        let Some(s) = v else { return false };
+        match s.split(',').map(LanguageIdentifier::from_str).collect::<Result<_, _>>() {
+            Ok(locales) => {
+                *slot = locales;
                 true
             }
-            None => false,
+            Err(_) => false,
         }
     }
 
@@ -882,6 +897,19 @@ pub(crate) fn parse_time_passes_format(slot: &mut TimePassesFormat, v: Option<&s
         }
     }
 
+    pub(crate) fn parse_diagnostic_filter(
+        slot: &mut Option<DiagnosticFilter>,
+        v: Option<&str>,
+    ) -> bool {
+        match v.and_then(|v| v.split_once(':')) {
+            Some(("changed-lines", path)) if !path.is_empty() => {
+                *slot = Some(DiagnosticFilter::ChangedLines(PathBuf::from(path)));
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub(crate) fn parse_dump_mono_stats(slot: &mut DumpMonoStatsFormat, v: Option<&str>) -> bool {
         match v {
             None => true,
@@ -991,6 +1019,30 @@ pub(crate) fn parse_instrument_xray(
         true
     }
 
+    pub(crate) fn parse_error_limit(slot: &mut Option<NonZeroUsize>, v: Option<&str>) -> bool {
+        match v.and_then(|s| s.parse().ok()) {
+            Some(limit) => {
+                *slot = NonZeroUsize::new(limit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn parse_explain_inline(slot: &mut Option<usize>, v: Option<&str>) -> bool {
+        match v {
+            Some(s) => {
+                *slot = s.parse().ok();
+                slot.is_some()
+            }
+            // Enough for the summary and the first example of most explanations.
+            None => {
+                *slot = Some(20);
+                true
+            }
+        }
+    }
+
     pub(crate) fn parse_treat_err_as_bug(slot: &mut Option<NonZeroUsize>, v: Option<&str>) -> bool {
         match v {
             Some(s) => match s.parse() {
@@ -1164,6 +1216,56 @@ pub(crate) fn parse_tls_model(slot: &mut Option<TlsModel>, v: Option<&str>) -> b
         true
     }
 
+    pub(crate) fn parse_macro_backtrace(slot: &mut MacroBacktrace, v: Option<&str>) -> bool {
+        *slot = match v {
+            Some("on" | "" | "yes" | "y") | None => MacroBacktrace::Yes,
+            Some("off" | "no" | "n") => MacroBacktrace::No,
+            Some("collapsed") => MacroBacktrace::Collapsed,
+            Some(depth) => match depth.parse() {
+                Ok(0) => MacroBacktrace::No,
+                Ok(depth) => MacroBacktrace::Depth(depth),
+                Err(_) => return false,
+            },
+        };
+        true
+    }
+
+    pub(crate) fn parse_json_column_convention(
+        slot: &mut ColumnConvention,
+        v: Option<&str>,
+    ) -> bool {
+        let Some((unit, base)) = v.and_then(|v| v.split_once('-')) else { return false };
+        let unit = match unit {
+            "char" => ColumnUnit::Char,
+            "utf8" => ColumnUnit::Utf8,
+            "utf16" => ColumnUnit::Utf16,
+            _ => return false,
+        };
+        let zero_based = match base {
+            "0based" => true,
+            "1based" => false,
+            _ => return false,
+        };
+        *slot = ColumnConvention { unit, zero_based };
+        true
+    }
+
+    pub(crate) fn parse_track_delayed_bugs(
+        slot: &mut DelayedBugBacktraces,
+        v: Option<&str>,
+    ) -> bool {
+        *slot = match v {
+            Some("on" | "" | "yes" | "y") | None => DelayedBugBacktraces::Always,
+            Some("off" | "no" | "n") => DelayedBugBacktraces::Never,
+            Some(n) => match n.parse() {
+                Ok(0) => DelayedBugBacktraces::Never,
+                Ok(n) => DelayedBugBacktraces::First(n),
+                Err(_) => return false,
+            },
+        };
+        true
+    }
+
     pub(crate) fn parse_terminal_url(slot: &mut TerminalUrl, v: Option<&str>) -> bool {
         *slot = match v {
             Some("on" | "" | "yes" | "y") | None => TerminalUrl::Yes,
@@ -1566,12 +1668,29 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
     debuginfo_compression: DebugInfoCompression = (DebugInfoCompression::None, parse_debuginfo_compression, [TRACKED],
         "compress debug info sections (none, zlib, zstd, default: none)"),
     deduplicate_diagnostics: bool = (true, parse_bool, [UNTRACKED],
-        "deduplicate identical diagnostics (default: yes)"),
+        "deduplicate identical diagnostics, showing how many duplicates each one had \
+        (default: yes)"),
     default_hidden_visibility: Option<bool> = (None, parse_opt_bool, [TRACKED],
         "overrides the `default_hidden_visibility` setting of the target"),
     dep_info_omit_d_target: bool = (false, parse_bool, [TRACKED],
         "in dep-info output, omit targets for tracking dependencies of the dep-info files \
         themselves (default: no)"),
+    deterministic_diagnostics: bool = (false, parse_bool, [UNTRACKED],
+        "print diagnostics sorted by their spans at the end of each compilation stage, so that \
+        the output doesn't depend on the order in which threads emit them (default: no)"),
+    diagnostic_baseline: Option<PathBuf> = (None, parse_opt_pathbuf, [UNTRACKED],
+        "file with the fingerprints of the warnings and lints that are not shown, one per line"),
+    diagnostic_filter: Option<DiagnosticFilter> = (None, parse_diagnostic_filter, [UNTRACKED],
+        "only show the warnings and lints that match a filter: \
+        `changed-lines:<patch file>` shows those pointing at lines changed by a unified diff"),
+    diagnostic_path_base: Option<PathBuf> = (None, parse_opt_pathbuf, [UNTRACKED],
+        "show the paths of files within this directory relative to it in human-readable \
+        diagnostics, while JSON diagnostics keep the full paths"),
+    diagnostics_log: Option<PathBuf> = (None, parse_opt_pathbuf, [UNTRACKED],
+        "append every emitted diagnostic to this file as a line of JSON, regardless of \
+        `--error-format`"),
+    diff_suggestions: bool = (false, parse_bool, [UNTRACKED],
+        "render structured suggestions as unified diffs of the lines they change (default: no)"),
     dual_proc_macros: bool = (false, parse_bool, [TRACKED],
         "load proc macros for both target and host, but only link to the target (default: no)"),
     dump_dep_graph: bool = (false, parse_bool, [UNTRACKED],
@@ -1611,6 +1730,15 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
         "emit a section containing stack size metadata (default: no)"),
     emit_thin_lto: bool = (true, parse_bool, [TRACKED],
         "emit the bc module with thin LTO info (default: yes)"),
+    error_limit: Option<NonZeroUsize> = (None, parse_error_limit, [UNTRACKED],
+        "stop showing errors after this many, while still compiling and failing at the end \
+        (default: 0, meaning no limit)"),
+    explain_lint_levels: bool = (false, parse_bool, [TRACKED],
+        "explain how the level of each emitted lint was decided, from its default level to \
+        command-line flags, attributes and `--cap-lints` (default: no)"),
+    explain_inline: Option<usize> = (None, parse_explain_inline, [UNTRACKED],
+        "show the long explanation of each error code after the first error with the code, \
+        trimmed to this many lines (default: no; 20 lines if no number is given)"),
     export_executable_symbols: bool = (false, parse_bool, [TRACKED],
         "export symbols from executables, as if they were dynamic libraries"),
     extra_const_ub_checks: bool = (false, parse_bool, [TRACKED],
@@ -1637,8 +1765,14 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
     graphviz_font: String = ("Courier, monospace".to_string(), parse_string, [UNTRACKED],
         "use the given `fontname` in graphviz output; can be overridden by setting \
         environment variable `RUSTC_GRAPHVIZ_FONT` (default: `Courier, monospace`)"),
+    group_diagnostics_by_file: bool = (false, parse_bool, [UNTRACKED],
+        "print diagnostics at the end of the compilation, grouped under the name of the file \
+        they point into and sorted by line (default: no)"),
     has_thread_local: Option<bool> = (None, parse_opt_bool, [TRACKED],
         "explicitly enable the `cfg(target_thread_local)` directive"),
+    hide_warnings_in: Vec<String> = (Vec::new(), parse_string_push, [UNTRACKED],
+        "do not show warnings pointing into files whose path matches this glob pattern, such as \
+        `target/generated/**` (may be specified multiple times)"),
     hir_stats: bool = (false, parse_bool, [UNTRACKED],
         "print some statistics about AST and HIR (default: no)"),
     human_readable_cgu_names: bool = (false, parse_bool, [TRACKED],
@@ -1678,6 +1812,15 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
          `=skip-entry`
          `=skip-exit`
          Multiple options can be combined with commas."),
+    json_array: bool = (false, parse_bool, [UNTRACKED],
+        "write JSON messages as a single array at the end of the compilation instead of one \
+        per line (default: no)"),
+    json_column_convention: ColumnConvention = (ColumnConvention::default(),
+        parse_json_column_convention, [UNTRACKED],
+        "how columns of spans are counted in JSON diagnostics (default: `char-1based`)"),
+    json_version: Option<u32> = (None, parse_opt_number, [UNTRACKED],
+        "version of the shape of JSON diagnostics to produce, 1 for the shape without \
+        `format_version` (default: the latest)"),
     layout_seed: Option<u64> = (None, parse_opt_number, [TRACKED],
         "seed layout randomization"),
     link_directives: bool = (true, parse_bool, [TRACKED],
@@ -1686,6 +1829,9 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
         "link native libraries in the linker invocation (default: yes)"),
     link_only: bool = (false, parse_bool, [TRACKED],
         "link the `.rlink` file generated by `-Z no-link` (default: no)"),
+    lint_summary: bool = (false, parse_bool, [UNTRACKED],
+        "at the end of the compilation, list how many times each lint was emitted, most \
+        frequent first (default: no)"),
     lint_mir: bool = (false, parse_bool, [UNTRACKED],
         "lint MIR before and after each transformation"),
     llvm_module_flag: Vec<(String, u32, String)> = (Vec::new(), parse_llvm_module_flag, [TRACKED],
@@ -1694,6 +1840,9 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
         "a list LLVM plugins to enable (space separated)"),
     llvm_time_trace: bool = (false, parse_bool, [UNTRACKED],
         "generate JSON tracing data file from LLVM data (default: no)"),
+    local_paths_in_diagnostics: bool = (false, parse_bool, [UNTRACKED],
+        "show the local paths of files remapped with `--remap-path-prefix` in human-readable \
+        diagnostics, and add them to the spans of JSON diagnostics (default: no)"),
     location_detail: LocationDetail = (LocationDetail::all(), parse_location_detail, [TRACKED],
         "what location details should be tracked when using caller_location, either \
         `none`, or a comma separated list of location details, for which \
@@ -1701,8 +1850,10 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
     ls: Vec<String> = (Vec::new(), parse_list, [UNTRACKED],
         "decode and print various parts of the crate metadata for a library crate \
         (space separated)"),
-    macro_backtrace: bool = (false, parse_bool, [UNTRACKED],
-        "show macro backtraces (default: no)"),
+    macro_backtrace: MacroBacktrace = (MacroBacktrace::No, parse_macro_backtrace, [UNTRACKED],
+        "show macro backtraces: `yes`, `no`, `collapsed` to only show the outermost and \
+        innermost expansions and those of macros from the current crate, or the number of \
+        expansions to show (default: no)"),
     maximal_hir_to_mir_coverage: bool = (false, parse_bool, [TRACKED],
         "save as much information as possible about the correspondence between MIR and HIR \
         as source scopes (default: no)"),
@@ -1759,6 +1910,10 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
         "disable generation of trait vptr in vtable for upcasting"),
     no_unique_section_names: bool = (false, parse_bool, [TRACKED],
         "do not use unique names for text and data sections when -Z function-sections is used"),
+    normalize_diagnostics: bool = (false, parse_bool, [UNTRACKED],
+        "replace the parts of human-readable diagnostics that differ between machines and builds \
+        by placeholders: absolute paths, positions in other crates and hashes in symbol names \
+        (default: no)"),
     normalize_docs: bool = (false, parse_bool, [TRACKED],
         "normalize associated items in rustdoc when generating documentation"),
     oom: OomStrategy = (OomStrategy::Abort, parse_oom_strategy, [TRACKED],
@@ -1773,6 +1928,9 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
         "panic strategy for panics in drops"),
     parse_only: bool = (false, parse_bool, [UNTRACKED],
         "parse only; do not compile, assemble, or link (default: no)"),
+    perf_notes_as_warnings: bool = (false, parse_bool, [TRACKED],
+        "show lints that are performance hints as warnings instead of performance notes, which \
+        makes `-D warnings` apply to them (default: no)"),
     plt: Option<bool> = (None, parse_opt_bool, [TRACKED],
         "whether to use the PLT when calling into shared libraries;
         only has effect for PIC code on systems with ELF binaries
@@ -1899,8 +2057,14 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
         "allow staticlibs to have rust dylib dependencies"),
     staticlib_prefer_dynamic: bool = (false, parse_bool, [TRACKED],
         "prefer dynamic linking to static linking for staticlibs (default: no)"),
+    stream_future_incompat: bool = (false, parse_bool, [UNTRACKED],
+        "emit each future-incompatibility diagnostic as soon as it is found, in addition to the \
+        future-incompatibility report (only with `--error-format=json`) (default: no)"),
     strict_init_checks: bool = (false, parse_bool, [TRACKED],
         "control if mem::uninitialized and mem::zeroed panic on more UB"),
+    suggest_allow_lints: bool = (false, parse_bool, [TRACKED],
+        "suggest allowing each emitted lint on the innermost item around it, for tools that \
+        offer it as a fix (default: no)"),
     #[rustc_lint_opt_deny_field_access("use `Session::teach` instead of this field")]
     teach: bool = (false, parse_bool, [TRACKED],
         "show extended diagnostic help (default: no)"),
@@ -1933,17 +2097,25 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
         "choose the TLS model to use (`rustc --print tls-models` for details)"),
     trace_macros: bool = (false, parse_bool, [UNTRACKED],
         "for every macro invocation, print its name and arguments (default: no)"),
+    track_delayed_bugs: DelayedBugBacktraces = (DelayedBugBacktraces::Always,
+        parse_track_delayed_bugs, [UNTRACKED],
+        "capture a backtrace for every delayed bug (`yes`), for none of them (`no`), or only for \
+        the given number of delayed bugs, the first ones (default: yes)"),
     track_diagnostics: bool = (false, parse_bool, [UNTRACKED],
         "tracks where in rustc a diagnostic was emitted"),
     // Diagnostics are considered side-effects of a query (see `QuerySideEffects`) and are saved
     // alongside query results and changes to translation options can affect diagnostics - so
     // translation options should be tracked.
     translate_additional_ftl: Option<PathBuf> = (None, parse_opt_pathbuf, [TRACKED],
-        "additional fluent translation to preferentially use (for testing translation)"),
+        "additional fluent translation to preferentially use, overriding the messages it \
+        defines (default: `RUSTC_TRANSLATE_ADDITIONAL_FTL` from the environment)"),
     translate_directionality_markers: bool = (false, parse_bool, [TRACKED],
         "emit directionality isolation markers in translated diagnostics"),
-    translate_lang: Option<LanguageIdentifier> = (None, parse_opt_langid, [TRACKED],
-        "language identifier for diagnostic output"),
+    translate_lang: Vec<LanguageIdentifier> = (Vec::new(), parse_langid_list, [TRACKED],
+        "language identifiers for diagnostic output, messages missing from the first locale are \
+        taken from the next one (e.g. `pt-BR,pt`)"),
+    translate_pseudo: bool = (false, parse_bool, [TRACKED],
+        "pseudo-localize translated diagnostics, to find text that isn't translated (default: no)"),
     translate_remapped_path_to_local_path: bool = (true, parse_bool, [TRACKED],
         "translate remapped paths into local paths when possible (default: yes)"),
     trap_unreachable: Option<bool> = (None, parse_opt_bool, [TRACKED],
@@ -1993,9 +2165,15 @@ pub(crate) fn parse_function_return(slot: &mut FunctionReturn, v: Option<&str>)
     #[rustc_lint_opt_deny_field_access("use `Session::verify_llvm_ir` instead of this field")]
     verify_llvm_ir: bool = (false, parse_bool, [TRACKED],
         "verify LLVM IR (default: no)"),
+    verify_suggestions: bool = (false, parse_bool, [UNTRACKED],
+        "check that machine-applicable suggestions produce code that still lexes, \
+        and delay a bug otherwise (default: no)"),
     virtual_function_elimination: bool = (false, parse_bool, [TRACKED],
         "enables dead virtual function elimination optimization. \
         Requires `-Clto[=[fat,yes]]`"),
+    warnings_output: Option<PathBuf> = (None, parse_opt_pathbuf, [UNTRACKED],
+        "write warnings, and notes and help messages that aren't part of an error, to this file \
+        instead of stderr, in the format of `--error-format`"),
     wasi_exec_model: Option<WasiExecModel> = (None, parse_wasi_exec_model, [TRACKED],
         "whether to build a wasi command or reactor"),
     write_long_types_to_disk: bool = (true, parse_bool, [UNTRACKED],
@@ -1,8 +1,8 @@
 use crate::code_stats::CodeStats;
 pub use crate::code_stats::{DataTypeKind, FieldInfo, FieldKind, SizeKind, VariantInfo};
 use crate::config::{
-    self, CrateType, FunctionReturn, InstrumentCoverage, OptLevel, OutFileName, OutputType,
-    RemapPathScopeComponents, SwitchWithOptPath,
+    self, CrateType, DiagnosticFilter, FunctionReturn, InstrumentCoverage, OptLevel, OutFileName,
+    OutputType, RemapPathScopeComponents, SwitchWithOptPath,
 };
 use crate::config::{ErrorOutputType, Input};
 use crate::errors;
@@ -19,11 +19,12 @@
 };
 use rustc_errors::annotate_snippet_emitter_writer::AnnotateSnippetEmitter;
 use rustc_errors::emitter::{DynEmitter, HumanEmitter, HumanReadableErrorType};
-use rustc_errors::json::JsonEmitter;
+use rustc_errors::json::{JsonArray, JsonEmitter, JSON_FORMAT_VERSION};
 use rustc_errors::registry::Registry;
 use rustc_errors::{
-    error_code, fallback_fluent_bundle, DiagCtxt, DiagnosticBuilder, DiagnosticMessage,
-    ErrorGuaranteed, FatalAbort, FluentBundle, IntoDiagnostic, LazyFallbackBundle, TerminalUrl,
+    error_code, fallback_fluent_bundle, fallback_fluent_bundle_with_options, ChangedLines,
+    DiagCtxt, DiagnosticBuilder, DiagnosticMessage, ErrorGuaranteed, FatalAbort, FluentBundle,
+    ColorConfig, IntoDiagnostic, LazyFallbackBundle, MacroBacktrace, PathGlob, TerminalUrl,
 };
 use rustc_macros::HashStable_Generic;
 pub use rustc_span::def_id::StableCrateId;
@@ -39,6 +40,7 @@
 use std::any::Any;
 use std::env;
 use std::fmt;
+use std::io::{self, Write};
 use std::ops::{Div, Mul};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -289,6 +291,7 @@ pub fn finish_diagnostics(&self, registry: &Registry) {
         self.check_miri_unleashed_features();
         self.dcx().print_error_count(registry);
         self.emit_future_breakage();
+        self.dcx().finish();
     }
 
     fn emit_future_breakage(&self) {
@@ -953,6 +956,7 @@ pub fn should_prefer_remapped_for_split_debuginfo_paths(&self) -> bool {
     }
 }
 
+/// The emitter for `--error-format`, which writes to `dst`, or to stderr if there is none.
 // JUSTIFICATION: part of session construction
 #[allow(rustc::bad_opt_access)]
 fn default_emitter(
@@ -961,6 +965,7 @@ fn default_emitter(
     source_map: Lrc<SourceMap>,
     bundle: Option<Lrc<FluentBundle>>,
     fallback_bundle: LazyFallbackBundle,
+    mut dst: Option<Box<dyn Write + Send>>,
 ) -> Box<DynEmitter> {
     let macro_backtrace = sopts.unstable_opts.macro_backtrace;
     let track_diagnostics = sopts.unstable_opts.track_diagnostics;
@@ -981,7 +986,10 @@ fn default_emitter(
         config::ErrorOutputType::HumanReadable(kind) => {
             let (short, color_config) = kind.unzip();
 
-            if let HumanReadableErrorType::AnnotateSnippet(_) = kind {
+            // The snippet emitter only writes to stderr, so other outputs use the default one.
+            if let HumanReadableErrorType::AnnotateSnippet(_) = kind
+                && dst.is_none()
+            {
                 let emitter = AnnotateSnippetEmitter::new(
                     Some(source_map),
                     bundle,
@@ -991,23 +999,39 @@ fn default_emitter(
                 );
                 Box::new(emitter.ui_testing(sopts.unstable_opts.ui_testing))
             } else {
-                let emitter = HumanEmitter::stderr(color_config, fallback_bundle)
+                let emitter = match dst {
+                    Some(dst) => HumanEmitter::without_colors(dst, fallback_bundle),
+                    None => HumanEmitter::stderr(color_config, fallback_bundle),
+                };
+                let emitter = emitter
                     .fluent_bundle(bundle)
                     .sm(Some(source_map))
                     .short_message(short)
                     .teach(sopts.unstable_opts.teach)
                     .diagnostic_width(sopts.diagnostic_width)
+                    .diff_suggestions(sopts.unstable_opts.diff_suggestions)
+                    .path_base(sopts.unstable_opts.diagnostic_path_base.clone())
+                    .local_paths(sopts.unstable_opts.local_paths_in_diagnostics)
+                    .normalize_output(sopts.unstable_opts.normalize_diagnostics)
                     .macro_backtrace(macro_backtrace)
                     .track_diagnostics(track_diagnostics)
                     .terminal_url(terminal_url)
+                    .explain_inline(sopts.unstable_opts.explain_inline)
+                    .registry(sopts.unstable_opts.explain_inline.map(|_| registry))
                     .ignored_directories_in_source_blocks(
                         sopts.unstable_opts.ignore_directory_in_diagnostics_source_blocks.clone(),
                     );
                 Box::new(emitter.ui_testing(sopts.unstable_opts.ui_testing))
             }
         }
-        config::ErrorOutputType::Json { pretty, json_rendered } => Box::new(
-            JsonEmitter::stderr(
+        config::ErrorOutputType::Json { pretty, json_rendered } => {
+            // The array on stderr is shared with the early errors of the driver.
+            let array = sopts.unstable_opts.json_array.then(|| match dst.take() {
+                Some(dst) => JsonArray::new(dst),
+                None => JsonArray::stderr(),
+            });
+            let emitter = JsonEmitter::new(
+                dst.unwrap_or_else(|| Box::new(io::BufWriter::new(io::stderr()))),
                 Some(registry),
                 source_map,
                 bundle,
@@ -1022,8 +1046,16 @@ fn default_emitter(
             .ui_testing(sopts.unstable_opts.ui_testing)
             .ignored_directories_in_source_blocks(
                 sopts.unstable_opts.ignore_directory_in_diagnostics_source_blocks.clone(),
-            ),
-        ),
+            )
+            .diff_suggestions(sopts.unstable_opts.diff_suggestions)
+            .column_convention(sopts.unstable_opts.json_column_convention)
+            .path_base(sopts.unstable_opts.diagnostic_path_base.clone())
+            .local_paths(sopts.unstable_opts.local_paths_in_diagnostics)
+            .normalize_output(sopts.unstable_opts.normalize_diagnostics)
+            .format_version(sopts.unstable_opts.json_version.unwrap_or(JSON_FORMAT_VERSION))
+            .array(array);
+            Box::new(emitter)
+        }
     }
 }
 
@@ -1081,19 +1113,101 @@ pub fn build_session(
         loader,
         sopts.file_path_mapping(),
         hash_kind,
+        sopts.working_dir.clone(),
     ));
 
-    let fallback_bundle = fallback_fluent_bundle(
+    let fallback_bundle = fallback_fluent_bundle_with_options(
         fluent_resources,
         sopts.unstable_opts.translate_directionality_markers,
+        sopts.unstable_opts.translate_pseudo,
     );
-    let emitter = default_emitter(&sopts, registry, source_map.clone(), bundle, fallback_bundle);
+    if let Some(version) = sopts.unstable_opts.json_version
+        && !(1..=JSON_FORMAT_VERSION).contains(&version)
+    {
+        early_dcx.early_fatal(format!(
+            "unknown JSON diagnostic version {version}, the supported versions are 1 to \
+            {JSON_FORMAT_VERSION}"
+        ));
+    }
+    let log_emitter = sopts.unstable_opts.diagnostics_log.as_ref().map(|path| {
+        // Builds run rustc many times with the same log, so it is appended to.
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path);
+        let file = file.unwrap_or_else(|err| {
+            early_dcx
+                .early_fatal(format!("failed to open diagnostics log `{}`: {err}", path.display()))
+        });
+        JsonEmitter::new(
+            Box::new(std::io::BufWriter::new(file)),
+            Some(registry.clone()),
+            source_map.clone(),
+            bundle.clone(),
+            fallback_bundle.clone(),
+            false,
+            HumanReadableErrorType::Default(ColorConfig::Never),
+            sopts.diagnostic_width,
+            sopts.unstable_opts.macro_backtrace,
+            sopts.unstable_opts.track_diagnostics,
+            TerminalUrl::No,
+        )
+        .column_convention(sopts.unstable_opts.json_column_convention)
+        .local_paths(sopts.unstable_opts.local_paths_in_diagnostics)
+        .format_version(sopts.unstable_opts.json_version.unwrap_or(JSON_FORMAT_VERSION))
+    });
+    let warning_emitter = sopts.unstable_opts.warnings_output.as_ref().map(|path| {
+        let file = std::fs::File::create(path).unwrap_or_else(|err| {
+            early_dcx.early_fatal(format!(
+                "failed to create warnings output `{}`: {err}",
+                path.display()
+            ))
+        });
+        default_emitter(
+            &sopts,
+            registry.clone(),
+            source_map.clone(),
+            bundle.clone(),
+            fallback_bundle.clone(),
+            Some(Box::new(io::BufWriter::new(file))),
+        )
+    });
+    let emitter =
+        default_emitter(&sopts, registry, source_map.clone(), bundle, fallback_bundle, None);
 
     let mut dcx = DiagCtxt::with_emitter(emitter)
         .with_flags(sopts.unstable_opts.dcx_flags(can_emit_warnings));
+    if let Some(warning_emitter) = warning_emitter {
+        dcx = dcx.with_warning_emitter(warning_emitter);
+    }
+    if let Some(log_emitter) = log_emitter {
+        dcx = dcx.with_log_emitter(Box::new(log_emitter));
+    }
     if let Some(ice_file) = ice_file {
         dcx = dcx.with_ice_file(ice_file);
     }
+    if let Some(path) = &sopts.unstable_opts.diagnostic_baseline {
+        let baseline = std::fs::read_to_string(path).unwrap_or_else(|err| {
+            early_dcx.early_fatal(format!(
+                "failed to read diagnostic baseline `{}`: {err}",
+                path.display()
+            ))
+        });
+        let fingerprints = baseline
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(String::from)
+            .collect();
+        dcx = dcx.with_baseline(fingerprints);
+    }
+    if let Some(DiagnosticFilter::ChangedLines(path)) = &sopts.unstable_opts.diagnostic_filter {
+        let patch = std::fs::read_to_string(path).unwrap_or_else(|err| {
+            early_dcx.early_fatal(format!("failed to read patch `{}`: {err}", path.display()))
+        });
+        dcx = dcx.with_changed_lines(ChangedLines::from_patch(&patch));
+    }
+    for pattern in &sopts.unstable_opts.hide_warnings_in {
+        let glob = PathGlob::new(pattern);
+        dcx.add_span_filter(move |path| glob.matches(path));
+    }
 
     // Now that the proper handler has been constructed, drop early_dcx to
     // prevent accidental use.
@@ -1121,6 +1235,12 @@ pub fn build_session(
         None
     };
 
+    let prof = SelfProfilerRef::new(
+        self_profiler,
+        sopts.unstable_opts.time_passes.then(|| sopts.unstable_opts.time_passes_format),
+    );
+    let dcx = dcx.with_self_profiler(prof.clone());
+
     let mut parse_sess = ParseSess::with_dcx(dcx, source_map);
     parse_sess.assume_incomplete_release = sopts.unstable_opts.assume_incomplete_release;
 
@@ -1141,11 +1261,6 @@ pub fn build_session(
     });
     let print_fuel = AtomicU64::new(0);
 
-    let prof = SelfProfilerRef::new(
-        self_profiler,
-        sopts.unstable_opts.time_passes.then(|| sopts.unstable_opts.time_passes_format),
-    );
-
     let ctfe_backtrace = Lock::new(match env::var("RUSTC_CTFE_BACKTRACE") {
         Ok(ref val) if val == "immediate" => CtfeBacktrace::Immediate,
         Ok(ref val) if val != "0" => CtfeBacktrace::Capture,
@@ -1410,7 +1525,13 @@ pub struct EarlyDiagCtxt {
 
 impl EarlyDiagCtxt {
     pub fn new(output: ErrorOutputType) -> Self {
-        let emitter = mk_emitter(output);
+        Self::with_json_array(output, false)
+    }
+
+    /// Like `EarlyDiagCtxt::new`, but with `json_array`, errors in the JSON format are collected
+    /// in the JSON array on stderr that the session writes with `-Zjson-array`.
+    pub fn with_json_array(output: ErrorOutputType, json_array: bool) -> Self {
+        let emitter = mk_emitter(output, json_array);
         Self { dcx: DiagCtxt::with_emitter(emitter) }
     }
 
@@ -1424,7 +1545,16 @@ pub fn abort_if_errors(&self) {
     pub fn abort_if_error_and_set_error_format(&mut self, output: ErrorOutputType) {
         self.dcx.abort_if_errors();
 
-        let emitter = mk_emitter(output);
+        let emitter = mk_emitter(output, false);
+        self.dcx = DiagCtxt::with_emitter(emitter);
+    }
+
+    /// Collects the later errors in the JSON array on stderr once `-Zjson-array` was parsed, see
+    /// `EarlyDiagCtxt::with_json_array`. Any errors prior to that will cause an abort.
+    pub fn abort_if_error_and_set_json_array(&mut self, output: ErrorOutputType) {
+        self.dcx.abort_if_errors();
+
+        let emitter = mk_emitter(output, true);
         self.dcx = DiagCtxt::with_emitter(emitter);
     }
 
@@ -1481,7 +1611,7 @@ pub fn initialize_checked_jobserver(&self) {
     }
 }
 
-fn mk_emitter(output: ErrorOutputType) -> Box<DynEmitter> {
+fn mk_emitter(output: ErrorOutputType, json_array: bool) -> Box<DynEmitter> {
     // FIXME(#100717): early errors aren't translated at the moment, so this is fine, but it will
     // need to reference every crate that might emit an early error for translation to work.
     let fallback_bundle =
@@ -1491,16 +1621,19 @@ fn mk_emitter(output: ErrorOutputType) -> Box<DynEmitter> {
             let (short, color_config) = kind.unzip();
             Box::new(HumanEmitter::stderr(color_config, fallback_bundle).short_message(short))
         }
-        config::ErrorOutputType::Json { pretty, json_rendered } => Box::new(JsonEmitter::basic(
-            pretty,
-            json_rendered,
-            None,
-            fallback_bundle,
-            None,
-            false,
-            false,
-            TerminalUrl::No,
-        )),
+        config::ErrorOutputType::Json { pretty, json_rendered } => Box::new(
+            JsonEmitter::basic(
+                pretty,
+                json_rendered,
+                None,
+                fallback_bundle,
+                None,
+                MacroBacktrace::No,
+                false,
+                TerminalUrl::No,
+            )
+            .array(json_array.then(JsonArray::stderr)),
+        ),
     };
     emitter
 }
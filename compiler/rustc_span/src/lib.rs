@@ -2470,21 +2470,105 @@ fn hash_stable(&self, ctx: &mut CTX, hasher: &mut StableHasher) {
 /// Useful type to use with `Result<>` indicate that an error has already
 /// been reported to the user, so no need to continue checking.
 ///
-/// The `()` field is necessary: it is non-`pub`, which means values of this
+/// The `_private` field is necessary: it is non-`pub`, which means values of this
 /// type cannot be constructed outside of this crate.
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
-#[derive(HashStable_Generic)]
-pub struct ErrorGuaranteed(());
+///
+/// In debug builds, a guarantee also records where it was handed out and, if it was handed out
+/// for a diagnostic, where that diagnostic was created and its message. Its `Debug` impl shows
+/// them, to find the code responsible for a guarantee without an error. All guarantees are
+/// equal regardless.
+#[derive(Clone, Copy)]
+pub struct ErrorGuaranteed {
+    _private: (),
+    #[cfg(debug_assertions)]
+    claimed_at: &'static std::panic::Location<'static>,
+    #[cfg(debug_assertions)]
+    diagnostic: Option<&'static ClaimedFor>,
+}
+
+/// The diagnostic an `ErrorGuaranteed` was handed out for, see `ErrorGuaranteed::claimed_for`.
+#[cfg(debug_assertions)]
+struct ClaimedFor {
+    emitted_at: String,
+    message: String,
+}
 
 impl ErrorGuaranteed {
     /// To be used only if you really know what you are doing... ideally, we would find a way to
     /// eliminate all calls to this method.
     #[deprecated = "`Session::span_delayed_bug` should be preferred over this function"]
+    #[track_caller]
     pub fn unchecked_claim_error_was_emitted() -> Self {
-        ErrorGuaranteed(())
+        ErrorGuaranteed {
+            _private: (),
+            #[cfg(debug_assertions)]
+            claimed_at: std::panic::Location::caller(),
+            #[cfg(debug_assertions)]
+            diagnostic: None,
+        }
+    }
+
+    /// Records the diagnostic this guarantee is handed out for: where in rustc it was created
+    /// and its primary message. Only debug builds keep this, and leak it, as guarantees are
+    /// `Copy`; there are few enough errors for that to not matter.
+    #[cfg(debug_assertions)]
+    #[doc(hidden)]
+    pub fn claimed_for(self, emitted_at: String, message: String) -> Self {
+        let diagnostic = Box::leak(Box::new(ClaimedFor { emitted_at, message }));
+        ErrorGuaranteed { diagnostic: Some(diagnostic), ..self }
+    }
+}
+
+impl fmt::Debug for ErrorGuaranteed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple("ErrorGuaranteed");
+        #[cfg(debug_assertions)]
+        match self.diagnostic {
+            Some(ClaimedFor { emitted_at, message }) => tuple.field(&format_args!(
+                "claimed at {} for `{message}` emitted at {emitted_at}",
+                self.claimed_at
+            )),
+            None => tuple.field(&format_args!("claimed at {}", self.claimed_at)),
+        };
+        #[cfg(not(debug_assertions))]
+        tuple.field(&());
+        tuple.finish()
+    }
+}
+
+impl PartialEq for ErrorGuaranteed {
+    #[inline]
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ErrorGuaranteed {}
+
+impl PartialOrd for ErrorGuaranteed {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ErrorGuaranteed {
+    #[inline]
+    fn cmp(&self, _other: &Self) -> Ordering {
+        Ordering::Equal
     }
 }
 
+impl Hash for ErrorGuaranteed {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+impl<CTX> HashStable<CTX> for ErrorGuaranteed {
+    #[inline]
+    fn hash_stable(&self, _hcx: &mut CTX, _hasher: &mut StableHasher) {}
+}
+
 impl<E: rustc_serialize::Encoder> Encodable<E> for ErrorGuaranteed {
     #[inline]
     fn encode(&self, _e: &mut E) {
@@ -176,14 +176,19 @@ pub struct SourceMap {
 
     /// The algorithm used for hashing the contents of each source file.
     hash_kind: SourceFileHashAlgorithm,
+
+    /// The working directory of the compiler, remapped like the paths of the source files.
+    working_dir: RealFileName,
 }
 
 impl SourceMap {
     pub fn new(path_mapping: FilePathMapping) -> SourceMap {
+        let working_dir = RealFileName::LocalPath(std::env::current_dir().unwrap_or_default());
         Self::with_file_loader_and_hash_kind(
             Box::new(RealFileLoader),
             path_mapping,
             SourceFileHashAlgorithm::Md5,
+            working_dir,
         )
     }
 
@@ -191,12 +196,14 @@ pub fn with_file_loader_and_hash_kind(
         file_loader: Box<dyn FileLoader + Sync + Send>,
         path_mapping: FilePathMapping,
         hash_kind: SourceFileHashAlgorithm,
+        working_dir: RealFileName,
     ) -> SourceMap {
         SourceMap {
             files: Default::default(),
             file_loader: IntoDynSyncSend(file_loader),
             path_mapping,
             hash_kind,
+            working_dir,
         }
     }
 
@@ -204,6 +211,10 @@ pub fn path_mapping(&self) -> &FilePathMapping {
         &self.path_mapping
     }
 
+    pub fn working_dir(&self) -> &RealFileName {
+        &self.working_dir
+    }
+
     pub fn file_exists(&self, path: &Path) -> bool {
         self.file_loader.file_exists(path)
     }
@@ -435,6 +446,22 @@ pub fn filename_for_diagnostics<'a>(&self, filename: &'a FileName) -> FileNameDi
         filename.display(self.path_mapping.filename_display_for_diagnostics)
     }
 
+    /// The local path of `file` if it was remapped with `--remap-path-prefix`. Files of other
+    /// crates have no local path anymore, so the mapping is reversed, which is only trusted if
+    /// the file the reversed path points to has the expected content.
+    pub fn local_path_of_remapped(&self, file: &SourceFile) -> Option<PathBuf> {
+        let FileName::Real(RealFileName::Remapped { local_path, virtual_name }) = &file.name else {
+            return None;
+        };
+        match local_path {
+            Some(local_path) => Some(local_path.clone()),
+            None => self
+                .path_mapping
+                .reverse_map_prefix_heuristically(virtual_name)
+                .filter(|_| self.ensure_source_file_source_present(file)),
+        }
+    }
+
     pub fn is_multiline(&self, sp: Span) -> bool {
         let lo = self.lookup_source_file_idx(sp.lo());
         let hi = self.lookup_source_file_idx(sp.hi());
@@ -690,7 +690,7 @@ pub enum PanicStrategy {
 }
 
 impl PanicStrategy {
-    pub fn desc(&self) -> &str {
+    pub fn desc(&self) -> &'static str {
         match *self {
             PanicStrategy::Unwind => "unwind",
             PanicStrategy::Abort => "abort",
@@ -1095,7 +1095,7 @@ pub enum SplitDebuginfo {
 }
 
 impl SplitDebuginfo {
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             SplitDebuginfo::Off => "off",
             SplitDebuginfo::Packed => "packed",
@@ -1332,7 +1332,7 @@ pub enum StackProtector {
 }
 
 impl StackProtector {
-    fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             StackProtector::None => "none",
             StackProtector::Basic => "basic",
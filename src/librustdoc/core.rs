@@ -3,7 +3,7 @@
 use rustc_data_structures::unord::UnordSet;
 use rustc_errors::emitter::{DynEmitter, HumanEmitter};
 use rustc_errors::json::JsonEmitter;
-use rustc_errors::TerminalUrl;
+use rustc_errors::{MacroBacktrace, TerminalUrl};
 use rustc_feature::UnstableFeatures;
 use rustc_hir::def::Res;
 use rustc_hir::def_id::{DefId, DefIdMap, DefIdSet, LocalDefId};
@@ -160,7 +160,7 @@ pub(crate) fn new_dcx(
                     pretty,
                     json_rendered,
                     diagnostic_width,
-                    false,
+                    MacroBacktrace::No,
                     unstable_opts.track_diagnostics,
                     TerminalUrl::No,
                 )
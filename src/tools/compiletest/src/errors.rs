@@ -18,6 +18,7 @@ pub enum ErrorKind {
     Note,
     Suggestion,
     Warning,
+    PerfNote,
 }
 
 impl FromStr for ErrorKind {
@@ -31,6 +32,7 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
             "NOTE" => Ok(ErrorKind::Note),
             "SUGGESTION" => Ok(ErrorKind::Suggestion),
             "WARN" | "WARNING" => Ok(ErrorKind::Warning),
+            "PERF-NOTE" => Ok(ErrorKind::PerfNote),
             _ => Err(()),
         }
     }
@@ -44,6 +46,7 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             ErrorKind::Note => write!(f, "note"),
             ErrorKind::Suggestion => write!(f, "suggestion"),
             ErrorKind::Warning => write!(f, "warning"),
+            ErrorKind::PerfNote => write!(f, "performance note"),
         }
     }
 }
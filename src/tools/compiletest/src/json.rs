@@ -23,6 +23,12 @@ struct ArtifactNotification {
     artifact: PathBuf,
 }
 
+#[derive(Deserialize)]
+struct UnfulfilledExpectationsNotification {
+    #[allow(dead_code)]
+    unfulfilled_expectations: Vec<serde_json::Value>,
+}
+
 #[derive(Deserialize)]
 struct UnusedExternNotification {
     #[allow(dead_code)]
@@ -49,6 +55,12 @@ struct FutureIncompatReport {
     future_incompat_report: Vec<FutureBreakageItem>,
 }
 
+#[derive(Deserialize)]
+struct FutureIncompatDiagnostic {
+    #[allow(dead_code)]
+    diagnostic: serde_json::Value,
+}
+
 #[derive(Deserialize)]
 struct FutureBreakageItem {
     diagnostic: Diagnostic,
@@ -124,6 +136,13 @@ pub fn extract_rendered(output: &str) -> String {
                 } else if serde_json::from_str::<UnusedExternNotification>(line).is_ok() {
                     // Ignore the notification.
                     None
+                } else if serde_json::from_str::<UnfulfilledExpectationsNotification>(line).is_ok()
+                {
+                    // Ignore the notification.
+                    None
+                } else if serde_json::from_str::<FutureIncompatDiagnostic>(line).is_ok() {
+                    // Ignore the diagnostic, it is also part of the future compat report.
+                    None
                 } else {
                     print!(
                         "failed to decode compiler output as json: line: {}\noutput: {}",
@@ -156,7 +175,9 @@ fn parse_line(file_name: &str, line: &str, output: &str, proc_res: &ProcRes) ->
             Err(error) => {
                 // Ignore the future compat report message - this is handled
                 // by `extract_rendered`
-                if serde_json::from_str::<FutureIncompatReport>(line).is_ok() {
+                if serde_json::from_str::<FutureIncompatReport>(line).is_ok()
+                    || serde_json::from_str::<FutureIncompatDiagnostic>(line).is_ok()
+                {
                     vec![]
                 } else {
                     proc_res.fatal(
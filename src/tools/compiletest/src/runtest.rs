@@ -1860,7 +1860,9 @@ fn is_unexpected_compiler_message(
             && match actual_error.kind {
                 Some(ErrorKind::Help) => expect_help,
                 Some(ErrorKind::Note) => expect_note,
-                Some(ErrorKind::Error) | Some(ErrorKind::Warning) => true,
+                Some(ErrorKind::Error) | Some(ErrorKind::Warning) | Some(ErrorKind::PerfNote) => {
+                    true
+                }
                 Some(ErrorKind::Suggestion) | None => false,
             }
     }
@@ -4257,6 +4259,13 @@ fn normalize_output(&self, output: &str, custom_rules: &[(String, String)]) -> S
             // since only humans process the stderr files.
             // Thus we just turn escaped newlines back into newlines.
             normalized = normalized.replace("\\n", "\n");
+
+            // Fingerprints of diagnostics hash the path of the file they point into, which
+            // differs between machines.
+            static FINGERPRINT_RE: Lazy<Regex> =
+                Lazy::new(|| Regex::new(r#"("fingerprint": ?)"[0-9a-f]{32}""#).unwrap());
+            normalized =
+                FINGERPRINT_RE.replace_all(&normalized, r#"$1"$$FINGERPRINT""#).into_owned();
         }
 
         // If there are `$SRC_DIR` normalizations with line and column numbers, then replace them
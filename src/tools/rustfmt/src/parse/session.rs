@@ -2,7 +2,7 @@
 use std::sync::atomic::{AtomicBool, Ordering};
 
 use rustc_data_structures::sync::{IntoDynSyncSend, Lrc};
-use rustc_errors::emitter::{DynEmitter, Emitter, HumanEmitter};
+use rustc_errors::emitter::{DynEmitter, Emitter, HumanEmitter, NullEmitter};
 use rustc_errors::translation::Translate;
 use rustc_errors::{
     ColorConfig, DiagCtxt, Diagnostic, DiagnosticBuilder, Level as DiagnosticLevel,
@@ -30,28 +30,8 @@ pub(crate) struct ParseSess {
 }
 
 /// Emitter which discards every error.
-struct SilentEmitter;
-
-impl Translate for SilentEmitter {
-    fn fluent_bundle(&self) -> Option<&Lrc<rustc_errors::FluentBundle>> {
-        None
-    }
-
-    fn fallback_fluent_bundle(&self) -> &rustc_errors::FluentBundle {
-        panic!("silent emitter attempted to translate a diagnostic");
-    }
-}
-
-impl Emitter for SilentEmitter {
-    fn source_map(&self) -> Option<&Lrc<SourceMap>> {
-        None
-    }
-
-    fn emit_diagnostic(&mut self, _db: &Diagnostic) {}
-}
-
 fn silent_emitter() -> Box<DynEmitter> {
-    Box::new(SilentEmitter {})
+    Box::new(NullEmitter::new())
 }
 
 /// Emit errors against every files expect ones specified in the `ignore_path_set`.
@@ -189,6 +189,7 @@
 /// rustc. Please check with the compiler team before adding an entry.
 const PERMITTED_RUSTC_DEPENDENCIES: &[&str] = &[
     // tidy-alphabetical-start
+    "addr2line",
     "adler",
     "ahash",
     "aho-corasick",
@@ -198,6 +199,7 @@
     "ar_archive_writer",
     "arrayvec",
     "autocfg",
+    "backtrace",
     "bitflags",
     "block-buffer",
     "byteorder", // via ruzstd in object in thorin-dwp
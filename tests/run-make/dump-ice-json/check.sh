@@ -0,0 +1,32 @@
+#!/bin/sh
+
+# The machine-readable report is only written when asked for.
+unset RUSTC_ICE_JSON
+$RUSTC src/lib.rs -Z treat-err-as-bug=1 1>$TMPDIR/rust-test-unset.log 2>&1
+should_be_empty=$(ls -l $TMPDIR/rustc-ice-*.json 2>/dev/null | wc -l)
+
+export RUSTC_ICE_JSON=$TMPDIR
+$RUSTC src/lib.rs -Z treat-err-as-bug=1 1>$TMPDIR/rust-test-set.log 2>&1
+content=$(cat $TMPDIR/rustc-ice-*.json)
+lines=$(cat $TMPDIR/rustc-ice-*.json | wc -l)
+rm $TMPDIR/rustc-ice-*.json
+
+echo "#### ICE report content:"
+echo $content
+echo "#### should_be_empty length:"
+echo $should_be_empty
+
+## Verify that a single report is written when asked for, and that it contains the message,
+## backtrace and query stack of the ICE along with the compiler flags.
+if [ $should_be_empty -eq 0 ] &&
+    [ $lines -eq 1 ] &&
+    [[ $content == *'"message":'* ]] &&
+    [[ $content == *'"backtrace":[{"col":'* ]] &&
+    [[ $content == *'"function":"'* ]] &&
+    [[ $content == *'"query_stack":["[typeck] type-checking `main`"'* ]] &&
+    [[ $content == *'"flags":["-Z","treat-err-as-bug=1"]'* ]] &&
+    [[ $content == *'"rustc_version":'* ]]; then
+    exit 0
+else
+    exit 1
+fi
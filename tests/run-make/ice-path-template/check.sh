@@ -0,0 +1,30 @@
+#!/bin/sh
+
+# The ICE dump is written to a path made from a template, creating the directories in it.
+export RUSTC_ICE="$TMPDIR/ice/{crate}/{timestamp}-{pid}.txt"
+export RUSTC_ICE_ENV=redacted
+export RUSTC_ICE_ARGS=full
+export RUSTC_ICE_QUERY_DEPTH=1
+export SOME_API_TOKEN=hunter2
+$RUSTC src/lib.rs --crate-name templated -Z treat-err-as-bug=1 1>$TMPDIR/rust-test.log 2>&1
+files=$(ls $TMPDIR/ice/templated/*.txt 2>/dev/null | wc -l)
+content=$(cat $TMPDIR/ice/templated/*.txt)
+rm -r $TMPDIR/ice
+
+echo "#### ICE Dump content:"
+echo $content
+echo "#### files:"
+echo $files
+
+## Verify that the dump is where the template says, that it contains the arguments and the
+## environment with secrets redacted, and that the query stack is cut off after one frame.
+if [ $files -eq 1 ] &&
+    [[ $content == *"arguments: "*"--crate-name templated"* ]] &&
+    [[ $content == *"SOME_API_TOKEN=[REDACTED]"* ]] &&
+    [[ $content != *"hunter2"* ]] &&
+    [[ $content == *"#0 [typeck] type-checking"* ]] &&
+    [[ $content != *"#1 [analysis]"* ]]; then
+    exit 0
+else
+    exit 1
+fi
@@ -0,0 +1 @@
+parse_struct_literal_body_without_path = this is a test message
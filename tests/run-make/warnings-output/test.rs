@@ -0,0 +1,4 @@
+fn main() {
+    let _x = (1);
+    let () = 2;
+}
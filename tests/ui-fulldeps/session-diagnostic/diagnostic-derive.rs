@@ -697,7 +697,7 @@ struct RawIdentDiagnosticArg {
 #[diag(no_crate_example)]
 struct SubdiagnosticBad {
     #[subdiagnostic(bad)]
-    //~^ ERROR `eager` is the only supported nested attribute for `subdiagnostic`
+    //~^ ERROR `eager` and `if = "..."` are the only supported nested attributes for `subdiagnostic`
     note: Note,
 }
 
@@ -713,7 +713,7 @@ struct SubdiagnosticBadStr {
 #[diag(no_crate_example)]
 struct SubdiagnosticBadTwice {
     #[subdiagnostic(bad, bad)]
-    //~^ ERROR `eager` is the only supported nested attribute for `subdiagnostic`
+    //~^ ERROR `eager` and `if = "..."` are the only supported nested attributes for `subdiagnostic`
     note: Note,
 }
 
@@ -721,7 +721,7 @@ struct SubdiagnosticBadTwice {
 #[diag(no_crate_example)]
 struct SubdiagnosticBadLitStr {
     #[subdiagnostic("bad")]
-    //~^ ERROR `eager` is the only supported nested attribute for `subdiagnostic`
+    //~^ ERROR `eager` and `if = "..."` are the only supported nested attributes for `subdiagnostic`
     note: Note,
 }
 
@@ -822,3 +822,76 @@ struct SuggestionOnVec {
     //~^ ERROR `#[suggestion(...)]` is not a valid attribute
     sub: Vec<Span>,
 }
+
+#[derive(Diagnostic)]
+#[diag(no_crate_example)]
+struct SubdiagnosticConditional {
+    #[subdiagnostic(if = "self.show_note")]
+    note: Note,
+    #[subdiagnostic(eager, if = "show_note")]
+    other_note: Note,
+    show_note: bool,
+}
+
+#[derive(Diagnostic)]
+#[diag(no_crate_example)]
+struct SubdiagnosticConditionalUnknownField {
+    #[subdiagnostic(if = "self.nonsense")]
+    //~^ ERROR `nonsense` doesn't refer to a field on this type
+    note: Note,
+}
+
+#[derive(Subdiagnostic)]
+#[diag_flatten]
+struct FlattenedContext {
+    name: String,
+    #[diag_flatten]
+    edition: Option<FlattenedEdition>,
+}
+
+#[derive(Subdiagnostic)]
+#[diag_flatten]
+struct FlattenedEdition {
+    edition: String,
+}
+
+#[derive(Diagnostic)]
+#[diag(no_crate_example)]
+struct Flattened {
+    #[primary_span]
+    span: Span,
+    #[diag_flatten]
+    context: FlattenedContext,
+}
+
+#[derive(Diagnostic)]
+#[diag(no_crate_example)]
+struct GenericArgs<'a, T: rustc_errors::IntoDiagnosticArg> {
+    expected: T,
+    found: T,
+    #[primary_span]
+    span: Span,
+    #[label]
+    label: Option<Span>,
+    name: &'a str,
+}
+
+#[derive(LintDiagnostic)]
+#[diag(no_crate_example)]
+struct GenericArgsLint<T>
+where
+    T: rustc_errors::IntoDiagnosticArg,
+{
+    found: T,
+}
+
+#[derive(Diagnostic)]
+#[diag(no_crate_example)]
+struct LabelsWithArgs {
+    #[primary_span]
+    span: Span,
+    #[label]
+    moved: Vec<(Span, usize)>,
+    #[label]
+    first: Option<(Span, &'static str)>,
+}
@@ -827,3 +827,76 @@ struct PrimarySpanOnVec {
     //~| NOTE there must be exactly one primary span
     sub: Vec<Span>,
 }
+
+#[derive(Subdiagnostic)]
+#[note(no_crate_example)]
+#[diag_flatten]
+//~^ ERROR `#[diag_flatten]` cannot be combined with a subdiagnostic kind
+struct FlattenWithKind {
+    var: String,
+}
+
+#[derive(Subdiagnostic)]
+#[diag_flatten]
+struct Flattened {
+    var: String,
+    #[diag_flatten]
+    inner: Option<FlattenedInner>,
+}
+
+#[derive(Subdiagnostic)]
+#[diag_flatten]
+struct FlattenedInner {
+    #[skip_arg]
+    skipped: Span,
+    edition: String,
+}
+
+#[derive(Subdiagnostic)]
+#[label(no_crate_example)]
+struct LabelOnVec {
+    #[primary_span]
+    spans: Vec<Span>,
+    var: String,
+}
+
+#[derive(Subdiagnostic)]
+#[note(no_crate_example)]
+#[label(no_crate_example)]
+#[help(no_crate_example)]
+struct NoteAndLabelOnVec {
+    #[primary_span]
+    spans: Vec<Span>,
+}
+
+#[derive(Subdiagnostic)]
+#[diag_flatten]
+struct SharedWrapper {
+    #[primary_span]
+    span: Span,
+    count: usize,
+    #[shared]
+    kind: SharedKind,
+}
+
+#[derive(Subdiagnostic)]
+#[shared]
+enum SharedKind {
+    #[label(no_crate_example)]
+    A,
+    #[note(no_crate_example)]
+    B { var: String },
+    #[help(no_crate_example)]
+    C {
+        #[primary_span]
+        other: Span,
+    },
+}
+
+#[derive(Subdiagnostic)]
+#[diag_flatten]
+//~^ ERROR `#[shared]` field without a `#[primary_span]` field
+struct SharedWithoutSpan {
+    #[shared]
+    kind: SharedKind,
+}
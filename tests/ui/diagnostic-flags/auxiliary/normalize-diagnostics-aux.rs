@@ -0,0 +1 @@
+pub fn takes_u32(_: u32) {}
@@ -0,0 +1,8 @@
+// compile-flags: -Zerror-limit=2
+// Errors after the limit are not shown, but still make the compilation fail.
+
+fn main() {
+    let _: u32 = "a"; //~ ERROR mismatched types
+    let _: u32 = "b"; //~ ERROR mismatched types
+    let _: u32 = "c";
+}
@@ -0,0 +1,7 @@
+// compile-flags: -Zexplain-inline=1
+// Only the first error with a code is followed by its explanation.
+
+fn main() {
+    let () = 4; //~ ERROR mismatched types
+    let () = 5; //~ ERROR mismatched types
+}
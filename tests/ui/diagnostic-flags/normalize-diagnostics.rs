@@ -0,0 +1,12 @@
+// aux-build:normalize-diagnostics-aux.rs
+// compile-flags: -Znormalize-diagnostics
+// normalize-stderr-test "tests/ui/diagnostic-flags/" -> "$$DIR/"
+// Positions in other crates are replaced by placeholders, and their files are only shown with
+// their names. Files of this crate are shown relative to the working directory if they are in it,
+// hence the normalization above.
+
+extern crate normalize_diagnostics_aux;
+
+fn main() {
+    normalize_diagnostics_aux::takes_u32("a"); //~ ERROR mismatched types
+}
@@ -15,7 +15,7 @@ enum Enum3 { D(i64), E, F }
 enum Enum4 { H(i64), I(i64), J }
 
 enum Enum5 {
-    L(i64, i64, i64, i64), //~ WARNING three times larger
+    L(i64, i64, i64, i64), //~ PERF-NOTE three times larger
     M(i64),
     N
 }
@@ -0,0 +1,8 @@
+// compile-flags: -Zdiagnostic-path-base={{src-base}}
+// normalize-stderr-test: "(errors)\\" -> "$1/"
+
+// Paths of files within the base directory are shown relative to it.
+
+fn main() {
+    ferris //~ ERROR cannot find value `ferris` in this scope
+}
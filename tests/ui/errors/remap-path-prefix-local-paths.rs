@@ -0,0 +1,10 @@
+// compile-flags: --remap-path-prefix={{src-base}}=remapped -Zlocal-paths-in-diagnostics
+// no-remap-src-base: Manually remap, so that the local path is shown despite the remapping.
+
+// The remapped paths aren't recognized by compiletest, so we
+// cannot use line-specific patterns.
+// error-pattern: E0425
+
+fn main() {
+    ferris
+}
@@ -0,0 +1,8 @@
+// Checks that `-Z explain-lint-levels` explains how the level of a lint was decided.
+// compile-flags: -Z explain-lint-levels -A non-snake-case
+
+#[deny(non_snake_case)]
+fn Foo() {}
+//~^ ERROR function `Foo` should have a snake case name
+
+fn main() {}
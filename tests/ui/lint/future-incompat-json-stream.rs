@@ -0,0 +1,10 @@
+// compile-flags: -Zfuture-incompat-test -Zstream-future-incompat --json=future-incompat
+// compile-flags: --error-format=json
+// check-pass
+
+// With `-Zstream-future-incompat`, each diagnostic of the future-incompatible report is also
+// emitted on its own as soon as it is found, before the report.
+
+fn main() {
+    let x = 1;
+}
@@ -0,0 +1,9 @@
+// check-pass
+// compile-flags: -Zlint-summary
+
+fn unused() {} //~ WARN function `unused` is never used
+
+fn main() {
+    let a = 1; //~ WARN unused variable: `a`
+    let b = 2; //~ WARN unused variable: `b`
+}
@@ -0,0 +1,15 @@
+// check-pass
+// revisions: deny allow
+//[deny] compile-flags: -D warnings
+//[allow] compile-flags: -A warnings
+// `-D warnings` doesn't turn lints that are performance notes into errors, but `-A warnings`
+// still silences them.
+
+#![warn(variant_size_differences)]
+
+enum _En {
+    A(u8),
+    B([u8; 1024]), //[deny]~ PERF-NOTE three times larger
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+// check-pass
+// Lints that are performance notes aren't turned into errors by `deny(warnings)`, but
+// `allow(warnings)` still silences them.
+
+#![warn(variant_size_differences)]
+#![deny(warnings)]
+
+enum _Shown {
+    A(u8),
+    B([u8; 1024]), //~ PERF-NOTE three times larger
+}
+
+#[allow(warnings)]
+enum _Allowed {
+    A(u8),
+    B([u8; 1024]),
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+// Checks that `-Z suggest-allow-lints` suggests allowing lints on the item they are emitted in,
+// either with a new attribute or by extending an existing one.
+// run-rustfix
+// check-pass
+// compile-flags: -Z suggest-allow-lints
+
+#![warn(dead_code)]
+
+fn unused() {}
+//~^ WARN function `unused` is never used
+
+#[allow(unused_variables)]
+pub(crate) fn also_unused() {}
+//~^ WARN function `also_unused` is never used
+
+fn main() {}
@@ -1,7 +1,9 @@
 // Test that the macro backtrace facility works
 // aux-build:ping.rs
-// revisions: default -Zmacro-backtrace
+// revisions: default -Zmacro-backtrace collapsed depth
 //[-Zmacro-backtrace] compile-flags: -Z macro-backtrace
+//[collapsed] compile-flags: -Z macro-backtrace=collapsed
+//[depth] compile-flags: -Z macro-backtrace=2
 
 #[macro_use] extern crate ping;
 